@@ -0,0 +1,330 @@
+//! Command-line tool for the iris matching pipeline.
+//!
+//! Iris codes and masks are stored using [`iris::io`], and public keys and encrypted codes are
+//! stored using [`encrypted::wire`](eyelid_match_ops::encrypted::wire). Private keys are stored
+//! in passphrase-encrypted key files, see [`keyfile`](eyelid_match_ops::encrypted::keyfile).
+//! All commands use the [`FullBits`] configuration.
+//!
+//! The private key passphrase is read from the `EYELID_KEY_PASSPHRASE` environment variable if it
+//! is set. Otherwise, the `keygen` and `match` commands prompt for it, and read it from stdin.
+//!
+//! The `match` and `bench` commands load matching settings from the `--config` file and
+//! `EYELID_` environment variables, see [`config`](eyelid_match_ops::config) for details. If the
+//! `num_threads` setting isn't zero, matching uses a thread pool with that many threads.
+//!
+//! The `match` command exits with status 0 if the codes match, and 1 if they don't. All commands
+//! exit with status 2 if there is an error.
+
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use clap::{Parser, Subcommand};
+use rand::Rng;
+use zeroize::Zeroizing;
+
+use eyelid_match_ops::{
+    config::MatcherConfig,
+    encoded::PolyCode,
+    encrypted::{EncryptedMatcher, EncryptedPolyCode},
+    iris::{
+        self,
+        conf::{mask_trailing_bits, IrisCode, IrisMask},
+    },
+    primitives::yashe::{PrivateKey, PublicKey, Yashe},
+    FullBits, FullRes, IrisConf,
+};
+
+/// The number of storage elements in each iris code or mask.
+const STORE_ELEM_LEN: usize = FullBits::STORE_ELEM_LEN;
+
+/// The environment variable containing the private key passphrase.
+const PASSPHRASE_VAR: &str = "EYELID_KEY_PASSPHRASE";
+
+/// Encrypts and matches iris codes.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The TOML file containing matching settings, which can be overridden by `EYELID_`
+    /// environment variables. Only used by `match` and `bench`.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// The command to run.
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// The pipeline steps that can be run.
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate a new private and public key.
+    Keygen {
+        /// The new file to write the encrypted private key to. Existing files aren't replaced.
+        #[arg(long)]
+        private_key: PathBuf,
+        /// The file to write the public key to.
+        #[arg(long)]
+        public_key: PathBuf,
+    },
+
+    /// Convert a text iris template into an iris code or mask file.
+    ///
+    /// The template has one line of `0` and `1` characters for each row of the iris image.
+    Encode {
+        /// The text template to read.
+        #[arg(long)]
+        input: PathBuf,
+        /// The file to write the iris code or mask to.
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Encrypt an iris code and mask for storage.
+    Encrypt {
+        /// The public key file.
+        #[arg(long)]
+        public_key: PathBuf,
+        /// The iris code file.
+        #[arg(long)]
+        code: PathBuf,
+        /// The iris mask file.
+        #[arg(long)]
+        mask: PathBuf,
+        /// The file to write the encrypted code to.
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Encrypt an iris code and mask, then match it against an encrypted code.
+    ///
+    /// Exits with status 0 if the codes match, and 1 if they don't.
+    Match {
+        /// The encrypted private key file.
+        #[arg(long)]
+        private_key: PathBuf,
+        /// The public key file.
+        #[arg(long)]
+        public_key: PathBuf,
+        /// The iris code file to match.
+        #[arg(long)]
+        code: PathBuf,
+        /// The iris mask file to match.
+        #[arg(long)]
+        mask: PathBuf,
+        /// The stored encrypted code file.
+        #[arg(long)]
+        encrypted: PathBuf,
+    },
+
+    /// Time key generation, encryption, and matching with random iris codes.
+    Bench {
+        /// The number of codes to encrypt and match.
+        #[arg(long, default_value_t = 3)]
+        iterations: u32,
+    },
+}
+
+/// The exit status for errors, which is different from a failed match.
+const ERROR_EXIT_CODE: u8 = 2;
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::from(ERROR_EXIT_CODE)
+        }
+    }
+}
+
+/// Runs the command in `cli`, and returns the exit status.
+fn run(cli: Cli) -> Result<ExitCode, Box<dyn Error>> {
+    // Other commands don't use the settings, so invalid settings don't stop them working.
+    let load_config = || MatcherConfig::load(cli.config.as_deref());
+
+    match cli.command {
+        Command::Keygen {
+            private_key,
+            public_key,
+        } => {
+            // Ask for the passphrase first, because key generation is slow.
+            let passphrase = read_passphrase()?;
+            let (private, public) = Yashe::<FullRes>::new().keygen(&mut rand::thread_rng());
+
+            private.save_encrypted(&private_key, passphrase.as_bytes())?;
+            fs::write(public_key, public.to_bytes())?;
+        }
+
+        Command::Encode { input, output } => {
+            let code = read_template(&fs::read_to_string(input)?)?;
+
+            fs::write(
+                output,
+                iris::io::to_bytes::<FullBits, STORE_ELEM_LEN>(&code),
+            )?;
+        }
+
+        Command::Encrypt {
+            public_key,
+            code,
+            mask,
+            output,
+        } => {
+            let public_key = PublicKey::from_bytes(&fs::read(public_key)?)?;
+            let (code, mask) = (read_code(&code)?, read_code(&mask)?);
+
+            // Encryption only needs the public key, so the private key is never loaded.
+            let encrypted = EncryptedPolyCode::<FullBits>::convert_and_encrypt_code(
+                Yashe::new(),
+                PolyCode::from_plaintext(&code, &mask),
+                &public_key,
+                &mut rand::thread_rng(),
+            );
+
+            fs::write(output, encrypted.to_bytes())?;
+        }
+
+        Command::Match {
+            private_key,
+            public_key,
+            code,
+            mask,
+            encrypted,
+        } => {
+            let matcher = EncryptedMatcher::<FullBits>::builder()
+                .keys(
+                    PrivateKey::load_encrypted(private_key, read_passphrase()?.as_bytes())?,
+                    PublicKey::from_bytes(&fs::read(public_key)?)?,
+                )
+                .config(&load_config()?)?
+                .build();
+
+            let query = matcher.encrypt_query(&read_code(&code)?, &read_code(&mask)?);
+            let stored = EncryptedPolyCode::from_bytes(&fs::read(encrypted)?)?;
+
+            if matcher.verify(&query, &stored)? {
+                println!("match");
+            } else {
+                println!("no match");
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+
+        Command::Bench { iterations } => bench(iterations, &load_config()?)?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Returns the private key passphrase from [`PASSPHRASE_VAR`], or prompts for it and reads it
+/// from stdin.
+fn read_passphrase() -> Result<Zeroizing<String>, Box<dyn Error>> {
+    let passphrase = match env::var_os(PASSPHRASE_VAR) {
+        Some(passphrase) => Zeroizing::new(
+            passphrase
+                .into_string()
+                .map_err(|_| format!("{PASSPHRASE_VAR} must be valid Unicode"))?,
+        ),
+        None => {
+            eprint!("Private key passphrase: ");
+            io::stderr().flush()?;
+
+            let mut passphrase = Zeroizing::new(String::new());
+            io::stdin().read_line(&mut passphrase)?;
+
+            let len = passphrase.trim_end_matches(['\r', '\n']).len();
+            passphrase.truncate(len);
+            passphrase
+        }
+    };
+
+    if passphrase.is_empty() {
+        return Err("the private key passphrase must not be empty".into());
+    }
+
+    Ok(passphrase)
+}
+
+/// Reads an iris code or mask file.
+fn read_code(path: &Path) -> Result<IrisCode<STORE_ELEM_LEN>, Box<dyn Error>> {
+    Ok(iris::io::from_bytes::<FullBits, STORE_ELEM_LEN>(
+        &fs::read(path)?,
+    )?)
+}
+
+/// Parses a text iris template, with one line of `0` and `1` characters for each row.
+/// Blank lines are ignored.
+fn read_template(text: &str) -> Result<IrisCode<STORE_ELEM_LEN>, Box<dyn Error>> {
+    let rows: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|row| !row.is_empty())
+        .collect();
+    let columns = rows.first().map_or(0, |row| row.len());
+
+    let bits = rows
+        .iter()
+        .flat_map(|row| row.chars())
+        .map(|bit| match bit {
+            '0' => Ok(false),
+            '1' => Ok(true),
+            _ => Err(format!("templates can only contain 0 and 1, found {bit:?}")),
+        })
+        .collect::<Result<Vec<bool>, _>>()?;
+
+    Ok(iris::io::from_row_major_bits::<FullBits, STORE_ELEM_LEN>(
+        &bits,
+        rows.len(),
+        columns,
+    )?)
+}
+
+/// Prints the average time taken by each pipeline step, using random iris codes.
+fn bench(iterations: u32, config: &MatcherConfig) -> Result<(), Box<dyn Error>> {
+    let mut rng = rand::thread_rng();
+    let mut random_code = || {
+        let mut code: IrisCode<STORE_ELEM_LEN> = IrisCode::ZERO;
+        rng.fill(code.as_raw_mut_slice());
+        mask_trailing_bits::<FullBits, STORE_ELEM_LEN>(&mut code);
+        code
+    };
+    let mask: IrisMask<STORE_ELEM_LEN> = !IrisMask::ZERO;
+
+    let start = Instant::now();
+    let matcher = EncryptedMatcher::<FullBits>::builder()
+        .config(config)?
+        .build();
+    println!("keygen: {:?}", start.elapsed());
+
+    let (mut enroll, mut encrypt_query, mut verify) =
+        (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+    for _ in 0..iterations {
+        let (code, query) = (random_code(), random_code());
+
+        let start = Instant::now();
+        let stored = matcher.enroll(&code, &mask);
+        enroll += start.elapsed();
+
+        let start = Instant::now();
+        let query = matcher.encrypt_query(&query, &mask);
+        encrypt_query += start.elapsed();
+
+        let start = Instant::now();
+        matcher.verify(&query, &stored)?;
+        verify += start.elapsed();
+    }
+
+    let iterations = iterations.max(1);
+    println!("enroll: {:?}", enroll / iterations);
+    println!("encrypt query: {:?}", encrypt_query / iterations);
+    println!("match: {:?}", verify / iterations);
+
+    Ok(())
+}
@@ -8,29 +8,38 @@
 //! ```sh
 //! RUSTFLAGS="--cfg slow_benchmarks" cargo bench --features benchmark
 //! ```
+//!
+//! To also write the results to a JSON file, for automated performance tracking:
+//! ```sh
+//! EYELID_BENCH_JSON=bench.json cargo bench --features benchmark
+//! ```
 
 #![cfg(feature = "benchmark")]
 // Allow missing docs in macro-produced code.
 // TODO: move the macros to a separate module and allow missing docs only in that module.
 #![allow(missing_docs)]
 
-use std::time::Duration;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{criterion_group, BenchmarkId, Criterion, Throughput};
 
 use eyelid_match_ops::{
     encoded::{PolyCode, PolyQuery},
-    encrypted::{convert_negative_coefficients, EncryptedPolyCode, EncryptedPolyQuery},
+    encrypted::EncryptedMatcher,
     plaintext::{
         self,
         test::gen::{random_iris_code, random_iris_mask},
     },
     primitives::{
-        poly::{self, test::gen::rand_poly, Poly, PolyConf},
+        poly::{self, test::gen::rand_poly, CpuBackend, Poly, PolyConf, PolyError, PolyMulBackend},
         yashe::{self, Ciphertext, Message, Yashe},
     },
-    EncodeConf, IrisConf, MiddleRes, TestRes,
+    FullRes, IrisConf, MiddleRes, TestRes,
 };
+use eyelid_test::bench::{self, BenchId};
 
 // Configure Criterion:
 // Define one group for each equivalent operation, so we can compare their times.
@@ -39,7 +48,15 @@ criterion_group! {
     // This can be any expression that returns a `Criterion` object.
     config = Criterion::default().sample_size(50);
     // List full match implementations here.
-    targets = bench_plaintext_full_match, bench_ciphertext_full_match
+    targets = bench_plaintext_full_match, bench_plaintext_full_match_rotated, bench_plaintext_full_match_pre_rotated, bench_ciphertext_full_match
+}
+
+criterion_group! {
+    name = bench_hamming;
+    // This can be any expression that returns a `Criterion` object.
+    config = Criterion::default();
+    // List masked Hamming distance implementations here.
+    targets = bench_hamming_masked_words, bench_hamming_masked_bitvec
 }
 
 criterion_group! {
@@ -47,7 +64,7 @@ criterion_group! {
     // This can be any expression that returns a `Criterion` object.
     config = Criterion::default().sample_size(10);
     // List cyclotomic multiplication implementations here.
-    targets = bench_naive_cyclotomic_mul, bench_rec_karatsuba_mul, bench_flat_karatsuba_mul
+    targets = bench_naive_cyclotomic_mul, bench_rec_karatsuba_mul, bench_flat_karatsuba_mul, bench_ntt_cyclotomic_mul
 }
 
 criterion_group! {
@@ -87,7 +104,7 @@ criterion_group! {
     // This can be any expression that returns a `Criterion` object.
     config = Criterion::default().sample_size(10);
     // List encryption implementations here.
-    targets = bench_enc
+    targets = bench_enc, bench_enroll
 }
 
 criterion_group! {
@@ -106,6 +123,22 @@ criterion_group! {
     targets = bench_yashe_msg_mul, bench_yashe_cipher_mul
 }
 
+criterion_group! {
+    name = bench_backends;
+    // This can be any expression that returns a `Criterion` object.
+    config = Criterion::default().sample_size(10);
+    // List cross-backend workloads here, and backends in `poly_mul_backends()`.
+    targets = bench_backend_mul, bench_backend_full_match
+}
+
+criterion_group! {
+    name = bench_identification;
+    // This can be any expression that returns a `Criterion` object.
+    config = Criterion::default().sample_size(10);
+    // List 1-to-N identification implementations here.
+    targets = bench_plaintext_identification, bench_encoded_identification, bench_encrypted_identification
+}
+
 // Middle resolution polynomial benchmarks.
 criterion_group! {
     name = bench_cyclotomic_multiplication_mid;
@@ -132,20 +165,30 @@ criterion_group! {
 }
 
 // List groups here.
-criterion_main!(
-    bench_full_match,
-    bench_cyclotomic_multiplication,
-    bench_poly_split_karatsuba,
-    bench_polynomial_modulus,
-    bench_inverse,
-    bench_key_generation,
-    bench_encryption,
-    bench_decryption,
-    bench_yashe_mul,
-    bench_cyclotomic_multiplication_mid,
-    bench_inverse_mid,
-    bench_key_generation_mid
-);
+// This is the same as `criterion_main!()`, but it also exports the results as JSON.
+fn main() {
+    let started = SystemTime::now();
+
+    bench_full_match();
+    bench_hamming();
+    bench_cyclotomic_multiplication();
+    bench_poly_split_karatsuba();
+    bench_polynomial_modulus();
+    bench_inverse();
+    bench_key_generation();
+    bench_encryption();
+    bench_decryption();
+    bench_yashe_mul();
+    bench_backends();
+    bench_identification();
+    bench_cyclotomic_multiplication_mid();
+    bench_inverse_mid();
+    bench_key_generation_mid();
+
+    Criterion::default().configure_from_args().final_summary();
+
+    bench::export_from_env(started, describe_bench).expect("benchmark results must be exported");
+}
 
 /// The name used for slow benchmark groups.
 pub const SLOW_BENCH_NAME: &str = "Slow";
@@ -156,6 +199,58 @@ pub const RANDOM_BITS_NAME: &str = "random";
 /// The name used for small randomly distributions.
 pub const SMALL_RANDOM_NAME: &str = "small rand";
 
+/// The environment variable which overrides the gallery sizes in the identification benchmarks,
+/// as a comma-separated list. For example: `EYELID_GALLERY_SIZES=1000,10000,100000`.
+pub const GALLERY_SIZES_VAR: &str = "EYELID_GALLERY_SIZES";
+
+/// The number of different codes in each identification gallery. Larger galleries repeat these
+/// codes, because matching time doesn't depend on the code bits.
+pub const DISTINCT_GALLERY_CODES: usize = 10;
+
+/// Returns the parameter configuration and polynomial multiplication backend of a benchmark,
+/// for its exported JSON result.
+fn describe_bench(id: &BenchId) -> (String, String) {
+    let config = if id.group_id.contains(" mid ") {
+        "MiddleRes"
+    } else {
+        "FullRes"
+    };
+
+    // Cross-backend groups use the backend name as the function name, other benchmarks use the
+    // default backend.
+    let backend = match &id.function_id {
+        Some(backend) if id.group_id.starts_with("Backend ") => backend.clone(),
+        _ => poly::poly_mul_backend::<FullRes>().name().to_string(),
+    };
+
+    (config.to_string(), backend)
+}
+
+/// Returns the gallery sizes from [`GALLERY_SIZES_VAR`], or `default_sizes` if it isn't set.
+fn gallery_sizes(default_sizes: &[usize]) -> Vec<usize> {
+    match std::env::var(GALLERY_SIZES_VAR) {
+        Ok(sizes) => sizes
+            .split(',')
+            .map(|size| {
+                size.trim()
+                    .parse()
+                    .expect("gallery sizes must be a comma-separated list of integers")
+            })
+            .collect(),
+        Err(_) => default_sizes.to_vec(),
+    }
+}
+
+/// Returns a gallery with `size` entries, by repeating the entries in `distinct`.
+fn repeat_gallery<T: Clone>(distinct: &[T], size: usize) -> Vec<T> {
+    distinct.iter().cycle().take(size).cloned().collect()
+}
+
+/// Returns the Criterion throughput for matching one query against a gallery of `size` codes.
+fn gallery_throughput(size: usize) -> Throughput {
+    Throughput::Elements(size.try_into().expect("gallery size must fit in u64"))
+}
+
 /// Run [`plaintext::is_iris_match()`] as a Criterion benchmark with random data.
 fn bench_plaintext_full_match(settings: &mut Criterion) {
     use eyelid_match_ops::FullBits;
@@ -180,38 +275,140 @@ fn bench_plaintext_full_match(settings: &mut Criterion) {
     );
 }
 
-/// Run [`encrypterd_poly_query::is_match()`] as a Criterion benchmark with random data.
+/// Run [`plaintext::is_iris_match_rotated()`] as a Criterion benchmark with random data.
+fn bench_plaintext_full_match_rotated(settings: &mut Criterion) {
+    use eyelid_match_ops::FullBits;
+
+    // Setup: generate different random iris codes and masks
+    let eye_new = random_iris_code();
+    let mask_new = random_iris_mask();
+    let eye_store = random_iris_code();
+    let mask_store = random_iris_mask();
+
+    settings.bench_with_input(
+        BenchmarkId::new("Plaintext full match with rotated copies", RANDOM_BITS_NAME),
+        &(eye_new, mask_new, eye_store, mask_store),
+        |benchmark, (eye_new, mask_new, eye_store, mask_store)| {
+            benchmark.iter_with_large_drop(|| {
+                // To avoid timing dropping the return value, this line must not end in ';'
+                plaintext::is_iris_match_rotated::<FullBits, { FullBits::STORE_ELEM_LEN }>(
+                    eye_new, mask_new, eye_store, mask_store,
+                )
+            })
+        },
+    );
+}
+
+/// Run [`plaintext::PreRotatedCode::is_match()`] as a Criterion benchmark with random data.
+fn bench_plaintext_full_match_pre_rotated(settings: &mut Criterion) {
+    use eyelid_match_ops::FullBits;
+
+    // Setup: generate different random iris codes and masks, and rotate the stored code
+    let eye_new = random_iris_code();
+    let mask_new = random_iris_mask();
+    let code_store = plaintext::PreRotatedCode::<FullBits, { FullBits::STORE_ELEM_LEN }>::new(
+        &random_iris_code(),
+        &random_iris_mask(),
+    );
+
+    settings.bench_with_input(
+        BenchmarkId::new(
+            "Plaintext full match with pre-rotated codes",
+            RANDOM_BITS_NAME,
+        ),
+        &(eye_new, mask_new, code_store),
+        |benchmark, (eye_new, mask_new, code_store)| {
+            benchmark.iter_with_large_drop(|| {
+                // To avoid timing dropping the return value, this line must not end in ';'
+                code_store.is_match(eye_new, mask_new)
+            })
+        },
+    );
+}
+
+/// Run [`plaintext::hamming_masked()`] as a Criterion benchmark with random data.
+fn bench_hamming_masked_words(settings: &mut Criterion) {
+    use eyelid_match_ops::FullBits;
+
+    let words = |code: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]>| {
+        code.as_raw_slice()
+            .iter()
+            .map(|word| *word as u64)
+            .collect::<Vec<_>>()
+    };
+
+    let eye_a = words(random_iris_code());
+    let eye_b = words(random_iris_code());
+    let mask = words(random_iris_mask());
+
+    settings.bench_with_input(
+        BenchmarkId::new("Masked Hamming distance with words", RANDOM_BITS_NAME),
+        &(eye_a, eye_b, mask),
+        |benchmark, (eye_a, eye_b, mask)| {
+            benchmark.iter_with_large_drop(|| plaintext::hamming_masked(eye_a, eye_b, mask))
+        },
+    );
+}
+
+/// Run bitvec's masked bit count as a Criterion benchmark with random data.
+fn bench_hamming_masked_bitvec(settings: &mut Criterion) {
+    use eyelid_match_ops::FullBits;
+
+    let eye_a: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
+    let eye_b: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
+    let mask: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_mask();
+
+    settings.bench_with_input(
+        BenchmarkId::new("Masked Hamming distance with bitvec", RANDOM_BITS_NAME),
+        &(eye_a, eye_b, mask),
+        |benchmark, (eye_a, eye_b, mask)| {
+            benchmark.iter_with_large_drop(|| ((*eye_a ^ *eye_b) & *mask).count_ones())
+        },
+    );
+}
+
+/// Run [`EncryptedMatcher::enroll()`] as a Criterion benchmark with random data.
+///
+/// Enable the `parallel` feature to benchmark parallel encryption.
+fn bench_enroll(settings: &mut Criterion) {
+    use eyelid_match_ops::FullBits;
+
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
+    let mask: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_mask();
+
+    settings.bench_with_input(
+        BenchmarkId::new("Enrollment", RANDOM_BITS_NAME),
+        &(matcher, eye, mask),
+        |benchmark, (matcher, eye, mask)| {
+            benchmark.iter_with_large_drop(|| matcher.enroll(eye, mask))
+        },
+    );
+}
+
+/// Run [`EncryptedMatcher::verify()`] as a Criterion benchmark with random data.
 fn bench_ciphertext_full_match(settings: &mut Criterion) {
     use eyelid_match_ops::FullBits;
 
-    let mut rng = rand::thread_rng();
-    let ctx: Yashe<<FullBits as EncodeConf>::PlainConf> = Yashe::new();
-    let (private_key, public_key) = ctx.keygen(&mut rng);
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
 
     let eye_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
     let mask_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_mask();
     let eye_store: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
     let mask_store: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_mask();
 
-    let mut poly_query: PolyQuery<FullBits> = PolyQuery::from_plaintext(&eye_new, &mask_new);
-    let mut poly_code = PolyCode::from_plaintext(&eye_store, &mask_store);
-
-    convert_negative_coefficients::<FullBits>(&mut poly_query.polys);
-    convert_negative_coefficients::<FullBits>(&mut poly_code.polys);
-
-    let encrypted_poly_query =
-        EncryptedPolyQuery::encrypt_query(ctx, poly_query.clone(), &public_key, &mut rng);
-    let encrypted_poly_code =
-        EncryptedPolyCode::encrypt_code(ctx, poly_code.clone(), &public_key, &mut rng);
+    let encrypted_poly_query = matcher.encrypt_query(&eye_new, &mask_new);
+    let encrypted_poly_code = matcher.enroll(&eye_store, &mask_store);
 
     settings.bench_with_input(
         BenchmarkId::new("Ciphertext full match", RANDOM_BITS_NAME),
-        &(encrypted_poly_query, private_key, encrypted_poly_code),
-        |benchmark, (encrypted_poly_query, private_key, encrypted_poly_code)| {
+        &(encrypted_poly_query, matcher, encrypted_poly_code),
+        |benchmark, (encrypted_poly_query, matcher, encrypted_poly_code)| {
             benchmark.iter_with_large_drop(|| {
                 // There aren't any large drops here, but we use the same benchmark method for consistency
-                encrypted_poly_query
-                    .is_match(ctx, private_key, encrypted_poly_code)
+                matcher
+                    .verify(encrypted_poly_query, encrypted_poly_code)
                     .expect("encrypted matching must work")
             })
         },
@@ -268,6 +465,23 @@ pub fn bench_rec_karatsuba_mul(settings: &mut Criterion) {
     );
 }
 
+/// Run [`poly::ntt_cyclotomic_mul()`] as a Criterion benchmark with random data.
+pub fn bench_ntt_cyclotomic_mul(settings: &mut Criterion) {
+    // Setup: generate random cyclotomic polynomials, and initialize the NTT tables
+    let p1: Poly<TestRes> = rand_poly(TestRes::MAX_POLY_DEGREE);
+    let p2: Poly<TestRes> = rand_poly(TestRes::MAX_POLY_DEGREE);
+    assert!(TestRes::ntt_tables().is_some());
+
+    settings.bench_with_input(
+        BenchmarkId::new("NTT mul poly", RANDOM_BITS_NAME),
+        &(p1, p2),
+        |benchmark, (p1, p2)| {
+            // To avoid timing dropping the return value, we require it to be returned from the closure.
+            benchmark.iter_with_large_drop(|| -> Poly<TestRes> { poly::ntt_cyclotomic_mul(p1, p2) })
+        },
+    );
+}
+
 /// Run [`poly::rec_karatsuba_mul()`] as a Criterion benchmark with random data on middle resolution.
 pub fn bench_rec_karatsuba_mul_mid(settings: &mut Criterion) {
     // Setup: generate random cyclotomic polynomials
@@ -406,8 +620,7 @@ pub fn bench_inv(settings: &mut Criterion) {
         &(p),
         |benchmark, p| {
             // To avoid timing dropping the return value, we require it to be returned from the closure.
-            benchmark
-                .iter_with_large_drop(|| -> Result<Poly<TestRes>, &'static str> { p.inverse() })
+            benchmark.iter_with_large_drop(|| -> Result<Poly<TestRes>, PolyError> { p.inverse() })
         },
     );
 }
@@ -427,8 +640,7 @@ pub fn bench_inv_mid(settings: &mut Criterion) {
         &(p),
         |benchmark, p| {
             // To avoid timing dropping the return value, we require it to be returned from the closure.
-            benchmark
-                .iter_with_large_drop(|| -> Result<Poly<MiddleRes>, &'static str> { p.inverse() })
+            benchmark.iter_with_large_drop(|| -> Result<Poly<MiddleRes>, PolyError> { p.inverse() })
         },
     );
 }
@@ -543,6 +755,208 @@ pub fn bench_yashe_cipher_mul(settings: &mut Criterion) {
     );
 }
 
+/// Returns the polynomial multiplication backends compared in the cross-backend benchmarks.
+///
+/// Accelerated backends are added here, so every backend is measured with the same workloads.
+fn poly_mul_backends() -> Vec<Arc<dyn PolyMulBackend<FullRes>>> {
+    vec![Arc::new(CpuBackend)]
+}
+
+/// Run [`poly::mul_poly()`] with each available backend as a Criterion benchmark with random data.
+pub fn bench_backend_mul(settings: &mut Criterion) {
+    // Setup: generate random cyclotomic polynomials
+    let p1: Poly<FullRes> = rand_poly(FullRes::MAX_POLY_DEGREE);
+    let p2: Poly<FullRes> = rand_poly(FullRes::MAX_POLY_DEGREE);
+
+    let mut group = settings.benchmark_group("Backend mul poly");
+
+    for backend in poly_mul_backends() {
+        if !backend.is_available() {
+            continue;
+        }
+
+        let name = backend.name();
+        poly::set_poly_mul_backend(backend);
+
+        group.bench_with_input(
+            BenchmarkId::new(name, RANDOM_BITS_NAME),
+            &(&p1, &p2),
+            |benchmark, (p1, p2)| {
+                // To avoid timing dropping the return value, we require it to be returned from the closure.
+                benchmark.iter_with_large_drop(|| -> Poly<FullRes> { poly::mul_poly(p1, p2) })
+            },
+        );
+    }
+
+    group.finish();
+    poly::reset_poly_mul_backend::<FullRes>();
+}
+
+/// Run [`EncryptedMatcher::verify()`] with each available backend as a Criterion benchmark with
+/// random data.
+pub fn bench_backend_full_match(settings: &mut Criterion) {
+    use eyelid_match_ops::FullBits;
+
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
+    let mask_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_mask();
+    let eye_store: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
+    let mask_store: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_mask();
+
+    let encrypted_poly_query = matcher.encrypt_query(&eye_new, &mask_new);
+    let encrypted_poly_code = matcher.enroll(&eye_store, &mask_store);
+
+    let mut group = settings.benchmark_group("Backend ciphertext full match");
+
+    for backend in poly_mul_backends() {
+        if !backend.is_available() {
+            continue;
+        }
+
+        let name = backend.name();
+        poly::set_poly_mul_backend(backend);
+
+        group.bench_with_input(
+            BenchmarkId::new(name, RANDOM_BITS_NAME),
+            &(&encrypted_poly_query, &matcher, &encrypted_poly_code),
+            |benchmark, (encrypted_poly_query, matcher, encrypted_poly_code)| {
+                benchmark.iter_with_large_drop(|| {
+                    matcher
+                        .verify(encrypted_poly_query, encrypted_poly_code)
+                        .expect("encrypted matching must work")
+                })
+            },
+        );
+    }
+
+    group.finish();
+    poly::reset_poly_mul_backend::<FullRes>();
+}
+
+/// Run [`plaintext::match_many()`] as a Criterion benchmark, matching one query against galleries
+/// of random codes.
+pub fn bench_plaintext_identification(settings: &mut Criterion) {
+    use eyelid_match_ops::FullBits;
+
+    let eye_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
+    let mask_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_mask();
+    let distinct = (0..DISTINCT_GALLERY_CODES)
+        .map(|_| (random_iris_code(), random_iris_mask()))
+        .collect::<Vec<_>>();
+
+    let mut group = settings.benchmark_group("Plaintext identification");
+
+    for size in gallery_sizes(&[1_000, 10_000, 100_000]) {
+        let gallery = repeat_gallery(&distinct, size);
+
+        group.throughput(gallery_throughput(size));
+        group.bench_with_input(
+            BenchmarkId::new(RANDOM_BITS_NAME, size),
+            &gallery,
+            |benchmark, gallery| {
+                benchmark.iter_with_large_drop(|| {
+                    plaintext::match_many::<FullBits, { FullBits::STORE_ELEM_LEN }>(
+                        &eye_new, &mask_new, gallery,
+                    )
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Run [`PolyQuery::is_match_many()`] as a Criterion benchmark, matching one query against
+/// galleries of random codes.
+pub fn bench_encoded_identification(settings: &mut Criterion) {
+    use eyelid_match_ops::FullBits;
+
+    let eye_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
+    let mask_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_mask();
+    let query = PolyQuery::<FullBits>::from_plaintext(&eye_new, &mask_new);
+    let distinct = (0..DISTINCT_GALLERY_CODES)
+        .map(|_| {
+            let eye_store: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> =
+                random_iris_code();
+            let mask_store: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> =
+                random_iris_mask();
+
+            PolyCode::<FullBits>::from_plaintext(&eye_store, &mask_store)
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = settings.benchmark_group("Encoded identification");
+
+    // Each encoded match multiplies full resolution polynomials, so the default galleries are
+    // smaller than the plaintext ones.
+    for size in gallery_sizes(&[10, 100]) {
+        let gallery = repeat_gallery(&distinct, size);
+
+        group.throughput(gallery_throughput(size));
+        group.bench_with_input(
+            BenchmarkId::new(RANDOM_BITS_NAME, size),
+            &gallery,
+            |benchmark, gallery| {
+                benchmark.iter_with_large_drop(|| {
+                    query
+                        .is_match_many(gallery)
+                        .expect("encoded matching must work")
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Run [`EncryptedPolyQuery::is_match_many()`] as a Criterion benchmark, matching one query
+/// against galleries of random codes.
+///
+/// [`EncryptedPolyQuery::is_match_many()`]: eyelid_match_ops::encrypted::EncryptedPolyQuery::is_match_many
+pub fn bench_encrypted_identification(settings: &mut Criterion) {
+    use eyelid_match_ops::FullBits;
+
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_code();
+    let mask_new: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> = random_iris_mask();
+    let query = matcher.encrypt_query(&eye_new, &mask_new);
+    let distinct = (0..DISTINCT_GALLERY_CODES)
+        .map(|_| {
+            let eye_store: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> =
+                random_iris_code();
+            let mask_store: bitvec::array::BitArray<[usize; FullBits::STORE_ELEM_LEN]> =
+                random_iris_mask();
+
+            matcher.enroll(&eye_store, &mask_store)
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = settings.benchmark_group("Encrypted identification");
+
+    // Each encrypted match takes much longer than a plaintext match, so the default galleries
+    // are small. Use `EYELID_GALLERY_SIZES` to measure larger galleries.
+    for size in gallery_sizes(&[10]) {
+        let gallery = repeat_gallery(&distinct, size);
+
+        group.throughput(gallery_throughput(size));
+        group.bench_with_input(
+            BenchmarkId::new(RANDOM_BITS_NAME, size),
+            &gallery,
+            |benchmark, gallery| {
+                benchmark.iter_with_large_drop(|| {
+                    query
+                        .is_match_many(matcher.ctx(), matcher.private_key(), gallery)
+                        .expect("encrypted matching must work")
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
 /// Run [`Yashe::keygen()`] as a Criterion benchmark with random data on middle resolution.
 pub fn bench_keygen_mid(settings: &mut Criterion) {
     // Setup parameters
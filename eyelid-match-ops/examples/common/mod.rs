@@ -0,0 +1,102 @@
+//! The message framing shared by the client and server examples.
+//!
+//! Each message is a one byte [`Kind`], a little-endian `u32` payload length, then the payload.
+//! Payloads use the [`wire`](eyelid_match_ops::encrypted::wire) and
+//! [`iris::io`](eyelid_match_ops::iris::io) encodings.
+
+use std::io::{self, Read, Write};
+
+/// The default server address.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7878";
+
+/// The largest payload accepted, which is much larger than any encrypted query or products.
+const MAX_PAYLOAD_LEN: usize = 64 * 1024 * 1024;
+
+/// The kinds of protocol messages, in the order they are usually sent.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Kind {
+    /// Client to server: the client's public key.
+    PublicKey,
+    /// Client to server: a plaintext iris code followed by its mask, to encrypt and enroll.
+    Enroll,
+    /// Server to client: the enrolled id, as a little-endian `u64`.
+    Enrolled,
+    /// Client to server: an encrypted query, to match against every enrolled code.
+    Query,
+    /// Server to client: the masked products of the query and one enrolled code.
+    Products,
+    /// Client to server: the client's distance share, decrypted from the last products.
+    Share,
+    /// Server to client: the ids of the matching codes, as little-endian `u64`s.
+    Matches,
+    /// Client to server: the session is finished.
+    Done,
+}
+
+impl Kind {
+    /// Every message kind, indexed by its byte.
+    const ALL: [Kind; 8] = [
+        Kind::PublicKey,
+        Kind::Enroll,
+        Kind::Enrolled,
+        Kind::Query,
+        Kind::Products,
+        Kind::Share,
+        Kind::Matches,
+        Kind::Done,
+    ];
+}
+
+/// Writes a message of `kind` containing `payload` to `stream`.
+pub fn write_message(stream: &mut impl Write, kind: Kind, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| usize::try_from(*len).is_ok_and(|len| len <= MAX_PAYLOAD_LEN))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "payload is too large"))?;
+
+    stream.write_all(&[kind as u8])?;
+    stream.write_all(&len.to_le_bytes())?;
+    stream.write_all(payload)?;
+
+    stream.flush()
+}
+
+/// Reads the next message from `stream`, and returns its kind and payload.
+pub fn read_message(stream: &mut impl Read) -> io::Result<(Kind, Vec<u8>)> {
+    let mut kind = [0; 1];
+    stream.read_exact(&mut kind)?;
+    let kind = Kind::ALL
+        .get(usize::from(kind[0]))
+        .copied()
+        .ok_or_else(|| invalid_data(format!("unknown message kind {}", kind[0])))?;
+
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = usize::try_from(u32::from_le_bytes(len))
+        .ok()
+        .filter(|len| *len <= MAX_PAYLOAD_LEN)
+        .ok_or_else(|| invalid_data("payload is too large".to_string()))?;
+
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload)?;
+
+    Ok((kind, payload))
+}
+
+/// Reads the next message from `stream`, and returns its payload if it is an `expected` message.
+pub fn expect_message(stream: &mut impl Read, expected: Kind) -> io::Result<Vec<u8>> {
+    match read_message(stream)? {
+        (kind, payload) if kind == expected => Ok(payload),
+        (kind, _) => Err(unexpected(kind)),
+    }
+}
+
+/// Returns an error for an unexpected message of `kind`.
+pub fn unexpected(kind: Kind) -> io::Error {
+    invalid_data(format!("unexpected {kind:?} message"))
+}
+
+/// Returns an invalid data error with `message`.
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
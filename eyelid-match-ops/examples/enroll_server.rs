@@ -0,0 +1,116 @@
+//! Example server, which enrolls iris codes and matches encrypted queries against them.
+//!
+//! The client owns the keys, and sends its public key to the server. The server encrypts the
+//! plaintext codes it enrolls, so only encrypted codes are stored. For each query, the server
+//! sends the client masked products for every enrolled code, and the client returns its share
+//! of the distances. The client's share doesn't reveal the distances to the client.
+//!
+//! The server combines the shares in the clear to decide which codes match, so it learns the
+//! per-rotation match and mask counts for every enrolled code. It also sees the plaintext codes
+//! it enrolls. This example shows the message flow, it doesn't hide the distances from the
+//! server.
+//!
+//! Run the server, then the client in another terminal:
+//! ```sh
+//! cargo run --release --example enroll_server
+//! cargo run --release --example query_client
+//! ```
+//! Both take an optional address argument. The server exits after one client session.
+
+use std::{env, error::Error, net::TcpListener};
+
+use eyelid_match_ops::{
+    encoded::PolyCode,
+    encrypted::{
+        shares::DistanceShare,
+        store::{CodeId, EncryptedCodeStore, MemoryCodeStore},
+        EncryptedPolyCode, EncryptedPolyQuery,
+    },
+    iris,
+    primitives::yashe::{PublicKey, Yashe},
+    FullBits, FullRes, IrisConf,
+};
+
+use common::{expect_message, read_message, unexpected, write_message, Kind, DEFAULT_ADDR};
+
+mod common;
+
+/// The number of storage elements in each iris code or mask.
+const STORE_ELEM_LEN: usize = FullBits::STORE_ELEM_LEN;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDR.to_string());
+    let listener = TcpListener::bind(&addr)?;
+    println!("listening on {addr}");
+
+    let (mut stream, client) = listener.accept()?;
+    println!("client connected from {client}");
+
+    let ctx = Yashe::<FullRes>::new();
+    let mut rng = rand::thread_rng();
+    let public_key = PublicKey::from_bytes(&expect_message(&mut stream, Kind::PublicKey)?)?;
+    let mut store = MemoryCodeStore::<FullBits>::new();
+
+    loop {
+        match read_message(&mut stream)? {
+            (Kind::Enroll, payload) => {
+                // Codes and masks have the same length.
+                let (code, mask) = payload.split_at(payload.len() / 2);
+                let code = iris::io::from_bytes::<FullBits, STORE_ELEM_LEN>(code)?;
+                let mask = iris::io::from_bytes::<FullBits, STORE_ELEM_LEN>(mask)?;
+
+                let encrypted = EncryptedPolyCode::convert_and_encrypt_code(
+                    ctx,
+                    PolyCode::from_plaintext(&code, &mask),
+                    &public_key,
+                    &mut rng,
+                );
+
+                let id = CodeId::try_from(store.len())?;
+                store.put(id, encrypted)?;
+                println!("enrolled code {id}");
+
+                write_message(&mut stream, Kind::Enrolled, &id.to_le_bytes())?;
+            }
+
+            (Kind::Query, payload) => {
+                let query = EncryptedPolyQuery::<FullBits>::from_bytes(&payload)?;
+                let mut matches = Vec::new();
+
+                for entry in store.scan() {
+                    let (id, code) = entry?;
+
+                    let (products, server_share) =
+                        query.masked_products(ctx, &public_key, &code, &mut rng);
+                    write_message(&mut stream, Kind::Products, &products.to_bytes())?;
+
+                    let client_share = DistanceShare::<FullBits>::from_bytes(&expect_message(
+                        &mut stream,
+                        Kind::Share,
+                    )?)?;
+
+                    // Combining the shares gives the server the counts for this code.
+                    if server_share.is_match(&client_share)? {
+                        matches.extend_from_slice(&id.to_le_bytes());
+                    }
+                }
+
+                println!(
+                    "query matched {} codes",
+                    matches.len() / size_of::<CodeId>()
+                );
+                write_message(&mut stream, Kind::Matches, &matches)?;
+            }
+
+            (Kind::Done, _) => break,
+
+            (kind, _) => return Err(unexpected(kind).into()),
+        }
+    }
+
+    println!("client session finished");
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! Example client, which owns the keys, enrolls iris codes on the server, then queries them.
+//!
+//! The client enrolls random codes, then queries with one of them, and checks that only that
+//! code matches. See the `enroll_server` example for the protocol.
+//!
+//! Run the server, then the client in another terminal:
+//! ```sh
+//! cargo run --release --example enroll_server
+//! cargo run --release --example query_client
+//! ```
+
+use std::{
+    env,
+    error::Error,
+    io,
+    net::TcpStream,
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::ThreadRng, Rng};
+
+use eyelid_match_ops::{
+    encoded::PolyQuery,
+    encrypted::{shares::MaskedProducts, store::CodeId, EncryptedPolyQuery},
+    iris::{
+        self,
+        conf::{mask_trailing_bits, IrisCode, IrisMask},
+    },
+    primitives::yashe::Yashe,
+    FullBits, FullRes, IrisConf,
+};
+
+use common::{expect_message, read_message, unexpected, write_message, Kind, DEFAULT_ADDR};
+
+mod common;
+
+/// The number of storage elements in each iris code or mask.
+const STORE_ELEM_LEN: usize = FullBits::STORE_ELEM_LEN;
+
+/// The number of random codes to enroll.
+const ENROLLED_CODES: usize = 3;
+
+/// The index of the enrolled code used as the query.
+const QUERY_INDEX: usize = 1;
+
+/// How long to wait for the server to start.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDR.to_string());
+
+    let ctx = Yashe::<FullRes>::new();
+    let mut rng = rand::thread_rng();
+    let (private_key, public_key) = ctx.keygen(&mut rng);
+
+    let mut stream = connect(&addr)?;
+    println!("connected to {addr}");
+    write_message(&mut stream, Kind::PublicKey, &public_key.to_bytes())?;
+
+    // Enroll plaintext codes, which the server encrypts using our public key.
+    let mask: IrisMask<STORE_ELEM_LEN> = !IrisMask::ZERO;
+    let codes: Vec<IrisCode<STORE_ELEM_LEN>> =
+        (0..ENROLLED_CODES).map(|_| random_code(&mut rng)).collect();
+
+    let mut ids = Vec::new();
+    for code in &codes {
+        let mut payload = iris::io::to_bytes::<FullBits, STORE_ELEM_LEN>(code);
+        payload.extend(iris::io::to_bytes::<FullBits, STORE_ELEM_LEN>(&mask));
+        write_message(&mut stream, Kind::Enroll, &payload)?;
+
+        let id = expect_message(&mut stream, Kind::Enrolled)?;
+        ids.push(CodeId::from_le_bytes(id.as_slice().try_into()?));
+    }
+    println!("enrolled codes {ids:?}");
+
+    // Query with an enrolled code, and decrypt our share of each distance.
+    let query = EncryptedPolyQuery::convert_and_encrypt_query(
+        ctx,
+        PolyQuery::<FullBits>::from_plaintext(&codes[QUERY_INDEX], &mask),
+        &public_key,
+        &mut rng,
+    );
+    write_message(&mut stream, Kind::Query, &query.to_bytes())?;
+
+    let matches = loop {
+        match read_message(&mut stream)? {
+            (Kind::Products, payload) => {
+                let products = MaskedProducts::<FullBits>::from_bytes(&payload)?;
+                let share = products.decrypt_share(ctx, &private_key);
+
+                write_message(&mut stream, Kind::Share, &share.to_bytes())?;
+            }
+
+            (Kind::Matches, payload) => {
+                break payload
+                    .chunks_exact(size_of::<CodeId>())
+                    .map(|id| id.try_into().map(CodeId::from_le_bytes))
+                    .collect::<Result<Vec<_>, _>>()?;
+            }
+
+            (kind, _) => return Err(unexpected(kind).into()),
+        }
+    };
+
+    write_message(&mut stream, Kind::Done, &[])?;
+    println!("query matched codes {matches:?}");
+
+    if matches != [ids[QUERY_INDEX]] {
+        return Err(format!("expected only code {} to match", ids[QUERY_INDEX]).into());
+    }
+
+    Ok(())
+}
+
+/// Connects to the server at `addr`, retrying while it starts.
+fn connect(addr: &str) -> io::Result<TcpStream> {
+    let start = Instant::now();
+
+    loop {
+        match TcpStream::connect(addr) {
+            Ok(stream) => return Ok(stream),
+            Err(_) if start.elapsed() < CONNECT_TIMEOUT => {
+                thread::sleep(Duration::from_millis(200))
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Returns a random iris code.
+fn random_code(rng: &mut ThreadRng) -> IrisCode<STORE_ELEM_LEN> {
+    let mut code: IrisCode<STORE_ELEM_LEN> = IrisCode::ZERO;
+    rng.fill(code.as_raw_mut_slice());
+    mask_trailing_bits::<FullBits, STORE_ELEM_LEN>(&mut code);
+
+    code
+}
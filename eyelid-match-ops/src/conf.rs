@@ -13,6 +13,14 @@ pub struct FullBits;
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct MiddleBits;
 
+/// Raw middle resolution iris code dimensions, with multiple blocks packed into each polynomial.
+///
+/// This uses 6 polynomials for each iris code or mask, rather than 8 for [`MiddleBits`].
+/// (Full resolution codes already use most of each polynomial, so packing doesn't reduce the
+/// number of polynomials they need.)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MiddleBitsPacked;
+
 /// Tiny test polynomials, used for finding edge cases in tests.
 /// Used for both a tiny resolution and a tiny block encoding.
 ///
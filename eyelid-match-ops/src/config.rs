@@ -0,0 +1,358 @@
+//! Runtime matcher settings, loaded from a TOML file or environment variables.
+//!
+//! A TOML config file can contain any of these settings:
+//! ```toml
+//! param_set = "full-v1"
+//! threshold = { numerator = 36, denominator = 100 }
+//! rotation_limit = 15
+//! backend = "cpu-karatsuba"
+//! num_threads = 4
+//! ```
+//!
+//! Each setting can be overridden by an environment variable, which is the setting name in
+//! upper case, with an [`ENV_PREFIX`]. Thresholds are written as `numerator/denominator`,
+//! for example, `EYELID_THRESHOLD=36/100`.
+//!
+//! Missing settings use the defaults for the parameter set, which defaults to
+//! [`ParamSet::Full`].
+
+use std::{env, ffi::OsString, fs, io, path::Path, str::FromStr};
+
+use serde::Deserialize;
+
+use crate::{
+    iris::conf::{DynIrisConf, MatchThreshold},
+    params::ParamSet,
+    primitives::poly::{modular_poly::backend::poly_mul_backend, PolyConf},
+    FullBits, MiddleBits,
+};
+
+#[cfg(feature = "parallel")]
+use crate::ThreadPoolConfig;
+
+#[cfg(test)]
+mod test;
+
+/// The prefix of the environment variables which override config file settings.
+pub const ENV_PREFIX: &str = "EYELID_";
+
+/// Errors that can happen when loading or validating a [`MatcherConfig`].
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// The config file could not be read.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The config file is not valid TOML, or contains unknown or invalid settings.
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    /// An environment variable setting could not be parsed.
+    #[error("environment variable {var} has invalid value {value:?}")]
+    InvalidEnv {
+        /// The name of the environment variable.
+        var: String,
+        /// The invalid value.
+        value: String,
+    },
+
+    /// There is no parameter set with this name.
+    #[error("unknown parameter set {0:?}")]
+    UnknownParamSet(String),
+
+    /// The parameter set can't be used to match iris codes.
+    #[error("parameter set {0} can't be used for matching")]
+    UnsupportedParamSet(ParamSet),
+
+    /// The threshold is not between 0 and 1, or its denominator is larger than
+    /// [`MatchThreshold::MAX_DENOMINATOR`].
+    #[error("match threshold must be between 0 and 1, with a denominator of at most u32::MAX")]
+    InvalidThreshold,
+
+    /// The rotation limit is larger than the limit for the parameter set.
+    #[error("rotation limit {limit} is larger than the maximum {max}")]
+    RotationLimitTooLarge {
+        /// The configured rotation limit.
+        limit: usize,
+        /// The rotation limit of the parameter set.
+        max: usize,
+    },
+
+    /// The thread pool for the configured number of threads could not be created.
+    #[cfg(feature = "parallel")]
+    #[error(transparent)]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+
+    /// The config uses different parameters to the matcher it is applied to.
+    #[error("config uses parameter set {found}, but the matcher uses {expected}")]
+    ParamSetMismatch {
+        /// The matcher's parameter set.
+        expected: ParamSet,
+        /// The config's parameter set.
+        found: ParamSet,
+    },
+
+    /// The preferred polynomial multiplication backend isn't registered, or isn't available.
+    #[error("preferred backend {preferred} is not being used, {active} is being used instead")]
+    BackendUnavailable {
+        /// The preferred backend name.
+        preferred: String,
+        /// The name of the backend which is being used.
+        active: &'static str,
+    },
+}
+
+/// Validated matcher settings.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MatcherConfig {
+    /// The encryption parameter set.
+    param_set: ParamSet,
+    /// The bit match threshold.
+    threshold: MatchThreshold,
+    /// The number of columns each column is compared to, on its left and right.
+    rotation_limit: usize,
+    /// The name of the preferred polynomial multiplication backend, or `None` for any backend.
+    backend: Option<String>,
+    /// The number of threads used by the `parallel` feature, or zero to keep the current
+    /// thread pool.
+    num_threads: usize,
+}
+
+/// Unvalidated settings, from a config file or environment variables.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    /// The name of the encryption parameter set.
+    param_set: Option<String>,
+    /// The bit match threshold.
+    threshold: Option<RawThreshold>,
+    /// The rotation limit.
+    rotation_limit: Option<usize>,
+    /// The name of the preferred backend.
+    backend: Option<String>,
+    /// The number of threads.
+    num_threads: Option<usize>,
+}
+
+/// An unvalidated bit match threshold.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawThreshold {
+    /// The numerator of the threshold.
+    numerator: usize,
+    /// The denominator of the threshold.
+    denominator: usize,
+}
+
+impl MatcherConfig {
+    /// Returns the default config for `param_set`.
+    ///
+    /// Returns [`ConfigError::UnsupportedParamSet`] if `param_set` can't be used for matching.
+    pub fn new(param_set: ParamSet) -> Result<Self, ConfigError> {
+        Self::validate(RawConfig {
+            param_set: Some(param_set.name().to_string()),
+            ..RawConfig::default()
+        })
+    }
+
+    /// Loads the config file at `path` if there is one, then overrides its settings with any
+    /// environment variables.
+    ///
+    /// Returns [`ConfigError::InvalidEnv`] if an [`ENV_PREFIX`] variable isn't valid Unicode.
+    /// Other variables are ignored.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let file = path.map(fs::read_to_string).transpose()?;
+
+        Self::from_sources(file.as_deref(), env::vars_os())
+    }
+
+    /// Parses a TOML config, ignoring environment variables.
+    pub fn from_toml_str(toml: &str) -> Result<Self, ConfigError> {
+        Self::validate(toml::from_str(toml)?)
+    }
+
+    /// Loads settings from environment variables, without a config file.
+    /// See [`MatcherConfig::load()`] for details.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_sources(None, env::vars_os())
+    }
+
+    /// Parses the TOML config `toml`, if any, then overrides its settings with the
+    /// [`ENV_PREFIX`] variables in `vars`.
+    pub(crate) fn from_sources(
+        toml: Option<&str>,
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Result<Self, ConfigError> {
+        let mut raw: RawConfig = match toml {
+            Some(toml) => toml::from_str(toml)?,
+            None => RawConfig::default(),
+        };
+
+        for (var, value) in vars {
+            // Variables used by other programs don't have to be valid Unicode.
+            if !var.to_string_lossy().starts_with(ENV_PREFIX) {
+                continue;
+            }
+
+            let (Some(var), Some(value)) = (var.to_str(), value.to_str()) else {
+                return Err(invalid_env(
+                    &var.to_string_lossy(),
+                    &value.to_string_lossy(),
+                ));
+            };
+            let name = &var[ENV_PREFIX.len()..];
+
+            match name {
+                "PARAM_SET" => raw.param_set = Some(value.to_string()),
+                "THRESHOLD" => raw.threshold = Some(parse_threshold(var, value)?),
+                "ROTATION_LIMIT" => raw.rotation_limit = Some(parse_env(var, value)?),
+                "BACKEND" => raw.backend = Some(value.to_string()),
+                "NUM_THREADS" => raw.num_threads = Some(parse_env(var, value)?),
+                // Other variables are used by other tools, like EYELID_BENCH_JSON.
+                _ => {}
+            }
+        }
+
+        Self::validate(raw)
+    }
+
+    /// Checks `raw`, and fills in missing settings with the defaults for its parameter set.
+    fn validate(raw: RawConfig) -> Result<Self, ConfigError> {
+        let param_set = match raw.param_set {
+            Some(name) => ParamSet::by_name(&name).ok_or(ConfigError::UnknownParamSet(name))?,
+            None => ParamSet::Full,
+        };
+        let iris_conf = iris_conf(param_set).ok_or(ConfigError::UnsupportedParamSet(param_set))?;
+
+        let rotation_limit = raw.rotation_limit.unwrap_or(iris_conf.rotation_limit());
+        if rotation_limit > iris_conf.rotation_limit() {
+            return Err(ConfigError::RotationLimitTooLarge {
+                limit: rotation_limit,
+                max: iris_conf.rotation_limit(),
+            });
+        }
+
+        let threshold = match raw.threshold {
+            Some(RawThreshold {
+                numerator,
+                denominator,
+            }) => {
+                MatchThreshold::new(numerator, denominator).ok_or(ConfigError::InvalidThreshold)?
+            }
+            None => iris_conf.threshold(),
+        };
+
+        Ok(Self {
+            param_set,
+            threshold,
+            rotation_limit,
+            backend: raw.backend,
+            num_threads: raw.num_threads.unwrap_or_default(),
+        })
+    }
+
+    /// Returns the encryption parameter set.
+    pub fn param_set(&self) -> ParamSet {
+        self.param_set
+    }
+
+    /// Returns the bit match threshold.
+    pub fn threshold(&self) -> MatchThreshold {
+        self.threshold
+    }
+
+    /// Returns the rotation limit when comparing irises.
+    pub fn rotation_limit(&self) -> usize {
+        self.rotation_limit
+    }
+
+    /// Returns the name of the preferred polynomial multiplication backend, if any.
+    pub fn backend(&self) -> Option<&str> {
+        self.backend.as_deref()
+    }
+
+    /// Returns the number of threads used by the `parallel` feature, or zero to keep the
+    /// current thread pool.
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    /// Returns the thread pool configuration for [`set_thread_pool()`](crate::set_thread_pool).
+    #[cfg(feature = "parallel")]
+    pub fn thread_pool_config(&self) -> ThreadPoolConfig {
+        ThreadPoolConfig {
+            num_threads: self.num_threads,
+            thread_name: None,
+        }
+    }
+
+    /// Uses a dedicated thread pool with the configured number of threads, if it isn't zero.
+    /// Otherwise, keeps the current thread pool.
+    ///
+    /// This is called by
+    /// [`EncryptedMatcherBuilder::config()`](crate::encrypted::EncryptedMatcherBuilder::config).
+    #[cfg(feature = "parallel")]
+    pub fn apply_thread_pool(&self) -> Result<(), ConfigError> {
+        if self.num_threads != 0 {
+            crate::set_thread_pool(self.thread_pool_config())?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the preferred backend is used to multiply polynomials in the `C`
+    /// configuration.
+    ///
+    /// Backends other than the default CPU backend must be registered by the application using
+    /// [`set_poly_mul_backend()`](crate::primitives::poly::modular_poly::backend::set_poly_mul_backend).
+    pub fn check_backend<C: PolyConf>(&self) -> Result<(), ConfigError> {
+        let Some(preferred) = &self.backend else {
+            return Ok(());
+        };
+
+        let active = poly_mul_backend::<C>().name();
+        if active != preferred {
+            return Err(ConfigError::BackendUnavailable {
+                preferred: preferred.clone(),
+                active,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the iris configuration matched using `param_set`, if it can be used for matching.
+fn iris_conf(param_set: ParamSet) -> Option<DynIrisConf> {
+    match param_set {
+        ParamSet::Full => Some(DynIrisConf::from_conf::<FullBits>()),
+        ParamSet::Middle => Some(DynIrisConf::from_conf::<MiddleBits>()),
+        ParamSet::Large => None,
+    }
+}
+
+/// Parses the value of the environment variable `var`.
+fn parse_env<T: FromStr>(var: &str, value: &str) -> Result<T, ConfigError> {
+    value.trim().parse().map_err(|_| invalid_env(var, value))
+}
+
+/// Parses a threshold written as `numerator/denominator` in the environment variable `var`.
+/// The threshold is checked later, along with thresholds from config files.
+fn parse_threshold(var: &str, value: &str) -> Result<RawThreshold, ConfigError> {
+    let (numerator, denominator) = value
+        .split_once('/')
+        .ok_or_else(|| invalid_env(var, value))?;
+
+    Ok(RawThreshold {
+        numerator: parse_env(var, numerator)?,
+        denominator: parse_env(var, denominator)?,
+    })
+}
+
+/// Returns an error for the invalid environment variable `var`.
+fn invalid_env(var: &str, value: &str) -> ConfigError {
+    ConfigError::InvalidEnv {
+        var: var.to_string(),
+        value: value.to_string(),
+    }
+}
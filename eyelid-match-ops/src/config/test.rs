@@ -0,0 +1,208 @@
+//! Tests for loading matcher settings.
+
+use std::ffi::OsString;
+
+use crate::{
+    config::{ConfigError, MatcherConfig},
+    encrypted::EncryptedMatcher,
+    iris::conf::{IrisConf, MatchThreshold},
+    params::ParamSet,
+    primitives::poly::{CpuBackend, PolyMulBackend},
+    FullBits, FullRes, MiddleBits,
+};
+
+/// Returns environment variables with `names` and `values`.
+fn vars<const N: usize>(vars: [(&str, &str); N]) -> Vec<(OsString, OsString)> {
+    vars.into_iter()
+        .map(|(name, value)| (name.into(), value.into()))
+        .collect()
+}
+
+/// Check that missing settings use the defaults for the parameter set.
+#[test]
+fn test_defaults() {
+    let config = MatcherConfig::from_sources(None, []).expect("defaults must be valid");
+    assert_eq!(
+        config,
+        MatcherConfig::new(ParamSet::Full).expect("full is valid")
+    );
+    assert_eq!(config.param_set(), ParamSet::Full);
+    assert_eq!(config.threshold(), MatchThreshold::from_conf::<FullBits>());
+    assert_eq!(config.rotation_limit(), FullBits::ROTATION_LIMIT);
+    assert_eq!(config.backend(), None);
+    assert_eq!(config.num_threads(), 0);
+
+    let config = MatcherConfig::from_toml_str(r#"param_set = "middle-v1""#)
+        .expect("middle config must be valid");
+    assert_eq!(
+        config.threshold(),
+        MatchThreshold::from_conf::<MiddleBits>()
+    );
+    assert_eq!(config.rotation_limit(), MiddleBits::ROTATION_LIMIT);
+
+    assert!(matches!(
+        MatcherConfig::new(ParamSet::Large),
+        Err(ConfigError::UnsupportedParamSet(ParamSet::Large))
+    ));
+}
+
+/// Check that config file settings are loaded, and overridden by environment variables.
+#[test]
+fn test_file_and_env() {
+    let toml = r#"
+        param_set = "full-v1"
+        threshold = { numerator = 1, denominator = 4 }
+        rotation_limit = 10
+        backend = "cpu-karatsuba"
+        num_threads = 4
+    "#;
+
+    let config = MatcherConfig::from_sources(Some(toml), []).expect("config must be valid");
+    assert_eq!(
+        config.threshold(),
+        MatchThreshold::new(1, 4).expect("valid")
+    );
+    assert_eq!(config.rotation_limit(), 10);
+    assert_eq!(config.backend(), Some("cpu-karatsuba"));
+    assert_eq!(config.num_threads(), 4);
+
+    let env = vars([
+        ("EYELID_THRESHOLD", "1/3"),
+        ("EYELID_ROTATION_LIMIT", " 2 "),
+        ("EYELID_NUM_THREADS", "8"),
+        ("EYELID_BENCH_JSON", "unrelated"),
+        ("THRESHOLD", "unprefixed"),
+    ]);
+    let config = MatcherConfig::from_sources(Some(toml), env).expect("config must be valid");
+    assert_eq!(
+        config.threshold(),
+        MatchThreshold::new(1, 3).expect("valid")
+    );
+    assert_eq!(config.rotation_limit(), 2);
+    assert_eq!(config.backend(), Some("cpu-karatsuba"));
+    assert_eq!(config.num_threads(), 8);
+}
+
+/// Check that invalid settings are rejected.
+#[test]
+fn test_invalid_settings() {
+    for toml in [
+        "param_set = 1",
+        "unknown = true",
+        "threshold = { numerator = -1, denominator = 1 }",
+        "rotation_limit = -1",
+    ] {
+        assert!(
+            matches!(
+                MatcherConfig::from_toml_str(toml),
+                Err(ConfigError::Toml(_))
+            ),
+            "{toml}"
+        );
+    }
+
+    assert!(matches!(
+        MatcherConfig::from_toml_str(r#"param_set = "full-v2""#),
+        Err(ConfigError::UnknownParamSet(name)) if name == "full-v2"
+    ));
+    assert!(matches!(
+        MatcherConfig::from_toml_str("rotation_limit = 16"),
+        Err(ConfigError::RotationLimitTooLarge { limit: 16, max: 15 })
+    ));
+
+    for (var, value) in [
+        ("EYELID_THRESHOLD", "1"),
+        ("EYELID_THRESHOLD", "a/2"),
+        ("EYELID_ROTATION_LIMIT", "-1"),
+        ("EYELID_NUM_THREADS", "many"),
+    ] {
+        assert!(
+            matches!(
+                MatcherConfig::from_sources(None, vars([(var, value)])),
+                Err(ConfigError::InvalidEnv { .. })
+            ),
+            "{var}={value}"
+        );
+    }
+
+    // Only eyelid variables have to be valid Unicode.
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+
+        let invalid = || OsString::from_vec(vec![b'x', 0xff]);
+
+        assert!(MatcherConfig::from_sources(None, [(invalid(), invalid())]).is_ok());
+        assert!(MatcherConfig::from_sources(None, [("PATH".into(), invalid())]).is_ok());
+        assert!(matches!(
+            MatcherConfig::from_sources(None, [("EYELID_BACKEND".into(), invalid())]),
+            Err(ConfigError::InvalidEnv { var, .. }) if var == "EYELID_BACKEND"
+        ));
+    }
+
+    // Thresholds which are out of range, or could overflow match calculations.
+    let too_large = MatchThreshold::MAX_DENOMINATOR as u64 + 1;
+    for threshold in [
+        "2/1".to_string(),
+        "1/0".to_string(),
+        format!("{too_large}/{too_large}"),
+        format!("1/{too_large}"),
+        format!("{}/{}", i64::MAX, i64::MAX),
+    ] {
+        let (numerator, denominator) = threshold.split_once('/').expect("has a fraction");
+        let toml =
+            format!("threshold = {{ numerator = {numerator}, denominator = {denominator} }}");
+
+        assert!(
+            matches!(
+                MatcherConfig::from_sources(None, vars([("EYELID_THRESHOLD", &threshold)])),
+                Err(ConfigError::InvalidThreshold)
+            ),
+            "{threshold}"
+        );
+        assert!(
+            matches!(
+                MatcherConfig::from_toml_str(&toml),
+                Err(ConfigError::InvalidThreshold)
+            ),
+            "{toml}"
+        );
+    }
+}
+
+/// Check that configs are applied to matchers with the same parameters and backend.
+#[test]
+fn test_matcher_builder_config() {
+    // The CPU backend name depends on the enabled features.
+    let cpu = PolyMulBackend::<FullRes>::name(&CpuBackend);
+    let config = MatcherConfig::from_toml_str(&format!(
+        r#"
+        threshold = {{ numerator = 0, denominator = 1 }}
+        rotation_limit = 3
+        backend = "{cpu}"
+        "#,
+    ))
+    .expect("config must be valid");
+
+    let matcher = EncryptedMatcher::<FullBits>::builder()
+        .config(&config)
+        .expect("config must apply to full parameters")
+        .build();
+    assert_eq!(matcher.threshold(), config.threshold());
+    assert_eq!(matcher.rotation_limit(), 3);
+
+    let middle = MatcherConfig::new(ParamSet::Middle).expect("middle is valid");
+    assert!(matches!(
+        EncryptedMatcher::<FullBits>::builder().config(&middle),
+        Err(ConfigError::ParamSetMismatch {
+            expected: ParamSet::Full,
+            found: ParamSet::Middle,
+        })
+    ));
+
+    let gpu = MatcherConfig::from_toml_str(r#"backend = "gpu""#).expect("config must be valid");
+    assert!(matches!(
+        gpu.check_backend::<FullRes>(),
+        Err(ConfigError::BackendUnavailable { preferred, .. }) if preferred == "gpu"
+    ));
+}
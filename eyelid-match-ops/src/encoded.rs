@@ -1,5 +1,7 @@
 //! Iris matching operations on polynomial-encoded bit vectors.
 
+use std::cmp::min;
+
 use ark_ff::Zero;
 use itertools::Itertools;
 use num_bigint::BigUint;
@@ -7,23 +9,28 @@ use num_bigint::BigUint;
 use crate::{
     iris::conf::IrisConf,
     plaintext::{index_1d, IrisCode, IrisMask},
-    primitives::poly::{Poly, PolyConf},
+    primitives::{
+        poly::{Poly, PolyConf},
+        yashe::noise::NoiseRecord,
+    },
 };
 
 pub use conf::{EncodeConf, FullRes, MiddleRes};
 
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub use conf::TestRes;
 
 pub mod conf;
 
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub mod test;
 
 /// An Iris code, encoded in polynomials. To be stored in the database.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct PolyCode<C: EncodeConf> {
-    /// The polynomials, encoding one block of rows each. Storage variant.
+    /// The polynomials, encoding one or more blocks of rows each. Storage variant.
     //
     // TODO: use read-only accessor methods instead of `pub` for all 4 fields in these 2 structs.
     pub polys: Vec<Poly<C::PlainConf>>,
@@ -33,18 +40,36 @@ pub struct PolyCode<C: EncodeConf> {
 
 /// An Iris code, encoded in polynomials. To be matched against PolyCode.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct PolyQuery<C: EncodeConf> {
-    /// The polynomials, encoding one block of rows each. Query variant.
+    /// The polynomials, encoding one or more blocks of rows each. Query variant.
     pub polys: Vec<Poly<C::PlainConf>>,
     /// The mask polynomials.
     pub masks: Vec<Poly<C::PlainConf>>,
 }
 
 /// Errors that can happen during matching.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, thiserror::Error)]
 pub enum MatchError {
     /// A plaintext coefficient was much larger than expected.
+    #[error("plaintext coefficient is out of range")]
     PlaintextOutOfRange,
+
+    /// A decrypted coefficient was too large to be converted to a count.
+    /// This can happen if the ciphertext is corrupted, or was encrypted with different parameters.
+    #[error("decrypted coefficient {value} for rotation {rotation} is out of range")]
+    CoefficientOutOfRange {
+        /// The index of the rotation, from `0` to `ROTATION_COMPARISONS - 1`.
+        rotation: usize,
+        /// The raw value of the decrypted coefficient.
+        value: u128,
+    },
+
+    /// The estimated noise in a ciphertext nearly exhausted the noise budget, so decryption
+    /// could be incorrect.
+    #[error("noise budget exhausted: {0:?}")]
+    NoiseBudgetExhausted(NoiseRecord),
 }
 
 impl<C: EncodeConf> PolyCode<C> {
@@ -55,10 +80,15 @@ impl<C: EncodeConf> PolyCode<C> {
         value: &IrisCode<STORE_ELEM_LEN>,
         mask: &IrisMask<STORE_ELEM_LEN>,
     ) -> Self {
-        let polys = (0..C::NUM_BLOCKS)
-            .map(|block_i| {
-                let first_row_i = block_i * C::ROWS_PER_BLOCK;
-                Self::from_plaintext_block(value, mask, first_row_i)
+        // Code blocks are packed in reverse order, so the inner products of each pair of
+        // query and code blocks end up at the same coefficients.
+        let polys = (0..C::NUM_POLYS)
+            .map(|poly_i| {
+                pack_blocks::<C, _, _>(
+                    poly_i,
+                    |first_row_i| Self::from_plaintext_block(value, mask, first_row_i),
+                    |slot_i| (C::BLOCKS_PER_POLY - 1 - slot_i) * C::PACKED_BLOCK_STRIDE,
+                )
             })
             .collect_vec();
 
@@ -108,12 +138,15 @@ impl<C: EncodeConf> PolyQuery<C> {
         value: &IrisCode<STORE_ELEM_LEN>,
         mask: &IrisMask<STORE_ELEM_LEN>,
     ) -> Self {
-        // This code is textually the same as PolyCode::from_plaintext, but the
-        // from_plaintext_block() method is different.
-        let polys = (0..C::NUM_BLOCKS)
-            .map(|block_i| {
-                let first_row_i = block_i * C::ROWS_PER_BLOCK;
-                Self::from_plaintext_block(value, mask, first_row_i)
+        // This code is similar to PolyCode::from_plaintext, but the from_plaintext_block()
+        // method and block order are different.
+        let polys = (0..C::NUM_POLYS)
+            .map(|poly_i| {
+                pack_blocks::<C, _, _>(
+                    poly_i,
+                    |first_row_i| Self::from_plaintext_block(value, mask, first_row_i),
+                    |slot_i| slot_i * C::PACKED_BLOCK_STRIDE,
+                )
             })
             .collect_vec();
 
@@ -181,6 +214,56 @@ impl<C: EncodeConf> PolyQuery<C> {
         Ok(false)
     }
 
+    /// Returns a list of results, which are true if `self` and each code in `codes` have enough
+    /// identical bits to meet the threshold. See [`PolyQuery::is_match()`] for details.
+    ///
+    /// With the `parallel` feature, the codes are matched in parallel using rayon.
+    pub fn is_match_many(&self, codes: &[PolyCode<C>]) -> Result<Vec<bool>, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+
+            crate::parallel::install(|| codes.par_iter().map(|code| self.is_match(code)).collect())
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            codes.iter().map(|code| self.is_match(code)).collect()
+        }
+    }
+
+    /// Returns the smallest fractional Hamming distance between `self` and `code`, over all
+    /// rotations.
+    ///
+    /// Rotations with no visible bits have a distance of zero, like in
+    /// [`plaintext::iris_distance()`](crate::plaintext::iris_distance).
+    #[allow(clippy::cast_precision_loss)]
+    pub fn min_distance(&self, code: &PolyCode<C>) -> Result<f64, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let match_counts = Self::accumulate_inner_products(&self.polys, &code.polys)?;
+        let mask_counts = Self::accumulate_inner_products(&self.masks, &code.masks)?;
+
+        let min_distance = match_counts
+            .into_iter()
+            .zip_eq(mask_counts)
+            // The distance is (t - d) / 2t
+            .map(|(d, t)| {
+                if t == 0 {
+                    0.0
+                } else {
+                    (t - d) as f64 / (2 * t) as f64
+                }
+            })
+            .fold(f64::INFINITY, f64::min);
+
+        Ok(min_distance)
+    }
+
     /// Accumulate the inner products of the polynomials for each block of rows.
     /// The result for each rotation is `D = #equal_bits - #different_bits`.
     fn accumulate_inner_products(
@@ -199,9 +282,10 @@ impl<C: EncodeConf> PolyQuery<C> {
             // Extract the inner products from particular coefficients.
             // Left-most rotation:              sδ - (v - u) - 1
             // Right-most rotation (inclusive): sδ - 1
+            // (Shifted up if blocks are packed.)
             let block_counts = product
                 .iter()
-                .skip(C::ROWS_PER_BLOCK * C::NUM_COLS_AND_PADS - C::EyeConf::ROTATION_COMPARISONS)
+                .skip(C::INNER_PRODUCT_START)
                 .take(C::EyeConf::ROTATION_COMPARISONS)
                 .map(|c| C::coeff_to_int(*c, MatchError::PlaintextOutOfRange))
                 .collect::<Result<Vec<_>, _>>()?;
@@ -219,6 +303,32 @@ impl<C: EncodeConf> PolyQuery<C> {
     }
 }
 
+/// Encode the blocks of rows for polynomial `poly_i`, and pack them into one polynomial.
+///
+/// Each block is encoded by `encode_block(first_row_i)`, then shifted by `slot_offset(slot_i)`,
+/// where `slot_i` is the index of the block within the polynomial.
+fn pack_blocks<C, E, O>(poly_i: usize, mut encode_block: E, slot_offset: O) -> Poly<C::PlainConf>
+where
+    C: EncodeConf,
+    E: FnMut(usize) -> Poly<C::PlainConf>,
+    O: Fn(usize) -> usize,
+{
+    let first_block_i = poly_i * C::BLOCKS_PER_POLY;
+    let end_block_i = min(first_block_i + C::BLOCKS_PER_POLY, C::NUM_BLOCKS);
+
+    let mut poly = Poly::zero();
+
+    for block_i in first_block_i..end_block_i {
+        let first_row_i = block_i * C::ROWS_PER_BLOCK;
+        let mut block = encode_block(first_row_i);
+
+        block.mul_xn(slot_offset(block_i - first_block_i));
+        poly += block;
+    }
+
+    poly
+}
+
 /// Create a mask polynomial from a polynomial of encoded bits.
 fn poly_bits_to_masks<C: EncodeConf>(bits: &Poly<C::PlainConf>) -> Poly<C::PlainConf> {
     let mut masks = Poly::non_canonical_zeroes(C::PlainConf::MAX_POLY_DEGREE);
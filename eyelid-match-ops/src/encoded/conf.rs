@@ -5,6 +5,7 @@ use num_bigint::BigUint;
 
 use crate::{
     encoded::MatchError, iris::conf::IrisConf, primitives::poly::PolyConf, FullBits, MiddleBits,
+    MiddleBitsPacked,
 };
 
 #[cfg(tiny_poly)]
@@ -36,6 +37,40 @@ pub trait EncodeConf {
     /// The number of iris bits in each block.
     const BLOCK_AND_PADS_BIT_LEN: usize = Self::NUM_COLS_AND_PADS * Self::ROWS_PER_BLOCK;
 
+    /// The number of blocks packed into each polynomial.
+    ///
+    /// Packing reduces the number of polynomials (and ciphertexts) for each iris code, but each
+    /// packed block needs extra padding. So packing only helps if blocks are much smaller than
+    /// the polynomial.
+    const BLOCKS_PER_POLY: usize = 1;
+
+    /// The number of polynomials needed to hold all the blocks of the code.
+    const NUM_POLYS: usize = Self::NUM_BLOCKS.div_ceil(Self::BLOCKS_PER_POLY);
+
+    /// The distance between the start of each packed block in a polynomial.
+    /// This padding stops the products of different blocks overlapping the inner products.
+    const PACKED_BLOCK_STRIDE: usize =
+        Self::BLOCK_AND_PADS_BIT_LEN + Self::EyeConf::ROTATION_COMPARISONS - 1;
+
+    /// The index of the first inner product coefficient in the product of two polynomials.
+    ///
+    /// Left-most rotation:              sδ - (v - u) - 1
+    /// Right-most rotation (inclusive): sδ - 1
+    ///
+    /// The inner products of packed blocks are summed, and shifted up by
+    /// `(BLOCKS_PER_POLY - 1) * PACKED_BLOCK_STRIDE`.
+    const INNER_PRODUCT_START: usize = (Self::BLOCKS_PER_POLY - 1) * Self::PACKED_BLOCK_STRIDE
+        + Self::BLOCK_AND_PADS_BIT_LEN
+        - Self::EyeConf::ROTATION_COMPARISONS;
+
+    /// The number of polynomial coefficients needed to hold the packed blocks, plus the
+    /// coefficients needed to keep the inner products clear of the negacyclic wrap-around in
+    /// the polynomial product.
+    const PACKED_POLY_LEN: usize = (Self::BLOCKS_PER_POLY - 1) * Self::PACKED_BLOCK_STRIDE
+        + Self::BLOCK_AND_PADS_BIT_LEN
+        + Self::EyeConf::ROTATION_COMPARISONS
+        - 1;
+
     /// Convert a prime field element to a signed integer, assuming the range from all equal to all different bits.
     /// Out of range values return `Err(err)`.
     fn coeff_to_int(
@@ -94,6 +129,10 @@ const_assert!(
     FullBits::NUM_COLS_AND_PADS * FullBits::ROWS_PER_BLOCK
         <= <<FullBits as EncodeConf>::PlainConf as PolyConf>::MAX_POLY_DEGREE
 );
+// The packed blocks and their inner products must fit in the configured polynomial.
+const_assert!(
+    FullBits::PACKED_POLY_LEN <= <<FullBits as EncodeConf>::PlainConf as PolyConf>::MAX_POLY_DEGREE
+);
 
 impl EncodeConf for MiddleBits {
     type EyeConf = MiddleBits;
@@ -116,6 +155,30 @@ const_assert!(
     MiddleBits::NUM_COLS_AND_PADS * MiddleBits::ROWS_PER_BLOCK
         <= <<MiddleBits as EncodeConf>::PlainConf as PolyConf>::MAX_POLY_DEGREE
 );
+const_assert!(
+    MiddleBits::PACKED_POLY_LEN
+        <= <<MiddleBits as EncodeConf>::PlainConf as PolyConf>::MAX_POLY_DEGREE
+);
+
+impl EncodeConf for MiddleBitsPacked {
+    type EyeConf = MiddleBits;
+    type PlainConf = MiddleRes;
+
+    const ROWS_PER_BLOCK: usize = 1;
+    const BLOCKS_PER_POLY: usize = 6;
+}
+// Packing must use fewer polynomials than the unpacked encoding.
+const_assert!(MiddleBitsPacked::NUM_POLYS < MiddleBits::NUM_POLYS);
+
+const_assert!(MiddleBitsPacked::ROWS_PER_BLOCK <= MiddleBits::COLUMN_LEN);
+const_assert_eq!(
+    MiddleBitsPacked::NUM_BLOCKS * MiddleBitsPacked::ROWS_PER_BLOCK,
+    MiddleBits::COLUMN_LEN
+);
+const_assert!(
+    MiddleBitsPacked::PACKED_POLY_LEN
+        <= <<MiddleBitsPacked as EncodeConf>::PlainConf as PolyConf>::MAX_POLY_DEGREE
+);
 
 #[cfg(tiny_poly)]
 impl EncodeConf for TinyTest {
@@ -139,6 +202,10 @@ mod tiny_test_asserts {
         TinyTest::NUM_COLS_AND_PADS * TinyTest::ROWS_PER_BLOCK
             <= <<TinyTest as EncodeConf>::PlainConf as PolyConf>::MAX_POLY_DEGREE
     );
+    const_assert!(
+        TinyTest::PACKED_POLY_LEN
+            <= <<TinyTest as EncodeConf>::PlainConf as PolyConf>::MAX_POLY_DEGREE
+    );
 }
 
 /// Large resolution polynomial parameters.
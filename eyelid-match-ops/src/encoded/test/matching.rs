@@ -4,7 +4,7 @@ use crate::{
     encoded::{PolyCode, PolyQuery},
     iris::conf::IrisConf,
     plaintext::test::matching::{different, matching},
-    FullBits, MiddleBits, TestBits,
+    EncodeConf, FullBits, MiddleBits, MiddleBitsPacked, TestBits,
 };
 
 /// Check matching test cases.
@@ -37,6 +37,23 @@ fn matching_codes() {
             code: {poly_code:?}"
         );
     }
+
+    for (description, eye_a, mask_a, eye_b, mask_b) in
+        matching::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>().iter()
+    {
+        let poly_query: PolyQuery<MiddleBitsPacked> = PolyQuery::from_plaintext(eye_a, mask_a);
+        let poly_code = PolyCode::from_plaintext(eye_b, mask_b);
+        assert_eq!(poly_query.polys.len(), MiddleBitsPacked::NUM_POLYS);
+        assert_eq!(poly_code.polys.len(), MiddleBitsPacked::NUM_POLYS);
+
+        let res = poly_query.is_match(&poly_code).expect("matching must work");
+        assert!(
+            res,
+            "{description} must match when packed:\n\
+            query: {poly_query:?}\n\
+            code: {poly_code:?}"
+        );
+    }
 }
 
 /// Check different (non-matching) test cases.
@@ -59,6 +76,21 @@ fn different_codes() {
         );
     }
 
+    for (description, eye_a, mask_a, eye_b, mask_b) in
+        different::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>().iter()
+    {
+        let poly_query: PolyQuery<MiddleBitsPacked> = PolyQuery::from_plaintext(eye_a, mask_a);
+        let poly_code = PolyCode::from_plaintext(eye_b, mask_b);
+
+        let res = poly_query.is_match(&poly_code).expect("matching must work");
+        assert!(
+            !res,
+            "{description} must not match when packed:\n\
+            query: {poly_query:?}\n\
+            code: {poly_code:?}"
+        );
+    }
+
     for (description, eye_a, mask_a, eye_b, mask_b) in
         different::<FullBits, { FullBits::STORE_ELEM_LEN }>().iter()
     {
@@ -74,3 +106,34 @@ fn different_codes() {
         );
     }
 }
+
+/// Check that matching a gallery gives the same results as matching each code.
+#[test]
+fn match_many_gallery() {
+    use crate::plaintext::test::gen::{random_iris_code, rotate_not_too_much, visible_iris_mask};
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+    let poly_query: PolyQuery<MiddleBits> = PolyQuery::from_plaintext(&eye, &mask);
+
+    let gallery = [
+        rotate_not_too_much::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye),
+        random_iris_code(),
+        eye,
+    ]
+    .iter()
+    .map(|eye_store| PolyCode::from_plaintext(eye_store, &mask))
+    .collect::<Vec<_>>();
+
+    let results = poly_query
+        .is_match_many(&gallery)
+        .expect("matching must work");
+
+    assert_eq!(results, [true, false, true]);
+    for (poly_code, result) in gallery.iter().zip(results) {
+        assert_eq!(
+            poly_query.is_match(poly_code).expect("matching must work"),
+            result
+        );
+    }
+}
@@ -2,26 +2,51 @@
 
 use itertools::Itertools;
 use num_bigint::{BigInt, BigUint};
-use rand::rngs::ThreadRng;
 
-use crate::iris::conf::IrisConf;
-use crate::primitives::poly::Poly;
+use crate::iris::conf::{IrisConf, MatchThreshold};
+use crate::metrics::MemorySize;
+use crate::primitives::{entropy::EntropySource, poly::Poly};
 use crate::{
     encoded::{MatchError, PolyCode, PolyQuery},
-    primitives::yashe::{Ciphertext, Message, PrivateKey, PublicKey, Yashe},
+    primitives::yashe::{
+        noise::{NoiseStage, NoiseTracker},
+        Ciphertext, KeySwitchKey, Message, PrivateKey, PublicKey, SharedContext, Yashe,
+    },
     EncodeConf, PolyConf, YasheConf,
 };
 
+#[cfg(feature = "rkyv")]
+pub mod archive;
+pub mod converted;
+#[cfg(feature = "decision-log")]
+pub mod decision_log;
+pub mod fusion;
+#[cfg(feature = "key-file")]
+pub mod keyfile;
+pub mod matcher;
+pub mod observer;
+pub mod shares;
+pub mod store;
 pub mod test;
+pub mod timing;
+pub mod wire;
+
+pub use crate::iris::conf::FusionPolicy;
+pub use converted::{ConvertedPolyCode, ConvertedPolyQuery};
+pub use matcher::{EncryptedMatchOutcome, EncryptedMatcher, EncryptedMatcherBuilder};
+pub use observer::{MatchDistance, MatchEvent, MatchObserver};
+pub use timing::{Stage, StageTimings};
 
 /// An encrypted iris code, encoded in polynomials. To be stored in the database.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct EncryptedPolyCode<C: EncodeConf>
 where
     C::PlainConf: YasheConf,
     <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
 {
-    /// The encrypted polynomials, encoding data, one block of rows each. Storage variant.
+    /// The encrypted polynomials, encoding data, one or more blocks of rows each. Storage variant.
     data: Vec<Ciphertext<C::PlainConf>>,
     /// The encrypted mask polynomials.
     masks: Vec<Ciphertext<C::PlainConf>>,
@@ -29,17 +54,39 @@ where
 
 /// An encrypted iris code, encoded in polynomials. To be matched against EncryptedPolyCode.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct EncryptedPolyQuery<C: EncodeConf>
 where
     C::PlainConf: YasheConf,
     <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
 {
-    /// The encrypted polynomials, encoding data, one block of rows each. Query variant.
+    /// The encrypted polynomials, encoding data, one or more blocks of rows each. Query variant.
     data: Vec<Ciphertext<C::PlainConf>>,
     /// The encrypted mask polynomials.
     masks: Vec<Ciphertext<C::PlainConf>>,
 }
 
+impl<C: EncodeConf> MemorySize for EncryptedPolyCode<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn heap_size(&self) -> usize {
+        self.data.heap_size() + self.masks.heap_size()
+    }
+}
+
+impl<C: EncodeConf> MemorySize for EncryptedPolyQuery<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn heap_size(&self) -> usize {
+        self.data.heap_size() + self.masks.heap_size()
+    }
+}
+
 /// -1 is encoded as Q-1, so we need to convert it to work modulo T.
 /// Given a vector of polynomials, for each coefficient, if it is larger than Q-1/2 then add T.
 /// Otherwise do nothing.
@@ -61,46 +108,80 @@ where
     }
 }
 
+/// Encrypts each polynomial in `polys` separately.
+///
+/// With the `parallel` feature, the polynomials are encrypted on rayon threads. Each polynomial
+/// uses its own RNG, seeded from `rng`, so all the randomness still comes from `rng`.
+fn encrypt_polys<C: YasheConf, E: EntropySource + ?Sized>(
+    ctx: Yashe<C>,
+    polys: Vec<Poly<C>>,
+    public_key: &PublicKey<C>,
+    rng: &mut E,
+) -> Vec<Ciphertext<C>>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+        use rayon::prelude::*;
+
+        let rngs = polys
+            .iter()
+            .map(|_| ChaCha20Rng::from_rng(&mut *rng).expect("entropy source must not fail"))
+            .collect_vec();
+
+        crate::parallel::install(|| {
+            polys
+                .into_par_iter()
+                .zip_eq(rngs)
+                .map(|(p, mut rng)| ctx.encrypt(Message { m: p }, public_key, &mut rng))
+                .collect()
+        })
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        polys
+            .into_iter()
+            .map(|p| ctx.encrypt(Message { m: p }, public_key, rng))
+            .collect()
+    }
+}
+
 impl<C: EncodeConf> EncryptedPolyCode<C>
 where
     C::PlainConf: YasheConf,
     <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
 {
     /// Convert and Encrypt a PolyCode by encrypting each polynomial.
-    pub fn convert_and_encrypt_code(
+    pub fn convert_and_encrypt_code<E: EntropySource + ?Sized>(
         ctx: Yashe<C::PlainConf>,
-        mut code: PolyCode<C>,
+        code: PolyCode<C>,
         public_key: &PublicKey<C::PlainConf>,
-        rng: &mut ThreadRng,
+        rng: &mut E,
     ) -> Self
     where
         C: EncodeConf,
     {
-        convert_negative_coefficients::<C>(&mut code.polys);
-        EncryptedPolyCode::encrypt_code(ctx, code, public_key, rng)
+        EncryptedPolyCode::encrypt_code(ctx, ConvertedPolyCode::new(code), public_key, rng)
     }
 
     /// Encrypts the message m encoded as a PolyCode, which is done by encrypting
     /// each component of the encoding separately, and returning a SimpleHammingEncodingCiphertext.
-    pub fn encrypt_code(
+    pub fn encrypt_code<E: EntropySource + ?Sized>(
         ctx: Yashe<C::PlainConf>,
-        code: PolyCode<C>,
+        code: ConvertedPolyCode<C>,
         public_key: &PublicKey<C::PlainConf>,
-        rng: &mut ThreadRng,
+        rng: &mut E,
     ) -> Self
     where
         C: EncodeConf,
     {
-        let data = code
-            .polys
-            .into_iter()
-            .map(|p| ctx.encrypt(Message::<C::PlainConf> { m: p }, public_key, rng))
-            .collect();
-        let masks = code
-            .masks
-            .into_iter()
-            .map(|p| ctx.encrypt(Message::<C::PlainConf> { m: p }, public_key, rng))
-            .collect();
+        let code = code.into_inner();
+        let data = encrypt_polys(ctx, code.polys, public_key, rng);
+        let masks = encrypt_polys(ctx, code.masks, public_key, rng);
         Self { data, masks }
     }
 }
@@ -112,37 +193,29 @@ where
     BigUint: From<<<C as EncodeConf>::PlainConf as PolyConf>::Coeff>,
 {
     /// Encrypt a PolyQuery by encrypting each polynomial.
-    pub fn convert_and_encrypt_query(
+    pub fn convert_and_encrypt_query<E: EntropySource + ?Sized>(
         ctx: Yashe<C::PlainConf>,
-        mut query: PolyQuery<C>,
+        query: PolyQuery<C>,
         public_key: &PublicKey<C::PlainConf>,
-        rng: &mut ThreadRng,
+        rng: &mut E,
     ) -> Self {
-        convert_negative_coefficients::<C>(&mut query.polys);
-        EncryptedPolyQuery::encrypt_query(ctx, query, public_key, rng)
+        EncryptedPolyQuery::encrypt_query(ctx, ConvertedPolyQuery::new(query), public_key, rng)
     }
 
     /// Encrypts the message m encoded as a PolyQuery, which is done by encrypting
     /// each component of the encoding separately, and returning a SimpleHammingEncodingCiphertext.
-    pub fn encrypt_query(
+    pub fn encrypt_query<E: EntropySource + ?Sized>(
         ctx: Yashe<C::PlainConf>,
-        query: PolyQuery<C>,
+        query: ConvertedPolyQuery<C>,
         public_key: &PublicKey<C::PlainConf>,
-        rng: &mut ThreadRng,
+        rng: &mut E,
     ) -> Self
     where
         C: EncodeConf,
     {
-        let data = query
-            .polys
-            .into_iter()
-            .map(|p| ctx.encrypt(Message::<C::PlainConf> { m: p }, public_key, rng))
-            .collect();
-        let masks = query
-            .masks
-            .into_iter()
-            .map(|p| ctx.encrypt(Message::<C::PlainConf> { m: p }, public_key, rng))
-            .collect();
+        let query = query.into_inner();
+        let data = encrypt_polys(ctx, query.polys, public_key, rng);
+        let masks = encrypt_polys(ctx, query.masks, public_key, rng);
         Self { data, masks }
     }
 
@@ -156,18 +229,406 @@ where
     where
         BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
     {
-        let match_counts =
-            Self::accumulate_inner_products(ctx, private_key, &self.data, &code.data)?;
-        let mask_counts =
-            Self::accumulate_inner_products(ctx, private_key, &self.masks, &code.masks)?;
+        let decrypt_product = |product| Ok(ctx.decrypt_mul(product, private_key));
+
+        self.is_match_helper(
+            ctx,
+            decrypt_product,
+            code,
+            MatchThreshold::from_conf::<C::EyeConf>(),
+            false,
+        )
+    }
+
+    /// Returns true if `self` and `code` have enough identical bits to meet `threshold`,
+    /// rather than the threshold in the configuration.
+    pub fn is_match_with_threshold(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        code: &EncryptedPolyCode<C>,
+        threshold: MatchThreshold,
+    ) -> Result<bool, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let decrypt_product = |product| Ok(ctx.decrypt_mul(product, private_key));
+
+        self.is_match_helper(ctx, decrypt_product, code, threshold, false)
+    }
+
+    /// Returns true if `self` and `code` have enough identical bits to meet `threshold`, only
+    /// checking rotations of up to `rotation_limit` columns to the left and right.
+    ///
+    /// Limits larger than the configured [`IrisConf::ROTATION_LIMIT`] check every rotation.
+    pub fn is_match_with_rotation_limit(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        code: &EncryptedPolyCode<C>,
+        threshold: MatchThreshold,
+        rotation_limit: usize,
+    ) -> Result<bool, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let decrypt_product = |product| Ok(ctx.decrypt_mul(product, private_key));
+        let is_match = self
+            .rotation_limited_counts(ctx, decrypt_product, code, rotation_limit)?
+            .into_iter()
+            .any(|(d, t)| threshold.is_encoded_match(d, t));
+
+        Ok(is_match)
+    }
+
+    /// Returns true if `self` and `code` have enough identical bits to meet `threshold`, only
+    /// checking rotations of up to `rotation_limit` columns to the left and right. Decrypts
+    /// using the cached private key square in `shared`.
+    ///
+    /// If `with_distance` is true, also returns the distance of the closest rotation.
+    /// Distances reveal information about the codes, so they are only calculated for observers
+    /// which explicitly request them.
+    ///
+    /// The products are all multiplied, then all decrypted, so the time taken by the multiply,
+    /// decrypt, and decide stages can be returned separately.
+    pub(crate) fn match_with_shared_context(
+        &self,
+        shared: &SharedContext<C::PlainConf>,
+        code: &EncryptedPolyCode<C>,
+        threshold: MatchThreshold,
+        rotation_limit: usize,
+        with_distance: bool,
+    ) -> Result<(bool, Option<MatchDistance>, StageTimings), MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let mut timings = StageTimings::default();
+
+        let products = timings.time(Stage::Multiply, || {
+            self.encrypted_distances(shared.ctx(), code)
+        });
+        let decrypted_products =
+            timings.time(Stage::Decrypt, || shared.decrypt_mul_batch(&products));
+
+        let (is_match, distance) = timings.time(Stage::Decide, || {
+            let (data_products, mask_products) = decrypted_products.split_at(self.data.len());
+            let match_counts = Self::accumulate_decrypted_products(data_products)?;
+            let mask_counts = Self::accumulate_decrypted_products(mask_products)?;
+            let counts = Self::limit_rotations(match_counts, mask_counts, rotation_limit);
+
+            let is_match = counts
+                .iter()
+                .any(|(d, t)| threshold.is_encoded_match(*d, *t));
+            let distance = with_distance.then(|| {
+                counts
+                    .into_iter()
+                    .map(|(d, t)| MatchDistance::from_encoded_counts(d, t))
+                    .min()
+                    .unwrap_or_default()
+            });
+
+            Ok::<_, MatchError>((is_match, distance))
+        })?;
+
+        Ok((is_match, distance, timings))
+    }
+
+    /// Returns the per-rotation match and mask counts of `self` and `code`, for rotations of up
+    /// to `rotation_limit` columns to the left and right.
+    fn rotation_limited_counts<F>(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        decrypt_product: F,
+        code: &EncryptedPolyCode<C>,
+        rotation_limit: usize,
+    ) -> Result<Vec<(i64, i64)>, MatchError>
+    where
+        F: FnMut(Ciphertext<C::PlainConf>) -> Result<Message<C::PlainConf>, MatchError>,
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let (match_counts, mask_counts) = self.decrypted_counts(ctx, decrypt_product, code)?;
+
+        Ok(Self::limit_rotations(
+            match_counts,
+            mask_counts,
+            rotation_limit,
+        ))
+    }
+
+    /// Returns the per-rotation match and mask counts, for rotations of up to `rotation_limit`
+    /// columns to the left and right.
+    fn limit_rotations(
+        match_counts: Vec<i64>,
+        mask_counts: Vec<i64>,
+        rotation_limit: usize,
+    ) -> Vec<(i64, i64)> {
+        // Counts are ordered from the left-most to the right-most rotation.
+        let skip = C::EyeConf::ROTATION_LIMIT.saturating_sub(rotation_limit);
+
+        match_counts
+            .into_iter()
+            .zip_eq(mask_counts)
+            .skip(skip)
+            .take(C::EyeConf::ROTATION_COMPARISONS - 2 * skip)
+            .collect()
+    }
+
+    /// Returns a list of results, which are true if `self` and each code in `codes` have enough
+    /// identical bits to meet the threshold.
+    ///
+    /// All the block products are decrypted as a batch using [`Yashe::decrypt_mul_batch()`],
+    /// which is faster than matching each code separately. But all the products are kept in
+    /// memory, so large galleries should be split into multiple calls.
+    pub fn is_match_many(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        codes: &[EncryptedPolyCode<C>],
+    ) -> Result<Vec<bool>, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let threshold = MatchThreshold::from_conf::<C::EyeConf>();
+
+        // Multiply the encrypted polynomials for every code: data products, then mask products.
+        let products = codes
+            .iter()
+            .flat_map(|code| self.encrypted_distances(ctx, code))
+            .collect_vec();
+        let decrypted_products = ctx.decrypt_mul_batch(&products, private_key);
+
+        decrypted_products
+            .chunks(self.data.len() + self.masks.len())
+            .map(|code_products| {
+                let (data_products, mask_products) = code_products.split_at(self.data.len());
+
+                let match_counts = Self::accumulate_decrypted_products(data_products)?;
+                let mask_counts = Self::accumulate_decrypted_products(mask_products)?;
+
+                Ok(match_counts
+                    .into_iter()
+                    .zip_eq(mask_counts)
+                    .any(|(d, t)| threshold.is_encoded_match(d, t)))
+            })
+            .collect()
+    }
+
+    /// Returns the encrypted products of `self` and `code`, without decrypting them.
+    ///
+    /// The data products for each block come first, followed by the mask products for each block.
+    /// Each product encrypts the per-rotation inner products of that block, starting at
+    /// coefficient [`EncodeConf::INNER_PRODUCT_START`]. The products must be decrypted using
+    /// [`Yashe::decrypt_mul()`], because they are encrypted under the private key squared.
+    ///
+    /// This allows other protocols, like MPC or zero-knowledge proofs, to make the match decision.
+    pub fn encrypted_distances(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        code: &EncryptedPolyCode<C>,
+    ) -> Vec<Ciphertext<C::PlainConf>> {
+        self.data
+            .iter()
+            .zip_eq(code.data.iter())
+            .chain(self.masks.iter().zip_eq(code.masks.iter()))
+            .map(|(a, b)| ctx.ciphertext_mul(a.clone(), b.clone()))
+            .collect()
+    }
+
+    /// Returns true if `self` and `code` have enough identical bits to meet the threshold.
+    ///
+    /// Unlike [`EncryptedPolyQuery::is_match()`], every rotation is checked, and the counts are
+    /// combined across rotations without branching. This avoids leaking whether there was a
+    /// match, or which rotation matched, through an early return.
+    ///
+    /// Only that combination is branch-free. Decryption, and the decoding of each decrypted
+    /// coefficient into a signed count, use variable-time arithmetic and branch on the value of
+    /// each coefficient. Decoding also returns early if a coefficient is out of range.
+    pub fn is_match_constant_time(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        code: &EncryptedPolyCode<C>,
+    ) -> Result<bool, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let decrypt_product = |product| Ok(ctx.decrypt_mul(product, private_key));
+
+        self.is_match_helper(
+            ctx,
+            decrypt_product,
+            code,
+            MatchThreshold::from_conf::<C::EyeConf>(),
+            true,
+        )
+    }
+
+    /// Returns true if `self` and `code` have enough identical bits to meet the threshold.
+    ///
+    /// Records the estimated noise of each ciphertext and product in `noise_tracker`.
+    /// Returns [`MatchError::NoiseBudgetExhausted`] if the tracker is configured to error, and
+    /// the noise budget is nearly exhausted.
+    pub fn is_match_with_noise_tracker(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        code: &EncryptedPolyCode<C>,
+        noise_tracker: &mut NoiseTracker,
+    ) -> Result<bool, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let budget_bits = C::PlainConf::noise_budget_bits();
+
+        for c in self
+            .data
+            .iter()
+            .chain(self.masks.iter())
+            .chain(code.data.iter())
+            .chain(code.masks.iter())
+        {
+            noise_tracker
+                .record(
+                    NoiseStage::Encrypt,
+                    ctx.noise_bits(c, private_key),
+                    budget_bits,
+                )
+                .map_err(MatchError::NoiseBudgetExhausted)?;
+        }
+
+        let decrypt_product = |product| {
+            noise_tracker
+                .record(
+                    NoiseStage::Multiply,
+                    ctx.mul_noise_bits(&product, private_key),
+                    budget_bits,
+                )
+                .map_err(MatchError::NoiseBudgetExhausted)?;
+
+            Ok(ctx.decrypt_mul(product, private_key))
+        };
+
+        self.is_match_helper(
+            ctx,
+            decrypt_product,
+            code,
+            MatchThreshold::from_conf::<C::EyeConf>(),
+            false,
+        )
+    }
+
+    /// Returns true if `self` and `code` have enough identical bits to meet the threshold, when
+    /// `self` and `code` are encrypted under different keys.
+    ///
+    /// `self` is encrypted under the `from` key, and `code` is encrypted under the `to` key.
+    /// `private_key` is the `to` private key, and `key_switch_key` is generated by
+    /// [`Yashe::generate_key_switch_key()`] from the `from` private key and `to` public key.
+    pub fn is_match_key_switched(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        key_switch_key: &KeySwitchKey<C::PlainConf>,
+        code: &EncryptedPolyCode<C>,
+    ) -> Result<bool, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let decrypt_product =
+            |product| Ok(ctx.decrypt(ctx.key_switch(product, key_switch_key), private_key));
+
+        self.is_match_helper(
+            ctx,
+            decrypt_product,
+            code,
+            MatchThreshold::from_conf::<C::EyeConf>(),
+            false,
+        )
+    }
+
+    /// Returns true if `self` and `code` have enough identical bits to meet the threshold, when
+    /// `self` and `code` are encrypted under different keys.
+    ///
+    /// Records the estimated noise of each `code` ciphertext and each key switched product in
+    /// `noise_tracker`. The noise in `self` can't be estimated, because it is encrypted under
+    /// the `from` key. Returns [`MatchError::NoiseBudgetExhausted`] if the tracker is configured
+    /// to error, and the noise budget is nearly exhausted.
+    ///
+    /// See [`Self::is_match_key_switched()`] for details of the keys.
+    pub fn is_match_key_switched_with_noise_tracker(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        key_switch_key: &KeySwitchKey<C::PlainConf>,
+        code: &EncryptedPolyCode<C>,
+        noise_tracker: &mut NoiseTracker,
+    ) -> Result<bool, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let budget_bits = C::PlainConf::noise_budget_bits();
+
+        for c in code.data.iter().chain(code.masks.iter()) {
+            noise_tracker
+                .record(
+                    NoiseStage::Encrypt,
+                    ctx.noise_bits(c, private_key),
+                    budget_bits,
+                )
+                .map_err(MatchError::NoiseBudgetExhausted)?;
+        }
+
+        let decrypt_product = |product| {
+            let switched = ctx.key_switch(product, key_switch_key);
+
+            noise_tracker
+                .record(
+                    NoiseStage::KeySwitch,
+                    ctx.noise_bits(&switched, private_key),
+                    budget_bits,
+                )
+                .map_err(MatchError::NoiseBudgetExhausted)?;
+
+            Ok(ctx.decrypt(switched, private_key))
+        };
+
+        self.is_match_helper(
+            ctx,
+            decrypt_product,
+            code,
+            MatchThreshold::from_conf::<C::EyeConf>(),
+            false,
+        )
+    }
+
+    /// Returns true if `self` and `code` have enough identical bits to meet `threshold`,
+    /// using `decrypt_product` to decrypt each block product.
+    ///
+    /// If `constant_time` is true, all rotations are checked, and the result is combined without
+    /// branching. Otherwise, returns as soon as a matching rotation is found.
+    fn is_match_helper<F>(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        decrypt_product: F,
+        code: &EncryptedPolyCode<C>,
+        threshold: MatchThreshold,
+        constant_time: bool,
+    ) -> Result<bool, MatchError>
+    where
+        F: FnMut(Ciphertext<C::PlainConf>) -> Result<Message<C::PlainConf>, MatchError>,
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let (match_counts, mask_counts) = self.decrypted_counts(ctx, decrypt_product, code)?;
+
+        if constant_time {
+            return Ok(Self::is_any_rotation_match_constant_time(
+                &match_counts,
+                &mask_counts,
+                threshold,
+            ));
+        }
 
         for (d, t) in match_counts.into_iter().zip_eq(mask_counts.into_iter()) {
-            // Match if the Hamming distance is less than a percentage threshold:
-            // (t - d) / 2t <= x%
-            #[allow(clippy::cast_possible_wrap)]
-            if (t - d) * (C::EyeConf::MATCH_DENOMINATOR as i64)
-                <= 2 * t * (C::EyeConf::MATCH_NUMERATOR as i64)
-            {
+            if threshold.is_encoded_match(d, t) {
                 return Ok(true);
             }
         }
@@ -175,14 +636,78 @@ where
         Ok(false)
     }
 
+    /// Returns the per-rotation match and mask counts of `self` and `code`, using
+    /// `decrypt_product` to decrypt each block product.
+    fn decrypted_counts<F>(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        mut decrypt_product: F,
+        code: &EncryptedPolyCode<C>,
+    ) -> Result<(Vec<i64>, Vec<i64>), MatchError>
+    where
+        F: FnMut(Ciphertext<C::PlainConf>) -> Result<Message<C::PlainConf>, MatchError>,
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let match_counts =
+            Self::accumulate_inner_products(ctx, &mut decrypt_product, &self.data, &code.data)?;
+        let mask_counts =
+            Self::accumulate_inner_products(ctx, &mut decrypt_product, &self.masks, &code.masks)?;
+
+        Ok((match_counts, mask_counts))
+    }
+
+    /// Returns true if any rotation meets `threshold`, checking every rotation without
+    /// branching on the counts.
+    fn is_any_rotation_match_constant_time(
+        match_counts: &[i64],
+        mask_counts: &[i64],
+        threshold: MatchThreshold,
+    ) -> bool {
+        let mut is_match = 0_i64;
+
+        for (d, t) in match_counts.iter().zip_eq(mask_counts.iter()) {
+            let margin = threshold.encoded_margin(*d, *t);
+
+            // The sign bit is 1 if the margin is negative, and 0 if it is a match.
+            is_match |= (margin >> (i64::BITS - 1)) + 1;
+        }
+
+        // Stop the compiler from turning the bitwise operations back into an early return.
+        std::hint::black_box(is_match) != 0
+    }
+
     /// Similarly to function `accumulate_inner_products`, but return a list containing the products, such that
     /// we can extract inner products later.
-    fn accumulate_inner_products(
+    fn accumulate_inner_products<F>(
         ctx: Yashe<C::PlainConf>,
-        private_key: &PrivateKey<C::PlainConf>,
+        decrypt_product: &mut F,
         a_polys: &[Ciphertext<C::PlainConf>],
         b_polys: &[Ciphertext<C::PlainConf>],
     ) -> Result<Vec<i64>, MatchError>
+    where
+        F: FnMut(Ciphertext<C::PlainConf>) -> Result<Message<C::PlainConf>, MatchError>,
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let decrypted_products = a_polys
+            .iter()
+            .zip_eq(b_polys.iter())
+            .map(|(a, b)| {
+                // Multiply the encrypted polynomials, which will yield encrypted inner products
+                // by the homomorphic property of the scheme.
+                let product = ctx.ciphertext_mul(a.clone(), b.clone());
+                // Decrypt to get the inner products.
+                decrypt_product(product)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::accumulate_decrypted_products(&decrypted_products)
+    }
+
+    /// Extract the inner products from each decrypted block product, and sum them for each
+    /// rotation.
+    fn accumulate_decrypted_products(
+        decrypted_products: &[Message<C::PlainConf>],
+    ) -> Result<Vec<i64>, MatchError>
     where
         BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
     {
@@ -190,43 +715,19 @@ where
         // compute T/2 as a big int
         let t_div_2 = BigInt::from(C::PlainConf::T / 2);
 
-        for (a, b) in a_polys.iter().zip_eq(b_polys.iter()) {
-            // Multiply the encrypted polynomials, which will yield encrypted inner products
-            // by the homomorphic property of the scheme.
-            let product = ctx.ciphertext_mul(a.clone(), b.clone());
-            // Decrypt to get the inner products.
-            let decrypted_product = ctx.decrypt_mul(product, private_key);
-
+        for decrypted_product in decrypted_products {
             // TODO: make the comparisons private
             // Extract the inner products from particular coefficients.
             // Left-most rotation:              sδ - (v - u) - 1
             // Right-most rotation (inclusive): sδ - 1
+            // (Shifted up if blocks are packed.)
             let block_counts = decrypted_product
                 .m
                 .iter()
-                .skip(C::ROWS_PER_BLOCK * C::NUM_COLS_AND_PADS - C::EyeConf::ROTATION_COMPARISONS)
+                .skip(C::INNER_PRODUCT_START)
                 .take(C::EyeConf::ROTATION_COMPARISONS)
-                .map(|c| {
-                    let coeff_res = C::PlainConf::coeff_as_big_int(*c);
-                    // When the coefficient is negative, we need to convert it to work modulo T.
-                    // Concretely, we temporarily negate the coefficient in order to get a small value
-                    // (since negative elements modulo Q are big and can't be converted to i64), then we
-                    // negate again to return the output.
-                    //
-                    // TODO: return a new MatchError variant rather than panicking using expect()
-                    if coeff_res > t_div_2 {
-                        let result = i64::try_from(BigUint::from(C::PlainConf::big_int_as_coeff(
-                            C::PlainConf::T - coeff_res,
-                        )))
-                        .expect("Could not convert a negative element to i64");
-                        Ok(-result)
-                    } else {
-                        let result =
-                            i64::try_from(BigUint::from(C::PlainConf::big_int_as_coeff(coeff_res)))
-                                .expect("Could not convert a positive from big int to i64");
-                        Ok(result)
-                    }
-                })
+                .enumerate()
+                .map(|(rotation, c)| Self::decrypted_coeff_to_int(rotation, *c, &t_div_2))
                 .collect::<Result<Vec<_>, _>>()?;
 
             // Accumulate the counts from all blocks, grouped by rotation.
@@ -240,4 +741,38 @@ where
 
         Ok(counts)
     }
+
+    /// Convert a decrypted coefficient modulo T to a signed integer count.
+    /// Out of range values return [`MatchError::CoefficientOutOfRange`].
+    fn decrypted_coeff_to_int(
+        rotation: usize,
+        c: <C::PlainConf as PolyConf>::Coeff,
+        t_div_2: &BigInt,
+    ) -> Result<i64, MatchError>
+    where
+        BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+    {
+        let out_of_range = || MatchError::CoefficientOutOfRange {
+            rotation,
+            value: C::PlainConf::coeff_as_u128(c),
+        };
+
+        let coeff_res = C::PlainConf::coeff_as_big_int(c);
+
+        // When the coefficient is negative, we need to convert it to work modulo T.
+        // Concretely, we temporarily negate the coefficient in order to get a small value
+        // (since negative elements modulo Q are big and can't be converted to i64), then we
+        // negate again to return the output.
+        if coeff_res > *t_div_2 {
+            let result = i64::try_from(BigUint::from(C::PlainConf::big_int_as_coeff(
+                C::PlainConf::T - coeff_res,
+            )))
+            .map_err(|_| out_of_range())?;
+            Ok(-result)
+        } else {
+            let result = i64::try_from(BigUint::from(C::PlainConf::big_int_as_coeff(coeff_res)))
+                .map_err(|_| out_of_range())?;
+            Ok(result)
+        }
+    }
 }
@@ -0,0 +1,191 @@
+//! Zero-copy archived encrypted codes, using [`rkyv`].
+//!
+//! Archived codes can be accessed directly from a byte buffer, such as a memory-mapped gallery
+//! file. Queries are matched against an [`ArchivedStoredPolyCode`] one ciphertext at a time, so
+//! the whole code is never copied into owned vectors.
+
+use itertools::Itertools;
+use num_bigint::BigUint;
+use rkyv::{rancor, util::AlignedVec, Archive, Deserialize, Serialize};
+
+use crate::{
+    encrypted::{store::StoreError, EncryptedPolyCode, EncryptedPolyQuery},
+    iris::conf::MatchThreshold,
+    primitives::{
+        poly::Poly,
+        yashe::{Ciphertext, Message, PrivateKey, Yashe},
+    },
+    EncodeConf, PolyConf, YasheConf,
+};
+
+/// The archivable form of a [`Ciphertext`].
+#[derive(Archive, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StoredCiphertext {
+    /// The ciphertext polynomial coefficients, in canonical form.
+    pub coeffs: Vec<u128>,
+}
+
+/// The archivable form of an [`EncryptedPolyCode`].
+#[derive(Archive, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StoredPolyCode {
+    /// The encrypted data polynomials.
+    pub data: Vec<StoredCiphertext>,
+    /// The encrypted mask polynomials.
+    pub masks: Vec<StoredCiphertext>,
+}
+
+impl<C: YasheConf> From<&Ciphertext<C>> for StoredCiphertext
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn from(ciphertext: &Ciphertext<C>) -> Self {
+        Self {
+            coeffs: ciphertext
+                .c
+                .coeffs
+                .iter()
+                .map(|coeff| C::coeff_as_u128(*coeff))
+                .collect(),
+        }
+    }
+}
+
+impl<C: EncodeConf> From<&EncryptedPolyCode<C>> for StoredPolyCode
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn from(code: &EncryptedPolyCode<C>) -> Self {
+        Self {
+            data: code.data.iter().map(StoredCiphertext::from).collect(),
+            masks: code.masks.iter().map(StoredCiphertext::from).collect(),
+        }
+    }
+}
+
+impl ArchivedStoredCiphertext {
+    /// Copies the archived coefficients into a new [`Ciphertext`].
+    ///
+    /// Returns [`StoreError::InvalidEncoding`] if the ciphertext doesn't fit the parameters in `C`.
+    pub fn to_ciphertext<C: YasheConf>(&self) -> Result<Ciphertext<C>, StoreError>
+    where
+        C::Coeff: From<u128> + From<u64> + From<i64>,
+    {
+        if self.coeffs.len() > C::MAX_POLY_DEGREE {
+            return Err(StoreError::InvalidEncoding);
+        }
+
+        let coeffs = self
+            .coeffs
+            .iter()
+            .map(|coeff| {
+                let coeff = coeff.to_native();
+
+                if coeff >= C::modulus_as_u128() {
+                    return Err(StoreError::InvalidEncoding);
+                }
+
+                Ok(C::Coeff::from(coeff))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Ciphertext {
+            c: Poly::from_coefficients_vec(coeffs),
+        })
+    }
+}
+
+impl ArchivedStoredPolyCode {
+    /// Checks that `bytes` contains a valid archived code, and returns a reference to it.
+    ///
+    /// `bytes` must be aligned to 16 bytes, for example by using an [`AlignedVec`] or a
+    /// memory-mapped file.
+    pub fn access(bytes: &[u8]) -> Result<&Self, StoreError> {
+        rkyv::access::<Self, rancor::Error>(bytes).map_err(|_| StoreError::InvalidEncoding)
+    }
+
+    /// Copies the archived code into a new [`EncryptedPolyCode`].
+    pub fn to_code<C: EncodeConf>(&self) -> Result<EncryptedPolyCode<C>, StoreError>
+    where
+        C::PlainConf: YasheConf,
+        <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+    {
+        if self.data.len() != C::NUM_POLYS || self.masks.len() != C::NUM_POLYS {
+            return Err(StoreError::InvalidEncoding);
+        }
+
+        let to_ciphertexts = |ciphertexts: &[ArchivedStoredCiphertext]| {
+            ciphertexts
+                .iter()
+                .map(ArchivedStoredCiphertext::to_ciphertext::<C::PlainConf>)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(EncryptedPolyCode {
+            data: to_ciphertexts(&self.data)?,
+            masks: to_ciphertexts(&self.masks)?,
+        })
+    }
+}
+
+impl<C: EncodeConf> EncryptedPolyCode<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Encodes `self` as an aligned archive, which can be accessed using
+    /// [`ArchivedStoredPolyCode::access()`].
+    pub fn to_archive(&self) -> Result<AlignedVec, StoreError> {
+        rkyv::to_bytes::<rancor::Error>(&StoredPolyCode::from(self))
+            .map_err(|_| StoreError::InvalidEncoding)
+    }
+}
+
+impl<C: EncodeConf> EncryptedPolyQuery<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+    BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+{
+    /// Returns true if `self` and the archived `code` have enough identical bits to meet the
+    /// threshold.
+    ///
+    /// Each archived ciphertext is only copied when it is multiplied.
+    pub fn is_match_archived(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        code: &ArchivedStoredPolyCode,
+    ) -> Result<bool, StoreError> {
+        if code.data.len() != self.data.len() || code.masks.len() != self.masks.len() {
+            return Err(StoreError::InvalidEncoding);
+        }
+
+        let decrypt_products =
+            |a_polys: &[Ciphertext<C::PlainConf>], b_polys: &[ArchivedStoredCiphertext]| {
+                a_polys
+                    .iter()
+                    .zip_eq(b_polys.iter())
+                    .map(|(a, b)| {
+                        let product =
+                            ctx.ciphertext_mul(a.clone(), b.to_ciphertext::<C::PlainConf>()?);
+                        Ok(ctx.decrypt_mul(product, private_key))
+                    })
+                    .collect::<Result<Vec<Message<C::PlainConf>>, StoreError>>()
+            };
+
+        let match_counts =
+            Self::accumulate_decrypted_products(&decrypt_products(&self.data, &code.data)?)
+                .map_err(StoreError::Match)?;
+        let mask_counts =
+            Self::accumulate_decrypted_products(&decrypt_products(&self.masks, &code.masks)?)
+                .map_err(StoreError::Match)?;
+
+        let threshold = MatchThreshold::from_conf::<C::EyeConf>();
+
+        Ok(match_counts
+            .into_iter()
+            .zip_eq(mask_counts)
+            .any(|(d, t)| threshold.is_encoded_match(d, t)))
+    }
+}
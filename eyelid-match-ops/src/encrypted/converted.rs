@@ -0,0 +1,115 @@
+//! Polynomial-encoded iris codes which are ready to be encrypted.
+//!
+//! Encryption works modulo T, but encoded codes represent -1 as Q-1. Codes must be converted
+//! using [`convert_negative_coefficients()`] before they are encrypted, otherwise matching
+//! silently gives the wrong results. These types make it impossible to encrypt an unconverted
+//! code.
+
+use crate::{
+    encoded::{PolyCode, PolyQuery},
+    encrypted::convert_negative_coefficients,
+    primitives::poly::Poly,
+    EncodeConf, PolyConf, YasheConf,
+};
+
+/// A [`PolyCode`] with its negative coefficients converted to work modulo T.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConvertedPolyCode<C: EncodeConf>(PolyCode<C>);
+
+/// A [`PolyQuery`] with its negative coefficients converted to work modulo T.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConvertedPolyQuery<C: EncodeConf>(PolyQuery<C>);
+
+impl<C: EncodeConf> ConvertedPolyCode<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Converts the negative coefficients in `code`.
+    pub fn new(mut code: PolyCode<C>) -> Self {
+        convert_negative_coefficients::<C>(&mut code.polys);
+        Self(code)
+    }
+
+    /// Wraps a `code` which has already been converted.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if `code` has any unconverted negative coefficients.
+    pub fn from_converted(code: PolyCode<C>) -> Self {
+        debug_assert!(
+            is_converted::<C>(&code.polys),
+            "code must be converted before it is encrypted"
+        );
+        Self(code)
+    }
+
+    /// Returns the converted code.
+    pub fn into_inner(self) -> PolyCode<C> {
+        self.0
+    }
+}
+
+impl<C: EncodeConf> ConvertedPolyQuery<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Converts the negative coefficients in `query`.
+    pub fn new(mut query: PolyQuery<C>) -> Self {
+        convert_negative_coefficients::<C>(&mut query.polys);
+        Self(query)
+    }
+
+    /// Wraps a `query` which has already been converted.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if `query` has any unconverted negative coefficients.
+    pub fn from_converted(query: PolyQuery<C>) -> Self {
+        debug_assert!(
+            is_converted::<C>(&query.polys),
+            "query must be converted before it is encrypted"
+        );
+        Self(query)
+    }
+
+    /// Returns the converted query.
+    pub fn into_inner(self) -> PolyQuery<C> {
+        self.0
+    }
+}
+
+impl<C: EncodeConf> From<PolyCode<C>> for ConvertedPolyCode<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn from(code: PolyCode<C>) -> Self {
+        Self::new(code)
+    }
+}
+
+impl<C: EncodeConf> From<PolyQuery<C>> for ConvertedPolyQuery<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn from(query: PolyQuery<C>) -> Self {
+        Self::new(query)
+    }
+}
+
+/// Returns true if none of the coefficients in `polys` are negative modulo Q.
+fn is_converted<C: EncodeConf>(polys: &[Poly<C::PlainConf>]) -> bool
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let half_modulus = C::PlainConf::modulus_minus_one_div_two_as_big_int();
+
+    polys.iter().all(|poly| {
+        poly.iter()
+            .all(|coeff| C::PlainConf::coeff_as_big_int(*coeff) <= half_modulus)
+    })
+}
@@ -0,0 +1,276 @@
+//! Privacy-preserving logs of encrypted match decisions.
+//!
+//! A [`DecisionLogger`] is a [`MatchObserver`] which turns each [`MatchEvent`] into a
+//! [`DecisionRecord`], and sends it to a [`DecisionSink`]. Records identify templates using a
+//! salted hash, so log entries can only be linked to stored templates by someone who knows the
+//! salt. Raw codes are never logged, and distances are only logged if they are explicitly
+//! enabled using [`DecisionLogger::with_distances()`].
+
+use std::{
+    fmt::{self, Debug, Write as _},
+    io::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::{Duration, SystemTime},
+};
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::{
+    encrypted::observer::{MatchDistance, MatchEvent, MatchObserver, TEMPLATE_HASH_LEN},
+    primitives::entropy::EntropySource,
+};
+
+/// The length of the secret salt used to hash templates.
+pub const SALT_LEN: usize = 32;
+
+/// The length of the salted template hash in each record.
+pub const TEMPLATE_ID_LEN: usize = 16;
+
+/// A logged match decision.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecisionRecord {
+    /// When the match finished.
+    pub timestamp: SystemTime,
+    /// The salted hash of the stored template.
+    pub template_id: [u8; TEMPLATE_ID_LEN],
+    /// The match decision, or `None` if matching failed.
+    pub decision: Option<bool>,
+    /// How long the match took.
+    pub duration: Duration,
+    /// The distance of the closest rotation, if distance logging is enabled.
+    pub distance: Option<MatchDistance>,
+}
+
+/// Receives every [`DecisionRecord`] from a [`DecisionLogger`].
+pub trait DecisionSink: Debug + Send + Sync {
+    /// Called after each match, with the record for that match.
+    fn record(&self, record: &DecisionRecord);
+}
+
+/// Logs match decisions to a [`DecisionSink`], keyed by salted template hashes.
+///
+/// Use [`EncryptedMatcherBuilder::observer()`](crate::encrypted::EncryptedMatcherBuilder::observer)
+/// to log every match performed by a matcher.
+pub struct DecisionLogger {
+    /// The secret salt used to hash templates.
+    salt: Zeroizing<[u8; SALT_LEN]>,
+    /// The sink which receives the records.
+    sink: Arc<dyn DecisionSink>,
+    /// True if distances are included in the records.
+    include_distances: bool,
+}
+
+impl DecisionLogger {
+    /// Returns a logger which sends records to `sink`, using a new random salt.
+    ///
+    /// Template ids are only consistent within the same logger. Use
+    /// [`DecisionLogger::with_salt()`] to share template ids between loggers.
+    ///
+    /// The salt is generated using [`rand::thread_rng()`].
+    pub fn new(sink: Arc<dyn DecisionSink>) -> Self {
+        Self::new_with_entropy(sink, &mut rand::thread_rng())
+    }
+
+    /// Returns a logger which sends records to `sink`, like [`DecisionLogger::new()`], using a
+    /// new salt generated by `rng`.
+    pub fn new_with_entropy<E: EntropySource + ?Sized>(
+        sink: Arc<dyn DecisionSink>,
+        rng: &mut E,
+    ) -> Self {
+        let mut salt = Zeroizing::new([0; SALT_LEN]);
+        rng.fill(salt.as_mut_slice());
+
+        Self {
+            salt,
+            sink,
+            include_distances: false,
+        }
+    }
+
+    /// Use `salt` to hash templates, so template ids are consistent for loggers with the same
+    /// salt. The salt must be kept secret, because it can be used to link records to templates.
+    pub fn with_salt(mut self, salt: [u8; SALT_LEN]) -> Self {
+        *self.salt = salt;
+        self
+    }
+
+    /// Include the distance of the closest rotation in each record.
+    ///
+    /// Distances reveal information about the codes, so they should only be logged where that
+    /// is explicitly permitted.
+    pub fn with_distances(mut self) -> Self {
+        self.include_distances = true;
+        self
+    }
+
+    /// Returns the salted hash of the template with `template_hash`.
+    pub fn template_id(&self, template_hash: &[u8; TEMPLATE_HASH_LEN]) -> [u8; TEMPLATE_ID_LEN] {
+        let digest = Sha256::new()
+            .chain_update(self.salt.as_slice())
+            .chain_update(template_hash)
+            .finalize();
+
+        let mut template_id = [0; TEMPLATE_ID_LEN];
+        template_id.copy_from_slice(&digest[..TEMPLATE_ID_LEN]);
+
+        template_id
+    }
+}
+
+impl Debug for DecisionLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't reveal the salt.
+        f.debug_struct("DecisionLogger")
+            .field("sink", &self.sink)
+            .field("include_distances", &self.include_distances)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MatchObserver for DecisionLogger {
+    fn on_match(&self, event: &MatchEvent) {
+        self.sink.record(&DecisionRecord {
+            timestamp: SystemTime::now(),
+            template_id: self.template_id(&event.template_hash),
+            decision: event.decision,
+            duration: event.duration,
+            distance: event.distance.filter(|_| self.include_distances),
+        });
+    }
+
+    fn wants_distance(&self) -> bool {
+        self.include_distances
+    }
+}
+
+/// A sink which keeps every record in memory.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    /// The records, in match order.
+    records: Mutex<Vec<DecisionRecord>>,
+}
+
+impl MemorySink {
+    /// Returns a new empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the records, in match order.
+    pub fn records(&self) -> Vec<DecisionRecord> {
+        // Records are only ever appended, so poisoning can be ignored.
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl DecisionSink for MemorySink {
+    fn record(&self, record: &DecisionRecord) {
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(*record);
+    }
+}
+
+/// A sink which writes each record to a writer as a line of JSON.
+///
+/// Each line has the fields `timestamp_ms`, `template_id` (in hex), `decision`, `duration_us`,
+/// and if distances are enabled, `differing_bits` and `visible_bits`.
+pub struct JsonLinesSink<W: Write + Send> {
+    /// The writer, locked so records from concurrent matches aren't interleaved.
+    writer: Mutex<W>,
+    /// The number of records which couldn't be written.
+    failed_writes: AtomicU64,
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    /// Returns a sink which writes to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            failed_writes: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of records which couldn't be written.
+    ///
+    /// Match results don't depend on logging, so write errors are counted rather than returned.
+    pub fn failed_writes(&self) -> u64 {
+        self.failed_writes.load(Ordering::Relaxed)
+    }
+
+    /// Returns the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<W: Write + Send> Debug for JsonLinesSink<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLinesSink")
+            .field("failed_writes", &self.failed_writes())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Write + Send> DecisionSink for JsonLinesSink<W> {
+    fn record(&self, record: &DecisionRecord) {
+        let line = json_line(record);
+
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        if writer
+            .write_all(line.as_bytes())
+            .and_then(|()| writer.flush())
+            .is_err()
+        {
+            self.failed_writes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Returns `record` as a line of JSON, including the trailing newline.
+fn json_line(record: &DecisionRecord) -> String {
+    let timestamp_ms = record
+        .timestamp
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let template_id = record
+        .template_id
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            // Writing to a String can't fail.
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+    let decision = match record.decision {
+        Some(decision) => decision.to_string(),
+        None => "null".to_string(),
+    };
+
+    let mut line = format!(
+        r#"{{"timestamp_ms":{timestamp_ms},"template_id":"{template_id}","decision":{decision},"duration_us":{}"#,
+        record.duration.as_micros()
+    );
+    if let Some(distance) = record.distance {
+        let _ = write!(
+            line,
+            r#","differing_bits":{},"visible_bits":{}"#,
+            distance.differing_bits, distance.visible_bits
+        );
+    }
+    line.push_str("}\n");
+
+    line
+}
@@ -0,0 +1,68 @@
+//! Combining encrypted match results from both eyes.
+
+use itertools::Itertools;
+use num_bigint::BigUint;
+
+use crate::{
+    encoded::MatchError,
+    encrypted::{EncryptedPolyCode, EncryptedPolyQuery},
+    iris::conf::{FusionPolicy, MatchThreshold},
+    primitives::yashe::{PrivateKey, Yashe},
+    EncodeConf, PolyConf, YasheConf,
+};
+
+impl<C: EncodeConf> EncryptedPolyQuery<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+    BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+{
+    /// Returns true if the left and right eyes match according to `policy`.
+    ///
+    /// `self` is the left eye query, and it is compared with `left_code`. `right_query` is
+    /// compared with `right_code`.
+    pub fn is_match_both_eyes(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        right_query: &Self,
+        left_code: &EncryptedPolyCode<C>,
+        right_code: &EncryptedPolyCode<C>,
+        policy: FusionPolicy,
+    ) -> Result<bool, MatchError> {
+        match policy {
+            FusionPolicy::And => Ok(self.is_match(ctx, private_key, left_code)?
+                && right_query.is_match(ctx, private_key, right_code)?),
+            FusionPolicy::Or => Ok(self.is_match(ctx, private_key, left_code)?
+                || right_query.is_match(ctx, private_key, right_code)?),
+            FusionPolicy::MinDistance => {
+                let decrypt_product = |product| Ok(ctx.decrypt_mul(product, private_key));
+
+                let (left_match, left_mask) =
+                    closest_rotation(self.decrypted_counts(ctx, decrypt_product, left_code)?);
+                let (right_match, right_mask) = closest_rotation(right_query.decrypted_counts(
+                    ctx,
+                    decrypt_product,
+                    right_code,
+                )?);
+
+                Ok(MatchThreshold::from_conf::<C::EyeConf>()
+                    .is_encoded_match(left_match + right_match, left_mask + right_mask))
+            }
+        }
+    }
+}
+
+/// Returns the match and mask counts of the rotation with the smallest Hamming distance.
+///
+/// Rotations without any visible bits are skipped. If no rotations have visible bits, returns
+/// zero counts.
+fn closest_rotation((match_counts, mask_counts): (Vec<i64>, Vec<i64>)) -> (i64, i64) {
+    match_counts
+        .into_iter()
+        .zip_eq(mask_counts)
+        .filter(|(_d, t)| *t > 0)
+        // The distance is (t - d) / 2t, so compare (t1 - d1) * t2 with (t2 - d2) * t1.
+        .min_by(|(d1, t1), (d2, t2)| ((t1 - d1) * t2).cmp(&((t2 - d2) * t1)))
+        .unwrap_or((0, 0))
+}
@@ -0,0 +1,228 @@
+//! Passphrase-encrypted private key files.
+//!
+//! Key files start with a header containing [`KEY_FILE_MAGIC`], the format version, and the
+//! encryption parameters. The header is followed by the Argon2id parameters and salt, the
+//! XChaCha20-Poly1305 nonce, then the encrypted [`PrivateKey::to_bytes()`] encoding.
+//!
+//! The encryption key is derived from the passphrase using Argon2id. The whole header is
+//! authenticated, so a modified file or a wrong passphrase are both rejected when loading.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand::Rng;
+use zeroize::Zeroizing;
+
+use crate::{
+    encrypted::{
+        store::StoreError,
+        wire::{decode_header, encode_header, take_bytes},
+    },
+    primitives::{entropy::EntropySource, yashe::PrivateKey},
+    YasheConf,
+};
+
+/// The bytes at the start of every key file.
+pub const KEY_FILE_MAGIC: [u8; 4] = *b"EYEF";
+
+/// The current version of the key file format.
+pub const KEY_FILE_VERSION: u16 = 1;
+
+/// The length of the random salt used to derive the encryption key.
+const SALT_LEN: usize = 16;
+
+/// The length of the random XChaCha20-Poly1305 nonce.
+const NONCE_LEN: usize = 24;
+
+/// The length of the derived encryption key.
+const KEY_LEN: usize = 32;
+
+/// The largest Argon2id memory cost accepted when loading, in KiB.
+/// This stops a modified file from using excessive memory before it is authenticated.
+const MAX_M_COST: u32 = 1024 * 1024;
+
+/// The largest Argon2id iteration count accepted when loading.
+/// This stops a modified file from using excessive time before it is authenticated.
+const MAX_T_COST: u32 = 64;
+
+/// The largest Argon2id parallelism accepted when loading.
+/// Each lane is hashed in turn, so this also bounds the time taken.
+const MAX_P_COST: u32 = 64;
+
+/// Errors that can happen when saving or loading key files.
+#[derive(Debug, thiserror::Error)]
+pub enum KeyFileError {
+    /// The key file could not be read or written.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The key file or the decrypted key has an invalid encoding, uses an unsupported version,
+    /// or was generated with different encryption parameters.
+    #[error(transparent)]
+    Encoding(#[from] StoreError),
+
+    /// The private key could not be encrypted.
+    #[error("private key could not be encrypted")]
+    Encryption,
+
+    /// The key file could not be decrypted.
+    /// This happens if the passphrase is wrong, or the file was modified.
+    #[error("key file could not be decrypted: wrong passphrase or modified file")]
+    Decryption,
+
+    /// The encryption key could not be derived from the passphrase.
+    #[error("key derivation failed: {0}")]
+    KeyDerivation(#[from] argon2::Error),
+}
+
+impl<C: YasheConf> PrivateKey<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Encrypts `self` using `passphrase`, and saves it to a new key file at `path`.
+    ///
+    /// Returns [`KeyFileError::Io`] if a file already exists at `path`. On Unix, the new file
+    /// is only readable and writable by its owner.
+    ///
+    /// Uses the default Argon2id parameters, which are recorded in the file.
+    /// The salt and nonce are generated using [`rand::thread_rng()`].
+    pub fn save_encrypted(
+        &self,
+        path: impl AsRef<Path>,
+        passphrase: &[u8],
+    ) -> Result<(), KeyFileError> {
+        self.save_encrypted_with_entropy(path, passphrase, &mut rand::thread_rng())
+    }
+
+    /// Encrypts `self` using `passphrase`, and saves it to a new key file at `path`, like
+    /// [`PrivateKey::save_encrypted()`]. The salt and nonce are generated using `rng`.
+    pub fn save_encrypted_with_entropy<E: EntropySource + ?Sized>(
+        &self,
+        path: impl AsRef<Path>,
+        passphrase: &[u8],
+        rng: &mut E,
+    ) -> Result<(), KeyFileError> {
+        let bytes = self.to_encrypted_bytes(passphrase, Params::DEFAULT, rng)?;
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        options.open(path)?.write_all(&bytes)?;
+
+        Ok(())
+    }
+
+    /// Loads a private key saved by [`PrivateKey::save_encrypted()`], using `passphrase`.
+    ///
+    /// Returns [`KeyFileError::Decryption`] if the passphrase is wrong, or the file was modified.
+    pub fn load_encrypted(path: impl AsRef<Path>, passphrase: &[u8]) -> Result<Self, KeyFileError> {
+        Self::from_encrypted_bytes(&fs::read(path)?, passphrase)
+    }
+
+    /// Encrypts `self` using a key derived from `passphrase` with `params`, and returns the
+    /// key file bytes. The salt and nonce are generated using `rng`.
+    pub(crate) fn to_encrypted_bytes<E: EntropySource + ?Sized>(
+        &self,
+        passphrase: &[u8],
+        params: Params,
+        rng: &mut E,
+    ) -> Result<Vec<u8>, KeyFileError> {
+        let mut salt = [0; SALT_LEN];
+        let mut nonce = [0; NONCE_LEN];
+        rng.fill(&mut salt);
+        rng.fill(&mut nonce);
+
+        let mut bytes = Vec::new();
+        encode_header::<C>(&mut bytes, KEY_FILE_MAGIC, KEY_FILE_VERSION);
+        for cost in [params.m_cost(), params.t_cost(), params.p_cost()] {
+            bytes.extend_from_slice(&cost.to_le_bytes());
+        }
+        bytes.extend_from_slice(&salt);
+        bytes.extend_from_slice(&nonce);
+
+        let cipher = derive_cipher(passphrase, &salt, params)?;
+        let plaintext = Zeroizing::new(self.to_bytes());
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &bytes,
+                },
+            )
+            .map_err(|_| KeyFileError::Encryption)?;
+
+        bytes.extend_from_slice(&ciphertext);
+
+        Ok(bytes)
+    }
+
+    /// Decrypts the key file `bytes` using `passphrase`, and decodes the private key.
+    pub(crate) fn from_encrypted_bytes(
+        bytes: &[u8],
+        passphrase: &[u8],
+    ) -> Result<Self, KeyFileError> {
+        let mut body = match decode_header::<C>(bytes, KEY_FILE_MAGIC)? {
+            (KEY_FILE_VERSION, body) => body,
+            (version, _) => return Err(StoreError::UnsupportedVersion(version).into()),
+        };
+
+        let m_cost = u32::from_le_bytes(take_bytes(&mut body)?);
+        let t_cost = u32::from_le_bytes(take_bytes(&mut body)?);
+        let p_cost = u32::from_le_bytes(take_bytes(&mut body)?);
+        let salt: [u8; SALT_LEN] = take_bytes(&mut body)?;
+        let nonce: [u8; NONCE_LEN] = take_bytes(&mut body)?;
+        let ciphertext = body;
+
+        if m_cost > MAX_M_COST || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+            return Err(StoreError::InvalidEncoding.into());
+        }
+        let params = Params::new(m_cost, t_cost, p_cost, Some(KEY_LEN))?;
+
+        let cipher = derive_cipher(passphrase, &salt, params)?;
+        let header_len = bytes.len() - ciphertext.len();
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(
+                    XNonce::from_slice(&nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: &bytes[..header_len],
+                    },
+                )
+                .map_err(|_| KeyFileError::Decryption)?,
+        );
+
+        Ok(Self::from_bytes(&plaintext)?)
+    }
+}
+
+/// Derives the file encryption key from `passphrase` and `salt` using Argon2id with `params`,
+/// and returns the cipher using that key.
+fn derive_cipher(
+    passphrase: &[u8],
+    salt: &[u8],
+    params: Params,
+) -> Result<XChaCha20Poly1305, KeyFileError> {
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+        passphrase,
+        salt,
+        key.as_mut_slice(),
+    )?;
+
+    Ok(XChaCha20Poly1305::new(Key::from_slice(key.as_slice())))
+}
@@ -0,0 +1,439 @@
+//! A high-level encrypted iris matching pipeline.
+//!
+//! [`EncryptedMatcher`] owns the encryption context, keys, and match threshold, so callers can
+//! enroll and verify plaintext iris codes without encoding, converting, and encrypting them
+//! manually.
+//!
+//! Matchers can also report how long each stage of a match took, using
+//! [`EncryptedMatcherBuilder::record_stage_timings()`].
+
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Instant,
+};
+
+use num_bigint::BigUint;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    encoded::{PolyCode, PolyQuery},
+    encrypted::{
+        observer::{template_hash, MatchEvent, MatchObserver},
+        store::{CodeId, EncryptedCodeStore},
+        timing::{Stage, StageTimings},
+        ConvertedPolyQuery, EncryptedPolyCode, EncryptedPolyQuery,
+    },
+    iris::conf::{IrisConf, MatchThreshold},
+    plaintext::{IrisCode, IrisMask},
+    primitives::{
+        entropy::EntropySource,
+        yashe::{PrivateKey, PublicKey, SharedContext, Yashe},
+    },
+    EncodeConf, PolyConf, Result, YasheConf,
+};
+
+#[cfg(feature = "config")]
+use crate::{
+    config::{ConfigError, MatcherConfig},
+    params::NamedParamSet,
+};
+
+/// Encrypts and matches iris codes, using the same context, keys, and threshold each time.
+///
+/// Created using [`EncryptedMatcher::builder()`].
+///
+/// Matchers are `Send` and `Sync`, but clones share an entropy source, so they take turns
+/// encrypting. To encrypt on multiple threads at once, build a matcher on each thread using the
+/// same [`EncryptedMatcherBuilder::shared_context()`].
+#[derive(Clone, Debug)]
+pub struct EncryptedMatcher<C: EncodeConf>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The encryption context and keys, which can be shared with other matchers.
+    shared: SharedContext<C::PlainConf>,
+    /// The match threshold.
+    threshold: MatchThreshold,
+    /// The number of columns each column is compared to, on its left and right.
+    rotation_limit: usize,
+    /// The entropy source used for encryption, shared with clones of this matcher.
+    rng: Arc<Mutex<dyn EntropySource + Send>>,
+    /// The observer notified after each match, if any.
+    observer: Option<Arc<dyn MatchObserver>>,
+    /// True if match outcomes include the time taken by each stage.
+    record_stage_timings: bool,
+}
+
+// Matchers are held across await points in request handlers.
+assert_impl_all!(EncryptedMatcher<crate::FullBits>: Send, Sync);
+
+/// The result of an encrypted match.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct EncryptedMatchOutcome {
+    /// True if the query and code have enough identical bits to meet the threshold.
+    pub is_match: bool,
+    /// The time taken by each stage, if stage timings are enabled.
+    pub timings: Option<StageTimings>,
+}
+
+/// Builds an [`EncryptedMatcher`]. Missing settings use the defaults for the configuration.
+#[derive(Clone, Debug)]
+pub struct EncryptedMatcherBuilder<C: EncodeConf>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The encryption context, or `None` for a new context.
+    ctx: Option<Yashe<C::PlainConf>>,
+    /// The private key, or `None` to generate new keys.
+    private_key: Option<PrivateKey<C::PlainConf>>,
+    /// The public key, or `None` to generate new keys.
+    public_key: Option<PublicKey<C::PlainConf>>,
+    /// The shared context and keys, which override the context and keys above.
+    shared: Option<SharedContext<C::PlainConf>>,
+    /// The match threshold, or `None` for the threshold in the configuration.
+    threshold: Option<MatchThreshold>,
+    /// The rotation limit, or `None` for the rotation limit in the configuration.
+    rotation_limit: Option<usize>,
+    /// The observer notified after each match, or `None` for no observer.
+    observer: Option<Arc<dyn MatchObserver>>,
+    /// True if match outcomes include the time taken by each stage.
+    record_stage_timings: bool,
+    /// The entropy source used for key generation and encryption, or `None` for a new
+    /// ChaCha20 RNG.
+    entropy_source: Option<Arc<Mutex<dyn EntropySource + Send>>>,
+}
+
+impl<C: EncodeConf> EncryptedMatcher<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+    BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+{
+    /// Returns a builder for a new matcher.
+    pub fn builder() -> EncryptedMatcherBuilder<C> {
+        EncryptedMatcherBuilder {
+            ctx: None,
+            private_key: None,
+            public_key: None,
+            shared: None,
+            threshold: None,
+            rotation_limit: None,
+            observer: None,
+            record_stage_timings: false,
+            entropy_source: None,
+        }
+    }
+
+    /// Encodes, converts, and encrypts a plaintext iris code and mask for storage.
+    pub fn enroll<const STORE_ELEM_LEN: usize>(
+        &self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> EncryptedPolyCode<C> {
+        let poly_code = PolyCode::from_plaintext(code, mask);
+
+        EncryptedPolyCode::convert_and_encrypt_code(
+            self.shared.ctx(),
+            poly_code,
+            self.shared.public_key(),
+            &mut *self.lock_rng(),
+        )
+    }
+
+    /// Encodes, converts, and encrypts a plaintext iris code and mask for matching.
+    pub fn encrypt_query<const STORE_ELEM_LEN: usize>(
+        &self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> EncryptedPolyQuery<C> {
+        let poly_query = PolyQuery::from_plaintext(code, mask);
+
+        EncryptedPolyQuery::convert_and_encrypt_query(
+            self.shared.ctx(),
+            poly_query,
+            self.shared.public_key(),
+            &mut *self.lock_rng(),
+        )
+    }
+
+    /// Returns true if `query` and `code` have enough identical bits to meet the threshold.
+    ///
+    /// Notifies the observer after matching, if there is one.
+    pub fn verify(
+        &self,
+        query: &EncryptedPolyQuery<C>,
+        code: &EncryptedPolyCode<C>,
+    ) -> Result<bool> {
+        Ok(self.verify_outcome(query, code)?.is_match)
+    }
+
+    /// Encodes, converts, and encrypts a plaintext iris code and mask, then matches it against
+    /// `code`.
+    ///
+    /// If stage timings are enabled, the outcome includes the time taken by every stage.
+    /// Notifies the observer after matching, if there is one.
+    pub fn verify_plaintext<const STORE_ELEM_LEN: usize>(
+        &self,
+        query_code: &IrisCode<STORE_ELEM_LEN>,
+        query_mask: &IrisMask<STORE_ELEM_LEN>,
+        code: &EncryptedPolyCode<C>,
+    ) -> Result<EncryptedMatchOutcome> {
+        let mut timings = StageTimings::default();
+
+        let query = timings.time(Stage::Encode, || {
+            ConvertedPolyQuery::new(PolyQuery::from_plaintext(query_code, query_mask))
+        });
+        let query = timings.time(Stage::Encrypt, || {
+            EncryptedPolyQuery::encrypt_query(
+                self.shared.ctx(),
+                query,
+                self.shared.public_key(),
+                &mut *self.lock_rng(),
+            )
+        });
+
+        let mut outcome = self.verify_outcome(&query, code)?;
+        if let Some(match_timings) = &mut outcome.timings {
+            match_timings.encode = timings.encode;
+            match_timings.encrypt = timings.encrypt;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Returns the outcome of matching `query` and `code`.
+    ///
+    /// If stage timings are enabled, the outcome includes the time taken by the multiply,
+    /// decrypt, and decide stages. The query is already encrypted, so the encode and encrypt
+    /// stages are zero.
+    /// Notifies the observer after matching, if there is one.
+    pub fn verify_outcome(
+        &self,
+        query: &EncryptedPolyQuery<C>,
+        code: &EncryptedPolyCode<C>,
+    ) -> Result<EncryptedMatchOutcome> {
+        let start = Instant::now();
+        let wants_distance = self
+            .observer
+            .as_ref()
+            .is_some_and(|observer| observer.wants_distance());
+
+        let result = query.match_with_shared_context(
+            &self.shared,
+            code,
+            self.threshold,
+            self.rotation_limit,
+            wants_distance,
+        );
+
+        if let Some(observer) = &self.observer {
+            observer.on_match(&MatchEvent {
+                template_hash: template_hash(code),
+                duration: start.elapsed(),
+                decision: result.as_ref().ok().map(|(is_match, _, _)| *is_match),
+                distance: result.as_ref().ok().and_then(|(_, distance, _)| *distance),
+            });
+        }
+
+        let (is_match, _, timings) = result?;
+
+        Ok(EncryptedMatchOutcome {
+            is_match,
+            timings: self.record_stage_timings.then_some(timings),
+        })
+    }
+
+    /// Returns the ids of all the codes in `store` that match `query`, in id order.
+    ///
+    /// Notifies the observer after matching each code, if there is one.
+    pub fn search<S>(&self, query: &EncryptedPolyQuery<C>, store: &S) -> Result<Vec<CodeId>>
+    where
+        S: EncryptedCodeStore<C>,
+    {
+        let mut matches = Vec::new();
+
+        for entry in store.scan() {
+            let (id, code) = entry?;
+
+            if self.verify(query, &code)? {
+                matches.push(id);
+            }
+        }
+
+        Ok(matches)
+    }
+
+    /// Returns the encryption context.
+    pub fn ctx(&self) -> Yashe<C::PlainConf> {
+        self.shared.ctx()
+    }
+
+    /// Returns the private key.
+    pub fn private_key(&self) -> &PrivateKey<C::PlainConf> {
+        self.shared.private_key()
+    }
+
+    /// Returns the public key.
+    pub fn public_key(&self) -> &PublicKey<C::PlainConf> {
+        self.shared.public_key()
+    }
+
+    /// Returns the shared context and keys, which can be used to build matchers on other threads.
+    pub fn shared_context(&self) -> &SharedContext<C::PlainConf> {
+        &self.shared
+    }
+
+    /// Returns the match threshold.
+    pub fn threshold(&self) -> MatchThreshold {
+        self.threshold
+    }
+
+    /// Returns the rotation limit.
+    pub fn rotation_limit(&self) -> usize {
+        self.rotation_limit
+    }
+
+    /// Returns true if match outcomes include the time taken by each stage.
+    pub fn records_stage_timings(&self) -> bool {
+        self.record_stage_timings
+    }
+    /// Locks and returns the entropy source.
+    fn lock_rng(&self) -> MutexGuard<'_, dyn EntropySource + Send> {
+        // A panic while generating randomness can't leave the RNG in an insecure state.
+        self.rng.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<C: EncodeConf> EncryptedMatcherBuilder<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Use `ctx` as the encryption context.
+    pub fn context(mut self, ctx: Yashe<C::PlainConf>) -> Self {
+        self.ctx = Some(ctx);
+        self
+    }
+
+    /// Use existing keys, rather than generating new keys.
+    pub fn keys(
+        mut self,
+        private_key: PrivateKey<C::PlainConf>,
+        public_key: PublicKey<C::PlainConf>,
+    ) -> Self {
+        self.private_key = Some(private_key);
+        self.public_key = Some(public_key);
+        self
+    }
+
+    /// Use the context and keys in `shared`, rather than any context or keys supplied using
+    /// [`EncryptedMatcherBuilder::context()`] or [`EncryptedMatcherBuilder::keys()`].
+    ///
+    /// Matchers built from clones of the same shared context also share its cached values.
+    pub fn shared_context(mut self, shared: SharedContext<C::PlainConf>) -> Self {
+        self.shared = Some(shared);
+        self
+    }
+
+    /// Use `threshold` as the match threshold, rather than the threshold in the configuration.
+    pub fn threshold(mut self, threshold: MatchThreshold) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Only check rotations of up to `rotation_limit` columns to the left and right, rather
+    /// than the rotation limit in the configuration.
+    ///
+    /// Limits larger than the configured limit check every rotation.
+    pub fn rotation_limit(mut self, rotation_limit: usize) -> Self {
+        self.rotation_limit = Some(rotation_limit.min(C::EyeConf::ROTATION_LIMIT));
+        self
+    }
+
+    /// Use the threshold and rotation limit in `config`.
+    ///
+    /// With the `parallel` feature, also applies the number of threads in `config`, using
+    /// [`MatcherConfig::apply_thread_pool()`].
+    ///
+    /// Returns an error if `config` uses different encryption parameters, its preferred
+    /// polynomial multiplication backend isn't being used, or its thread pool can't be created.
+    #[cfg(feature = "config")]
+    pub fn config(self, config: &MatcherConfig) -> Result<Self, ConfigError>
+    where
+        C::PlainConf: NamedParamSet,
+    {
+        if config.param_set() != C::PlainConf::PARAM_SET {
+            return Err(ConfigError::ParamSetMismatch {
+                expected: C::PlainConf::PARAM_SET,
+                found: config.param_set(),
+            });
+        }
+        config.check_backend::<C::PlainConf>()?;
+        #[cfg(feature = "parallel")]
+        config.apply_thread_pool()?;
+
+        Ok(self
+            .threshold(config.threshold())
+            .rotation_limit(config.rotation_limit()))
+    }
+
+    /// Notify `observer` after every match.
+    pub fn observer(mut self, observer: Arc<dyn MatchObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Include the time taken by each stage in every [`EncryptedMatchOutcome`].
+    ///
+    /// Timing each stage is cheap compared to encrypted matching, but it is disabled by default,
+    /// because timings are usually only needed to diagnose slow matches.
+    pub fn record_stage_timings(mut self) -> Self {
+        self.record_stage_timings = true;
+        self
+    }
+
+    /// Use `source` for key generation and encryption, rather than a ChaCha20 RNG seeded by the
+    /// operating system.
+    ///
+    /// Keys supplied using [`EncryptedMatcherBuilder::keys()`] or
+    /// [`EncryptedMatcherBuilder::shared_context()`] aren't regenerated.
+    pub fn entropy_source(mut self, source: impl EntropySource + Send + 'static) -> Self {
+        self.entropy_source = Some(Arc::new(Mutex::new(source)));
+        self
+    }
+
+    /// Returns a new matcher, generating keys if they weren't supplied.
+    pub fn build(self) -> EncryptedMatcher<C> {
+        let rng = self
+            .entropy_source
+            .unwrap_or_else(|| Arc::new(Mutex::new(ChaCha20Rng::from_entropy())));
+        let shared = self.shared.unwrap_or_else(|| {
+            let ctx = self.ctx.unwrap_or_else(Yashe::new);
+            match (self.private_key, self.public_key) {
+                (Some(private_key), Some(public_key)) => {
+                    SharedContext::new(ctx, private_key, public_key)
+                }
+                _ => SharedContext::generate(
+                    ctx,
+                    &mut *rng.lock().unwrap_or_else(PoisonError::into_inner),
+                ),
+            }
+        });
+        let threshold = self
+            .threshold
+            .unwrap_or_else(MatchThreshold::from_conf::<C::EyeConf>);
+        let rotation_limit = self.rotation_limit.unwrap_or(C::EyeConf::ROTATION_LIMIT);
+
+        EncryptedMatcher {
+            shared,
+            threshold,
+            rotation_limit,
+            rng,
+            observer: self.observer,
+            record_stage_timings: self.record_stage_timings,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Audit hooks for encrypted match operations.
+//!
+//! A [`MatchObserver`] is notified after every match performed by an
+//! [`EncryptedMatcher`](crate::encrypted::EncryptedMatcher), including each stored code checked
+//! by [`EncryptedMatcher::search()`](crate::encrypted::EncryptedMatcher::search).
+//!
+//! Only [`EncryptedMatcher`](crate::encrypted::EncryptedMatcher) is audited. The lower-level
+//! [`EncryptedPolyQuery`](crate::encrypted::EncryptedPolyQuery) methods, like `is_match()`,
+//! `is_match_many()`, `is_match_constant_time()`, and `search()`, never notify observers.
+//!
+//! Observers only receive metadata that doesn't reveal the iris codes or their distances, so they
+//! can be used for audit logging and rate limiting. Observers can explicitly request distances
+//! using [`MatchObserver::wants_distance()`].
+
+use std::{cmp::Ordering, fmt::Debug, time::Duration};
+
+use sha2::{Digest, Sha256};
+
+use crate::{encrypted::EncryptedPolyCode, EncodeConf, PolyConf, YasheConf};
+
+/// The length of a [`template_hash()`].
+pub const TEMPLATE_HASH_LEN: usize = 32;
+
+/// Non-sensitive metadata about an encrypted match.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MatchEvent {
+    /// A hash of the encrypted stored code, which identifies the template without revealing it.
+    pub template_hash: [u8; TEMPLATE_HASH_LEN],
+    /// How long the match took.
+    pub duration: Duration,
+    /// The match decision, or `None` if matching failed.
+    pub decision: Option<bool>,
+    /// The distance of the closest rotation, if the observer requested it, and matching
+    /// succeeded.
+    pub distance: Option<MatchDistance>,
+}
+
+/// The Hamming distance between two codes, at a single rotation.
+///
+/// Distances are ordered by the fraction of visible bits that are different.
+#[derive(Copy, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchDistance {
+    /// The number of visible bits that are different.
+    pub differing_bits: u64,
+    /// The number of bits that are visible in both codes.
+    pub visible_bits: u64,
+}
+
+/// Receives a [`MatchEvent`] after every match performed by an
+/// [`EncryptedMatcher`](crate::encrypted::EncryptedMatcher).
+pub trait MatchObserver: Debug + Send + Sync {
+    /// Called after each match, with metadata about that match.
+    fn on_match(&self, event: &MatchEvent);
+
+    /// Returns true if [`MatchEvent::distance`] should be calculated.
+    ///
+    /// Distances reveal information about the codes, so they are not calculated by default.
+    fn wants_distance(&self) -> bool {
+        false
+    }
+}
+
+impl MatchDistance {
+    /// Returns the distance for the encoded counts of a rotation.
+    /// See [`MatchThreshold::encoded_margin()`](crate::iris::conf::MatchThreshold::encoded_margin)
+    /// for details.
+    pub(crate) fn from_encoded_counts(match_count: i64, mask_count: i64) -> Self {
+        // Each visible bit adds 1 to the match count if it is the same, and subtracts 1 if it is
+        // different.
+        Self {
+            differing_bits: (mask_count - match_count).unsigned_abs() / 2,
+            visible_bits: mask_count.unsigned_abs(),
+        }
+    }
+
+    /// Returns the fraction of visible bits that are different, or zero if no bits are visible.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn fraction(&self) -> f64 {
+        if self.visible_bits == 0 {
+            return 0.0;
+        }
+
+        self.differing_bits as f64 / self.visible_bits as f64
+    }
+}
+
+impl PartialEq for MatchDistance {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MatchDistance {}
+
+impl PartialOrd for MatchDistance {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MatchDistance {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Compare the fractions without rounding, treating no visible bits as zero distance.
+        let scaled =
+            |a: &Self, b: &Self| u128::from(a.differing_bits) * u128::from(b.visible_bits.max(1));
+
+        scaled(self, other)
+            .cmp(&scaled(other, self))
+            .then(self.visible_bits.cmp(&other.visible_bits).reverse())
+    }
+}
+
+/// Returns the SHA-256 hash of the encrypted `code`, for use in a [`MatchEvent`].
+///
+/// The hash only depends on the serialized code, so it is stable across library builds and
+/// toolchain upgrades.
+pub fn template_hash<C: EncodeConf>(code: &EncryptedPolyCode<C>) -> [u8; TEMPLATE_HASH_LEN]
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    Sha256::digest(code.to_bytes()).into()
+}
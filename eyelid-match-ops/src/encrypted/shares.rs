@@ -0,0 +1,244 @@
+//! Secret-shared output for encrypted iris matching.
+//!
+//! The server adds a random mask to each encrypted product, and keeps the negated mask as its
+//! share. The client decrypts the masked products to get its share. Neither share reveals the
+//! Hamming distances, but the sum of the shares modulo T is the per-block, per-rotation counts.
+
+use std::marker::PhantomData;
+
+use ark_ff::One;
+use itertools::Itertools;
+use num_bigint::BigUint;
+
+use crate::{
+    encoded::MatchError,
+    encrypted::{EncryptedPolyCode, EncryptedPolyQuery},
+    iris::conf::{IrisConf, MatchThreshold},
+    primitives::{
+        entropy::EntropySource,
+        poly::Poly,
+        yashe::{Ciphertext, Message, PrivateKey, PublicKey, Yashe},
+    },
+    EncodeConf, PolyConf, YasheConf,
+};
+
+/// The masked encrypted products of a query and a code, produced by the server.
+/// Decrypting them gives the client's [`DistanceShare`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaskedProducts<C: EncodeConf>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The masked products of the data polynomials, one for each block.
+    pub(super) data: Vec<Ciphertext<C::PlainConf>>,
+    /// The masked products of the mask polynomials, one for each block.
+    pub(super) masks: Vec<Ciphertext<C::PlainConf>>,
+}
+
+/// One party's additive share of the per-rotation counts, modulo T.
+///
+/// Counts are shared separately for each block, because the total counts can be larger than T.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DistanceShare<C: EncodeConf> {
+    /// The share of the number of matching minus non-matching visible bits, for each block and
+    /// rotation.
+    pub match_counts: Vec<Vec<u64>>,
+    /// The share of the number of visible bits, for each block and rotation.
+    pub mask_counts: Vec<Vec<u64>>,
+    /// A zero-sized marker, which binds the config type to the outer type.
+    _conf: PhantomData<C>,
+}
+
+impl<C: EncodeConf> EncryptedPolyQuery<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+    BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+{
+    /// Multiplies `self` and `code`, then adds a random mask to each product.
+    ///
+    /// Returns the masked products to send to the client, and the server's share of the counts.
+    pub fn masked_products<E: EntropySource + ?Sized>(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        public_key: &PublicKey<C::PlainConf>,
+        code: &EncryptedPolyCode<C>,
+        rng: &mut E,
+    ) -> (MaskedProducts<C>, DistanceShare<C>) {
+        let (data, match_counts) =
+            Self::mask_inner_products(ctx, public_key, &self.data, &code.data, rng);
+        let (masks, mask_counts) =
+            Self::mask_inner_products(ctx, public_key, &self.masks, &code.masks, rng);
+
+        (
+            MaskedProducts { data, masks },
+            DistanceShare::new(match_counts, mask_counts),
+        )
+    }
+
+    /// Multiplies each pair of polynomials, and adds a random mask to each product.
+    /// Returns the masked products, and the negated masks for each block and rotation.
+    fn mask_inner_products<E: EntropySource + ?Sized>(
+        ctx: Yashe<C::PlainConf>,
+        public_key: &PublicKey<C::PlainConf>,
+        a_polys: &[Ciphertext<C::PlainConf>],
+        b_polys: &[Ciphertext<C::PlainConf>],
+        rng: &mut E,
+    ) -> (Vec<Ciphertext<C::PlainConf>>, Vec<Vec<u64>>) {
+        let mut products = Vec::with_capacity(a_polys.len());
+        let mut counts = Vec::with_capacity(a_polys.len());
+
+        for (a, b) in a_polys.iter().zip_eq(b_polys.iter()) {
+            let product = ctx.ciphertext_mul(a.clone(), b.clone());
+
+            // Products are decrypted using the private key squared, so the mask must also be a
+            // product. Multiplying by an encryption of one doesn't change the mask.
+            let mut mask = ctx.sample_uniform_range(0..C::PlainConf::T, rng);
+            mask.truncate_to_canonical_form();
+            let encrypted_mask = ctx.encrypt(Message { m: mask.clone() }, public_key, rng);
+            let encrypted_one = ctx.encrypt(Message { m: Poly::one() }, public_key, rng);
+            let encrypted_mask = ctx.ciphertext_mul(encrypted_mask, encrypted_one);
+
+            products.push(ctx.ciphertext_add(product, encrypted_mask));
+
+            // The server's share is the negated mask.
+            counts.push(share_counts::<C>(&mask, true));
+        }
+
+        (products, counts)
+    }
+}
+
+impl<C: EncodeConf> MaskedProducts<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Decrypts the masked products, returning the client's share of the counts.
+    pub fn decrypt_share(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+    ) -> DistanceShare<C> {
+        let decrypt_counts = |products: &[Ciphertext<C::PlainConf>]| {
+            products
+                .iter()
+                .map(|product| {
+                    let decrypted_product = ctx.decrypt_mul(product.clone(), private_key);
+                    share_counts::<C>(&decrypted_product.m, false)
+                })
+                .collect()
+        };
+
+        DistanceShare::new(decrypt_counts(&self.data), decrypt_counts(&self.masks))
+    }
+}
+
+impl<C: EncodeConf> DistanceShare<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Returns a new share from per-block, per-rotation counts modulo T.
+    pub fn new(match_counts: Vec<Vec<u64>>, mask_counts: Vec<Vec<u64>>) -> Self {
+        Self {
+            match_counts,
+            mask_counts,
+            _conf: PhantomData,
+        }
+    }
+
+    /// Combines two shares, returning the per-rotation match and mask counts.
+    ///
+    /// Returns [`MatchError::CoefficientOutOfRange`] if a combined count is too large.
+    ///
+    /// # Panics
+    ///
+    /// If the shares have a different number of blocks or rotations.
+    pub fn combine(&self, other: &Self) -> Result<(Vec<i64>, Vec<i64>), MatchError> {
+        Ok((
+            combine_counts::<C>(&self.match_counts, &other.match_counts)?,
+            combine_counts::<C>(&self.mask_counts, &other.mask_counts)?,
+        ))
+    }
+
+    /// Combines two shares, and returns true if any rotation meets the threshold configured in
+    /// `C`.
+    pub fn is_match(&self, other: &Self) -> Result<bool, MatchError> {
+        self.is_match_with_threshold(other, MatchThreshold::from_conf::<C::EyeConf>())
+    }
+
+    /// Combines two shares, and returns true if any rotation meets `threshold`.
+    pub fn is_match_with_threshold(
+        &self,
+        other: &Self,
+        threshold: MatchThreshold,
+    ) -> Result<bool, MatchError> {
+        let (match_counts, mask_counts) = self.combine(other)?;
+
+        Ok(match_counts
+            .into_iter()
+            .zip_eq(mask_counts)
+            .any(|(d, t)| threshold.is_encoded_match(d, t)))
+    }
+}
+
+/// Returns the inner product coefficients of `poly`, modulo T.
+/// If `negate` is true, negates them modulo T.
+fn share_counts<C: EncodeConf>(poly: &Poly<C::PlainConf>, negate: bool) -> Vec<u64>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let t = C::PlainConf::t_as_u128();
+    let mut counts = vec![0; C::EyeConf::ROTATION_COMPARISONS];
+
+    for (count, coeff) in counts.iter_mut().zip(
+        poly.iter()
+            .skip(C::INNER_PRODUCT_START)
+            .take(C::EyeConf::ROTATION_COMPARISONS),
+    ) {
+        let coeff = C::PlainConf::coeff_as_u128(*coeff) % t;
+        let coeff = if negate { (t - coeff) % t } else { coeff };
+
+        // The coefficient is less than T, which is a u64.
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            *count = coeff as u64;
+        }
+    }
+
+    counts
+}
+
+/// Adds the shares for each block modulo T, then sums the signed block counts for each rotation.
+fn combine_counts<C: EncodeConf>(a: &[Vec<u64>], b: &[Vec<u64>]) -> Result<Vec<i64>, MatchError>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let t = C::PlainConf::t_as_u128();
+    let mut counts = vec![0; C::EyeConf::ROTATION_COMPARISONS];
+
+    for (a_block, b_block) in a.iter().zip_eq(b.iter()) {
+        for (rotation, (a, b)) in a_block.iter().zip_eq(b_block.iter()).enumerate() {
+            let value = (u128::from(*a) + u128::from(*b)) % t;
+
+            // Values above T/2 are negative.
+            let block_count = if value > t / 2 {
+                i128::try_from(value).map(|value| value - C::PlainConf::t_as_i128())
+            } else {
+                i128::try_from(value)
+            };
+            let block_count = block_count
+                .ok()
+                .and_then(|block_count| i64::try_from(block_count).ok())
+                .ok_or(MatchError::CoefficientOutOfRange { rotation, value })?;
+
+            counts[rotation] += block_count;
+        }
+    }
+
+    Ok(counts)
+}
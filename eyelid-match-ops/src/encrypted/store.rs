@@ -0,0 +1,184 @@
+//! Storage for encrypted iris code databases.
+//!
+//! Enrolled codes are stored by id in an [`EncryptedCodeStore`], then queries are matched against
+//! every code in the store using [`EncryptedPolyQuery::search()`].
+
+use std::{collections::BTreeMap, mem::size_of};
+
+use num_bigint::BigUint;
+
+use crate::{
+    encoded::MatchError,
+    encrypted::{EncryptedPolyCode, EncryptedPolyQuery},
+    metrics::MemorySize,
+    primitives::yashe::{PrivateKey, Yashe},
+    EncodeConf, PolyConf, YasheConf,
+};
+
+#[cfg(feature = "sled")]
+pub mod sled;
+
+#[cfg(feature = "sled")]
+pub use self::sled::SledCodeStore;
+
+/// The unique id of a code in an [`EncryptedCodeStore`].
+pub type CodeId = u64;
+
+/// An iterator over the codes in an [`EncryptedCodeStore`], with their ids.
+pub type CodeIter<'store, C> =
+    Box<dyn Iterator<Item = Result<(CodeId, EncryptedPolyCode<C>), StoreError>> + 'store>;
+
+/// Errors that can happen when storing or searching encrypted codes.
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    /// Matching a query against a stored code failed.
+    #[error(transparent)]
+    Match(#[from] MatchError),
+
+    /// A stored code could not be decoded.
+    /// This can happen if the store is corrupted, or was written with different parameters.
+    #[error("stored code has an invalid encoding")]
+    InvalidEncoding,
+
+    /// A stored code uses an encoding version that this library can't decode.
+    #[error("stored code uses unsupported encoding version {0}")]
+    UnsupportedVersion(u16),
+
+    /// The sled database returned an error.
+    #[cfg(feature = "sled")]
+    #[error(transparent)]
+    Sled(#[from] ::sled::Error),
+}
+
+/// A database of encrypted iris codes, indexed by id.
+pub trait EncryptedCodeStore<C: EncodeConf>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Stores `code` with `id`, replacing any existing code with that id.
+    fn put(&mut self, id: CodeId, code: EncryptedPolyCode<C>) -> Result<(), StoreError>;
+
+    /// Returns the code with `id`, or `None` if there is no code with that id.
+    fn get(&self, id: CodeId) -> Result<Option<EncryptedPolyCode<C>>, StoreError>;
+
+    /// Returns all the codes in the store, in id order.
+    fn scan(&self) -> CodeIter<'_, C>;
+}
+
+/// An in-memory [`EncryptedCodeStore`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemoryCodeStore<C: EncodeConf>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The stored codes, ordered by id.
+    codes: BTreeMap<CodeId, EncryptedPolyCode<C>>,
+}
+
+impl<C: EncodeConf> MemoryCodeStore<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Returns a new empty store.
+    pub fn new() -> Self {
+        Self {
+            codes: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of codes in the store.
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Returns true if there are no codes in the store.
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+}
+
+impl<C: EncodeConf> MemorySize for MemoryCodeStore<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Returns the memory used by the stored ids and codes. The map's internal nodes are not
+    /// included.
+    fn heap_size(&self) -> usize {
+        self.codes
+            .values()
+            .map(|code| size_of::<CodeId>() + code.memory_size())
+            .sum()
+    }
+}
+
+impl<C: EncodeConf> Default for MemoryCodeStore<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Cloning codes needs a `Clone` config, but all the config marker types are `Copy`.
+impl<C: EncodeConf + Clone> EncryptedCodeStore<C> for MemoryCodeStore<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn put(&mut self, id: CodeId, code: EncryptedPolyCode<C>) -> Result<(), StoreError> {
+        self.codes.insert(id, code);
+
+        Ok(())
+    }
+
+    fn get(&self, id: CodeId) -> Result<Option<EncryptedPolyCode<C>>, StoreError> {
+        Ok(self.codes.get(&id).cloned())
+    }
+
+    fn scan(&self) -> CodeIter<'_, C> {
+        Box::new(self.codes.iter().map(|(id, code)| Ok((*id, code.clone()))))
+    }
+}
+
+impl<C: EncodeConf> EncryptedPolyQuery<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+    BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+{
+    /// Returns the ids of all the codes in `store` that match `self`, in id order.
+    ///
+    /// This doesn't notify any [`MatchObserver`](crate::encrypted::MatchObserver). Use
+    /// [`EncryptedMatcher::search()`](crate::encrypted::EncryptedMatcher::search) for audited
+    /// searches.
+    pub fn search<S>(
+        &self,
+        ctx: Yashe<C::PlainConf>,
+        private_key: &PrivateKey<C::PlainConf>,
+        store: &S,
+    ) -> Result<Vec<CodeId>, StoreError>
+    where
+        S: EncryptedCodeStore<C>,
+    {
+        let mut matches = Vec::new();
+
+        for entry in store.scan() {
+            let (id, code) = entry?;
+
+            if self
+                .is_match(ctx, private_key, &code)
+                .map_err(StoreError::Match)?
+            {
+                matches.push(id);
+            }
+        }
+
+        Ok(matches)
+    }
+}
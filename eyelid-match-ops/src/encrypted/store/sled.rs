@@ -0,0 +1,85 @@
+//! A [`sled`]-backed encrypted code store.
+
+use crate::{
+    encrypted::{
+        store::{CodeId, CodeIter, EncryptedCodeStore, StoreError},
+        EncryptedPolyCode,
+    },
+    EncodeConf, PolyConf, YasheConf,
+};
+
+/// An [`EncryptedCodeStore`] backed by a [`sled::Tree`].
+///
+/// Codes are stored using big-endian ids as keys, so scans are in id order.
+/// Values use the versioned encoding from [`EncryptedPolyCode::to_bytes()`]. Codes written in
+/// older versions must be migrated using [`SledCodeStore::migrate()`] before they can be read.
+#[derive(Clone, Debug)]
+pub struct SledCodeStore {
+    /// The sled tree containing the codes.
+    tree: ::sled::Tree,
+}
+
+impl SledCodeStore {
+    /// Returns a store using the codes in `tree`.
+    pub fn new(tree: ::sled::Tree) -> Self {
+        Self { tree }
+    }
+
+    /// Re-encodes every stored code that doesn't use the current encoding version.
+    /// Returns the number of codes that were migrated.
+    pub fn migrate<C: EncodeConf>(&self) -> Result<usize, StoreError>
+    where
+        C::PlainConf: YasheConf,
+        <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+    {
+        let mut migrated = 0;
+
+        for entry in self.tree.iter() {
+            let (key, bytes) = entry.map_err(StoreError::Sled)?;
+            let code = EncryptedPolyCode::<C>::migrate(&bytes)?;
+            let new_bytes = code.to_bytes();
+
+            if new_bytes != bytes.as_ref() {
+                self.tree.insert(key, new_bytes).map_err(StoreError::Sled)?;
+                migrated += 1;
+            }
+        }
+
+        Ok(migrated)
+    }
+}
+
+impl<C: EncodeConf> EncryptedCodeStore<C> for SledCodeStore
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn put(&mut self, id: CodeId, code: EncryptedPolyCode<C>) -> Result<(), StoreError> {
+        self.tree
+            .insert(id.to_be_bytes(), code.to_bytes())
+            .map_err(StoreError::Sled)?;
+
+        Ok(())
+    }
+
+    fn get(&self, id: CodeId) -> Result<Option<EncryptedPolyCode<C>>, StoreError> {
+        self.tree
+            .get(id.to_be_bytes())
+            .map_err(StoreError::Sled)?
+            .map(|bytes| EncryptedPolyCode::from_bytes(&bytes))
+            .transpose()
+    }
+
+    fn scan(&self) -> CodeIter<'_, C> {
+        Box::new(self.tree.iter().map(|entry| {
+            let (key, bytes) = entry.map_err(StoreError::Sled)?;
+            let id = CodeId::from_be_bytes(
+                key.as_ref()
+                    .try_into()
+                    .map_err(|_| StoreError::InvalidEncoding)?,
+            );
+
+            Ok((id, EncryptedPolyCode::from_bytes(&bytes)?))
+        }))
+    }
+}
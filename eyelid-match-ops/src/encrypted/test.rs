@@ -1,4 +1,37 @@
 //! Encrypted iris matching tests.
 
+#[cfg(all(test, feature = "rkyv"))]
+mod archive;
+
+#[cfg(test)]
+mod converted;
+
+#[cfg(test)]
+mod fusion;
+
+#[cfg(all(test, feature = "decision-log"))]
+mod decision_log;
+
+#[cfg(all(test, feature = "key-file"))]
+mod keyfile;
+
+#[cfg(test)]
+mod matcher;
+
 #[cfg(test)]
 mod matching;
+
+#[cfg(all(test, feature = "serde"))]
+mod serialize;
+
+#[cfg(test)]
+mod shares;
+
+#[cfg(test)]
+mod store;
+
+#[cfg(test)]
+mod timing;
+
+#[cfg(test)]
+mod wire;
@@ -0,0 +1,51 @@
+//! Tests for zero-copy archived encrypted codes.
+
+use crate::encrypted::archive::ArchivedStoredPolyCode;
+use crate::encrypted::store::StoreError;
+use crate::encrypted::EncryptedMatcher;
+use crate::iris::conf::IrisConf;
+use crate::plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask};
+use crate::FullBits;
+
+/// Check that archived codes can be matched without copying them into owned codes.
+#[test]
+fn test_archived_code_matching() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let eye_c = random_iris_code();
+    let mask = visible_iris_mask();
+
+    let similar = matcher.enroll(&eye_b, &mask);
+    let different = matcher.enroll(&eye_c, &mask);
+    let query = matcher.encrypt_query(&eye_a, &mask);
+
+    let similar_bytes = similar.to_archive().expect("archiving must work");
+    let different_bytes = different.to_archive().expect("archiving must work");
+
+    let archived_similar =
+        ArchivedStoredPolyCode::access(&similar_bytes).expect("archive must be valid");
+    let archived_different =
+        ArchivedStoredPolyCode::access(&different_bytes).expect("archive must be valid");
+
+    assert_eq!(
+        archived_similar
+            .to_code::<FullBits>()
+            .expect("archive must decode"),
+        similar
+    );
+
+    assert!(query
+        .is_match_archived(matcher.ctx(), matcher.private_key(), archived_similar)
+        .expect("matching must work"));
+    assert!(!query
+        .is_match_archived(matcher.ctx(), matcher.private_key(), archived_different)
+        .expect("matching must work"));
+
+    // Truncated archives are rejected.
+    assert!(matches!(
+        ArchivedStoredPolyCode::access(&similar_bytes[..similar_bytes.len() - 1]),
+        Err(StoreError::InvalidEncoding)
+    ));
+}
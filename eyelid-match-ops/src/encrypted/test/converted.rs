@@ -0,0 +1,50 @@
+//! Tests for converting encoded codes before encryption.
+
+use crate::encoded::{PolyCode, PolyQuery};
+use crate::encrypted::{convert_negative_coefficients, ConvertedPolyCode, ConvertedPolyQuery};
+use crate::iris::conf::IrisConf;
+use crate::plaintext::test::gen::{random_iris_code, visible_iris_mask};
+use crate::FullBits;
+
+/// Check that conversion matches [`convert_negative_coefficients()`].
+#[test]
+fn test_converted_code() {
+    let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    let poly_code = PolyCode::<FullBits>::from_plaintext(&eye, &mask);
+    let poly_query = PolyQuery::<FullBits>::from_plaintext(&eye, &mask);
+
+    let mut expected_code = poly_code.clone();
+    convert_negative_coefficients::<FullBits>(&mut expected_code.polys);
+    let mut expected_query = poly_query.clone();
+    convert_negative_coefficients::<FullBits>(&mut expected_query.polys);
+
+    let converted_code = ConvertedPolyCode::new(poly_code);
+    let converted_query = ConvertedPolyQuery::from(poly_query);
+    assert_eq!(converted_code.clone().into_inner(), expected_code);
+    assert_eq!(converted_query.clone().into_inner(), expected_query);
+
+    // Already converted codes can be wrapped without converting them again.
+    assert_eq!(
+        ConvertedPolyCode::from_converted(expected_code),
+        converted_code
+    );
+    assert_eq!(
+        ConvertedPolyQuery::from_converted(expected_query),
+        converted_query
+    );
+}
+
+/// Check that wrapping an unconverted code is caught in debug builds.
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "code must be converted")]
+fn test_unconverted_code() {
+    let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    let poly_code = PolyCode::<FullBits>::from_plaintext(&eye, &mask);
+
+    ConvertedPolyCode::from_converted(poly_code);
+}
@@ -0,0 +1,187 @@
+//! Tests for privacy-preserving decision logging.
+
+use std::{
+    io::{self, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::encrypted::decision_log::{
+    DecisionLogger, DecisionRecord, DecisionSink, JsonLinesSink, MemorySink,
+};
+use crate::encrypted::observer::{template_hash, TEMPLATE_HASH_LEN};
+use crate::encrypted::{EncryptedMatcher, MatchDistance, MatchEvent, MatchObserver};
+use crate::iris::conf::IrisConf;
+use crate::plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask};
+use crate::FullBits;
+
+/// A writer which always fails.
+#[derive(Debug)]
+struct FailingWriter;
+
+impl Write for FailingWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns a matcher with the same keys as `matcher`, which notifies `observer`.
+fn logging_matcher(
+    matcher: &EncryptedMatcher<FullBits>,
+    observer: impl MatchObserver + 'static,
+) -> EncryptedMatcher<FullBits> {
+    EncryptedMatcher::<FullBits>::builder()
+        .context(matcher.ctx())
+        .keys(matcher.private_key().clone(), matcher.public_key().clone())
+        .observer(Arc::new(observer))
+        .build()
+}
+
+/// Check that decisions are logged with salted template ids, and distances are only logged
+/// when they are enabled.
+#[test]
+fn test_decision_log() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let eye_c = random_iris_code();
+    let mask = visible_iris_mask();
+
+    let query = matcher.encrypt_query(&eye_a, &mask);
+    let similar = matcher.enroll(&eye_b, &mask);
+    let different = matcher.enroll(&eye_c, &mask);
+
+    // Distances are not logged by default.
+    let sink = Arc::new(MemorySink::new());
+    let logger = DecisionLogger::new(sink.clone());
+    let expected_id = logger.template_id(&template_hash(&similar));
+    let private_matcher = logging_matcher(&matcher, logger);
+
+    assert!(private_matcher
+        .verify(&query, &similar)
+        .expect("matching must work"));
+
+    let records = sink.records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].template_id, expected_id);
+    assert_eq!(records[0].decision, Some(true));
+    assert_eq!(records[0].distance, None);
+
+    // Distances are logged when they are enabled.
+    let salt = [7; 32];
+    let sink = Arc::new(MemorySink::new());
+    let logger = DecisionLogger::new(sink.clone())
+        .with_salt(salt)
+        .with_distances();
+    let distance_matcher = logging_matcher(&matcher, logger);
+
+    assert!(distance_matcher
+        .verify(&query, &similar)
+        .expect("matching must work"));
+    assert!(!distance_matcher
+        .verify(&query, &different)
+        .expect("matching must work"));
+
+    let records = sink.records();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].decision, Some(true));
+    assert_eq!(records[1].decision, Some(false));
+
+    let similar_distance = records[0].distance.expect("distance must be logged");
+    let different_distance = records[1].distance.expect("distance must be logged");
+    assert!(similar_distance < different_distance);
+    assert!(similar_distance.visible_bits > 0);
+    #[allow(clippy::cast_precision_loss)]
+    let threshold =
+        matcher.threshold().numerator() as f64 / matcher.threshold().denominator() as f64;
+    assert!(similar_distance.fraction() < threshold);
+    assert!(different_distance.fraction() > threshold);
+
+    // Template ids only depend on the salt and the template.
+    let other_logger = DecisionLogger::new(Arc::new(MemorySink::new())).with_salt(salt);
+    assert_eq!(
+        records[0].template_id,
+        other_logger.template_id(&template_hash(&similar))
+    );
+    assert_ne!(records[0].template_id, expected_id);
+    assert_ne!(records[0].template_id, records[1].template_id);
+
+    // The salt is not revealed by the logger's debug output.
+    assert!(!format!("{other_logger:?}").contains("7, 7"));
+}
+
+/// Check that random salts come from the supplied entropy source.
+#[test]
+fn test_decision_log_entropy_source() {
+    let seeded_id = |seed| {
+        DecisionLogger::new_with_entropy(
+            Arc::new(MemorySink::new()),
+            &mut ChaCha20Rng::seed_from_u64(seed),
+        )
+        .template_id(&[1; TEMPLATE_HASH_LEN])
+    };
+
+    assert_eq!(seeded_id(7), seeded_id(7));
+    assert_ne!(seeded_id(7), seeded_id(8));
+}
+
+/// Check that records are written as JSON lines, and write errors are counted.
+#[test]
+fn test_json_lines_sink() {
+    let record = {
+        let sink = Arc::new(MemorySink::new());
+        let logger = DecisionLogger::new(sink.clone()).with_distances();
+        logger.on_match(&MatchEvent {
+            template_hash: [42; TEMPLATE_HASH_LEN],
+            duration: Duration::from_micros(1500),
+            decision: Some(false),
+            distance: Some(MatchDistance {
+                differing_bits: 3,
+                visible_bits: 10,
+            }),
+        });
+        sink.records()[0]
+    };
+
+    let sink = JsonLinesSink::new(Vec::new());
+    sink.record(&record);
+    sink.record(&DecisionRecord {
+        decision: None,
+        distance: None,
+        ..record
+    });
+    assert_eq!(sink.failed_writes(), 0);
+
+    let output = String::from_utf8(sink.into_inner()).expect("output must be UTF-8");
+    let lines: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line must be valid JSON"))
+        .collect();
+    assert_eq!(lines.len(), 2);
+
+    let template_id: String = record
+        .template_id
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    assert_eq!(lines[0]["template_id"], template_id.as_str());
+    assert_eq!(lines[0]["decision"], false);
+    assert_eq!(lines[0]["duration_us"], 1500);
+    assert_eq!(lines[0]["differing_bits"], 3);
+    assert_eq!(lines[0]["visible_bits"], 10);
+
+    assert!(lines[1]["decision"].is_null());
+    assert!(lines[1].get("differing_bits").is_none());
+
+    let failing = JsonLinesSink::new(FailingWriter);
+    failing.record(&record);
+    assert_eq!(failing.failed_writes(), 1);
+}
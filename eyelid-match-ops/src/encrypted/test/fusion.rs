@@ -0,0 +1,42 @@
+//! Tests for combining encrypted match results from both eyes.
+
+use crate::encrypted::{EncryptedMatcher, FusionPolicy};
+use crate::iris::conf::IrisConf;
+use crate::plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask};
+use crate::FullBits;
+
+/// Check each fusion policy with one matching eye and one non-matching eye.
+#[test]
+fn test_both_eyes_fusion() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+    let mask = visible_iris_mask();
+
+    let left_eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let right_eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let left_query = matcher.encrypt_query(&left_eye, &mask);
+    let right_query = matcher.encrypt_query(&right_eye, &mask);
+
+    // The left eye is identical, and the right eye is a different iris.
+    let identical_left = matcher.enroll(&left_eye, &mask);
+    // The left eye is a third different, which doesn't make up for the right eye.
+    let similar_left = matcher.enroll(&similar_iris_code(&left_eye), &mask);
+    let different_right = matcher.enroll(&random_iris_code(), &mask);
+
+    let is_match = |left_code, policy| {
+        left_query
+            .is_match_both_eyes(
+                matcher.ctx(),
+                matcher.private_key(),
+                &right_query,
+                left_code,
+                &different_right,
+                policy,
+            )
+            .expect("matching must work")
+    };
+
+    assert!(!is_match(&identical_left, FusionPolicy::And));
+    assert!(is_match(&identical_left, FusionPolicy::Or));
+    assert!(is_match(&identical_left, FusionPolicy::MinDistance));
+    assert!(!is_match(&similar_left, FusionPolicy::MinDistance));
+}
@@ -0,0 +1,180 @@
+//! Tests for passphrase-encrypted private key files.
+
+use std::{env, fs, process};
+
+use argon2::Params;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::encrypted::keyfile::{KeyFileError, KEY_FILE_MAGIC, KEY_FILE_VERSION};
+use crate::encrypted::store::StoreError;
+use crate::encrypted::wire::HEADER_LEN;
+use crate::primitives::yashe::{PrivateKey, Yashe};
+use crate::{MiddleRes, TestRes};
+
+/// The passphrase used to encrypt test keys.
+const PASSPHRASE: &[u8] = b"correct horse battery staple";
+
+/// Check that private keys round-trip through key files.
+#[test]
+fn test_key_file_round_trip() {
+    let ctx: Yashe<TestRes> = Yashe::new();
+    let (private_key, _public_key) = ctx.keygen(&mut rand::thread_rng());
+
+    let path = env::temp_dir().join(format!("eyelid-key-file-test-{}", process::id()));
+    private_key
+        .save_encrypted(&path, PASSPHRASE)
+        .expect("saving must work");
+
+    let bytes = fs::read(&path).expect("key file must exist");
+    assert!(bytes.starts_with(&KEY_FILE_MAGIC));
+    // The key is not stored in plaintext.
+    let plain_bytes = private_key.to_bytes();
+    assert!(!bytes
+        .windows(plain_bytes.len() - HEADER_LEN)
+        .any(|window| window == &plain_bytes[HEADER_LEN..]));
+
+    let loaded =
+        PrivateKey::<TestRes>::load_encrypted(&path, PASSPHRASE).expect("loading must work");
+    assert_eq!(loaded, private_key);
+
+    // Wrong passphrases are rejected.
+    assert!(matches!(
+        PrivateKey::<TestRes>::load_encrypted(&path, b"wrong passphrase"),
+        Err(KeyFileError::Decryption)
+    ));
+
+    fs::remove_file(&path).expect("key file must be removable");
+
+    // Missing files are rejected.
+    assert!(matches!(
+        PrivateKey::<TestRes>::load_encrypted(&path, PASSPHRASE),
+        Err(KeyFileError::Io(_))
+    ));
+}
+
+/// Check that new key files are only accessible by their owner, and existing files are kept.
+#[cfg(unix)]
+#[test]
+fn test_key_file_permissions() {
+    use std::{io, os::unix::fs::PermissionsExt};
+
+    let ctx: Yashe<TestRes> = Yashe::new();
+    let (private_key, _public_key) = ctx.keygen(&mut rand::thread_rng());
+
+    let path = env::temp_dir().join(format!(
+        "eyelid-key-file-permissions-test-{}",
+        process::id()
+    ));
+    private_key
+        .save_encrypted(&path, PASSPHRASE)
+        .expect("saving must work");
+
+    let metadata = fs::metadata(&path).expect("key file must exist");
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+    // Existing files are not replaced.
+    let bytes = fs::read(&path).expect("key file must exist");
+    assert!(matches!(
+        private_key.save_encrypted(&path, PASSPHRASE),
+        Err(KeyFileError::Io(error)) if error.kind() == io::ErrorKind::AlreadyExists
+    ));
+    assert_eq!(fs::read(&path).expect("key file must exist"), bytes);
+
+    fs::remove_file(&path).expect("key file must be removable");
+}
+
+/// Check that modified, truncated, and mismatched key files are rejected.
+#[test]
+fn test_key_file_rejected() {
+    let ctx: Yashe<TestRes> = Yashe::new();
+    let (private_key, _public_key) = ctx.keygen(&mut rand::thread_rng());
+
+    // Use cheap key derivation parameters to speed up the test.
+    let params = Params::new(8, 1, 1, None).expect("parameters must be valid");
+    let bytes = private_key
+        .to_encrypted_bytes(PASSPHRASE, params.clone(), &mut rand::thread_rng())
+        .expect("encryption must work");
+
+    assert_eq!(
+        PrivateKey::<TestRes>::from_encrypted_bytes(&bytes, PASSPHRASE)
+            .expect("decryption must work"),
+        private_key
+    );
+
+    // Each encryption uses a new salt and nonce.
+    let other_bytes = private_key
+        .to_encrypted_bytes(PASSPHRASE, params.clone(), &mut rand::thread_rng())
+        .expect("encryption must work");
+    assert_ne!(other_bytes, bytes);
+
+    // The salt and nonce come from the supplied entropy source.
+    let seeded_bytes = |seed| {
+        private_key
+            .to_encrypted_bytes(
+                PASSPHRASE,
+                params.clone(),
+                &mut ChaCha20Rng::seed_from_u64(seed),
+            )
+            .expect("encryption must work")
+    };
+    assert_eq!(seeded_bytes(7), seeded_bytes(7));
+    assert_ne!(seeded_bytes(7), seeded_bytes(8));
+
+    // Modifying the key derivation parameters or the ciphertext is detected.
+    for i in [HEADER_LEN, bytes.len() - 1] {
+        let mut modified = bytes.clone();
+        modified[i] ^= 1;
+        assert!(matches!(
+            PrivateKey::<TestRes>::from_encrypted_bytes(&modified, PASSPHRASE),
+            Err(KeyFileError::Decryption)
+        ));
+    }
+
+    // Excessive key derivation costs are rejected before deriving the key.
+    for (offset, cost) in [
+        (0, 1024 * 1024 + 1),
+        (4, 65),
+        (8, 65),
+        (4, u32::MAX),
+        (8, u32::MAX),
+    ] {
+        let mut expensive = bytes.clone();
+        expensive[HEADER_LEN + offset..HEADER_LEN + offset + 4]
+            .copy_from_slice(&cost.to_le_bytes());
+        assert!(
+            matches!(
+                PrivateKey::<TestRes>::from_encrypted_bytes(&expensive, PASSPHRASE),
+                Err(KeyFileError::Encoding(StoreError::InvalidEncoding))
+            ),
+            "{offset}: {cost}"
+        );
+    }
+
+    // Truncated files are rejected.
+    assert!(matches!(
+        PrivateKey::<TestRes>::from_encrypted_bytes(&bytes[..HEADER_LEN + 8], PASSPHRASE),
+        Err(KeyFileError::Encoding(StoreError::InvalidEncoding))
+    ));
+
+    // Keys for other encryption parameters are rejected.
+    assert!(matches!(
+        PrivateKey::<MiddleRes>::from_encrypted_bytes(&bytes, PASSPHRASE),
+        Err(KeyFileError::Encoding(StoreError::InvalidEncoding))
+    ));
+
+    // Unknown versions are rejected.
+    let mut future = bytes.clone();
+    future[KEY_FILE_MAGIC.len()..KEY_FILE_MAGIC.len() + 2]
+        .copy_from_slice(&(KEY_FILE_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        PrivateKey::<TestRes>::from_encrypted_bytes(&future, PASSPHRASE),
+        Err(KeyFileError::Encoding(StoreError::UnsupportedVersion(_)))
+    ));
+
+    // Unencrypted keys are rejected.
+    assert!(matches!(
+        PrivateKey::<TestRes>::from_encrypted_bytes(&private_key.to_bytes(), PASSPHRASE),
+        Err(KeyFileError::Encoding(StoreError::InvalidEncoding))
+    ));
+}
@@ -0,0 +1,426 @@
+//! Tests for the high-level encrypted matching pipeline.
+
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Instant,
+};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+use crate::encrypted::observer::template_hash;
+use crate::encrypted::store::{EncryptedCodeStore, MemoryCodeStore};
+use crate::encrypted::{EncryptedMatcher, MatchEvent, MatchObserver, Stage};
+use crate::iris::conf::{IrisConf, MatchThreshold};
+use crate::plaintext::test::gen::{
+    random_iris_code, random_iris_mask, similar_iris_code, visible_iris_mask,
+};
+use crate::plaintext::{hamming, rotate, rotations};
+use crate::primitives::yashe::Ciphertext;
+use crate::{EncodeConf, FullBits, FullRes, YasheConf};
+
+/// Records every match event.
+#[derive(Debug, Default)]
+struct RecordingObserver {
+    /// The recorded events, in match order.
+    events: Mutex<Vec<MatchEvent>>,
+}
+
+impl MatchObserver for RecordingObserver {
+    fn on_match(&self, event: &MatchEvent) {
+        self.events
+            .lock()
+            .expect("lock must not be poisoned")
+            .push(*event);
+    }
+}
+
+/// Check that enrolled codes are verified using the matcher's threshold.
+#[test]
+fn test_matcher_enroll_verify() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+    assert_eq!(matcher.threshold(), MatchThreshold::from_conf::<FullBits>());
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let eye_c = random_iris_code();
+    let mask = visible_iris_mask();
+
+    let query = matcher.encrypt_query(&eye_a, &mask);
+    let similar = matcher.enroll(&eye_b, &mask);
+    let different = matcher.enroll(&eye_c, &mask);
+
+    assert!(matcher
+        .verify(&query, &similar)
+        .expect("matching must work"));
+    assert!(!matcher
+        .verify(&query, &different)
+        .expect("matching must work"));
+
+    // Batch matching gives the same results, in the same order.
+    let codes = [different.clone(), similar.clone(), different];
+    assert_eq!(
+        query.is_match_many(matcher.ctx(), matcher.private_key(), &codes),
+        Ok(vec![false, true, false])
+    );
+
+    // Similar codes have some different bits, so they don't match with a zero threshold.
+    let observer = Arc::new(RecordingObserver::default());
+    let strict_matcher = EncryptedMatcher::<FullBits>::builder()
+        .context(matcher.ctx())
+        .keys(matcher.private_key().clone(), matcher.public_key().clone())
+        .threshold(MatchThreshold::new(0, 1).expect("zero is a valid threshold"))
+        .observer(observer.clone())
+        .build();
+    assert!(!strict_matcher
+        .verify(&query, &similar)
+        .expect("matching must work"));
+
+    // The observer only gets metadata about the match.
+    let events = observer.events.lock().expect("lock must not be poisoned");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].template_hash, template_hash(&similar));
+    assert_eq!(events[0].decision, Some(false));
+    assert_eq!(events[0].distance, None);
+
+    // Template hashes are stable digests of the serialized code.
+    let expected_hash: [u8; 32] = Sha256::digest(similar.to_bytes()).into();
+    assert_eq!(template_hash(&similar), expected_hash);
+}
+
+/// Check that searching a store notifies the observer for every stored code.
+#[test]
+fn test_matcher_search() {
+    let observer = Arc::new(RecordingObserver::default());
+    let matcher = EncryptedMatcher::<FullBits>::builder()
+        .observer(observer.clone())
+        .build();
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let eye_c = random_iris_code();
+    let mask = visible_iris_mask();
+
+    let query = matcher.encrypt_query(&eye_a, &mask);
+    let similar = matcher.enroll(&eye_b, &mask);
+    let different = matcher.enroll(&eye_c, &mask);
+
+    let mut store = MemoryCodeStore::new();
+    store
+        .put(1, different.clone())
+        .expect("memory stores can't fail");
+    store
+        .put(2, similar.clone())
+        .expect("memory stores can't fail");
+
+    assert_eq!(
+        matcher.search(&query, &store).expect("searching must work"),
+        vec![2]
+    );
+
+    // Codes are matched in id order.
+    let events = observer.events.lock().expect("lock must not be poisoned");
+    let decisions: Vec<_> = events
+        .iter()
+        .map(|event| (event.template_hash, event.decision))
+        .collect();
+    assert_eq!(
+        decisions,
+        vec![
+            (template_hash(&different), Some(false)),
+            (template_hash(&similar), Some(true)),
+        ]
+    );
+}
+
+/// Check that the raw products decrypt to the per-rotation counts used for matching, and to the
+/// plaintext counts at each rotation.
+#[test]
+fn test_encrypted_distances() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let (mask_a, mask_b) = (random_iris_mask(), random_iris_mask());
+
+    let query = matcher.encrypt_query(&eye_a, &mask_a);
+    let stored = matcher.enroll(&eye_b, &mask_b);
+    let ctx = matcher.ctx();
+
+    // The raw products contain the data products for each block, then the mask products.
+    let products = query.encrypted_distances(ctx, &stored);
+    assert_eq!(products.len(), 2 * FullBits::NUM_POLYS);
+
+    let (data_products, mask_products) = products.split_at(FullBits::NUM_POLYS);
+    let accumulate = |products: &[Ciphertext<FullRes>]| {
+        let mut counts = vec![0; FullBits::ROTATION_COMPARISONS];
+        for product in products {
+            let decrypted = ctx.decrypt_mul(product.clone(), matcher.private_key());
+            let block_counts = decrypted
+                .m
+                .iter()
+                .skip(FullBits::INNER_PRODUCT_START)
+                .take(FullBits::ROTATION_COMPARISONS);
+
+            // Decrypted counts are modulo T, with negative counts above T/2.
+            for (count, coeff) in counts.iter_mut().zip(block_counts) {
+                let (coeff, t) = (FullRes::coeff_as_u128(*coeff), u128::from(FullRes::T));
+                *count += if coeff > t / 2 {
+                    -i64::try_from(t - coeff).expect("counts must fit in i64")
+                } else {
+                    i64::try_from(coeff).expect("counts must fit in i64")
+                };
+            }
+        }
+        counts
+    };
+    let match_counts = accumulate(data_products);
+    let mask_counts = accumulate(mask_products);
+
+    // The counts are the same as the counts used by is_match().
+    let decrypt_product = |product| Ok(ctx.decrypt_mul(product, matcher.private_key()));
+    assert_eq!(
+        query.decrypted_counts(ctx, decrypt_product, &stored),
+        Ok((match_counts.clone(), mask_counts.clone()))
+    );
+
+    // The match count is `visible - 2 * differences`, and the mask count is `visible`.
+    for (i, rotation) in rotations::<FullBits>(FullBits::ROTATION_LIMIT).enumerate() {
+        let (differences, visible) = hamming::<FullBits, { FullBits::STORE_ELEM_LEN }>(
+            &eye_a,
+            &mask_a,
+            &rotate::<FullBits, { FullBits::STORE_ELEM_LEN }>(eye_b, rotation),
+            &rotate::<FullBits, { FullBits::STORE_ELEM_LEN }>(mask_b, rotation),
+        );
+        let (differences, visible) = (
+            i64::try_from(differences).expect("counts must fit in i64"),
+            i64::try_from(visible).expect("counts must fit in i64"),
+        );
+
+        assert_eq!(
+            (match_counts[i], mask_counts[i]),
+            (visible - 2 * differences, visible),
+            "rotation {rotation}"
+        );
+    }
+}
+
+/// Check that the matcher only checks rotations within its rotation limit.
+#[test]
+fn test_matcher_rotation_limit() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+    assert_eq!(matcher.rotation_limit(), FullBits::ROTATION_LIMIT);
+
+    let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    let query = matcher.encrypt_query(&eye, &mask);
+    let rotated = matcher.enroll(
+        &rotate::<FullBits, { FullBits::STORE_ELEM_LEN }>(eye, 5),
+        &mask,
+    );
+
+    assert!(matcher
+        .verify(&query, &rotated)
+        .expect("matching must work"));
+
+    for (rotation_limit, is_match) in [(4, false), (5, true)] {
+        let limited_matcher = EncryptedMatcher::<FullBits>::builder()
+            .context(matcher.ctx())
+            .keys(matcher.private_key().clone(), matcher.public_key().clone())
+            .rotation_limit(rotation_limit)
+            .build();
+        assert_eq!(
+            limited_matcher
+                .verify(&query, &rotated)
+                .expect("matching must work"),
+            is_match,
+        );
+    }
+
+    // Limits are clamped to the configured limit.
+    let clamped = EncryptedMatcher::<FullBits>::builder()
+        .rotation_limit(usize::MAX)
+        .build();
+    assert_eq!(clamped.rotation_limit(), FullBits::ROTATION_LIMIT);
+}
+
+/// Check that matchers on different threads can share a context, keys, and cached values.
+#[test]
+fn test_matcher_shared_context() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let mask = visible_iris_mask();
+
+    let query = matcher.encrypt_query(&eye_a, &mask);
+    let shared = matcher.shared_context().clone();
+
+    // Each thread builds its own matcher, and enrolls its own code.
+    let results: Vec<bool> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let (shared, query) = (shared.clone(), &query);
+                scope.spawn(move || {
+                    let thread_matcher = EncryptedMatcher::<FullBits>::builder()
+                        .shared_context(shared)
+                        .build();
+                    let similar = thread_matcher.enroll(&eye_b, &mask);
+
+                    thread_matcher
+                        .verify(query, &similar)
+                        .expect("matching must work")
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("matching must not panic"))
+            .collect()
+    });
+
+    assert_eq!(results, [true, true]);
+    assert!(matcher.shared_context().is_cached());
+    assert!(matcher.shared_context().ptr_eq(&shared));
+}
+
+/// Check that a single matcher can enroll and match on multiple threads.
+#[test]
+fn test_matcher_across_threads() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let mask = visible_iris_mask();
+
+    let results: Vec<bool> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let (matcher, eye_a, eye_b, mask) = (&matcher, &eye_a, &eye_b, &mask);
+                scope.spawn(move || {
+                    let query = matcher.encrypt_query(eye_a, mask);
+                    let similar = matcher.enroll(eye_b, mask);
+
+                    matcher
+                        .verify(&query, &similar)
+                        .expect("matching must work")
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("matching must not panic"))
+            .collect()
+    });
+
+    assert_eq!(results, [true, true]);
+}
+
+/// Check that stage timings are only included when they are enabled.
+#[test]
+fn test_matcher_stage_timings() {
+    let matcher = EncryptedMatcher::<FullBits>::builder()
+        .record_stage_timings()
+        .build();
+    assert!(matcher.records_stage_timings());
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let mask = visible_iris_mask();
+
+    let similar = matcher.enroll(&eye_b, &mask);
+
+    let start = Instant::now();
+    let outcome = matcher
+        .verify_plaintext(&eye_a, &mask, &similar)
+        .expect("matching must work");
+    let elapsed = start.elapsed();
+
+    assert!(outcome.is_match);
+    let timings = outcome.timings.expect("timings must be enabled");
+    for stage in [
+        Stage::Encode,
+        Stage::Encrypt,
+        Stage::Multiply,
+        Stage::Decrypt,
+    ] {
+        assert!(!timings.get(stage).is_zero(), "{stage} must be timed");
+    }
+    assert!(timings.total() <= elapsed);
+
+    // Timings are disabled by default.
+    let mut untimed = EncryptedMatcher::<FullBits>::builder()
+        .shared_context(matcher.shared_context().clone())
+        .build();
+    assert!(!untimed.records_stage_timings());
+
+    let outcome = untimed
+        .verify_plaintext(&eye_a, &mask, &similar)
+        .expect("matching must work");
+    assert!(outcome.is_match);
+    assert_eq!(outcome.timings, None);
+}
+
+/// Check that matchers use the supplied entropy source for keys and encryption.
+#[test]
+fn test_matcher_entropy_source() {
+    let build = |seed| {
+        EncryptedMatcher::<FullBits>::builder()
+            .entropy_source(ChaCha20Rng::seed_from_u64(seed))
+            .build()
+    };
+    let matcher = build(1);
+    let same_matcher = build(1);
+    let other_matcher = build(2);
+
+    assert_eq!(matcher.private_key(), same_matcher.private_key());
+    assert_eq!(matcher.public_key(), same_matcher.public_key());
+    assert_ne!(matcher.public_key(), other_matcher.public_key());
+
+    let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    let code = matcher.enroll(&eye, &mask);
+    assert_eq!(code, same_matcher.enroll(&eye, &mask));
+    assert_ne!(code, other_matcher.enroll(&eye, &mask));
+
+    // Later encryptions use new randomness from the source.
+    assert_ne!(code, matcher.enroll(&eye, &mask));
+}
+
+/// Check that invalid thresholds are rejected.
+#[test]
+fn test_invalid_threshold() {
+    assert_eq!(MatchThreshold::new(1, 0), None);
+    assert_eq!(MatchThreshold::new(2, 1), None);
+    assert!(MatchThreshold::new(1, 1).is_some());
+}
+
+/// Check that extreme thresholds and counts don't overflow.
+#[test]
+fn test_extreme_threshold() {
+    let max = MatchThreshold::MAX_DENOMINATOR;
+    if let Some(too_large) = max.checked_add(1) {
+        assert_eq!(MatchThreshold::new(too_large, too_large), None);
+        assert_eq!(MatchThreshold::new(1, too_large), None);
+    }
+
+    let all = MatchThreshold::new(max, max).expect("largest threshold is valid");
+    let almost_all = MatchThreshold::new(max - 1, max).expect("large threshold is valid");
+    let none = MatchThreshold::new(0, max).expect("zero is a valid threshold");
+
+    assert_eq!(all.encoded_margin(i64::MAX, i64::MAX), i64::MAX);
+    assert!(all.is_encoded_match(i64::MAX, i64::MAX));
+    assert_eq!(none.encoded_margin(i64::MIN, i64::MAX), i64::MIN);
+    assert!(!none.is_encoded_match(i64::MIN, i64::MAX));
+
+    assert!(all.is_plaintext_match(usize::MAX, usize::MAX));
+    assert!(!almost_all.is_plaintext_match(usize::MAX, usize::MAX));
+    assert!(almost_all.is_plaintext_match(max - 1, max));
+    assert!(!almost_all.is_plaintext_match(max, max));
+}
@@ -112,3 +112,206 @@ where
         );
     }
 }
+
+/// Check that out of range decrypted coefficients return an error, rather than panicking.
+#[test]
+fn test_out_of_range_coefficient() {
+    use num_bigint::BigInt;
+
+    use crate::encoded::MatchError;
+
+    let t_div_2 = BigInt::from(FullRes::T / 2);
+
+    // A coefficient between T and Q/2 can't be converted to a count.
+    let value = 1_u128 << 70;
+    let coeff = <FullRes as PolyConf>::Coeff::from(value);
+
+    let res = EncryptedPolyQuery::<FullBits>::decrypted_coeff_to_int(3, coeff, &t_div_2);
+    assert_eq!(
+        res,
+        Err(MatchError::CoefficientOutOfRange { rotation: 3, value })
+    );
+
+    // Small positive and negative coefficients are converted correctly.
+    let coeff = <FullRes as PolyConf>::Coeff::from(5_u64);
+    let res = EncryptedPolyQuery::<FullBits>::decrypted_coeff_to_int(0, coeff, &t_div_2);
+    assert_eq!(res, Ok(5));
+
+    let coeff = <FullRes as PolyConf>::Coeff::from(FullRes::T - 5);
+    let res = EncryptedPolyQuery::<FullBits>::decrypted_coeff_to_int(0, coeff, &t_div_2);
+    assert_eq!(res, Ok(-5));
+}
+
+/// Check matching and different test cases when the query and code are encrypted under different
+/// keys.
+#[test]
+fn test_key_switched_homomorphic_codes() {
+    use crate::plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask};
+
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<FullRes> = Yashe::new();
+    let (private_key_a, public_key_a) = ctx.keygen(&mut rng);
+    let (private_key_b, public_key_b) = ctx.keygen(&mut rng);
+    let key_switch_key = ctx.generate_key_switch_key(&mut rng, &private_key_a, &public_key_b);
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let eye_c = random_iris_code();
+    let mask = visible_iris_mask();
+
+    // The query is encrypted under key a, the codes under key b.
+    let poly_query: PolyQuery<FullBits> = PolyQuery::from_plaintext(&eye_a, &mask);
+    let encrypted_poly_query =
+        EncryptedPolyQuery::convert_and_encrypt_query(ctx, poly_query, &public_key_a, &mut rng);
+
+    for (description, eye, expected) in [("similar", eye_b, true), ("different", eye_c, false)] {
+        let poly_code: PolyCode<FullBits> = PolyCode::from_plaintext(&eye, &mask);
+        let encrypted_poly_code =
+            EncryptedPolyCode::convert_and_encrypt_code(ctx, poly_code, &public_key_b, &mut rng);
+
+        let res = encrypted_poly_query
+            .is_match_key_switched(ctx, &private_key_b, &key_switch_key, &encrypted_poly_code)
+            .expect("key switched matching must work");
+        assert_eq!(res, expected, "{description} key switched match result");
+    }
+}
+
+/// Check that matching records noise for every ciphertext and product, and that the default
+/// parameters stay within the noise budget.
+#[test]
+fn test_noise_tracked_homomorphic_codes() {
+    use crate::plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask};
+    use crate::primitives::yashe::noise::{NoiseAction, NoiseStage, NoiseTracker};
+
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<FullRes> = Yashe::new();
+    let (private_key, public_key) = ctx.keygen(&mut rng);
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let mask = visible_iris_mask();
+
+    let poly_query: PolyQuery<FullBits> = PolyQuery::from_plaintext(&eye_a, &mask);
+    let poly_code: PolyCode<FullBits> = PolyCode::from_plaintext(&eye_b, &mask);
+    let encrypted_poly_query =
+        EncryptedPolyQuery::convert_and_encrypt_query(ctx, poly_query, &public_key, &mut rng);
+    let encrypted_poly_code =
+        EncryptedPolyCode::convert_and_encrypt_code(ctx, poly_code, &public_key, &mut rng);
+
+    let mut noise_tracker = NoiseTracker::new(1, NoiseAction::Error);
+    let res = encrypted_poly_query
+        .is_match_with_noise_tracker(ctx, &private_key, &encrypted_poly_code, &mut noise_tracker)
+        .expect("noise must be within the budget");
+    assert!(res, "similar codes must match");
+
+    let stage_count = |stage| {
+        noise_tracker
+            .records()
+            .iter()
+            .filter(|record| record.stage == stage)
+            .count()
+    };
+    assert_eq!(stage_count(NoiseStage::Encrypt), 4 * FullBits::NUM_POLYS);
+    assert_eq!(stage_count(NoiseStage::Multiply), 2 * FullBits::NUM_POLYS);
+}
+
+/// Check that key switched matching records noise for every code ciphertext and switched product,
+/// and that the default parameters stay within the noise budget.
+#[test]
+fn test_noise_tracked_key_switched_codes() {
+    use crate::plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask};
+    use crate::primitives::yashe::noise::{NoiseAction, NoiseStage, NoiseTracker};
+
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<FullRes> = Yashe::new();
+    let (private_key_a, public_key_a) = ctx.keygen(&mut rng);
+    let (private_key_b, public_key_b) = ctx.keygen(&mut rng);
+    let key_switch_key = ctx.generate_key_switch_key(&mut rng, &private_key_a, &public_key_b);
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let mask = visible_iris_mask();
+
+    let poly_query: PolyQuery<FullBits> = PolyQuery::from_plaintext(&eye_a, &mask);
+    let poly_code: PolyCode<FullBits> = PolyCode::from_plaintext(&eye_b, &mask);
+    let encrypted_poly_query =
+        EncryptedPolyQuery::convert_and_encrypt_query(ctx, poly_query, &public_key_a, &mut rng);
+    let encrypted_poly_code =
+        EncryptedPolyCode::convert_and_encrypt_code(ctx, poly_code, &public_key_b, &mut rng);
+
+    let mut noise_tracker = NoiseTracker::new(1, NoiseAction::Error);
+    let res = encrypted_poly_query
+        .is_match_key_switched_with_noise_tracker(
+            ctx,
+            &private_key_b,
+            &key_switch_key,
+            &encrypted_poly_code,
+            &mut noise_tracker,
+        )
+        .expect("noise must be within the budget");
+    assert!(res, "similar codes must match");
+
+    let stage_count = |stage| {
+        noise_tracker
+            .records()
+            .iter()
+            .filter(|record| record.stage == stage)
+            .count()
+    };
+    assert_eq!(stage_count(NoiseStage::Encrypt), 2 * FullBits::NUM_POLYS);
+    assert_eq!(stage_count(NoiseStage::KeySwitch), 2 * FullBits::NUM_POLYS);
+    assert_eq!(stage_count(NoiseStage::Multiply), 0);
+}
+
+/// Check that the constant-time decision gives the same results as the early return decision.
+#[test]
+fn test_constant_time_decision() {
+    use crate::plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask};
+
+    use crate::iris::conf::MatchThreshold;
+
+    type Query = EncryptedPolyQuery<FullBits>;
+    let threshold = MatchThreshold::from_conf::<<FullBits as EncodeConf>::EyeConf>();
+
+    // A single rotation with identical bits is a match, wherever it is.
+    let mask_counts = vec![100; <FullBits as EncodeConf>::EyeConf::ROTATION_COMPARISONS];
+    let mut match_counts = vec![-100; <FullBits as EncodeConf>::EyeConf::ROTATION_COMPARISONS];
+    assert!(!Query::is_any_rotation_match_constant_time(
+        &match_counts,
+        &mask_counts,
+        threshold
+    ));
+    for rotation in [0, match_counts.len() / 2, match_counts.len() - 1] {
+        match_counts[rotation] = 100;
+        assert!(Query::is_any_rotation_match_constant_time(
+            &match_counts,
+            &mask_counts,
+            threshold
+        ));
+        match_counts[rotation] = -100;
+    }
+
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<FullRes> = Yashe::new();
+    let (private_key, public_key) = ctx.keygen(&mut rng);
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let eye_c = random_iris_code();
+    let mask = visible_iris_mask();
+
+    let poly_query: PolyQuery<FullBits> = PolyQuery::from_plaintext(&eye_a, &mask);
+    let encrypted_poly_query =
+        EncryptedPolyQuery::convert_and_encrypt_query(ctx, poly_query, &public_key, &mut rng);
+
+    for (description, eye, expected) in [("similar", eye_b, true), ("different", eye_c, false)] {
+        let poly_code: PolyCode<FullBits> = PolyCode::from_plaintext(&eye, &mask);
+        let encrypted_poly_code =
+            EncryptedPolyCode::convert_and_encrypt_code(ctx, poly_code, &public_key, &mut rng);
+
+        let res = encrypted_poly_query
+            .is_match_constant_time(ctx, &private_key, &encrypted_poly_code)
+            .expect("constant time matching must work");
+        assert_eq!(res, expected, "{description} constant time match result");
+    }
+}
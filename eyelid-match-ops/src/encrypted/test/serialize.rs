@@ -0,0 +1,98 @@
+//! Tests for serializing pipeline states with serde.
+
+use crate::{
+    encoded::{PolyCode, PolyQuery},
+    encrypted::{EncryptedMatcher, EncryptedPolyCode, EncryptedPolyQuery},
+    iris::conf::{DynIrisConf, IrisCode, IrisConf, IrisMask, MatchThreshold, MinVisibleBits},
+    plaintext::test::gen::{random_iris_code, rotate_not_too_much, visible_iris_mask},
+    primitives::{
+        poly::Poly,
+        yashe::{PrivateKey, Yashe},
+    },
+    FullBits, FullRes,
+};
+
+/// Serializes `value` to JSON, then deserializes it.
+fn round_trip<T>(value: &T) -> T
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let json = serde_json::to_string(value).expect("serialization must work");
+
+    serde_json::from_str(&json).expect("deserialization must work")
+}
+
+/// Check that every stage of the pipeline round-trips, and the restored states still match.
+#[test]
+fn test_pipeline_round_trip() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+    let rotated = rotate_not_too_much::<FullBits, { FullBits::STORE_ELEM_LEN }>(&eye);
+
+    let restored_eye: IrisCode<{ FullBits::STORE_ELEM_LEN }> = round_trip(&eye);
+    let restored_mask: IrisMask<{ FullBits::STORE_ELEM_LEN }> = round_trip(&mask);
+    assert_eq!(restored_eye, eye);
+    assert_eq!(restored_mask, mask);
+
+    let poly_code = PolyCode::<FullBits>::from_plaintext(&rotated, &mask);
+    let poly_query = PolyQuery::<FullBits>::from_plaintext(&eye, &mask);
+    assert_eq!(round_trip(&poly_code), poly_code);
+    assert_eq!(round_trip(&poly_query), poly_query);
+
+    let private_key: PrivateKey<FullRes> = round_trip(matcher.private_key());
+    assert_eq!(&private_key, matcher.private_key());
+    assert_eq!(round_trip(matcher.public_key()), *matcher.public_key());
+
+    let code: EncryptedPolyCode<FullBits> = round_trip(&matcher.enroll(&rotated, &mask));
+    let query: EncryptedPolyQuery<FullBits> = round_trip(&matcher.encrypt_query(&eye, &mask));
+    let ctx: Yashe<FullRes> = round_trip(&matcher.ctx());
+
+    assert!(query
+        .is_match(ctx, &private_key, &code)
+        .expect("matching must work"));
+}
+
+/// Check that invalid parameters and polynomials are rejected.
+#[test]
+fn test_invalid_values_rejected() {
+    let threshold = MatchThreshold::from_conf::<FullBits>();
+    assert_eq!(round_trip(&threshold), threshold);
+    assert!(serde_json::from_str::<MatchThreshold>(r#"{"numerator":2,"denominator":1}"#).is_err());
+
+    let conf = DynIrisConf::from_conf::<FullBits>();
+    assert_eq!(round_trip(&conf), conf);
+    let empty =
+        r#"{"columns":0,"rows":1,"rotation_limit":0,"threshold":{"numerator":1,"denominator":2}}"#;
+    assert!(serde_json::from_str::<DynIrisConf>(empty).is_err());
+    for huge in [
+        r#"{"columns":10,"rows":1,"rotation_limit":18446744073709551615,"threshold":{"numerator":1,"denominator":2}}"#,
+        r#"{"columns":10,"rows":1,"rotation_limit":9223372036854775808,"threshold":{"numerator":1,"denominator":2}}"#,
+        r#"{"columns":18446744073709551615,"rows":2,"rotation_limit":0,"threshold":{"numerator":1,"denominator":2}}"#,
+        r#"{"columns":18446744073709551615,"rows":1,"rotation_limit":0,"threshold":{"numerator":1,"denominator":2}}"#,
+    ] {
+        assert!(serde_json::from_str::<DynIrisConf>(huge).is_err(), "{huge}");
+    }
+
+    let min_visible = MinVisibleBits::fraction(1, 2).expect("half is a valid fraction");
+    assert_eq!(round_trip(&min_visible), min_visible);
+    assert_eq!(
+        round_trip(&MinVisibleBits::Absolute(5)),
+        MinVisibleBits::Absolute(5)
+    );
+    for fraction in [
+        r#"{"Fraction":{"numerator":1,"denominator":0}}"#,
+        r#"{"Fraction":{"numerator":2,"denominator":1}}"#,
+        r#"{"Fraction":{"numerator":1,"denominator":18446744073709551615}}"#,
+    ] {
+        assert!(
+            serde_json::from_str::<MinVisibleBits>(fraction).is_err(),
+            "{fraction}"
+        );
+    }
+
+    // Coefficients must be reduced modulo the field modulus.
+    let unreduced = format!("[{:?}]", [u8::MAX; 16]);
+    assert!(serde_json::from_str::<Poly<FullRes>>(&unreduced).is_err());
+}
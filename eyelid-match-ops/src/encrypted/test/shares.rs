@@ -0,0 +1,65 @@
+//! Tests for secret-shared encrypted matching output.
+
+use crate::encoded::{PolyCode, PolyQuery};
+use crate::encrypted::{EncryptedPolyCode, EncryptedPolyQuery};
+use crate::iris::conf::{IrisConf, MatchThreshold};
+use crate::plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask};
+use crate::primitives::yashe::Yashe;
+use crate::{FullBits, FullRes};
+
+/// Check that combining the server and client shares gives the same match results, and that the
+/// shares don't contain the counts themselves.
+#[test]
+fn test_masked_shares() {
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<FullRes> = Yashe::new();
+    let (private_key, public_key) = ctx.keygen(&mut rng);
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let eye_c = random_iris_code();
+    let mask = visible_iris_mask();
+
+    let poly_query: PolyQuery<FullBits> = PolyQuery::from_plaintext(&eye_a, &mask);
+    let encrypted_poly_query =
+        EncryptedPolyQuery::convert_and_encrypt_query(ctx, poly_query, &public_key, &mut rng);
+
+    for (description, eye, expected) in [("similar", eye_b, true), ("different", eye_c, false)] {
+        let poly_code: PolyCode<FullBits> = PolyCode::from_plaintext(&eye, &mask);
+        let encrypted_poly_code =
+            EncryptedPolyCode::convert_and_encrypt_code(ctx, poly_code, &public_key, &mut rng);
+
+        let (masked_products, server_share) =
+            encrypted_poly_query.masked_products(ctx, &public_key, &encrypted_poly_code, &mut rng);
+        let client_share = masked_products.decrypt_share(ctx, &private_key);
+
+        let res = server_share
+            .is_match(&client_share)
+            .expect("combined shares must be in range");
+        assert_eq!(res, expected, "{description} masked share match result");
+
+        // Runtime thresholds are used instead of the configured threshold.
+        let strict = MatchThreshold::new(0, 1).expect("zero is a valid threshold");
+        assert!(
+            !server_share
+                .is_match_with_threshold(&client_share, strict)
+                .expect("combined shares must be in range"),
+            "{description} masked share must not match with a zero threshold"
+        );
+
+        // Every rotation of a fully visible mask has the same number of visible bits,
+        // but the masked shares are random.
+        let (_match_counts, mask_counts) = server_share
+            .combine(&client_share)
+            .expect("combined shares must be in range");
+        assert!(
+            mask_counts.iter().all(|t| *t == mask_counts[0]),
+            "{description} mask counts must be equal: {mask_counts:?}"
+        );
+        assert_ne!(
+            client_share.mask_counts[0].iter().min(),
+            client_share.mask_counts[0].iter().max(),
+            "{description} client share must be masked"
+        );
+    }
+}
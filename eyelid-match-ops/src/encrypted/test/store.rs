@@ -0,0 +1,70 @@
+//! Tests for encrypted code storage and search.
+
+use crate::encrypted::store::{EncryptedCodeStore, MemoryCodeStore};
+use crate::encrypted::EncryptedMatcher;
+use crate::iris::conf::IrisConf;
+use crate::plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask};
+use crate::FullBits;
+
+/// Check that searching a store returns the ids of the matching codes.
+#[test]
+fn test_search_memory_store() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye_a = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let eye_b = similar_iris_code(&eye_a);
+    let eye_c = random_iris_code();
+    let mask = visible_iris_mask();
+
+    let mut store = MemoryCodeStore::<FullBits>::new();
+    for (id, eye) in [(7, eye_c), (3, eye_b)] {
+        store
+            .put(id, matcher.enroll(&eye, &mask))
+            .expect("memory store never fails");
+    }
+    assert_eq!(store.len(), 2);
+    assert!(store.get(3).expect("memory store never fails").is_some());
+    assert!(store.get(4).expect("memory store never fails").is_none());
+
+    let encrypted_poly_query = matcher.encrypt_query(&eye_a, &mask);
+
+    let matches = encrypted_poly_query
+        .search(matcher.ctx(), matcher.private_key(), &store)
+        .expect("searching must work");
+    assert_eq!(matches, vec![3]);
+}
+
+/// Check that codes round-trip through a sled store, in id order.
+#[cfg(feature = "sled")]
+#[test]
+fn test_sled_store() {
+    use crate::encrypted::store::SledCodeStore;
+
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+    let mask = visible_iris_mask();
+
+    let db = sled::Config::new()
+        .temporary(true)
+        .open()
+        .expect("temporary database must open");
+    let mut store = SledCodeStore::new(db.open_tree("codes").expect("tree must open"));
+
+    let mut codes = Vec::new();
+    for id in [300, 2] {
+        let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+        let encrypted_poly_code = matcher.enroll(&eye, &mask);
+        store
+            .put(id, encrypted_poly_code.clone())
+            .expect("sled store must work");
+        codes.push((id, encrypted_poly_code));
+    }
+
+    let stored = EncryptedCodeStore::<FullBits>::get(&store, 300).expect("sled store must work");
+    assert_eq!(stored.as_ref(), Some(&codes[0].1));
+
+    let scanned = EncryptedCodeStore::<FullBits>::scan(&store)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("sled store must work");
+    codes.reverse();
+    assert_eq!(scanned, codes);
+}
@@ -0,0 +1,40 @@
+//! Tests for per-stage match timings.
+
+use std::time::Duration;
+
+use crate::encrypted::{Stage, StageTimings};
+
+/// Check that stage timings are accumulated and summed correctly.
+#[test]
+fn test_stage_timings() {
+    let mut timings = StageTimings::default();
+    assert_eq!(timings.total(), Duration::ZERO);
+
+    for (millis, stage) in (1..).zip(Stage::ALL) {
+        *timings.get_mut(stage) += Duration::from_millis(millis);
+    }
+    *timings.get_mut(Stage::Decide) += Duration::from_millis(5);
+
+    assert_eq!(timings.encode, Duration::from_millis(1));
+    assert_eq!(timings.multiply, Duration::from_millis(3));
+    assert_eq!(timings.decide, Duration::from_millis(10));
+    assert_eq!(timings.total(), Duration::from_millis(20));
+
+    // Stages are listed in matching order.
+    let stages: Vec<_> = timings.iter().map(|(stage, _)| stage).collect();
+    assert_eq!(stages, Stage::ALL);
+
+    let value = timings.time(Stage::Encrypt, || 42);
+    assert_eq!(value, 42);
+    assert!(timings.encrypt >= Duration::from_millis(2));
+}
+
+/// Check that stage names match their display format.
+#[test]
+fn test_stage_names() {
+    let names: Vec<_> = Stage::ALL.iter().map(Stage::to_string).collect();
+    assert_eq!(
+        names,
+        ["encode", "encrypt", "multiply", "decrypt", "decide"]
+    );
+}
@@ -0,0 +1,221 @@
+//! Tests for the versioned encrypted code encoding.
+
+use crate::encrypted::shares::{DistanceShare, MaskedProducts};
+use crate::encrypted::store::StoreError;
+use crate::encrypted::wire::{
+    HEADER_LEN, KEY_MAGIC, MAGIC, PRODUCTS_MAGIC, QUERY_MAGIC, SHARE_MAGIC,
+};
+use crate::encrypted::{EncryptedMatcher, EncryptedPolyCode, EncryptedPolyQuery};
+use crate::iris::conf::IrisConf;
+use crate::plaintext::test::gen::{random_iris_code, visible_iris_mask};
+use crate::primitives::yashe::{PrivateKey, PublicKey, Yashe};
+use crate::{FullBits, TestRes};
+
+/// Returns a newly encrypted code.
+fn encrypted_code() -> EncryptedPolyCode<FullBits> {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    matcher.enroll(&eye, &mask)
+}
+
+/// Check that codes round-trip through the current encoding, and invalid encodings are rejected.
+#[test]
+fn test_encoding_versions() {
+    let code = encrypted_code();
+
+    let bytes = code.to_bytes();
+    assert!(bytes.starts_with(&MAGIC));
+    assert_eq!(
+        u16::from_le_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]),
+        EncryptedPolyCode::<FullBits>::VERSION
+    );
+
+    let decoded = EncryptedPolyCode::<FullBits>::from_bytes(&bytes).expect("decoding must work");
+    assert_eq!(decoded, code);
+    let migrated = EncryptedPolyCode::<FullBits>::migrate(&bytes).expect("migration must work");
+    assert_eq!(migrated, code);
+
+    // Bodies without a header are rejected, because their parameters can't be checked.
+    let headerless_bytes = &bytes[HEADER_LEN..];
+    assert!(matches!(
+        EncryptedPolyCode::<FullBits>::from_bytes(headerless_bytes),
+        Err(StoreError::InvalidEncoding)
+    ));
+    assert!(matches!(
+        EncryptedPolyCode::<FullBits>::migrate(headerless_bytes),
+        Err(StoreError::InvalidEncoding)
+    ));
+
+    // Unknown future versions are rejected.
+    let mut future_bytes = bytes.clone();
+    future_bytes[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+    assert!(matches!(
+        EncryptedPolyCode::<FullBits>::migrate(&future_bytes),
+        Err(StoreError::UnsupportedVersion(u16::MAX))
+    ));
+
+    // Codes encrypted with different parameters are rejected.
+    let mut other_params_bytes = bytes;
+    other_params_bytes[HEADER_LEN - 1] ^= 1;
+    assert!(matches!(
+        EncryptedPolyCode::<FullBits>::migrate(&other_params_bytes),
+        Err(StoreError::InvalidEncoding)
+    ));
+}
+
+/// Check that queries round-trip through their encoding, and codes are rejected.
+#[test]
+fn test_query_encoding() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+    let query = matcher.encrypt_query(&eye, &mask);
+    let code = matcher.enroll(&eye, &mask);
+
+    let bytes = query.to_bytes();
+    assert!(bytes.starts_with(&QUERY_MAGIC));
+    assert_eq!(
+        EncryptedPolyQuery::<FullBits>::from_bytes(&bytes).expect("decoding must work"),
+        query
+    );
+
+    // Codes and queries can't be confused.
+    assert!(matches!(
+        EncryptedPolyQuery::<FullBits>::from_bytes(&code.to_bytes()),
+        Err(StoreError::InvalidEncoding)
+    ));
+    assert!(matches!(
+        EncryptedPolyCode::<FullBits>::from_bytes(&bytes),
+        Err(StoreError::InvalidEncoding)
+    ));
+}
+
+/// Check that keys round-trip through their encodings, and other encodings are rejected.
+#[test]
+fn test_key_encoding() {
+    let ctx: Yashe<TestRes> = Yashe::new();
+    let (private_key, public_key) = ctx.keygen(&mut rand::thread_rng());
+
+    let private_bytes = private_key.to_bytes();
+    let public_bytes = public_key.to_bytes();
+    assert!(private_bytes.starts_with(&KEY_MAGIC));
+    assert!(public_bytes.starts_with(&KEY_MAGIC));
+
+    assert_eq!(
+        PrivateKey::<TestRes>::from_bytes(&private_bytes).expect("decoding must work"),
+        private_key
+    );
+    assert_eq!(
+        PublicKey::<TestRes>::from_bytes(&public_bytes).expect("decoding must work"),
+        public_key
+    );
+
+    // Keys of the wrong kind are rejected.
+    assert!(matches!(
+        PublicKey::<TestRes>::from_bytes(&private_bytes),
+        Err(StoreError::InvalidEncoding)
+    ));
+
+    // Codes are rejected.
+    let mut code_bytes = public_bytes;
+    code_bytes[..MAGIC.len()].copy_from_slice(&MAGIC);
+    assert!(matches!(
+        PublicKey::<TestRes>::from_bytes(&code_bytes),
+        Err(StoreError::InvalidEncoding)
+    ));
+}
+
+/// Check that a sled store only reads and migrates versioned encodings.
+#[cfg(feature = "sled")]
+#[test]
+fn test_sled_store_migration() {
+    use crate::encrypted::store::{EncryptedCodeStore, SledCodeStore};
+
+    let code = encrypted_code();
+    let bytes = code.to_bytes();
+
+    let db = sled::Config::new()
+        .temporary(true)
+        .open()
+        .expect("temporary database must open");
+    let tree = db.open_tree("codes").expect("tree must open");
+    tree.insert(2_u64.to_be_bytes(), bytes.as_slice())
+        .expect("sled must work");
+
+    let store = SledCodeStore::new(tree.clone());
+    let stored = EncryptedCodeStore::<FullBits>::get(&store, 2).expect("sled store must work");
+    assert_eq!(stored.as_ref(), Some(&code));
+
+    // Codes in the current version don't need to be migrated.
+    assert_eq!(store.migrate::<FullBits>().expect("migration must work"), 0);
+
+    // Bodies without a header are rejected by reads and migrations.
+    tree.insert(1_u64.to_be_bytes(), &bytes[HEADER_LEN..])
+        .expect("sled must work");
+    assert!(matches!(
+        EncryptedCodeStore::<FullBits>::get(&store, 1),
+        Err(StoreError::InvalidEncoding)
+    ));
+    assert!(matches!(
+        EncryptedCodeStore::<FullBits>::scan(&store).next(),
+        Some(Err(StoreError::InvalidEncoding))
+    ));
+    assert!(matches!(
+        store.migrate::<FullBits>(),
+        Err(StoreError::InvalidEncoding)
+    ));
+}
+
+/// Check that the messages of the secret-shared output protocol round-trip through their
+/// encodings, and invalid shares are rejected.
+#[test]
+fn test_share_encoding() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+
+    let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+    let query = matcher.encrypt_query(&eye, &mask);
+    let code = matcher.enroll(&eye, &mask);
+
+    let (masked_products, server_share) = query.masked_products(
+        matcher.ctx(),
+        matcher.public_key(),
+        &code,
+        &mut rand::thread_rng(),
+    );
+
+    let products_bytes = masked_products.to_bytes();
+    assert!(products_bytes.starts_with(&PRODUCTS_MAGIC));
+    let decoded_products =
+        MaskedProducts::<FullBits>::from_bytes(&products_bytes).expect("decoding must work");
+    assert_eq!(decoded_products, masked_products);
+
+    let client_share = decoded_products.decrypt_share(matcher.ctx(), matcher.private_key());
+    let share_bytes = client_share.to_bytes();
+    assert!(share_bytes.starts_with(&SHARE_MAGIC));
+    let decoded_share =
+        DistanceShare::<FullBits>::from_bytes(&share_bytes).expect("decoding must work");
+    assert_eq!(decoded_share, client_share);
+    assert!(server_share
+        .is_match(&decoded_share)
+        .expect("combined shares must be in range"));
+
+    // Shares with missing counts would panic when combined, so they are rejected.
+    let short_share = DistanceShare::<FullBits>::new(vec![vec![0]], vec![vec![0]]);
+    assert!(matches!(
+        DistanceShare::<FullBits>::from_bytes(&short_share.to_bytes()),
+        Err(StoreError::InvalidEncoding)
+    ));
+    assert!(matches!(
+        DistanceShare::<FullBits>::from_bytes(&share_bytes[..share_bytes.len() - 1]),
+        Err(StoreError::InvalidEncoding)
+    ));
+    assert!(matches!(
+        MaskedProducts::<FullBits>::from_bytes(&share_bytes),
+        Err(StoreError::InvalidEncoding)
+    ));
+}
@@ -0,0 +1,118 @@
+//! Per-stage latency breakdowns for encrypted matches.
+//!
+//! When [`EncryptedMatcherBuilder::record_stage_timings()`] is enabled, each
+//! [`EncryptedMatchOutcome`] includes the time taken by each matching [`Stage`], so operators can
+//! see where time goes without attaching a profiler.
+//!
+//! [`EncryptedMatcherBuilder::record_stage_timings()`]: crate::encrypted::EncryptedMatcherBuilder::record_stage_timings
+//! [`EncryptedMatchOutcome`]: crate::encrypted::EncryptedMatchOutcome
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// A stage of an encrypted match.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stage {
+    /// Encoding and converting the plaintext query into polynomials.
+    Encode,
+    /// Encrypting the query polynomials.
+    Encrypt,
+    /// Multiplying the encrypted query and stored code.
+    Multiply,
+    /// Decrypting the products.
+    Decrypt,
+    /// Extracting the counts from the decrypted products, and making the match decision.
+    Decide,
+}
+
+/// The time taken by each stage of an encrypted match.
+///
+/// Stages which weren't performed, like encoding and encrypting pre-encrypted queries, are zero.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StageTimings {
+    /// The time taken to encode the query.
+    pub encode: Duration,
+    /// The time taken to encrypt the query.
+    pub encrypt: Duration,
+    /// The time taken to multiply the query and code.
+    pub multiply: Duration,
+    /// The time taken to decrypt the products.
+    pub decrypt: Duration,
+    /// The time taken to make the match decision.
+    pub decide: Duration,
+}
+
+impl Stage {
+    /// Every stage, in matching order.
+    pub const ALL: [Stage; 5] = [
+        Stage::Encode,
+        Stage::Encrypt,
+        Stage::Multiply,
+        Stage::Decrypt,
+        Stage::Decide,
+    ];
+
+    /// Returns the name of the stage, for logging.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Encode => "encode",
+            Stage::Encrypt => "encrypt",
+            Stage::Multiply => "multiply",
+            Stage::Decrypt => "decrypt",
+            Stage::Decide => "decide",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl StageTimings {
+    /// Returns the time taken by `stage`.
+    pub fn get(&self, stage: Stage) -> Duration {
+        match stage {
+            Stage::Encode => self.encode,
+            Stage::Encrypt => self.encrypt,
+            Stage::Multiply => self.multiply,
+            Stage::Decrypt => self.decrypt,
+            Stage::Decide => self.decide,
+        }
+    }
+
+    /// Returns a mutable reference to the time taken by `stage`.
+    pub fn get_mut(&mut self, stage: Stage) -> &mut Duration {
+        match stage {
+            Stage::Encode => &mut self.encode,
+            Stage::Encrypt => &mut self.encrypt,
+            Stage::Multiply => &mut self.multiply,
+            Stage::Decrypt => &mut self.decrypt,
+            Stage::Decide => &mut self.decide,
+        }
+    }
+
+    /// Returns each stage and the time it took, in matching order.
+    pub fn iter(&self) -> impl Iterator<Item = (Stage, Duration)> + '_ {
+        Stage::ALL.into_iter().map(|stage| (stage, self.get(stage)))
+    }
+
+    /// Returns the total time taken by all the stages.
+    pub fn total(&self) -> Duration {
+        self.iter().map(|(_, duration)| duration).sum()
+    }
+
+    /// Runs `f`, and adds the time it took to `stage`.
+    pub(crate) fn time<T>(&mut self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        *self.get_mut(stage) += start.elapsed();
+
+        result
+    }
+}
@@ -0,0 +1,504 @@
+//! A versioned byte encoding for stored encrypted codes.
+//!
+//! Encoded codes start with a header containing [`MAGIC`], the format version, and the
+//! encryption parameters. The header is followed by the data ciphertexts, then the mask
+//! ciphertexts. Each list of ciphertexts and each ciphertext is prefixed by its length, and
+//! coefficients are stored in little-endian order.
+//!
+//! Queries use the same layout as codes, starting with [`QUERY_MAGIC`]. Keys use the same
+//! header, starting with [`KEY_MAGIC`], followed by their polynomials.
+//!
+//! The secret-shared output protocol in [`shares`](crate::encrypted::shares) uses the same
+//! layout for masked products, starting with [`PRODUCTS_MAGIC`]. Distance shares start with
+//! [`SHARE_MAGIC`], followed by the match counts, then the mask counts. Each list of counts is
+//! prefixed by its number of blocks, and each block is prefixed by its number of rotations.
+
+use crate::{
+    encrypted::{
+        shares::{DistanceShare, MaskedProducts},
+        store::StoreError,
+        EncryptedPolyCode, EncryptedPolyQuery,
+    },
+    iris::conf::IrisConf,
+    primitives::{
+        poly::Poly,
+        yashe::{Ciphertext, PrivateKey, PublicKey},
+    },
+    EncodeConf, PolyConf, YasheConf,
+};
+
+/// The bytes at the start of every versioned encoding.
+pub const MAGIC: [u8; 4] = *b"EYEC";
+
+/// The bytes at the start of every query encoding.
+pub const QUERY_MAGIC: [u8; 4] = *b"EYEQ";
+
+/// The bytes at the start of every key encoding.
+pub const KEY_MAGIC: [u8; 4] = *b"EYEK";
+
+/// The bytes at the start of every masked products encoding.
+pub const PRODUCTS_MAGIC: [u8; 4] = *b"EYEP";
+
+/// The bytes at the start of every distance share encoding.
+pub const SHARE_MAGIC: [u8; 4] = *b"EYES";
+
+/// The current version of the key encodings.
+pub const KEY_VERSION: u16 = 1;
+
+/// The length of the versioned header: magic, version, degree, modulus, and T.
+pub const HEADER_LEN: usize = MAGIC.len() + 2 + LEN_BYTES + COEFF_BYTES + 8;
+
+/// The number of bytes used to store each coefficient.
+const COEFF_BYTES: usize = 16;
+
+/// The number of bytes used to store each length.
+const LEN_BYTES: usize = 4;
+
+impl<C: EncodeConf> EncryptedPolyCode<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The current version of the byte encoding produced by [`EncryptedPolyCode::to_bytes()`].
+    pub const VERSION: u16 = 1;
+
+    /// Encodes `self` as bytes, using the current [`EncryptedPolyCode::VERSION`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        encode_header::<C::PlainConf>(&mut bytes, MAGIC, Self::VERSION);
+        encode_body(&mut bytes, &self.data, &self.masks);
+
+        bytes
+    }
+
+    /// Decodes a code encoded by [`EncryptedPolyCode::to_bytes()`].
+    ///
+    /// Only the current version is accepted, use [`EncryptedPolyCode::migrate()`] for older
+    /// encodings. Returns [`StoreError::InvalidEncoding`] if the code was encoded with different
+    /// encryption parameters.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StoreError> {
+        match decode_header::<C::PlainConf>(bytes, MAGIC)? {
+            (Self::VERSION, body) => Self::decode(body),
+            (version, _) => Err(StoreError::UnsupportedVersion(version)),
+        }
+    }
+
+    /// Decodes a code encoded by any supported version, upgrading it to the current layout.
+    ///
+    /// Re-encoding the result with [`EncryptedPolyCode::to_bytes()`] completes the migration.
+    /// Codes encrypted with different encryption parameters can't be migrated, because they
+    /// would need to be decrypted and re-encrypted.
+    ///
+    /// Returns [`StoreError::InvalidEncoding`] if the bytes don't start with a versioned header.
+    pub fn migrate(old_bytes: &[u8]) -> Result<Self, StoreError> {
+        // When the layout changes, older versions are decoded here.
+        match decode_header::<C::PlainConf>(old_bytes, MAGIC)? {
+            (Self::VERSION, body) => Self::decode(body),
+            (version, _) => Err(StoreError::UnsupportedVersion(version)),
+        }
+    }
+
+    /// Decodes a code from the body of its encoding.
+    fn decode(body: &[u8]) -> Result<Self, StoreError> {
+        let (data, masks) = decode_body::<C>(body)?;
+
+        Ok(Self { data, masks })
+    }
+}
+
+impl<C: EncodeConf> EncryptedPolyQuery<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The current version of the byte encoding produced by [`EncryptedPolyQuery::to_bytes()`].
+    pub const VERSION: u16 = 1;
+
+    /// Encodes `self` as bytes, using the current [`EncryptedPolyQuery::VERSION`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        encode_header::<C::PlainConf>(&mut bytes, QUERY_MAGIC, Self::VERSION);
+        encode_body(&mut bytes, &self.data, &self.masks);
+
+        bytes
+    }
+
+    /// Decodes a query encoded by [`EncryptedPolyQuery::to_bytes()`].
+    ///
+    /// Returns [`StoreError::InvalidEncoding`] if the query was encoded with different
+    /// encryption parameters.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StoreError> {
+        match decode_header::<C::PlainConf>(bytes, QUERY_MAGIC)? {
+            (Self::VERSION, body) => {
+                let (data, masks) = decode_body::<C>(body)?;
+
+                Ok(Self { data, masks })
+            }
+            (version, _) => Err(StoreError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+impl<C: EncodeConf> MaskedProducts<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The current version of the byte encoding produced by [`MaskedProducts::to_bytes()`].
+    pub const VERSION: u16 = 1;
+
+    /// Encodes `self` as bytes, using the current [`MaskedProducts::VERSION`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        encode_header::<C::PlainConf>(&mut bytes, PRODUCTS_MAGIC, Self::VERSION);
+        encode_body(&mut bytes, &self.data, &self.masks);
+
+        bytes
+    }
+
+    /// Decodes masked products encoded by [`MaskedProducts::to_bytes()`].
+    ///
+    /// Returns [`StoreError::InvalidEncoding`] if the products were encoded with different
+    /// encryption parameters.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StoreError> {
+        match decode_header::<C::PlainConf>(bytes, PRODUCTS_MAGIC)? {
+            (Self::VERSION, body) => {
+                let (data, masks) = decode_body::<C>(body)?;
+
+                Ok(Self { data, masks })
+            }
+            (version, _) => Err(StoreError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+impl<C: EncodeConf> DistanceShare<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The current version of the byte encoding produced by [`DistanceShare::to_bytes()`].
+    pub const VERSION: u16 = 1;
+
+    /// Encodes `self` as bytes, using the current [`DistanceShare::VERSION`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        encode_header::<C::PlainConf>(&mut bytes, SHARE_MAGIC, Self::VERSION);
+
+        for counts in [&self.match_counts, &self.mask_counts] {
+            encode_len(&mut bytes, counts.len());
+
+            for block in counts {
+                encode_len(&mut bytes, block.len());
+
+                for count in block {
+                    bytes.extend_from_slice(&count.to_le_bytes());
+                }
+            }
+        }
+
+        bytes
+    }
+
+    /// Decodes a share encoded by [`DistanceShare::to_bytes()`].
+    ///
+    /// Returns [`StoreError::InvalidEncoding`] if the share was encoded with different encryption
+    /// parameters, doesn't have a count for every block and rotation, or has a count that isn't
+    /// reduced modulo T. So decoded shares can always be combined.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StoreError> {
+        let mut bytes = match decode_header::<C::PlainConf>(bytes, SHARE_MAGIC)? {
+            (Self::VERSION, body) => body,
+            (version, _) => return Err(StoreError::UnsupportedVersion(version)),
+        };
+
+        let match_counts = decode_counts::<C>(&mut bytes)?;
+        let mask_counts = decode_counts::<C>(&mut bytes)?;
+
+        if !bytes.is_empty() {
+            return Err(StoreError::InvalidEncoding);
+        }
+
+        Ok(Self::new(match_counts, mask_counts))
+    }
+}
+
+impl<C: YasheConf> PrivateKey<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Encodes `self` as bytes, using the current [`KEY_VERSION`].
+    ///
+    /// The encoding contains secret key material, so it must be stored securely.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        encode_header::<C>(&mut bytes, KEY_MAGIC, KEY_VERSION);
+        for poly in [&self.f, &self.priv_key_inv, &self.priv_key] {
+            encode_poly(&mut bytes, poly);
+        }
+
+        bytes
+    }
+
+    /// Decodes a private key encoded by [`PrivateKey::to_bytes()`].
+    ///
+    /// Returns [`StoreError::InvalidEncoding`] if the key was generated with different
+    /// encryption parameters.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StoreError> {
+        let mut bytes = decode_key_header::<C>(bytes)?;
+
+        let key = Self {
+            f: decode_poly(&mut bytes)?,
+            priv_key_inv: decode_poly(&mut bytes)?,
+            priv_key: decode_poly(&mut bytes)?,
+        };
+
+        if !bytes.is_empty() {
+            return Err(StoreError::InvalidEncoding);
+        }
+
+        Ok(key)
+    }
+}
+
+impl<C: YasheConf> PublicKey<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Encodes `self` as bytes, using the current [`KEY_VERSION`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        encode_header::<C>(&mut bytes, KEY_MAGIC, KEY_VERSION);
+        encode_poly(&mut bytes, &self.h);
+
+        bytes
+    }
+
+    /// Decodes a public key encoded by [`PublicKey::to_bytes()`].
+    ///
+    /// Returns [`StoreError::InvalidEncoding`] if the key was generated with different
+    /// encryption parameters.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StoreError> {
+        let mut bytes = decode_key_header::<C>(bytes)?;
+
+        let key = Self {
+            h: decode_poly(&mut bytes)?,
+        };
+
+        if !bytes.is_empty() {
+            return Err(StoreError::InvalidEncoding);
+        }
+
+        Ok(key)
+    }
+}
+
+/// Appends a versioned header starting with `magic` to `bytes`.
+pub(crate) fn encode_header<C: YasheConf>(bytes: &mut Vec<u8>, magic: [u8; 4], version: u16)
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    bytes.extend_from_slice(&magic);
+    bytes.extend_from_slice(&version.to_le_bytes());
+    encode_params::<C>(bytes);
+}
+
+/// Appends the encryption parameters in the header to `bytes`: degree, modulus, and T.
+pub(crate) fn encode_params<C: YasheConf>(bytes: &mut Vec<u8>)
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    encode_len(bytes, C::MAX_POLY_DEGREE);
+    bytes.extend_from_slice(&C::modulus_as_u128().to_le_bytes());
+    bytes.extend_from_slice(&C::T.to_le_bytes());
+}
+
+/// Checks the key header at the start of `bytes`, and returns the bytes after the header.
+fn decode_key_header<C: YasheConf>(bytes: &[u8]) -> Result<&[u8], StoreError>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    match decode_header::<C>(bytes, KEY_MAGIC)? {
+        (KEY_VERSION, body) => Ok(body),
+        (version, _) => Err(StoreError::UnsupportedVersion(version)),
+    }
+}
+
+/// Appends the data ciphertexts, then the mask ciphertexts, of a code or query to `bytes`.
+fn encode_body<C: YasheConf>(bytes: &mut Vec<u8>, data: &[Ciphertext<C>], masks: &[Ciphertext<C>])
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    for ciphertexts in [data, masks] {
+        encode_len(bytes, ciphertexts.len());
+
+        for ciphertext in ciphertexts {
+            encode_poly(bytes, &ciphertext.c);
+        }
+    }
+}
+
+/// Appends the length and coefficients of `poly` to `bytes`.
+fn encode_poly<C: YasheConf>(bytes: &mut Vec<u8>, poly: &Poly<C>)
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    encode_len(bytes, poly.coeffs.len());
+
+    for coeff in poly.coeffs.iter() {
+        bytes.extend_from_slice(&C::coeff_as_u128(*coeff).to_le_bytes());
+    }
+}
+
+/// Checks the versioned header starting with `magic` at the start of `bytes`.
+/// Returns the version, and the bytes after the header.
+pub(crate) fn decode_header<C: YasheConf>(
+    mut bytes: &[u8],
+    magic: [u8; 4],
+) -> Result<(u16, &[u8]), StoreError>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    if take_bytes::<{ MAGIC.len() }>(&mut bytes)? != magic {
+        return Err(StoreError::InvalidEncoding);
+    }
+
+    let version = u16::from_le_bytes(take_bytes(&mut bytes)?);
+
+    let degree = decode_len(&mut bytes)?;
+    let modulus = u128::from_le_bytes(take_bytes(&mut bytes)?);
+    let t = u64::from_le_bytes(take_bytes(&mut bytes)?);
+
+    if degree != C::MAX_POLY_DEGREE || modulus != C::modulus_as_u128() || t != C::T {
+        return Err(StoreError::InvalidEncoding);
+    }
+
+    Ok((version, bytes))
+}
+
+/// Decodes the data and mask ciphertexts encoded by [`encode_body()`].
+#[allow(clippy::type_complexity)]
+fn decode_body<C: EncodeConf>(
+    mut bytes: &[u8],
+) -> Result<(Vec<Ciphertext<C::PlainConf>>, Vec<Ciphertext<C::PlainConf>>), StoreError>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let data = decode_ciphertexts::<C>(&mut bytes)?;
+    let masks = decode_ciphertexts::<C>(&mut bytes)?;
+
+    if !bytes.is_empty() || data.len() != C::NUM_POLYS || masks.len() != C::NUM_POLYS {
+        return Err(StoreError::InvalidEncoding);
+    }
+
+    Ok((data, masks))
+}
+
+/// Decodes a list of ciphertexts from the start of `bytes`, and advances `bytes` past them.
+fn decode_ciphertexts<C: EncodeConf>(
+    bytes: &mut &[u8],
+) -> Result<Vec<Ciphertext<C::PlainConf>>, StoreError>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let len = decode_len(bytes)?;
+
+    (0..len)
+        .map(|_| {
+            Ok(Ciphertext {
+                c: decode_poly(bytes)?,
+            })
+        })
+        .collect()
+}
+
+/// Decodes the per-block, per-rotation counts of a distance share from the start of `bytes`, and
+/// advances `bytes` past them.
+fn decode_counts<C: EncodeConf>(bytes: &mut &[u8]) -> Result<Vec<Vec<u64>>, StoreError>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    if decode_len(bytes)? != C::NUM_POLYS {
+        return Err(StoreError::InvalidEncoding);
+    }
+
+    (0..C::NUM_POLYS)
+        .map(|_| {
+            if decode_len(bytes)? != C::EyeConf::ROTATION_COMPARISONS {
+                return Err(StoreError::InvalidEncoding);
+            }
+
+            (0..C::EyeConf::ROTATION_COMPARISONS)
+                .map(|_| {
+                    let count = u64::from_le_bytes(take_bytes(bytes)?);
+
+                    if count >= C::PlainConf::T {
+                        return Err(StoreError::InvalidEncoding);
+                    }
+
+                    Ok(count)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Decodes a polynomial from the start of `bytes`, and advances `bytes` past it.
+fn decode_poly<C: YasheConf>(bytes: &mut &[u8]) -> Result<Poly<C>, StoreError>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let coeff_len = decode_len(bytes)?;
+    if coeff_len > C::MAX_POLY_DEGREE {
+        return Err(StoreError::InvalidEncoding);
+    }
+
+    let coeffs = (0..coeff_len)
+        .map(|_| {
+            let coeff = take_bytes::<COEFF_BYTES>(bytes)?;
+            let coeff = u128::from_le_bytes(coeff);
+
+            if coeff >= C::modulus_as_u128() {
+                return Err(StoreError::InvalidEncoding);
+            }
+
+            Ok(C::Coeff::from(coeff))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Poly::from_coefficients_vec(coeffs))
+}
+
+/// Appends `len` to `bytes`.
+fn encode_len(bytes: &mut Vec<u8>, len: usize) {
+    // Lengths are bounded by the polynomial degree and number of polynomials.
+    #[allow(clippy::cast_possible_truncation)]
+    bytes.extend_from_slice(&(len as u32).to_le_bytes());
+}
+
+/// Decodes a length from the start of `bytes`, and advances `bytes` past it.
+fn decode_len(bytes: &mut &[u8]) -> Result<usize, StoreError> {
+    let len = u32::from_le_bytes(take_bytes::<LEN_BYTES>(bytes)?);
+
+    usize::try_from(len).map_err(|_| StoreError::InvalidEncoding)
+}
+
+/// Removes `N` bytes from the start of `bytes`, and returns them.
+pub(crate) fn take_bytes<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], StoreError> {
+    if bytes.len() < N {
+        return Err(StoreError::InvalidEncoding);
+    }
+
+    let (taken, rest) = bytes.split_at(N);
+    *bytes = rest;
+
+    taken.try_into().map_err(|_| StoreError::InvalidEncoding)
+}
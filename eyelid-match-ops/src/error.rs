@@ -0,0 +1,63 @@
+//! The library-wide error type.
+//!
+//! Each module returns its own specific error type, so callers can handle individual failures.
+//! All of those errors convert into [`Error`] using `?`, for callers which just need to report
+//! them.
+
+pub use crate::{
+    iris::{interop::InteropError, io::IoError},
+    pipeline::TemplateId,
+};
+
+#[cfg(feature = "fhe")]
+pub use crate::{encoded::MatchError, encrypted::store::StoreError, primitives::poly::PolyError};
+
+#[cfg(feature = "config")]
+pub use crate::config::ConfigError;
+
+#[cfg(feature = "key-file")]
+pub use crate::encrypted::keyfile::KeyFileError;
+
+/// A result with the library-wide [`Error`] type.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors that can happen anywhere in this library.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A polynomial operation failed.
+    #[cfg(feature = "fhe")]
+    #[error(transparent)]
+    Poly(#[from] PolyError),
+
+    /// Importing or decoding an iris code or mask failed.
+    #[error(transparent)]
+    Io(#[from] IoError),
+
+    /// Importing an open-iris template failed.
+    #[error(transparent)]
+    Interop(#[from] InteropError),
+
+    /// Matching failed, including when the YASHE noise budget was exhausted.
+    #[cfg(feature = "fhe")]
+    #[error(transparent)]
+    Match(#[from] MatchError),
+
+    /// Storing, loading, or searching encrypted codes failed.
+    #[cfg(feature = "fhe")]
+    #[error(transparent)]
+    Store(#[from] StoreError),
+
+    /// Loading or applying a matcher config failed.
+    #[cfg(feature = "config")]
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    /// Saving or loading an encrypted private key file failed.
+    #[cfg(feature = "key-file")]
+    #[error(transparent)]
+    KeyFile(#[from] KeyFileError),
+
+    /// A matching pipeline has no template with this id.
+    #[error("no template with id {0}")]
+    UnknownTemplate(TemplateId),
+}
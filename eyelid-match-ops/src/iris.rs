@@ -1,3 +1,11 @@
 //! Scheme-independent iris code and configurations.
 
 pub mod conf;
+pub mod downsample;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+pub mod interop;
+pub mod io;
+pub mod test;
+
+pub use downsample::downsample;
@@ -44,6 +44,346 @@ pub trait IrisConf {
     const MATCH_DENOMINATOR: usize = 100;
 }
 
+/// A bit match threshold for a successful iris match, which can be chosen at runtime.
+/// The default is the threshold in the [`IrisConf`].
+///
+/// Codes match if the fraction of visible bits that are different is at most
+/// `numerator / denominator`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedMatchThreshold"))]
+pub struct MatchThreshold {
+    /// The numerator of the bit match threshold.
+    numerator: usize,
+    /// The denominator of the bit match threshold.
+    denominator: usize,
+}
+
+impl MatchThreshold {
+    /// The largest supported threshold denominator.
+    ///
+    /// Bounding the denominator (and therefore the numerator) stops the match calculations
+    /// overflowing.
+    pub const MAX_DENOMINATOR: usize = u32::MAX as usize;
+
+    /// Returns a new threshold, or `None` if the threshold is not between 0 and 1, or the
+    /// denominator is greater than [`MatchThreshold::MAX_DENOMINATOR`].
+    pub fn new(numerator: usize, denominator: usize) -> Option<Self> {
+        if denominator == 0 || denominator > Self::MAX_DENOMINATOR || numerator > denominator {
+            return None;
+        }
+
+        Some(Self {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// Returns the threshold configured in `C`.
+    pub fn from_conf<C: IrisConf>() -> Self {
+        Self {
+            numerator: C::MATCH_NUMERATOR,
+            denominator: C::MATCH_DENOMINATOR,
+        }
+    }
+
+    /// Returns the numerator of the threshold.
+    pub fn numerator(&self) -> usize {
+        self.numerator
+    }
+
+    /// Returns the denominator of the threshold.
+    pub fn denominator(&self) -> usize {
+        self.denominator
+    }
+
+    /// Returns how far the encoded counts for a rotation are within the threshold.
+    /// Zero or positive values are a match, negative values are not.
+    ///
+    /// `match_count` is the number of matching minus non-matching visible bits, and
+    /// `mask_count` is the number of visible bits.
+    ///
+    /// Margins which don't fit in an `i64` are saturated, so they keep the correct sign.
+    pub fn encoded_margin(&self, match_count: i64, mask_count: i64) -> i64 {
+        let (match_count, mask_count) = (i128::from(match_count), i128::from(mask_count));
+
+        // Match if the Hamming distance is less than a percentage threshold:
+        // (t - d) / 2t <= x%
+        //
+        // The threshold is at most `u32::MAX`, so this calculation can't overflow an `i128`.
+        #[allow(clippy::cast_possible_wrap)]
+        let margin = 2 * mask_count * (self.numerator as i128)
+            - (mask_count - match_count) * (self.denominator as i128);
+
+        #[allow(clippy::cast_possible_truncation)]
+        let margin = margin.clamp(i64::MIN.into(), i64::MAX.into()) as i64;
+
+        margin
+    }
+
+    /// Returns true if the encoded counts for a rotation meet the threshold.
+    /// See [`MatchThreshold::encoded_margin()`] for details.
+    pub fn is_encoded_match(&self, match_count: i64, mask_count: i64) -> bool {
+        self.encoded_margin(match_count, mask_count) >= 0
+    }
+
+    /// Returns true if `differences` out of `visible` bits meets the threshold.
+    pub fn is_plaintext_match(&self, differences: usize, visible: usize) -> bool {
+        // Each factor is at most `usize::MAX`, so the products fit in a `u128`.
+        (differences as u128) * (self.denominator as u128)
+            <= (visible as u128) * (self.numerator as u128)
+    }
+}
+
+/// A deserialized [`MatchThreshold`], which hasn't been checked yet.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedMatchThreshold {
+    /// The numerator of the bit match threshold.
+    numerator: usize,
+    /// The denominator of the bit match threshold.
+    denominator: usize,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedMatchThreshold> for MatchThreshold {
+    type Error = &'static str;
+
+    fn try_from(threshold: UncheckedMatchThreshold) -> Result<Self, Self::Error> {
+        Self::new(threshold.numerator, threshold.denominator).ok_or(
+            "match threshold must be between 0 and 1, with a denominator of at most u32::MAX",
+        )
+    }
+}
+
+/// How the left and right eye comparisons are combined into a single match decision.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FusionPolicy {
+    /// Both eyes must match.
+    #[default]
+    And,
+
+    /// Either eye can match.
+    Or,
+
+    /// The combined distance of both eyes must meet the threshold.
+    ///
+    /// The closest rotation of each eye is used, then the differing and visible bits of both
+    /// eyes are added together. This lets a clear image of one eye make up for a noisy image of
+    /// the other eye.
+    MinDistance,
+}
+
+/// The minimum number of visible bits needed to make a match decision.
+/// Comparisons with fewer visible bits are indeterminate, rather than matching.
+///
+/// Use [`MinVisibleBits::fraction()`] to create checked fractions.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedMinVisibleBits"))]
+pub enum MinVisibleBits {
+    /// An absolute number of visible bits.
+    Absolute(usize),
+
+    /// A fraction of [`IrisConf::DATA_BIT_LEN`], rounded up.
+    Fraction {
+        /// The numerator of the fraction.
+        numerator: usize,
+        /// The denominator of the fraction. Must not be zero.
+        denominator: usize,
+    },
+}
+
+/// A deserialized [`MinVisibleBits`], which hasn't been checked yet.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+enum UncheckedMinVisibleBits {
+    /// An absolute number of visible bits.
+    Absolute(usize),
+
+    /// A fraction of [`IrisConf::DATA_BIT_LEN`], rounded up.
+    Fraction {
+        /// The numerator of the fraction.
+        numerator: usize,
+        /// The denominator of the fraction.
+        denominator: usize,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedMinVisibleBits> for MinVisibleBits {
+    type Error = &'static str;
+
+    fn try_from(min_visible: UncheckedMinVisibleBits) -> Result<Self, Self::Error> {
+        match min_visible {
+            UncheckedMinVisibleBits::Absolute(bits) => Ok(Self::Absolute(bits)),
+            UncheckedMinVisibleBits::Fraction {
+                numerator,
+                denominator,
+            } => Self::fraction(numerator, denominator).ok_or(
+                "minimum visible bits must be between 0 and 1, with a denominator of at most u32::MAX",
+            ),
+        }
+    }
+}
+
+impl Default for MinVisibleBits {
+    /// Returns a minimum of zero visible bits, which is always met.
+    fn default() -> Self {
+        MinVisibleBits::Absolute(0)
+    }
+}
+
+impl MinVisibleBits {
+    /// The largest supported fraction denominator.
+    pub const MAX_DENOMINATOR: usize = u32::MAX as usize;
+
+    /// Returns a new fraction of the visible bits, or `None` if the fraction is not between 0
+    /// and 1, or the denominator is greater than [`MinVisibleBits::MAX_DENOMINATOR`].
+    pub fn fraction(numerator: usize, denominator: usize) -> Option<Self> {
+        if denominator == 0 || denominator > Self::MAX_DENOMINATOR || numerator > denominator {
+            return None;
+        }
+
+        Some(MinVisibleBits::Fraction {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// Returns the minimum number of visible bits for the configuration `C`.
+    ///
+    /// # Panics
+    ///
+    /// If the fraction has a zero denominator.
+    pub fn min_bits<C: IrisConf>(&self) -> usize {
+        match *self {
+            MinVisibleBits::Absolute(bits) => bits,
+            MinVisibleBits::Fraction {
+                numerator,
+                denominator,
+            } => {
+                // Each factor is at most `usize::MAX`, so the product fits in a `u128`.
+                let bits =
+                    (C::DATA_BIT_LEN as u128 * numerator as u128).div_ceil(denominator as u128);
+
+                // Unchecked fractions larger than 1 can require more bits than any code has.
+                usize::try_from(bits).unwrap_or(usize::MAX)
+            }
+        }
+    }
+}
+
+/// The dimensions and matching rules for an iris code, chosen at runtime.
+///
+/// This supports sensor formats which don't have an [`IrisConf`] marker type. Codes and masks
+/// use the same column-major layout as [`IrisConf`] codes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedDynIrisConf"))]
+pub struct DynIrisConf {
+    /// The number of columns in an iris code or mask.
+    columns: usize,
+    /// The number of rows in an iris code or mask.
+    rows: usize,
+    /// The number of columns each column is compared to, on its left and right.
+    rotation_limit: usize,
+    /// The bit match threshold for a successful iris match.
+    threshold: MatchThreshold,
+}
+
+impl DynIrisConf {
+    /// Returns a new configuration, or `None` if the code is empty, the code length doesn't fit
+    /// in an `isize`, or the rotations would compare a column with itself more than once.
+    pub fn new(
+        columns: usize,
+        rows: usize,
+        rotation_limit: usize,
+        threshold: MatchThreshold,
+    ) -> Option<Self> {
+        // Check the length first, so the number of columns and the rotation limit also fit in an
+        // `isize`.
+        let data_bit_len = columns.checked_mul(rows)?;
+        if isize::try_from(data_bit_len).is_err() {
+            return None;
+        }
+
+        // The rotations are `-rotation_limit..=rotation_limit`, so there must be at least
+        // `rotation_limit * 2 + 1` columns.
+        if columns == 0 || rows == 0 || rotation_limit >= columns.div_ceil(2) {
+            return None;
+        }
+
+        Some(Self {
+            columns,
+            rows,
+            rotation_limit,
+            threshold,
+        })
+    }
+
+    /// Returns the configuration of `C`.
+    pub fn from_conf<C: IrisConf>() -> Self {
+        Self {
+            columns: C::COLUMNS,
+            rows: C::COLUMN_LEN,
+            rotation_limit: C::ROTATION_LIMIT,
+            threshold: MatchThreshold::from_conf::<C>(),
+        }
+    }
+
+    /// Returns the number of columns in an iris code or mask.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the number of rows in an iris code or mask.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the rotation limit when comparing irises.
+    pub fn rotation_limit(&self) -> usize {
+        self.rotation_limit
+    }
+
+    /// Returns the bit match threshold.
+    pub fn threshold(&self) -> MatchThreshold {
+        self.threshold
+    }
+
+    /// Returns the length of an iris code or mask.
+    pub fn data_bit_len(&self) -> usize {
+        // Checked in `new()`, so it never wraps.
+        self.columns * self.rows
+    }
+}
+
+/// A deserialized [`DynIrisConf`], which hasn't been checked yet.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedDynIrisConf {
+    /// The number of columns in an iris code or mask.
+    columns: usize,
+    /// The number of rows in an iris code or mask.
+    rows: usize,
+    /// The number of columns each column is compared to, on its left and right.
+    rotation_limit: usize,
+    /// The bit match threshold for a successful iris match.
+    threshold: MatchThreshold,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedDynIrisConf> for DynIrisConf {
+    type Error = &'static str;
+
+    fn try_from(conf: UncheckedDynIrisConf) -> Result<Self, Self::Error> {
+        Self::new(conf.columns, conf.rows, conf.rotation_limit, conf.threshold)
+            .ok_or("iris dimensions or rotation limit are invalid")
+    }
+}
+
 /// A type alias for the underlying array element type.
 /// Not currently configurable via the trait.
 type IrisStore = usize;
@@ -69,6 +409,25 @@ pub type IrisCode<const STORE_ELEM_LEN: usize> = BitArray<[IrisStore; STORE_ELEM
 ///       correctly.
 pub type IrisMask<const STORE_ELEM_LEN: usize> = BitArray<[IrisStore; STORE_ELEM_LEN]>;
 
+/// Clears the unused bits at the end of an iris code or mask, after the first
+/// [`IrisConf::DATA_BIT_LEN`] bits.
+///
+/// Codes and masks are canonical when their unused bits are clear. Imported and downsampled codes
+/// are always canonical, but codes built from raw storage words might not be.
+pub fn mask_trailing_bits<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    bits: &mut IrisCode<STORE_ELEM_LEN>,
+) {
+    bits[C::DATA_BIT_LEN..].fill(false);
+}
+
+/// Returns true if any unused bits at the end of an iris code or mask are set.
+/// See [`mask_trailing_bits()`] for details.
+pub fn has_trailing_bits<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    bits: &IrisCode<STORE_ELEM_LEN>,
+) -> bool {
+    bits[C::DATA_BIT_LEN..].any()
+}
+
 impl IrisConf for FullBits {
     const COLUMNS: usize = 200;
     const COLUMN_LEN: usize = 16 * 2 * 2;
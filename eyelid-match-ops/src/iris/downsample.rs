@@ -0,0 +1,66 @@
+//! Reducing iris codes to a lower resolution.
+
+use crate::{
+    iris::conf::{IrisCode, IrisConf, IrisMask},
+    plaintext::index_1d,
+};
+
+/// Reduces a code and mask in the `From` configuration to half the rows and half the columns,
+/// in the `To` configuration. For example, from [`FullBits`](crate::FullBits) to
+/// [`MiddleBits`](crate::MiddleBits).
+///
+/// Each output bit is the majority of the visible bits in a 2×2 block of input bits: rows
+/// `2r` and `2r + 1`, and columns `2c` and `2c + 1`. The output bit is only visible if at least
+/// 2 bits in the block are visible, and they don't have an equal number of set and unset bits.
+/// This avoids turning a block of mostly masked or noisy bits into a confident output bit.
+///
+/// # Panics
+///
+/// At compile time, if `From` doesn't have exactly twice as many rows and columns as `To`.
+pub fn downsample<
+    From: IrisConf,
+    To: IrisConf,
+    const FROM_STORE_ELEM_LEN: usize,
+    const TO_STORE_ELEM_LEN: usize,
+>(
+    code: &IrisCode<FROM_STORE_ELEM_LEN>,
+    mask: &IrisMask<FROM_STORE_ELEM_LEN>,
+) -> (IrisCode<TO_STORE_ELEM_LEN>, IrisMask<TO_STORE_ELEM_LEN>) {
+    const {
+        assert!(From::COLUMNS == 2 * To::COLUMNS);
+        assert!(From::COLUMN_LEN == 2 * To::COLUMN_LEN);
+    }
+
+    let mut new_code = IrisCode::ZERO;
+    let mut new_mask = IrisMask::ZERO;
+
+    for col_i in 0..To::COLUMNS {
+        for row_i in 0..To::COLUMN_LEN {
+            let mut visible = 0;
+            let mut set = 0;
+
+            for (block_row_i, block_col_i) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                let i = index_1d(
+                    From::COLUMN_LEN,
+                    2 * row_i + block_row_i,
+                    2 * col_i + block_col_i,
+                );
+
+                if mask[i] {
+                    visible += 1;
+                    set += usize::from(code[i]);
+                }
+            }
+
+            let unset = visible - set;
+            let i = index_1d(To::COLUMN_LEN, row_i, col_i);
+
+            if visible >= 2 && set != unset {
+                new_mask.set(i, true);
+                new_code.set(i, set > unset);
+            }
+        }
+    }
+
+    (new_code, new_mask)
+}
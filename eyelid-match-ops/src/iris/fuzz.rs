@@ -0,0 +1,82 @@
+//! Generating iris codes and masks from fuzzer input.
+//!
+//! [`IrisCode`] and [`IrisMask`] are type aliases for a `bitvec` type, so they can't implement
+//! [`Arbitrary`] directly. Instead, [`ArbitraryIris`] generates a code and mask for an iris
+//! configuration, with its unused bits cleared. Fuzz targets can take a tuple of two of them,
+//! and pass them through the plaintext, encoded, and encrypted matchers.
+
+use std::marker::PhantomData;
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::iris::{
+    conf::{IrisCode, IrisConf, IrisMask},
+    io::packed_len,
+};
+
+/// An iris code and mask in the `C` configuration, generated from fuzzer input.
+pub struct ArbitraryIris<C: IrisConf, const STORE_ELEM_LEN: usize> {
+    /// The iris code.
+    pub code: IrisCode<STORE_ELEM_LEN>,
+    /// The iris mask.
+    pub mask: IrisMask<STORE_ELEM_LEN>,
+
+    /// The iris configuration of the code and mask.
+    _conf: PhantomData<fn() -> C>,
+}
+
+// Derived impls would require `C: Clone` and `C: Debug`, but `C` is only a marker type.
+impl<C: IrisConf, const STORE_ELEM_LEN: usize> Clone for ArbitraryIris<C, STORE_ELEM_LEN> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C: IrisConf, const STORE_ELEM_LEN: usize> Copy for ArbitraryIris<C, STORE_ELEM_LEN> {}
+
+impl<C: IrisConf, const STORE_ELEM_LEN: usize> std::fmt::Debug
+    for ArbitraryIris<C, STORE_ELEM_LEN>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArbitraryIris")
+            .field("code", &self.code)
+            .field("mask", &self.mask)
+            .finish()
+    }
+}
+
+impl<'a, C: IrisConf, const STORE_ELEM_LEN: usize> Arbitrary<'a>
+    for ArbitraryIris<C, STORE_ELEM_LEN>
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            code: arbitrary_bits::<C, STORE_ELEM_LEN>(u)?,
+            mask: arbitrary_bits::<C, STORE_ELEM_LEN>(u)?,
+            _conf: PhantomData,
+        })
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        let len = 2 * packed_len::<C>();
+
+        (len, Some(len))
+    }
+}
+
+/// Returns an iris code or mask in the `C` configuration, using bytes from `u`.
+///
+/// Missing bytes are treated as zeroes, so fuzzers can shrink their input. Unused bits are
+/// always clear.
+pub fn arbitrary_bits<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    u: &mut Unstructured<'_>,
+) -> arbitrary::Result<IrisCode<STORE_ELEM_LEN>> {
+    let mut bytes = vec![0; packed_len::<C>()];
+    u.fill_buffer(&mut bytes)?;
+
+    let mut bits = IrisCode::ZERO;
+    for i in 0..C::DATA_BIT_LEN {
+        bits.set(i, bytes[i / 8] & (1 << (i % 8)) != 0);
+    }
+
+    Ok(bits)
+}
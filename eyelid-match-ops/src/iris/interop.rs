@@ -0,0 +1,311 @@
+//! Conversion from the Worldcoin open-iris template format.
+//!
+//! open-iris templates have [`OPEN_IRIS_ROWS`] radial rows and [`OPEN_IRIS_COLUMNS`] angular
+//! columns, with [`OPEN_IRIS_CELL_BITS`] bits in each cell: the real and imaginary responses of
+//! each of the [`OPEN_IRIS_FILTERS`] filters. Serialized templates use the "old" array layout,
+//! with shape `(rows, columns, cell_bits)`. The `iris_codes` and `mask_codes` arrays of an
+//! `IrisTemplate` use the stacked layout, with shape `(filters, rows, columns, 2)`.
+//!
+//! Each open-iris column becomes a [`FullBits`] column, with the cell bits of each row stored
+//! next to each other. So the bit at `(row, column, cell_bit)` is at
+//! `index_1d(FullBits::COLUMN_LEN, row * OPEN_IRIS_CELL_BITS + cell_bit, column)`.
+//!
+//! open-iris rotates templates along the column axis using `np.roll()`, so rolling a template
+//! by `n` columns is the same as [`rotate()`](crate::plaintext::rotate)`::<FullBits>(code, n)`.
+//! Set mask bits are visible in both formats.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{
+    iris::conf::{IrisCode, IrisConf, IrisMask},
+    plaintext::index_1d,
+    FullBits,
+};
+
+/// The number of radial rows in an open-iris template.
+pub const OPEN_IRIS_ROWS: usize = 16;
+
+/// The number of angular columns in an open-iris template.
+pub const OPEN_IRIS_COLUMNS: usize = 200;
+
+/// The number of filters used to create an open-iris template.
+pub const OPEN_IRIS_FILTERS: usize = 2;
+
+/// The number of bits in each cell of an open-iris template, a real and imaginary bit for each
+/// filter.
+pub const OPEN_IRIS_CELL_BITS: usize = OPEN_IRIS_FILTERS * 2;
+
+/// The storage length of [`FullBits`] codes and masks.
+const FULL_STORE_ELEM_LEN: usize = FullBits::STORE_ELEM_LEN;
+
+// Every open-iris bit has a matching FullBits bit, and rotations are compatible.
+const_assert_eq!(OPEN_IRIS_ROWS * OPEN_IRIS_CELL_BITS, FullBits::COLUMN_LEN);
+const_assert_eq!(OPEN_IRIS_COLUMNS, FullBits::COLUMNS);
+
+/// The NPY file magic string.
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// NPY headers are padded so the data is aligned to this many bytes.
+const NPY_ALIGN: usize = 64;
+
+/// Errors that can happen when importing open-iris templates.
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum InteropError {
+    /// The base64 text could not be decoded.
+    #[error("invalid base64 text")]
+    InvalidBase64,
+
+    /// The packed code has the wrong number of bytes.
+    #[error("packed code has {0} bytes, which doesn't match the template dimensions")]
+    InvalidLength(usize),
+
+    /// The array shape isn't a supported open-iris layout.
+    #[error("array has shape {0:?}, which isn't an open-iris template layout")]
+    InvalidShape(Vec<usize>),
+
+    /// The array has an element type other than booleans or bytes.
+    #[error("array has unsupported element type {0:?}")]
+    UnsupportedDtype(String),
+
+    /// The NPY file is malformed.
+    #[error("invalid NPY file: {0}")]
+    InvalidNpy(&'static str),
+}
+
+/// A serialized open-iris template, as produced by `IrisTemplate.serialize()`.
+///
+/// The codes are base64 text containing `np.packbits()` of the template arrays, in the
+/// `(rows, columns, cell_bits)` layout.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpenIrisTemplate {
+    /// The packed iris code.
+    pub iris_codes: String,
+    /// The packed mask code.
+    pub mask_codes: String,
+    /// The open-iris code version, if known.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub iris_code_version: Option<String>,
+}
+
+impl OpenIrisTemplate {
+    /// Returns the serialized template for `code` and `mask`, without a code version.
+    pub fn new(code: &IrisCode<FULL_STORE_ELEM_LEN>, mask: &IrisMask<FULL_STORE_ELEM_LEN>) -> Self {
+        Self {
+            iris_codes: to_packed_base64(code),
+            mask_codes: to_packed_base64(mask),
+            iris_code_version: None,
+        }
+    }
+
+    /// Returns the iris code and mask in the template.
+    pub fn code_and_mask(
+        &self,
+    ) -> Result<(IrisCode<FULL_STORE_ELEM_LEN>, IrisMask<FULL_STORE_ELEM_LEN>), InteropError> {
+        Ok((
+            from_packed_base64(&self.iris_codes)?,
+            from_packed_base64(&self.mask_codes)?,
+        ))
+    }
+}
+
+/// Decodes an iris code or mask from base64 `np.packbits()` output, in the
+/// `(rows, columns, cell_bits)` layout.
+pub fn from_packed_base64(text: &str) -> Result<IrisCode<FULL_STORE_ELEM_LEN>, InteropError> {
+    let bytes = STANDARD
+        .decode(text.trim())
+        .map_err(|_| InteropError::InvalidBase64)?;
+    if bytes.len() != FullBits::DATA_BIT_LEN / 8 {
+        return Err(InteropError::InvalidLength(bytes.len()));
+    }
+
+    // np.packbits() puts the first bit in the most significant bit.
+    Ok(from_cells(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0))
+}
+
+/// Encodes an iris code or mask as base64 `np.packbits()` output, in the
+/// `(rows, columns, cell_bits)` layout.
+pub fn to_packed_base64(code: &IrisCode<FULL_STORE_ELEM_LEN>) -> String {
+    let mut bytes = vec![0; FullBits::DATA_BIT_LEN / 8];
+    for (i, bit) in to_cells(code).enumerate() {
+        bytes[i / 8] |= u8::from(bit) << (7 - i % 8);
+    }
+
+    STANDARD.encode(bytes)
+}
+
+/// Decodes an iris code or mask from an NPY file containing a boolean or `uint8` array.
+///
+/// The array can use the `(rows, columns, cell_bits)` layout, or the stacked
+/// `(filters, rows, columns, 2)` layout.
+pub fn from_npy(bytes: &[u8]) -> Result<IrisCode<FULL_STORE_ELEM_LEN>, InteropError> {
+    let (header, data) = split_npy(bytes)?;
+
+    let dtype = npy_field(header, "descr")?
+        .trim_matches(|c| c == '\'' || c == '"')
+        .to_string();
+    if !matches!(dtype.as_str(), "|b1" | "|u1" | "<u1" | ">u1") {
+        return Err(InteropError::UnsupportedDtype(dtype));
+    }
+    if npy_field(header, "fortran_order")? != "False" {
+        return Err(InteropError::InvalidNpy(
+            "Fortran order arrays are not supported",
+        ));
+    }
+
+    let shape = parse_shape(npy_field(header, "shape")?)?;
+    let len = shape
+        .iter()
+        .try_fold(1_usize, |len, &dim| len.checked_mul(dim))
+        .ok_or(InteropError::InvalidNpy("array shape is too large"))?;
+    if data.len() != len {
+        return Err(InteropError::InvalidNpy(
+            "data length doesn't match the array shape",
+        ));
+    }
+    if data.iter().any(|&value| value > 1) {
+        return Err(InteropError::InvalidNpy("array values must be 0 or 1"));
+    }
+
+    match shape.as_slice() {
+        [OPEN_IRIS_ROWS, OPEN_IRIS_COLUMNS, OPEN_IRIS_CELL_BITS] => {
+            Ok(from_cells(|i| data[i] == 1))
+        }
+        [OPEN_IRIS_FILTERS, OPEN_IRIS_ROWS, OPEN_IRIS_COLUMNS, 2] => Ok(from_cells(|i| {
+            let (row, column, cell_bit) = cell_position(i);
+            let (filter, part) = (cell_bit / 2, cell_bit % 2);
+
+            data[((filter * OPEN_IRIS_ROWS + row) * OPEN_IRIS_COLUMNS + column) * 2 + part] == 1
+        })),
+        _ => Err(InteropError::InvalidShape(shape)),
+    }
+}
+
+/// Encodes an iris code or mask as an NPY file containing a boolean array, in the
+/// `(rows, columns, cell_bits)` layout.
+pub fn to_npy(code: &IrisCode<FULL_STORE_ELEM_LEN>) -> Vec<u8> {
+    let mut header = format!(
+        "{{'descr': '|b1', 'fortran_order': False, 'shape': ({OPEN_IRIS_ROWS}, {OPEN_IRIS_COLUMNS}, {OPEN_IRIS_CELL_BITS}), }}"
+    );
+    // The magic, version, and header length take 10 bytes, and the header ends with a newline.
+    let unpadded_len = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded_len.next_multiple_of(NPY_ALIGN) - unpadded_len));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(NPY_MAGIC.len() + 4 + header.len() + FullBits::DATA_BIT_LEN);
+    bytes.extend_from_slice(NPY_MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    // The header is much shorter than u16::MAX.
+    #[allow(clippy::cast_possible_truncation)]
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend(to_cells(code).map(u8::from));
+
+    bytes
+}
+
+/// Returns the `(row, column, cell_bit)` of index `i` in the `(rows, columns, cell_bits)`
+/// layout.
+fn cell_position(i: usize) -> (usize, usize, usize) {
+    let cell_bit = i % OPEN_IRIS_CELL_BITS;
+    let cell = i / OPEN_IRIS_CELL_BITS;
+
+    (cell / OPEN_IRIS_COLUMNS, cell % OPEN_IRIS_COLUMNS, cell_bit)
+}
+
+/// Returns the [`FullBits`] index of index `i` in the `(rows, columns, cell_bits)` layout.
+fn full_bits_index(i: usize) -> usize {
+    let (row, column, cell_bit) = cell_position(i);
+
+    index_1d(
+        FullBits::COLUMN_LEN,
+        row * OPEN_IRIS_CELL_BITS + cell_bit,
+        column,
+    )
+}
+
+/// Returns a code with the bit at each index in the `(rows, columns, cell_bits)` layout.
+fn from_cells(bit: impl Fn(usize) -> bool) -> IrisCode<FULL_STORE_ELEM_LEN> {
+    let mut code = IrisCode::ZERO;
+    for i in 0..FullBits::DATA_BIT_LEN {
+        code.set(full_bits_index(i), bit(i));
+    }
+
+    code
+}
+
+/// Returns the bits of `code` in the `(rows, columns, cell_bits)` layout.
+fn to_cells(code: &IrisCode<FULL_STORE_ELEM_LEN>) -> impl Iterator<Item = bool> + '_ {
+    (0..FullBits::DATA_BIT_LEN).map(|i| code[full_bits_index(i)])
+}
+
+/// Splits an NPY file into its header dictionary and data.
+fn split_npy(bytes: &[u8]) -> Result<(&str, &[u8]), InteropError> {
+    let rest = bytes
+        .strip_prefix(NPY_MAGIC)
+        .ok_or(InteropError::InvalidNpy("missing NPY magic string"))?;
+
+    let (header_len, rest) = match rest {
+        [1, _, len @ ..] if len.len() >= 2 => {
+            (usize::from(u16::from_le_bytes([len[0], len[1]])), &len[2..])
+        }
+        [2 | 3, _, len @ ..] if len.len() >= 4 => {
+            let header_len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]);
+            (
+                usize::try_from(header_len)
+                    .map_err(|_| InteropError::InvalidNpy("header is too long"))?,
+                &len[4..],
+            )
+        }
+        _ => return Err(InteropError::InvalidNpy("unsupported NPY version")),
+    };
+
+    if rest.len() < header_len {
+        return Err(InteropError::InvalidNpy("header is truncated"));
+    }
+    let (header, data) = rest.split_at(header_len);
+    let header =
+        std::str::from_utf8(header).map_err(|_| InteropError::InvalidNpy("header is not text"))?;
+
+    Ok((header, data))
+}
+
+/// Returns the text of the value of `key` in an NPY header dictionary.
+fn npy_field<'h>(header: &'h str, key: &str) -> Result<&'h str, InteropError> {
+    let missing = InteropError::InvalidNpy("header is missing a field");
+
+    let start = header.find(&format!("'{key}'")).ok_or(missing.clone())? + key.len() + 2;
+    let value = header[start..]
+        .trim_start()
+        .strip_prefix(':')
+        .ok_or(missing.clone())?
+        .trim_start();
+
+    // Shapes are tuples, which contain commas.
+    let end = if value.starts_with('(') {
+        value.find(')').map(|end| end + 1)
+    } else {
+        value.find([',', '}'])
+    };
+
+    Ok(value[..end.ok_or(missing)?].trim())
+}
+
+/// Parses an NPY shape tuple, like `(16, 200, 4)`.
+fn parse_shape(shape: &str) -> Result<Vec<usize>, InteropError> {
+    shape
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(str::trim)
+        .filter(|dimension| !dimension.is_empty())
+        .map(|dimension| {
+            dimension
+                .parse()
+                .map_err(|_| InteropError::InvalidNpy("invalid array shape"))
+        })
+        .collect()
+}
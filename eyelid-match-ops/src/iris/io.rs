@@ -0,0 +1,210 @@
+//! Import and export of iris codes and masks.
+//!
+//! The byte format starts with a header containing the number of rows and columns, as
+//! little-endian `u32`s. The header is followed by the bits in [`index_1d()`] order, packed
+//! into bytes with the lowest index in the least significant bit. Unused bits in the last byte
+//! are zero.
+//!
+//! Open-source iris pipelines usually store templates as 2D arrays of bits, with one row of the
+//! iris image per array row. Use [`from_row_major_bits()`] and [`to_row_major_bits()`] to
+//! convert them.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{
+    iris::conf::{IrisCode, IrisConf},
+    plaintext::index_1d,
+};
+
+/// The number of bytes used to store each dimension in the header.
+const DIMENSION_BYTES: usize = 4;
+
+/// The length of the header: rows, then columns.
+pub const HEADER_LEN: usize = 2 * DIMENSION_BYTES;
+
+/// Errors that can happen when importing iris codes or masks.
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum IoError {
+    /// The code has different dimensions to the configuration.
+    #[error("code has {rows} rows and {columns} columns, which don't match the configuration")]
+    DimensionMismatch {
+        /// The number of rows in the imported code.
+        rows: usize,
+        /// The number of columns in the imported code.
+        columns: usize,
+    },
+
+    /// The code has the wrong number of bits or bytes for its dimensions.
+    #[error("code has the wrong length for its dimensions")]
+    InvalidLength,
+
+    /// Unused bits at the end of the code are set.
+    #[error("unused bits at the end of the code are set")]
+    UnusedBitsSet,
+
+    /// The base64 text could not be decoded.
+    #[error("invalid base64 text")]
+    InvalidBase64,
+}
+
+/// Encodes an iris code or mask as bytes, including a dimension header.
+pub fn to_bytes<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    code: &IrisCode<STORE_ELEM_LEN>,
+) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + packed_len::<C>());
+
+    write_dimensions::<C>(&mut bytes);
+    pack_bits::<C, STORE_ELEM_LEN>(code, &mut bytes);
+
+    bytes
+}
+
+/// Decodes an iris code or mask encoded by [`to_bytes()`].
+///
+/// Returns [`IoError::DimensionMismatch`] if the code has different dimensions to `C`.
+pub fn from_bytes<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    bytes: &[u8],
+) -> Result<IrisCode<STORE_ELEM_LEN>, IoError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(IoError::InvalidLength);
+    }
+    let (header, data) = bytes.split_at(HEADER_LEN);
+    let (rows, columns) = read_dimensions(header)?;
+    check_dimensions::<C>(rows, columns)?;
+
+    unpack_bits::<C, STORE_ELEM_LEN>(data)
+}
+
+/// Encodes an iris code or mask as base64 text, including a dimension header.
+pub fn to_base64<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    code: &IrisCode<STORE_ELEM_LEN>,
+) -> String {
+    STANDARD.encode(to_bytes::<C, STORE_ELEM_LEN>(code))
+}
+
+/// Decodes an iris code or mask encoded by [`to_base64()`].
+pub fn from_base64<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    text: &str,
+) -> Result<IrisCode<STORE_ELEM_LEN>, IoError> {
+    let bytes = STANDARD
+        .decode(text.trim())
+        .map_err(|_| IoError::InvalidBase64)?;
+
+    from_bytes::<C, STORE_ELEM_LEN>(&bytes)
+}
+
+/// Converts a 2D array of bits to an iris code or mask.
+///
+/// `bits` contains `rows` rows of `columns` bits each, so the bit at `(row, column)` is
+/// `bits[row * columns + column]`.
+pub fn from_row_major_bits<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    bits: &[bool],
+    rows: usize,
+    columns: usize,
+) -> Result<IrisCode<STORE_ELEM_LEN>, IoError> {
+    check_dimensions::<C>(rows, columns)?;
+
+    if bits.len() != C::DATA_BIT_LEN {
+        return Err(IoError::InvalidLength);
+    }
+
+    let mut code = IrisCode::ZERO;
+    for (i, bit) in bits.iter().enumerate() {
+        let (row_i, col_i) = (i / C::COLUMNS, i % C::COLUMNS);
+        code.set(index_1d(C::COLUMN_LEN, row_i, col_i), *bit);
+    }
+
+    Ok(code)
+}
+
+/// Converts an iris code or mask to a 2D array of bits, in the layout used by
+/// [`from_row_major_bits()`].
+pub fn to_row_major_bits<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    code: &IrisCode<STORE_ELEM_LEN>,
+) -> Vec<bool> {
+    (0..C::COLUMN_LEN)
+        .flat_map(|row_i| {
+            (0..C::COLUMNS).map(move |col_i| code[index_1d(C::COLUMN_LEN, row_i, col_i)])
+        })
+        .collect()
+}
+
+/// Returns the number of bytes used to store the bits of a code or mask, without a header.
+pub(crate) const fn packed_len<C: IrisConf>() -> usize {
+    C::DATA_BIT_LEN.div_ceil(8)
+}
+
+/// Appends the bits of `code` to `bytes`, without a header.
+pub(crate) fn pack_bits<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    code: &IrisCode<STORE_ELEM_LEN>,
+    bytes: &mut Vec<u8>,
+) {
+    for chunk in code[..C::DATA_BIT_LEN].chunks(8) {
+        let byte = chunk
+            .iter()
+            .by_vals()
+            .enumerate()
+            .fold(0_u8, |byte, (i, bit)| byte | (u8::from(bit) << i));
+        bytes.push(byte);
+    }
+}
+
+/// Decodes bits encoded by [`pack_bits()`].
+pub(crate) fn unpack_bits<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    data: &[u8],
+) -> Result<IrisCode<STORE_ELEM_LEN>, IoError> {
+    if data.len() != packed_len::<C>() {
+        return Err(IoError::InvalidLength);
+    }
+
+    let mut code = IrisCode::ZERO;
+    for (i, byte) in data.iter().enumerate() {
+        for bit_i in 0..8 {
+            if byte & (1 << bit_i) == 0 {
+                continue;
+            }
+
+            let index = i * 8 + bit_i;
+            if index >= C::DATA_BIT_LEN {
+                return Err(IoError::UnusedBitsSet);
+            }
+            code.set(index, true);
+        }
+    }
+
+    Ok(code)
+}
+
+/// Reads the rows and columns from a dimension header.
+pub(crate) fn read_dimensions(header: &[u8]) -> Result<(usize, usize), IoError> {
+    if header.len() != HEADER_LEN {
+        return Err(IoError::InvalidLength);
+    }
+    let (rows, columns) = header.split_at(DIMENSION_BYTES);
+
+    let dimension = |bytes: &[u8]| {
+        let bytes = bytes.try_into().map_err(|_| IoError::InvalidLength)?;
+        usize::try_from(u32::from_le_bytes(bytes)).map_err(|_| IoError::InvalidLength)
+    };
+
+    Ok((dimension(rows)?, dimension(columns)?))
+}
+
+/// Appends the dimension header for `C` to `bytes`.
+pub(crate) fn write_dimensions<C: IrisConf>(bytes: &mut Vec<u8>) {
+    // The dimensions are small constants, so they will never truncate.
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        bytes.extend_from_slice(&(C::COLUMN_LEN as u32).to_le_bytes());
+        bytes.extend_from_slice(&(C::COLUMNS as u32).to_le_bytes());
+    }
+}
+
+/// Returns [`IoError::DimensionMismatch`] if the dimensions are different to `C`.
+pub(crate) fn check_dimensions<C: IrisConf>(rows: usize, columns: usize) -> Result<(), IoError> {
+    if rows != C::COLUMN_LEN || columns != C::COLUMNS {
+        return Err(IoError::DimensionMismatch { rows, columns });
+    }
+
+    Ok(())
+}
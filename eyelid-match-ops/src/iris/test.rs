@@ -0,0 +1,13 @@
+//! Iris code tests.
+
+#[cfg(test)]
+mod downsample;
+
+#[cfg(all(test, feature = "arbitrary", feature = "fhe"))]
+mod fuzz;
+
+#[cfg(test)]
+mod interop;
+
+#[cfg(test)]
+mod io;
@@ -0,0 +1,77 @@
+//! Tests for reducing iris codes to a lower resolution.
+
+use crate::iris::{
+    conf::{IrisCode, IrisMask},
+    downsample,
+};
+use crate::plaintext::{
+    index_1d, is_iris_match,
+    test::gen::{random_iris_code, similar_iris_code, visible_iris_mask},
+};
+use crate::{FullBits, IrisConf, MiddleBits};
+
+/// Downsamples a full resolution code and mask.
+fn downsample_full(
+    code: &IrisCode<{ FullBits::STORE_ELEM_LEN }>,
+    mask: &IrisMask<{ FullBits::STORE_ELEM_LEN }>,
+) -> (
+    IrisCode<{ MiddleBits::STORE_ELEM_LEN }>,
+    IrisMask<{ MiddleBits::STORE_ELEM_LEN }>,
+) {
+    downsample::<FullBits, MiddleBits, { FullBits::STORE_ELEM_LEN }, { MiddleBits::STORE_ELEM_LEN }>(
+        code, mask,
+    )
+}
+
+/// Check the majority and masking rules for a single block.
+#[test]
+fn test_downsample_block() {
+    let mut code = IrisCode::ZERO;
+    let mut mask = IrisMask::ZERO;
+
+    // Block (0, 0): three visible bits, two set.
+    for (row_i, col_i, bit) in [(0, 0, true), (1, 0, true), (0, 1, false)] {
+        let i = index_1d(FullBits::COLUMN_LEN, row_i, col_i);
+        code.set(i, bit);
+        mask.set(i, true);
+    }
+    // Block (1, 0): two visible bits, which disagree.
+    for (row_i, col_i, bit) in [(2, 0, true), (3, 1, false)] {
+        let i = index_1d(FullBits::COLUMN_LEN, row_i, col_i);
+        code.set(i, bit);
+        mask.set(i, true);
+    }
+    // Block (0, 1): one visible bit.
+    let i = index_1d(FullBits::COLUMN_LEN, 0, 2);
+    code.set(i, true);
+    mask.set(i, true);
+
+    let (new_code, new_mask) = downsample_full(&code, &mask);
+
+    assert_eq!(new_mask.iter_ones().collect::<Vec<_>>(), vec![0]);
+    assert_eq!(new_code.iter_ones().collect::<Vec<_>>(), vec![0]);
+}
+
+/// Check that downsampled similar codes still match.
+#[test]
+fn test_downsample_matching() {
+    let eye = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let same = downsample_full(&eye, &visible_iris_mask());
+
+    let (new_eye, new_mask) = same;
+    assert!(is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+        &new_eye, &new_mask, &new_eye, &new_mask
+    ));
+
+    let other = downsample_full(&random_iris_code(), &visible_iris_mask());
+    assert!(
+        !is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            &new_eye, &new_mask, &other.0, &other.1
+        )
+    );
+
+    let similar = downsample_full(&similar_iris_code(&eye), &visible_iris_mask());
+    assert!(is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+        &new_eye, &new_mask, &similar.0, &similar.1
+    ));
+}
@@ -0,0 +1,55 @@
+//! Tests for generating iris codes and masks from fuzzer input.
+
+use arbitrary::{Arbitrary, Unstructured};
+use rand::Rng;
+
+use crate::{
+    encoded::{PolyCode, PolyQuery},
+    iris::{conf::IrisConf, fuzz::ArbitraryIris},
+    plaintext::is_iris_match,
+    MiddleBits,
+};
+
+/// A pair of irises in the [`MiddleBits`] configuration.
+type IrisPair = (
+    ArbitraryIris<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>,
+    ArbitraryIris<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>,
+);
+
+/// Check that generated irises use the fuzzer input, and match the same way in the plaintext and
+/// encoded pipelines.
+#[test]
+fn test_arbitrary_irises() {
+    // Empty input is all zeroes.
+    let (new, store) =
+        IrisPair::arbitrary(&mut Unstructured::new(&[])).expect("empty input is valid");
+    assert_eq!(new.code.count_ones() + new.mask.count_ones(), 0);
+    assert_eq!(store.code.count_ones() + store.mask.count_ones(), 0);
+
+    let len = IrisPair::size_hint(0).0;
+    assert_eq!(len, 4 * MiddleBits::DATA_BIT_LEN / 8);
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..3 {
+        let mut input = vec![0_u8; len];
+        rng.fill(input.as_mut_slice());
+
+        let (new, store) =
+            IrisPair::arbitrary(&mut Unstructured::new(&input)).expect("input is long enough");
+        assert_eq!(new.code.as_raw_slice()[0].to_le_bytes(), input[..8]);
+
+        let query = PolyQuery::<MiddleBits>::from_plaintext(&new.code, &new.mask);
+        let code = PolyCode::<MiddleBits>::from_plaintext(&store.code, &store.mask);
+
+        assert_eq!(
+            query.is_match(&code).expect("matching must work"),
+            is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                &new.code,
+                &new.mask,
+                &store.code,
+                &store.mask,
+            ),
+            "{new:?}, {store:?}",
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Tests for open-iris template conversion.
+
+use crate::iris::interop::{
+    from_npy, from_packed_base64, to_npy, to_packed_base64, InteropError, OpenIrisTemplate,
+    OPEN_IRIS_CELL_BITS, OPEN_IRIS_COLUMNS, OPEN_IRIS_FILTERS, OPEN_IRIS_ROWS,
+};
+use crate::plaintext::test::gen::{random_iris_code, random_iris_mask};
+use crate::plaintext::{index_1d, rotate};
+use crate::{FullBits, IrisConf};
+
+/// Returns an NPY version 1.0 file with `dtype`, `shape`, and `data`.
+fn npy(dtype: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+    let header = format!("{{'descr': '{dtype}', 'fortran_order': False, 'shape': {shape}, }}\n");
+
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend_from_slice(
+        &u16::try_from(header.len())
+            .expect("short header")
+            .to_le_bytes(),
+    );
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(data);
+
+    bytes
+}
+
+/// Returns the index of `(row, column, cell_bit)` in the `(rows, columns, cell_bits)` layout.
+fn cell_index(row: usize, column: usize, cell_bit: usize) -> usize {
+    (row * OPEN_IRIS_COLUMNS + column) * OPEN_IRIS_CELL_BITS + cell_bit
+}
+
+/// Check that codes and masks round-trip through packed base64 and NPY files.
+#[test]
+fn test_round_trip() {
+    let code = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = random_iris_mask::<{ FullBits::STORE_ELEM_LEN }>();
+
+    let template = OpenIrisTemplate::new(&code, &mask);
+    assert_eq!(template.code_and_mask(), Ok((code, mask)));
+
+    for bits in [code, mask] {
+        assert_eq!(from_packed_base64(&to_packed_base64(&bits)), Ok(bits));
+
+        let npy = to_npy(&bits);
+        assert!(npy.starts_with(b"\x93NUMPY\x01\x00"));
+        // The data is aligned, like numpy's own files.
+        assert_eq!((npy.len() - FullBits::DATA_BIT_LEN) % 64, 0);
+        assert_eq!(from_npy(&npy), Ok(bits));
+    }
+}
+
+/// Check that open-iris cells map to iris code rows and columns.
+#[test]
+fn test_layout() {
+    let (row, column, cell_bit) = (3, 5, 2);
+    let expected = vec![index_1d(
+        FullBits::COLUMN_LEN,
+        row * OPEN_IRIS_CELL_BITS + cell_bit,
+        column,
+    )];
+
+    // Old layout, packed with np.packbits(), which puts the first bit in the high bit.
+    let i = cell_index(row, column, cell_bit);
+    let mut packed = vec![0_u8; FullBits::DATA_BIT_LEN / 8];
+    packed[i / 8] = 0x80 >> (i % 8);
+    let text = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &packed);
+    let code = from_packed_base64(&text).expect("packed code must be valid");
+    assert_eq!(code.iter_ones().collect::<Vec<_>>(), expected);
+
+    // Old layout, as an NPY array.
+    let mut data = vec![0_u8; FullBits::DATA_BIT_LEN];
+    data[i] = 1;
+    let code = from_npy(&npy("|b1", "(16, 200, 4)", &data)).expect("array must be valid");
+    assert_eq!(code.iter_ones().collect::<Vec<_>>(), expected);
+
+    // Stacked layout: cell bit 2 is the real part of the second filter.
+    let mut data = vec![0_u8; FullBits::DATA_BIT_LEN];
+    data[((OPEN_IRIS_ROWS + row) * OPEN_IRIS_COLUMNS + column) * 2] = 1;
+    let code = from_npy(&npy("|u1", "(2, 16, 200, 2)", &data)).expect("array must be valid");
+    assert_eq!(code.iter_ones().collect::<Vec<_>>(), expected);
+    assert_eq!(OPEN_IRIS_FILTERS * 2, OPEN_IRIS_CELL_BITS);
+}
+
+/// Check that np.roll() along the column axis matches iris code rotations.
+#[test]
+fn test_rotation_convention() {
+    let code = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let file = to_npy(&code);
+    let data = &file[file.len() - FullBits::DATA_BIT_LEN..];
+
+    // Rolling by the number of columns minus n is the same as rolling by -n.
+    for shift in [1, 15, OPEN_IRIS_COLUMNS - 1, OPEN_IRIS_COLUMNS - 15] {
+        // np.roll(array, shift, axis=1)
+        let mut rolled = vec![0_u8; FullBits::DATA_BIT_LEN];
+        for row in 0..OPEN_IRIS_ROWS {
+            for column in 0..OPEN_IRIS_COLUMNS {
+                let rolled_column = (column + shift) % OPEN_IRIS_COLUMNS;
+                for cell_bit in 0..OPEN_IRIS_CELL_BITS {
+                    rolled[cell_index(row, rolled_column, cell_bit)] =
+                        data[cell_index(row, column, cell_bit)];
+                }
+            }
+        }
+
+        let rolled = from_npy(&npy("|b1", "(16, 200, 4)", &rolled)).expect("array must be valid");
+        let amount = isize::try_from(shift).expect("shift must be small");
+        assert_eq!(rolled, rotate::<FullBits, _>(code, amount), "{shift}");
+    }
+}
+
+/// Check that invalid templates are rejected.
+#[test]
+fn test_invalid_templates() {
+    assert_eq!(
+        from_packed_base64("not base64!"),
+        Err(InteropError::InvalidBase64)
+    );
+    assert_eq!(
+        from_packed_base64("AAAA"),
+        Err(InteropError::InvalidLength(3))
+    );
+
+    let data = vec![0_u8; FullBits::DATA_BIT_LEN];
+    assert_eq!(
+        from_npy(&npy("|b1", "(200, 16, 4)", &data)),
+        Err(InteropError::InvalidShape(vec![200, 16, 4]))
+    );
+    assert_eq!(
+        from_npy(&npy("<f8", "(16, 200, 4)", &data)),
+        Err(InteropError::UnsupportedDtype("<f8".to_string()))
+    );
+    assert!(matches!(
+        from_npy(&npy("|b1", "(16, 200, 4)", &data[1..])),
+        Err(InteropError::InvalidNpy(_))
+    ));
+    assert!(matches!(from_npy(&data), Err(InteropError::InvalidNpy(_))));
+
+    // Shapes with more elements than usize::MAX are rejected.
+    let huge = format!("({}, {}, 4)", usize::MAX, usize::MAX);
+    assert!(matches!(
+        from_npy(&npy("|b1", &huge, &data)),
+        Err(InteropError::InvalidNpy("array shape is too large"))
+    ));
+
+    let mut not_bits = data.clone();
+    not_bits[0] = 2;
+    assert!(matches!(
+        from_npy(&npy("|u1", "(16, 200, 4)", &not_bits)),
+        Err(InteropError::InvalidNpy(_))
+    ));
+}
+
+/// Check that serialized open-iris templates can be parsed from JSON.
+#[cfg(feature = "serde")]
+#[test]
+fn test_serialized_template() {
+    let code = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = random_iris_mask::<{ FullBits::STORE_ELEM_LEN }>();
+
+    let json = format!(
+        r#"{{"iris_codes": "{}", "mask_codes": "{}", "iris_code_version": "v2.1"}}"#,
+        to_packed_base64(&code),
+        to_packed_base64(&mask)
+    );
+    let template: OpenIrisTemplate = serde_json::from_str(&json).expect("JSON must be valid");
+    assert_eq!(template.iris_code_version.as_deref(), Some("v2.1"));
+    assert_eq!(template.code_and_mask(), Ok((code, mask)));
+
+    // The version is optional.
+    let json = serde_json::to_string(&OpenIrisTemplate::new(&code, &mask)).expect("must serialize");
+    assert!(!json.contains("iris_code_version"));
+    let template: OpenIrisTemplate = serde_json::from_str(&json).expect("JSON must be valid");
+    assert_eq!(template.code_and_mask(), Ok((code, mask)));
+}
@@ -0,0 +1,91 @@
+//! Tests for iris code import and export.
+
+use crate::iris::io::{
+    from_base64, from_bytes, from_row_major_bits, to_base64, to_bytes, to_row_major_bits, IoError,
+    HEADER_LEN,
+};
+use crate::plaintext::test::gen::{random_iris_code, random_iris_mask};
+use crate::{FullBits, IrisConf, MiddleBits};
+
+/// Check that codes and masks round-trip through bytes and base64.
+#[test]
+fn test_bytes_and_base64() {
+    let code = random_iris_code::<{ FullBits::STORE_ELEM_LEN }>();
+    let mask = random_iris_mask::<{ FullBits::STORE_ELEM_LEN }>();
+
+    for bits in [code, mask] {
+        let bytes = to_bytes::<FullBits, { FullBits::STORE_ELEM_LEN }>(&bits);
+        assert_eq!(bytes.len(), HEADER_LEN + FullBits::DATA_BIT_LEN / 8);
+        assert_eq!(
+            from_bytes::<FullBits, { FullBits::STORE_ELEM_LEN }>(&bytes),
+            Ok(bits)
+        );
+
+        let text = to_base64::<FullBits, { FullBits::STORE_ELEM_LEN }>(&bits);
+        assert_eq!(
+            from_base64::<FullBits, { FullBits::STORE_ELEM_LEN }>(&text),
+            Ok(bits)
+        );
+    }
+
+    // Codes from a different configuration are rejected.
+    let bytes = to_bytes::<FullBits, { FullBits::STORE_ELEM_LEN }>(&code);
+    assert_eq!(
+        from_bytes::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&bytes),
+        Err(IoError::DimensionMismatch {
+            rows: FullBits::COLUMN_LEN,
+            columns: FullBits::COLUMNS,
+        })
+    );
+    assert_eq!(
+        from_bytes::<FullBits, { FullBits::STORE_ELEM_LEN }>(&bytes[..bytes.len() - 1]),
+        Err(IoError::InvalidLength)
+    );
+    assert_eq!(
+        from_base64::<FullBits, { FullBits::STORE_ELEM_LEN }>("not base64!"),
+        Err(IoError::InvalidBase64)
+    );
+}
+
+/// Check the 2D row-major layout adapter.
+#[test]
+fn test_row_major_bits() {
+    let code = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+
+    let bits = to_row_major_bits::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&code);
+    assert_eq!(bits.len(), MiddleBits::DATA_BIT_LEN);
+    assert_eq!(
+        from_row_major_bits::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            &bits,
+            MiddleBits::COLUMN_LEN,
+            MiddleBits::COLUMNS,
+        ),
+        Ok(code)
+    );
+
+    // The second bit of the first row is in the second column.
+    let mut bits = vec![false; MiddleBits::DATA_BIT_LEN];
+    bits[1] = true;
+    let code = from_row_major_bits::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+        &bits,
+        MiddleBits::COLUMN_LEN,
+        MiddleBits::COLUMNS,
+    )
+    .expect("dimensions must match");
+    assert_eq!(
+        code.iter_ones().collect::<Vec<_>>(),
+        vec![MiddleBits::COLUMN_LEN]
+    );
+
+    assert_eq!(
+        from_row_major_bits::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            &bits,
+            MiddleBits::COLUMNS,
+            MiddleBits::COLUMN_LEN,
+        ),
+        Err(IoError::DimensionMismatch {
+            rows: MiddleBits::COLUMNS,
+            columns: MiddleBits::COLUMN_LEN,
+        })
+    );
+}
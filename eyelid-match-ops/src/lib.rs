@@ -10,27 +10,60 @@
 //! [`encrypted`]: the same operations on fully homomorphic encrypted, polynomial-encoded bit
 //!                vectors.
 //!
-//! Configurations are in [`conf`] and [`iris`], and building blocks are in [`primitives`].
+//! Configurations are in [`conf`] and [`iris`], named encryption parameters are in [`params`],
+//! and building blocks are in [`primitives`].
+//! Errors from every module can be converted into [`Error`], and memory use and operation counts
+//! are reported by [`metrics`].
+//!
+//! The [`encoded`], [`encrypted`], [`params`], [`metrics`], and [`primitives`] modules need the
+//! `fhe` feature, which is enabled by default. Disable default features for a plaintext-only
+//! build, with a much smaller dependency tree.
+//!
+//! The library builds for `wasm32-unknown-unknown`, so [`plaintext`] and [`encoded`] matching can
+//! run in browsers. Encrypted matching times each match using [`std::time::Instant`], which is
+//! not available in browsers.
 
 #[macro_use]
 extern crate static_assertions;
 
 pub mod conf;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "fhe")]
 pub mod encoded;
+#[cfg(feature = "fhe")]
 pub mod encrypted;
+pub mod error;
 pub mod iris;
+#[cfg(feature = "fhe")]
+pub mod metrics;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "fhe")]
+pub mod params;
+pub mod pipeline;
 pub mod plaintext;
+#[cfg(feature = "fhe")]
 pub mod primitives;
 
-pub use conf::{FullBits, MiddleBits};
+#[cfg(all(any(test, feature = "test-util"), feature = "fhe"))]
+pub mod stats;
+
+pub use conf::{FullBits, MiddleBits, MiddleBitsPacked};
+#[cfg(feature = "fhe")]
 pub use encoded::{EncodeConf, FullRes, MiddleRes};
+pub use error::{Error, Result};
 pub use iris::conf::IrisConf;
-pub use primitives::{poly::PolyConf, yashe::YasheConf};
+#[cfg(feature = "parallel")]
+pub use parallel::{set_thread_pool, ThreadPoolConfig};
+pub use pipeline::IrisMatcher;
+#[cfg(feature = "fhe")]
+pub use primitives::{entropy::EntropySource, poly::PolyConf, yashe::YasheConf};
 
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub use conf::TestBits;
 
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(all(any(test, feature = "test-util"), feature = "fhe"))]
 pub use encoded::TestRes;
 
 #[cfg(tiny_poly)]
@@ -0,0 +1,199 @@
+//! Memory use and operation counts, for capacity planning and monitoring.
+//!
+//! [`MemorySize`] reports the memory used by live keys, queries, and stored codes, and
+//! [`ArtifactSizes`] collects those sizes for a matching deployment. [`operation_counts()`]
+//! returns the number of expensive polynomial operations performed by this library.
+//!
+//! With the `metrics` feature, operation counts and artifact sizes are also reported using the
+//! [`metrics`](::metrics) crate facade, so they can be exported by any `metrics` recorder.
+
+use std::{
+    mem::{size_of, size_of_val},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    encoded::{PolyCode, PolyQuery},
+    encrypted::{store::MemoryCodeStore, EncryptedPolyQuery},
+    primitives::{
+        poly::Poly,
+        yashe::{Ciphertext, PrivateKey, PublicKey},
+    },
+    EncodeConf, PolyConf, YasheConf,
+};
+
+#[cfg(test)]
+mod test;
+
+/// The `metrics` counter name for the number of polynomial multiplications.
+pub const POLY_MULS_METRIC: &str = "eyelid_poly_muls_total";
+
+/// The `metrics` counter name for the number of polynomial reductions.
+pub const POLY_REDUCTIONS_METRIC: &str = "eyelid_poly_reductions_total";
+
+/// The `metrics` gauge name for artifact sizes in bytes. The artifact is in the `artifact` label.
+pub const ARTIFACT_BYTES_METRIC: &str = "eyelid_artifact_bytes";
+
+/// The number of polynomial multiplications since the last reset.
+static POLY_MULS: AtomicU64 = AtomicU64::new(0);
+
+/// The number of polynomial reductions since the last reset.
+static POLY_REDUCTIONS: AtomicU64 = AtomicU64::new(0);
+
+/// The number of expensive operations performed by this library, in all threads.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct OperationCounts {
+    /// The number of cyclotomic polynomial multiplications, using any backend.
+    pub poly_muls: u64,
+    /// The number of reductions by the polynomial modulus.
+    pub poly_reductions: u64,
+}
+
+/// Returns the number of expensive operations performed since the last call to
+/// [`reset_operation_counts()`].
+pub fn operation_counts() -> OperationCounts {
+    OperationCounts {
+        poly_muls: POLY_MULS.load(Ordering::Relaxed),
+        poly_reductions: POLY_REDUCTIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Resets the operation counts to zero. Counts reported using the `metrics` facade are not reset.
+pub fn reset_operation_counts() {
+    POLY_MULS.store(0, Ordering::Relaxed);
+    POLY_REDUCTIONS.store(0, Ordering::Relaxed);
+}
+
+/// Counts a polynomial multiplication.
+pub(crate) fn count_poly_mul() {
+    POLY_MULS.fetch_add(1, Ordering::Relaxed);
+
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(POLY_MULS_METRIC).increment(1);
+}
+
+/// Counts a polynomial reduction.
+pub(crate) fn count_poly_reduction() {
+    POLY_REDUCTIONS.fetch_add(1, Ordering::Relaxed);
+
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(POLY_REDUCTIONS_METRIC).increment(1);
+}
+
+/// The memory used by a value, including its heap allocations.
+pub trait MemorySize {
+    /// Returns the number of bytes allocated on the heap by this value.
+    fn heap_size(&self) -> usize;
+
+    /// Returns the total number of bytes used by this value, including its heap allocations.
+    fn memory_size(&self) -> usize {
+        size_of_val(self) + self.heap_size()
+    }
+}
+
+impl<T: MemorySize> MemorySize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<C: PolyConf> MemorySize for Poly<C> {
+    fn heap_size(&self) -> usize {
+        self.coeffs.capacity() * size_of::<C::Coeff>()
+    }
+}
+
+impl<C: YasheConf> MemorySize for Ciphertext<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn heap_size(&self) -> usize {
+        self.c.heap_size()
+    }
+}
+
+impl<C: YasheConf> MemorySize for PrivateKey<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn heap_size(&self) -> usize {
+        self.f.heap_size() + self.priv_key_inv.heap_size() + self.priv_key.heap_size()
+    }
+}
+
+impl<C: YasheConf> MemorySize for PublicKey<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn heap_size(&self) -> usize {
+        self.h.heap_size()
+    }
+}
+
+impl<C: EncodeConf> MemorySize for PolyCode<C> {
+    fn heap_size(&self) -> usize {
+        self.polys.heap_size() + self.masks.heap_size()
+    }
+}
+
+impl<C: EncodeConf> MemorySize for PolyQuery<C> {
+    fn heap_size(&self) -> usize {
+        self.polys.heap_size() + self.masks.heap_size()
+    }
+}
+
+/// The memory used by the live artifacts of an encrypted matching deployment, in bytes.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ArtifactSizes {
+    /// The private key.
+    pub private_key: usize,
+    /// The public key.
+    pub public_key: usize,
+    /// A single encrypted query, ready to be matched.
+    pub query: usize,
+    /// All the encrypted codes in the gallery.
+    pub gallery: usize,
+    /// The number of codes in the gallery.
+    pub gallery_len: usize,
+}
+
+impl ArtifactSizes {
+    /// Returns the memory used by each artifact.
+    pub fn new<C: EncodeConf>(
+        private_key: &PrivateKey<C::PlainConf>,
+        public_key: &PublicKey<C::PlainConf>,
+        query: &EncryptedPolyQuery<C>,
+        gallery: &MemoryCodeStore<C>,
+    ) -> Self
+    where
+        C::PlainConf: YasheConf,
+        <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+    {
+        Self {
+            private_key: private_key.memory_size(),
+            public_key: public_key.memory_size(),
+            query: query.memory_size(),
+            gallery: gallery.heap_size(),
+            gallery_len: gallery.len(),
+        }
+    }
+
+    /// Returns the average memory used by each code in the gallery, or zero if it is empty.
+    pub fn bytes_per_code(&self) -> usize {
+        self.gallery.checked_div(self.gallery_len).unwrap_or(0)
+    }
+
+    /// Reports these sizes as `metrics` gauges, labelled by artifact.
+    #[cfg(feature = "metrics")]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn record(&self) {
+        for (artifact, bytes) in [
+            ("private_key", self.private_key),
+            ("public_key", self.public_key),
+            ("query", self.query),
+            ("gallery", self.gallery),
+        ] {
+            ::metrics::gauge!(ARTIFACT_BYTES_METRIC, "artifact" => artifact).set(bytes as f64);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Tests for memory use and operation counts.
+
+use std::mem::size_of;
+
+use crate::{
+    encrypted::{
+        store::{EncryptedCodeStore, MemoryCodeStore},
+        EncryptedMatcher,
+    },
+    iris::conf::IrisConf,
+    metrics::{operation_counts, ArtifactSizes, MemorySize},
+    plaintext::test::gen::{random_iris_code, visible_iris_mask},
+    primitives::poly::{test::gen::rand_poly, PolyConf},
+    FullBits, FullRes,
+};
+
+/// Check that polynomial operations are counted.
+///
+/// Other tests run concurrently, so the counts can only be checked for increases.
+#[test]
+fn test_operation_counts() {
+    let a = rand_poly::<FullRes>(FullRes::MAX_POLY_DEGREE - 1);
+    let b = rand_poly::<FullRes>(FullRes::MAX_POLY_DEGREE - 1);
+
+    let before = operation_counts();
+    let _product = &a * &b;
+    let after = operation_counts();
+
+    assert!(after.poly_muls > before.poly_muls);
+    assert!(after.poly_reductions > before.poly_reductions);
+}
+
+/// Check that artifact sizes include every coefficient, and grow with the gallery.
+#[test]
+fn test_artifact_sizes() {
+    let matcher = EncryptedMatcher::<FullBits>::builder().build();
+    let mask = visible_iris_mask();
+
+    let query = matcher.encrypt_query(&random_iris_code::<{ FullBits::STORE_ELEM_LEN }>(), &mask);
+
+    let mut store = MemoryCodeStore::<FullBits>::new();
+    let empty = ArtifactSizes::new(matcher.private_key(), matcher.public_key(), &query, &store);
+    assert_eq!(empty.gallery, 0);
+    assert_eq!(empty.bytes_per_code(), 0);
+
+    for id in 0..2 {
+        let code = matcher.enroll(&random_iris_code(), &mask);
+        store.put(id, code).expect("memory store never fails");
+    }
+    let sizes = ArtifactSizes::new(matcher.private_key(), matcher.public_key(), &query, &store);
+
+    let min_poly_size = FullRes::MAX_POLY_DEGREE * size_of::<<FullRes as PolyConf>::Coeff>();
+    assert!(sizes.public_key >= min_poly_size);
+    assert!(sizes.private_key >= 3 * min_poly_size);
+    assert!(sizes.query >= query.heap_size());
+    assert_eq!(sizes.gallery_len, 2);
+    assert_eq!(sizes.gallery, store.heap_size());
+    assert!(sizes.bytes_per_code() >= min_poly_size);
+}
@@ -0,0 +1,83 @@
+//! Configuration of the thread pool used by the `parallel` feature.
+//!
+//! By default, parallel matching and encryption run on rayon's global thread pool. Servers can
+//! call [`set_thread_pool()`] to run them on a dedicated pool instead, bounding the number of
+//! threads this library uses.
+
+use std::sync::{Arc, PoisonError, RwLock};
+
+use lazy_static::lazy_static;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+#[cfg(test)]
+mod test;
+
+/// The configuration of a dedicated thread pool.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ThreadPoolConfig {
+    /// The number of threads in the pool. Zero uses rayon's default, which is based on the number
+    /// of CPUs and the `RAYON_NUM_THREADS` environment variable.
+    pub num_threads: usize,
+
+    /// The name prefix for pool threads. Each thread's index is appended to this prefix.
+    pub thread_name: Option<String>,
+}
+
+lazy_static! {
+    /// The dedicated thread pool, or `None` if rayon's global pool is used.
+    static ref THREAD_POOL: RwLock<Option<Arc<ThreadPool>>> = RwLock::new(None);
+}
+
+/// Builds a thread pool with `config`, and uses it for all parallel operations in this library,
+/// replacing any previously set pool.
+///
+/// Operations which are already running finish on the pool they started on.
+pub fn set_thread_pool(config: ThreadPoolConfig) -> Result<(), ThreadPoolBuildError> {
+    let mut builder = ThreadPoolBuilder::new().num_threads(config.num_threads);
+    if let Some(prefix) = config.thread_name {
+        builder = builder.thread_name(move |index| format!("{prefix}{index}"));
+    }
+
+    let pool = builder.build()?;
+
+    // The pool is never left in an inconsistent state, so poisoning can be ignored.
+    *THREAD_POOL.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(pool));
+
+    Ok(())
+}
+
+/// Removes the dedicated thread pool, so parallel operations use rayon's global pool.
+pub fn reset_thread_pool() {
+    *THREAD_POOL.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Returns the number of threads used by parallel operations in this library.
+pub fn current_num_threads() -> usize {
+    match thread_pool() {
+        Some(pool) => pool.current_num_threads(),
+        None => rayon::current_num_threads(),
+    }
+}
+
+/// Returns the dedicated thread pool, if one has been set.
+fn thread_pool() -> Option<Arc<ThreadPool>> {
+    THREAD_POOL
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Runs `op` on the dedicated thread pool, if one has been set. Rayon parallel iterators in `op`
+/// run on that pool.
+///
+/// Otherwise, runs `op` on the current thread, so its parallel iterators use the global pool.
+pub(crate) fn install<OP, R>(op: OP) -> R
+where
+    OP: FnOnce() -> R + Send,
+    R: Send,
+{
+    match thread_pool() {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
@@ -0,0 +1,36 @@
+//! Tests for thread pool configuration.
+
+use std::thread;
+
+use rayon::prelude::*;
+
+use super::{current_num_threads, install, reset_thread_pool, set_thread_pool, ThreadPoolConfig};
+
+/// Check that parallel operations run on the configured pool, then on the global pool after a
+/// reset.
+///
+/// This is the only test which changes the pool, so it doesn't interfere with other tests.
+#[test]
+fn test_set_thread_pool() {
+    set_thread_pool(ThreadPoolConfig {
+        num_threads: 2,
+        thread_name: Some("eyelid-test-".to_string()),
+    })
+    .expect("a 2 thread pool builds");
+
+    assert_eq!(current_num_threads(), 2);
+
+    let names: Vec<Option<String>> = install(|| {
+        (0..64)
+            .into_par_iter()
+            .map(|_| thread::current().name().map(str::to_string))
+            .collect()
+    });
+    assert!(names.iter().all(|name| name
+        .as_deref()
+        .is_some_and(|name| name.starts_with("eyelid-test-"))));
+
+    reset_thread_pool();
+
+    assert_eq!(current_num_threads(), rayon::current_num_threads());
+}
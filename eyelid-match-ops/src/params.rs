@@ -0,0 +1,132 @@
+//! Named encryption parameter sets.
+//!
+//! Each [`ParamSet`] has a stable name, which can be used to choose parameters in wire formats,
+//! command-line tools, and services. Names include a version, so the parameters for a name never
+//! change. New parameters get a new name.
+
+use std::fmt;
+
+use crate::{
+    encoded::conf::LargeRes,
+    encrypted::{
+        store::StoreError,
+        wire::{encode_params, HEADER_LEN, KEY_MAGIC, MAGIC, QUERY_MAGIC},
+    },
+    FullRes, MiddleRes, PolyConf, YasheConf,
+};
+
+#[cfg(test)]
+mod test;
+
+/// A named set of encryption parameters, which corresponds to a compile-time [`YasheConf`].
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ParamSet {
+    /// The [`FullRes`] parameters, used to encrypt [`FullBits`](crate::FullBits) codes.
+    Full,
+    /// The [`MiddleRes`] parameters, used to encrypt [`MiddleBits`](crate::MiddleBits) codes.
+    Middle,
+    /// The [`LargeRes`] parameters, which are only used for experimentation.
+    Large,
+}
+
+impl ParamSet {
+    /// Every parameter set, in the order they were added.
+    pub const ALL: [ParamSet; 3] = [ParamSet::Full, ParamSet::Middle, ParamSet::Large];
+
+    /// Returns the parameter set called `name`, or `None` if there is no set with that name.
+    pub fn by_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|set| set.name() == name)
+    }
+
+    /// Returns the stable name of this parameter set.
+    pub fn name(self) -> &'static str {
+        match self {
+            ParamSet::Full => "full-v1",
+            ParamSet::Middle => "middle-v1",
+            ParamSet::Large => "large-v1",
+        }
+    }
+
+    /// Returns the maximum polynomial degree of this parameter set.
+    pub fn poly_degree(self) -> usize {
+        match self {
+            ParamSet::Full => FullRes::MAX_POLY_DEGREE,
+            ParamSet::Middle => MiddleRes::MAX_POLY_DEGREE,
+            ParamSet::Large => LargeRes::MAX_POLY_DEGREE,
+        }
+    }
+
+    /// Returns the plaintext modulus `T` of this parameter set.
+    pub fn plaintext_modulus(self) -> u64 {
+        match self {
+            ParamSet::Full => FullRes::T,
+            ParamSet::Middle => MiddleRes::T,
+            ParamSet::Large => LargeRes::T,
+        }
+    }
+
+    /// Returns the encoded parameters of this set, as they appear in
+    /// [`wire`](crate::encrypted::wire) headers after the magic and version.
+    pub fn header(self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        match self {
+            ParamSet::Full => encode_params::<FullRes>(&mut bytes),
+            ParamSet::Middle => encode_params::<MiddleRes>(&mut bytes),
+            ParamSet::Large => encode_params::<LargeRes>(&mut bytes),
+        }
+
+        bytes
+    }
+
+    /// Returns the parameter set used by a [`wire`](crate::encrypted::wire) encoding of a code,
+    /// query, or key.
+    ///
+    /// Returns [`StoreError::InvalidEncoding`] if the encoding doesn't have a valid header, or
+    /// its parameters don't match any named set.
+    pub fn from_header(bytes: &[u8]) -> Result<Self, StoreError> {
+        let magic = bytes
+            .get(..MAGIC.len())
+            .ok_or(StoreError::InvalidEncoding)?;
+        if ![MAGIC, QUERY_MAGIC, KEY_MAGIC].iter().any(|m| m == magic) {
+            return Err(StoreError::InvalidEncoding);
+        }
+
+        // Skip the magic and version.
+        let params = bytes
+            .get(MAGIC.len() + 2..HEADER_LEN)
+            .ok_or(StoreError::InvalidEncoding)?;
+
+        Self::ALL
+            .into_iter()
+            .find(|set| set.header() == params)
+            .ok_or(StoreError::InvalidEncoding)
+    }
+}
+
+impl fmt::Display for ParamSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A [`YasheConf`] with a named [`ParamSet`].
+pub trait NamedParamSet: YasheConf
+where
+    Self::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The named parameter set for this configuration.
+    const PARAM_SET: ParamSet;
+}
+
+impl NamedParamSet for FullRes {
+    const PARAM_SET: ParamSet = ParamSet::Full;
+}
+
+impl NamedParamSet for MiddleRes {
+    const PARAM_SET: ParamSet = ParamSet::Middle;
+}
+
+impl NamedParamSet for LargeRes {
+    const PARAM_SET: ParamSet = ParamSet::Large;
+}
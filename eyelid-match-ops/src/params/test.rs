@@ -0,0 +1,44 @@
+//! Tests for named parameter sets.
+
+use crate::{
+    params::{NamedParamSet, ParamSet},
+    primitives::yashe::Yashe,
+    MiddleRes, PolyConf, YasheConf,
+};
+
+/// Check that every parameter set can be found by its name, and names and headers are unique.
+#[test]
+fn test_by_name() {
+    for set in ParamSet::ALL {
+        assert_eq!(ParamSet::by_name(set.name()), Some(set));
+        assert_eq!(set.to_string(), set.name());
+
+        for other in ParamSet::ALL.into_iter().filter(|other| *other != set) {
+            assert_ne!(set.name(), other.name());
+            assert_ne!(set.header(), other.header());
+        }
+    }
+
+    assert_eq!(ParamSet::by_name("full"), None);
+    assert_eq!(ParamSet::by_name("full-v2"), None);
+}
+
+/// Check that the parameter set of an encoded key is detected from its header.
+#[test]
+fn test_from_header() {
+    let (_private_key, public_key) = Yashe::<MiddleRes>::new().keygen(&mut rand::thread_rng());
+    let bytes = public_key.to_bytes();
+
+    assert_eq!(
+        ParamSet::from_header(&bytes).ok(),
+        Some(MiddleRes::PARAM_SET)
+    );
+    assert_eq!(
+        MiddleRes::PARAM_SET.poly_degree(),
+        MiddleRes::MAX_POLY_DEGREE
+    );
+    assert_eq!(MiddleRes::PARAM_SET.plaintext_modulus(), MiddleRes::T);
+
+    assert!(ParamSet::from_header(&bytes[..10]).is_err());
+    assert!(ParamSet::from_header(b"not an encoding at all, just some bytes").is_err());
+}
@@ -0,0 +1,302 @@
+//! Interchangeable matching pipelines, behind one object-safe interface.
+//!
+//! Each pipeline enrolls plaintext iris codes into its own gallery, in its own representation,
+//! then verifies or identifies plaintext queries against that gallery. Applications can choose
+//! a pipeline at runtime, or run several side by side, using `Box<dyn IrisMatcher<_>>`.
+//!
+//! The [`PlaintextPipeline`] and [`EncodedPipeline`] use the match threshold and rotation limit
+//! in their configuration. The [`EncryptedPipeline`] uses the settings of its
+//! [`EncryptedMatcher`], and notifies its observer after every match.
+
+use std::{fmt::Debug, marker::PhantomData};
+
+use crate::{
+    iris::conf::{IrisCode, IrisConf, IrisMask},
+    plaintext::{is_iris_match, match_many},
+    Error, Result,
+};
+
+#[cfg(feature = "fhe")]
+use num_bigint::BigUint;
+
+#[cfg(feature = "fhe")]
+use crate::{
+    encoded::{PolyCode, PolyQuery},
+    encrypted::{EncryptedMatcher, EncryptedPolyCode},
+    EncodeConf, PolyConf, YasheConf,
+};
+
+#[cfg(test)]
+mod test;
+
+/// The id of an enrolled template, which is its index in the pipeline's gallery.
+pub type TemplateId = usize;
+
+/// Enrolls and matches plaintext iris codes, using a specific matching pipeline.
+///
+/// This trait is object-safe, so pipelines can be used as `dyn IrisMatcher<STORE_ELEM_LEN>`.
+pub trait IrisMatcher<const STORE_ELEM_LEN: usize>: Debug {
+    /// Returns a short name for the pipeline, for logging and comparisons.
+    fn name(&self) -> &'static str;
+
+    /// Adds `code` and `mask` to the gallery, and returns the id of the new template.
+    fn enroll(
+        &mut self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<TemplateId>;
+
+    /// Returns true if `code` and `mask` match the template with `id`.
+    ///
+    /// Returns [`Error::UnknownTemplate`] if there is no template with `id`.
+    fn verify(
+        &mut self,
+        id: TemplateId,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<bool>;
+
+    /// Returns the ids of every template that matches `code` and `mask`, in id order.
+    fn identify(
+        &mut self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<Vec<TemplateId>>;
+}
+
+/// Returns the ids of the true results in `is_match`.
+fn matching_ids(is_match: impl IntoIterator<Item = bool>) -> Vec<TemplateId> {
+    is_match
+        .into_iter()
+        .enumerate()
+        .filter_map(|(id, is_match)| is_match.then_some(id))
+        .collect()
+}
+
+/// Matches raw iris codes, using [`is_iris_match()`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlaintextPipeline<C: IrisConf, const STORE_ELEM_LEN: usize> {
+    /// The enrolled codes and masks.
+    gallery: Vec<(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>)>,
+    /// The iris configuration.
+    _conf: PhantomData<C>,
+}
+
+impl<C: IrisConf, const STORE_ELEM_LEN: usize> PlaintextPipeline<C, STORE_ELEM_LEN> {
+    /// Returns a pipeline with an empty gallery.
+    pub fn new() -> Self {
+        Self {
+            gallery: Vec::new(),
+            _conf: PhantomData,
+        }
+    }
+}
+
+impl<C: IrisConf, const STORE_ELEM_LEN: usize> Default for PlaintextPipeline<C, STORE_ELEM_LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: IrisConf + Debug, const STORE_ELEM_LEN: usize> IrisMatcher<STORE_ELEM_LEN>
+    for PlaintextPipeline<C, STORE_ELEM_LEN>
+{
+    fn name(&self) -> &'static str {
+        "plaintext"
+    }
+
+    fn enroll(
+        &mut self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<TemplateId> {
+        self.gallery.push((*code, *mask));
+
+        Ok(self.gallery.len() - 1)
+    }
+
+    fn verify(
+        &mut self,
+        id: TemplateId,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<bool> {
+        let (eye_store, mask_store) = self.gallery.get(id).ok_or(Error::UnknownTemplate(id))?;
+
+        Ok(is_iris_match::<C, STORE_ELEM_LEN>(
+            code, mask, eye_store, mask_store,
+        ))
+    }
+
+    fn identify(
+        &mut self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<Vec<TemplateId>> {
+        Ok(matching_ids(match_many::<C, STORE_ELEM_LEN>(
+            code,
+            mask,
+            &self.gallery,
+        )))
+    }
+}
+
+/// Matches polynomial-encoded iris codes, using [`PolyQuery::is_match()`].
+#[cfg(feature = "fhe")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EncodedPipeline<C: EncodeConf> {
+    /// The enrolled codes.
+    gallery: Vec<PolyCode<C>>,
+}
+
+#[cfg(feature = "fhe")]
+impl<C: EncodeConf> EncodedPipeline<C> {
+    /// Returns a pipeline with an empty gallery.
+    pub fn new() -> Self {
+        Self {
+            gallery: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "fhe")]
+impl<C: EncodeConf> Default for EncodedPipeline<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "fhe")]
+impl<C: EncodeConf + Debug, const STORE_ELEM_LEN: usize> IrisMatcher<STORE_ELEM_LEN>
+    for EncodedPipeline<C>
+where
+    BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+{
+    fn name(&self) -> &'static str {
+        "encoded"
+    }
+
+    fn enroll(
+        &mut self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<TemplateId> {
+        self.gallery.push(PolyCode::from_plaintext(code, mask));
+
+        Ok(self.gallery.len() - 1)
+    }
+
+    fn verify(
+        &mut self,
+        id: TemplateId,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<bool> {
+        let stored = self.gallery.get(id).ok_or(Error::UnknownTemplate(id))?;
+
+        Ok(PolyQuery::<C>::from_plaintext(code, mask).is_match(stored)?)
+    }
+
+    fn identify(
+        &mut self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<Vec<TemplateId>> {
+        let query = PolyQuery::<C>::from_plaintext(code, mask);
+
+        Ok(matching_ids(query.is_match_many(&self.gallery)?))
+    }
+}
+
+/// Matches encrypted iris codes, using an [`EncryptedMatcher`].
+#[cfg(feature = "fhe")]
+#[derive(Clone, Debug)]
+pub struct EncryptedPipeline<C: EncodeConf>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The matcher, which encrypts codes and queries, and decrypts match results.
+    matcher: EncryptedMatcher<C>,
+    /// The enrolled encrypted codes.
+    gallery: Vec<EncryptedPolyCode<C>>,
+}
+
+#[cfg(feature = "fhe")]
+impl<C: EncodeConf> EncryptedPipeline<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Returns a pipeline using `matcher`, with an empty gallery.
+    pub fn new(matcher: EncryptedMatcher<C>) -> Self {
+        Self {
+            matcher,
+            gallery: Vec::new(),
+        }
+    }
+
+    /// Returns the matcher.
+    pub fn matcher(&self) -> &EncryptedMatcher<C> {
+        &self.matcher
+    }
+
+    /// Returns the enrolled encrypted codes, indexed by template id.
+    pub fn gallery(&self) -> &[EncryptedPolyCode<C>] {
+        &self.gallery
+    }
+}
+
+#[cfg(feature = "fhe")]
+impl<C: EncodeConf + Debug, const STORE_ELEM_LEN: usize> IrisMatcher<STORE_ELEM_LEN>
+    for EncryptedPipeline<C>
+where
+    C::PlainConf: YasheConf,
+    <C::PlainConf as PolyConf>::Coeff: From<u128> + From<u64> + From<i64>,
+    BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+{
+    fn name(&self) -> &'static str {
+        "encrypted"
+    }
+
+    fn enroll(
+        &mut self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<TemplateId> {
+        let encrypted = self.matcher.enroll(code, mask);
+        self.gallery.push(encrypted);
+
+        Ok(self.gallery.len() - 1)
+    }
+
+    fn verify(
+        &mut self,
+        id: TemplateId,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<bool> {
+        if id >= self.gallery.len() {
+            return Err(Error::UnknownTemplate(id));
+        }
+        let query = self.matcher.encrypt_query(code, mask);
+
+        self.matcher.verify(&query, &self.gallery[id])
+    }
+
+    fn identify(
+        &mut self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<Vec<TemplateId>> {
+        let query = self.matcher.encrypt_query(code, mask);
+
+        let is_match = self
+            .gallery
+            .iter()
+            .map(|stored| self.matcher.verify(&query, stored))
+            .collect::<Result<Vec<bool>>>()?;
+
+        Ok(matching_ids(is_match))
+    }
+}
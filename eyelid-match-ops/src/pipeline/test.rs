@@ -0,0 +1,75 @@
+//! Tests for interchangeable matching pipelines.
+
+use crate::{
+    iris::conf::IrisConf,
+    pipeline::{IrisMatcher, PlaintextPipeline},
+    plaintext::test::gen::{random_iris_code, similar_iris_code, visible_iris_mask},
+    Error, FullBits,
+};
+
+#[cfg(feature = "fhe")]
+use crate::{
+    encrypted::EncryptedMatcher,
+    pipeline::{EncodedPipeline, EncryptedPipeline},
+};
+
+/// The storage length of the codes matched in these tests.
+const STORE_ELEM_LEN: usize = FullBits::STORE_ELEM_LEN;
+
+/// Returns every pipeline, as trait objects.
+fn pipelines() -> Vec<Box<dyn IrisMatcher<STORE_ELEM_LEN>>> {
+    vec![
+        Box::new(PlaintextPipeline::<FullBits, STORE_ELEM_LEN>::new()),
+        #[cfg(feature = "fhe")]
+        Box::new(EncodedPipeline::<FullBits>::new()),
+        #[cfg(feature = "fhe")]
+        Box::new(EncryptedPipeline::new(
+            EncryptedMatcher::<FullBits>::builder().build(),
+        )),
+    ]
+}
+
+/// Check that every pipeline enrolls, verifies, and identifies codes the same way.
+#[test]
+fn test_pipelines_agree() {
+    let eye_a = random_iris_code::<STORE_ELEM_LEN>();
+    let eye_b = similar_iris_code(&eye_a);
+    let eye_c = random_iris_code();
+    let mask = visible_iris_mask();
+
+    for mut pipeline in pipelines() {
+        let name = pipeline.name();
+
+        let similar = pipeline.enroll(&eye_b, &mask).expect("enrolling must work");
+        let different = pipeline.enroll(&eye_c, &mask).expect("enrolling must work");
+        assert_eq!((similar, different), (0, 1), "{name}");
+
+        assert!(
+            pipeline
+                .verify(similar, &eye_a, &mask)
+                .expect("matching must work"),
+            "{name}"
+        );
+        assert!(
+            !pipeline
+                .verify(different, &eye_a, &mask)
+                .expect("matching must work"),
+            "{name}"
+        );
+        assert_eq!(
+            pipeline
+                .identify(&eye_a, &mask)
+                .expect("matching must work"),
+            vec![similar],
+            "{name}"
+        );
+
+        assert!(
+            matches!(
+                pipeline.verify(2, &eye_a, &mask),
+                Err(Error::UnknownTemplate(2))
+            ),
+            "{name}"
+        );
+    }
+}
@@ -1,10 +1,24 @@
 //! Iris matching operations on raw bit vectors.
 
-use crate::iris::conf::IrisConf;
+use std::ops::RangeInclusive;
+
+use bitvec::{field::BitField, slice::BitSlice};
+use itertools::izip;
+
+use crate::iris::conf::{has_trailing_bits, DynIrisConf, IrisConf, MatchThreshold, MinVisibleBits};
 
 pub use crate::iris::conf::{IrisCode, IrisMask};
 
-#[cfg(any(test, feature = "benchmark"))]
+pub mod fusion;
+pub mod gallery;
+pub mod prerotated;
+pub mod template_protection;
+
+pub use fusion::is_match_both_eyes;
+pub use prerotated::{match_many_pre_rotated, PreRotatedCode};
+pub use template_protection::TemplateKey;
+
+#[cfg(any(test, feature = "test-util"))]
 pub mod test;
 
 /// Returns the 1D index of a bit from 2D indices.
@@ -12,7 +26,100 @@ pub fn index_1d(column_len: usize, row_i: usize, col_i: usize) -> usize {
     col_i * column_len + row_i
 }
 
+/// The number of independent popcount accumulators in [`hamming_masked()`].
+const HAMMING_LANES: usize = 4;
+
+/// Returns the number of bits that are different in `words_a` and `words_b`, and set in
+/// `mask_words`.
+///
+/// Each word is counted using [`masked_differences()`], which the rotated comparisons in this
+/// module also use.
+///
+/// The words are processed in independent lanes, so the compiler can use vector or hardware
+/// popcount instructions, when they are enabled for the target. For example, using
+/// `RUSTFLAGS="-C target-cpu=native"`. This crate forbids unsafe code, so there are no
+/// hand-written intrinsics.
+///
+/// # Panics
+///
+/// If the slices have different lengths.
+pub fn hamming_masked(words_a: &[u64], words_b: &[u64], mask_words: &[u64]) -> usize {
+    assert_eq!(
+        words_a.len(),
+        words_b.len(),
+        "words must be the same length"
+    );
+    assert_eq!(
+        words_a.len(),
+        mask_words.len(),
+        "mask must be the same length"
+    );
+
+    let mut lane_counts = [0_u64; HAMMING_LANES];
+
+    let chunks_a = words_a.chunks_exact(HAMMING_LANES);
+    let chunks_b = words_b.chunks_exact(HAMMING_LANES);
+    let mask_chunks = mask_words.chunks_exact(HAMMING_LANES);
+
+    let (rem_a, rem_b, mask_rem) = (
+        chunks_a.remainder(),
+        chunks_b.remainder(),
+        mask_chunks.remainder(),
+    );
+
+    for (a, b, mask) in izip!(chunks_a, chunks_b, mask_chunks) {
+        for lane in 0..HAMMING_LANES {
+            lane_counts[lane] += u64::from(masked_differences(a[lane], b[lane], mask[lane]));
+        }
+    }
+
+    for (a, b, mask) in izip!(rem_a, rem_b, mask_rem) {
+        lane_counts[0] += u64::from(masked_differences(*a, *b, *mask));
+    }
+
+    // The count is at most the number of bits in the slices, which fits in usize.
+    #[allow(clippy::cast_possible_truncation)]
+    let count = lane_counts.iter().sum::<u64>() as usize;
+
+    count
+}
+
+/// Returns the number of bits that are different in `word_a` and `word_b`, and set in `mask`.
+#[inline]
+fn masked_differences(word_a: u64, word_b: u64, mask: u64) -> u32 {
+    ((word_a ^ word_b) & mask).count_ones()
+}
+
+/// Returns the number of different visible bits, and the number of visible bits, between
+/// `eye_a` and `eye_b`, after masking with `mask_a` and `mask_b`. The codes are compared at a
+/// single alignment, without any rotation.
+///
+/// A bit is visible if it is set in both masks. The matchers in this module compare these counts
+/// with the threshold at each rotation, so scoring and fusion logic can use this function to get
+/// exactly the same results. Unused bits at the end of the codes and masks are ignored.
+#[must_use = "comparisons do nothing unless you check their result"]
+pub fn hamming<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_a: &IrisCode<STORE_ELEM_LEN>,
+    mask_a: &IrisMask<STORE_ELEM_LEN>,
+    eye_b: &IrisCode<STORE_ELEM_LEN>,
+    mask_b: &IrisMask<STORE_ELEM_LEN>,
+) -> (usize, usize) {
+    rotated_counts::<C, STORE_ELEM_LEN>(eye_a, mask_a, eye_b, mask_b, 0)
+}
+
+/// Returns the rotations from `-limit..=limit`, in increasing order.
+///
+/// Limits larger than [`ROTATION_LIMIT`](IrisConf::ROTATION_LIMIT) are reduced to that limit.
+#[allow(clippy::cast_possible_wrap)]
+pub fn rotations<C: IrisConf>(limit: usize) -> RangeInclusive<isize> {
+    // This constant is tiny compared to isize, so it will never wrap.
+    let limit = limit.min(C::ROTATION_LIMIT) as isize;
+
+    -limit..=limit
+}
+
 /// Rotates the iris code by the given amount along the second dimension.
+/// Unused bits at the end of the code are not rotated.
 #[must_use = "rotations do nothing unless you assign them to a variable"]
 #[allow(clippy::cast_sign_loss)]
 pub fn rotate<C: IrisConf, const STORE_ELEM_LEN: usize>(
@@ -20,13 +127,68 @@ pub fn rotate<C: IrisConf, const STORE_ELEM_LEN: usize>(
     amount: isize,
 ) -> IrisCode<STORE_ELEM_LEN> {
     if amount < 0 {
-        code.rotate_left((-amount) as usize * C::COLUMN_LEN);
+        code[..C::DATA_BIT_LEN].rotate_left((-amount) as usize * C::COLUMN_LEN);
     } else {
-        code.rotate_right(amount as usize * C::COLUMN_LEN);
+        code[..C::DATA_BIT_LEN].rotate_right(amount as usize * C::COLUMN_LEN);
     }
     code
 }
 
+/// Rotates an iris code and mask to a canonical orientation, and returns them with the rotation
+/// that was applied.
+///
+/// The orientation is the phase of the first circular harmonic of the columns, using the
+/// difference between set and unset visible bits in each column. Rotating a code changes this
+/// phase by the same amount, so rotated scans of the same iris are normalized to nearly the same
+/// orientation. Normalizing at enrollment lets matching use a smaller rotation window, with
+/// [`is_iris_match_with_rotation_limit()`].
+///
+/// Codes without a clear orientation, such as fully occluded codes, are not rotated.
+#[must_use = "rotations do nothing unless you assign them to a variable"]
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_precision_loss
+)]
+pub fn normalize_rotation<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    code: &IrisCode<STORE_ELEM_LEN>,
+    mask: &IrisMask<STORE_ELEM_LEN>,
+) -> (IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>, isize) {
+    use std::f64::consts::TAU;
+
+    let visible_set = *code & *mask;
+    let (mut re, mut im) = (0.0, 0.0);
+
+    for col_i in 0..C::COLUMNS {
+        let column = col_i * C::COLUMN_LEN..(col_i + 1) * C::COLUMN_LEN;
+        let visible = mask[column.clone()].count_ones();
+        let set = visible_set[column].count_ones();
+
+        // Set bits count as 1, and unset bits count as -1.
+        let weight = 2.0 * set as f64 - visible as f64;
+        let angle = TAU * col_i as f64 / C::COLUMNS as f64;
+
+        re += weight * angle.cos();
+        im += weight * angle.sin();
+    }
+
+    // Very small phases are rounding errors, rather than an orientation.
+    if re.abs() < 1e-9 && im.abs() < 1e-9 {
+        return (*code, *mask, 0);
+    }
+
+    // Rotating by `amount` columns adds `amount` to the phase, so subtract the current phase.
+    // The number of columns is tiny compared to isize, so it will never wrap.
+    let phase = (im.atan2(re) / TAU * C::COLUMNS as f64).round() as isize;
+    let offset = (-phase).rem_euclid(C::COLUMNS as isize);
+
+    (
+        rotate::<C, STORE_ELEM_LEN>(*code, offset),
+        rotate::<C, STORE_ELEM_LEN>(*mask, offset),
+        offset,
+    )
+}
+
 /// Returns true if `eye_new` and `eye_store` have enough identical bits to meet the threshold,
 /// after masking with `mask_new` and `mask_store`, and rotating from
 /// [`-ROTATION_LIMIT..ROTATION_LIMIT`](IrisConf::ROTATION_LIMIT).
@@ -35,18 +197,499 @@ pub fn rotate<C: IrisConf, const STORE_ELEM_LEN: usize>(
 ///
 /// This function takes references to avoid memory copies, which would otherwise be silent.
 /// ([`IrisCode`] and [`IrisMask`] are [`Copy`] types.)
+///
+/// Rotations are done by offsetting column indexes, so the codes are never copied or rotated.
+/// Stored codes can be normalized at enrollment with [`normalize_rotation()`], so matching can
+/// use a smaller rotation window with [`is_iris_match_with_rotation_limit()`].
 #[must_use = "matching does nothing unless you check its result"]
-#[allow(clippy::cast_possible_wrap)]
 pub fn is_iris_match<C: IrisConf, const STORE_ELEM_LEN: usize>(
     eye_new: &IrisCode<STORE_ELEM_LEN>,
     mask_new: &IrisMask<STORE_ELEM_LEN>,
     eye_store: &IrisCode<STORE_ELEM_LEN>,
     mask_store: &IrisMask<STORE_ELEM_LEN>,
 ) -> bool {
+    is_iris_match_with_rotation_limit::<C, STORE_ELEM_LEN>(
+        eye_new,
+        mask_new,
+        eye_store,
+        mask_store,
+        C::ROTATION_LIMIT,
+    )
+}
+
+/// Returns true if `eye_new` and `eye_store` have enough identical bits to meet the threshold,
+/// after masking with `mask_new` and `mask_store`, and rotating from
+/// `-rotation_limit..rotation_limit`.
+///
+/// Smaller rotation limits are faster, so they can be used as a prefilter before a full match.
+/// Limits larger than [`ROTATION_LIMIT`](IrisConf::ROTATION_LIMIT) are reduced to that limit.
+#[must_use = "matching does nothing unless you check its result"]
+pub fn is_iris_match_with_rotation_limit<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    eye_store: &IrisCode<STORE_ELEM_LEN>,
+    mask_store: &IrisMask<STORE_ELEM_LEN>,
+    rotation_limit: usize,
+) -> bool {
+    let threshold = MatchThreshold::from_conf::<C>();
+
+    // TODO: If smaller rotations are more likely to exit early, start with them first.
+    for rotation in rotations::<C>(rotation_limit) {
+        let (differences, unmasked) =
+            rotated_counts::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store, rotation);
+
+        // And compare with the threshold.
+        if threshold.is_plaintext_match(differences, unmasked) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Returns a list of results, which are true if `eye_new` and `eye_store` meet each threshold in
+/// `thresholds`, after masking and rotating like [`is_iris_match()`].
+///
+/// The Hamming distance is only calculated once per rotation, and then compared with every
+/// threshold. This is faster than matching separately for each operating point, for example, a
+/// strict verification threshold and a loose watchlist threshold.
+#[must_use = "matching does nothing unless you check its result"]
+pub fn is_iris_match_multi<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    eye_store: &IrisCode<STORE_ELEM_LEN>,
+    mask_store: &IrisMask<STORE_ELEM_LEN>,
+    thresholds: &[MatchThreshold],
+) -> Vec<bool> {
+    let mut results = vec![false; thresholds.len()];
+
+    for rotation in rotations::<C>(C::ROTATION_LIMIT) {
+        // Every threshold has already matched.
+        if results.iter().all(|is_match| *is_match) {
+            break;
+        }
+
+        let (differences, unmasked) =
+            rotated_counts::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store, rotation);
+
+        for (is_match, threshold) in results.iter_mut().zip(thresholds) {
+            *is_match |= threshold.is_plaintext_match(differences, unmasked);
+        }
+    }
+
+    results
+}
+
+/// The result of a match that can be indeterminate, from [`iris_match_with_min_visible()`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MatchResult {
+    /// The codes have enough identical bits to meet the threshold.
+    Match,
+
+    /// The codes don't meet the threshold at any rotation with enough visible bits.
+    NoMatch,
+
+    /// None of the rotations have enough visible bits to make a decision.
+    Indeterminate,
+}
+
+/// Returns the result of matching `eye_new` and `eye_store`, like [`is_iris_match()`], but only
+/// using rotations with at least `min_visible` bits visible in both masks.
+///
+/// This avoids mostly occluded codes matching each other, because they have few different bits.
+#[must_use = "matching does nothing unless you check its result"]
+pub fn iris_match_with_min_visible<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    eye_store: &IrisCode<STORE_ELEM_LEN>,
+    mask_store: &IrisMask<STORE_ELEM_LEN>,
+    min_visible: MinVisibleBits,
+) -> MatchResult {
+    let min_bits = min_visible.min_bits::<C>();
+    let threshold = MatchThreshold::from_conf::<C>();
+
+    let mut result = MatchResult::Indeterminate;
+
+    for rotation in rotations::<C>(C::ROTATION_LIMIT) {
+        let (differences, unmasked) =
+            rotated_counts::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store, rotation);
+
+        if unmasked < min_bits {
+            continue;
+        }
+
+        if threshold.is_plaintext_match(differences, unmasked) {
+            return MatchResult::Match;
+        }
+
+        result = MatchResult::NoMatch;
+    }
+
+    result
+}
+
+/// The closest rotation between two iris codes, from [`iris_distance()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IrisDistance {
+    /// The smallest fraction of visible bits that are different, over all rotations.
+    /// Rotations with no visible bits have a distance of zero, like in [`is_iris_match()`].
+    pub min_fraction: f64,
+    /// The number of columns the stored code was rotated by to get the smallest distance.
+    /// If several rotations have the same distance, the smallest rotation is used.
+    pub best_rotation: isize,
+    /// The number of visible bits at the best rotation.
+    pub visible_bits: usize,
+}
+
+/// Returns the smallest fractional Hamming distance between `eye_new` and `eye_store`, after
+/// masking with `mask_new` and `mask_store`, and rotating from
+/// [`-ROTATION_LIMIT..ROTATION_LIMIT`](IrisConf::ROTATION_LIMIT).
+///
+/// The codes match using [`is_iris_match()`] if `min_fraction` is at most the match threshold.
+#[must_use = "matching does nothing unless you check its result"]
+pub fn iris_distance<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    eye_store: &IrisCode<STORE_ELEM_LEN>,
+    mask_store: &IrisMask<STORE_ELEM_LEN>,
+) -> IrisDistance {
+    closest_rotation(rotations::<C>(C::ROTATION_LIMIT), |rotation| {
+        rotated_counts::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store, rotation)
+    })
+}
+
+/// Returns a map of the different visible bits between `eye_new` and `eye_store`, at the best
+/// rotation from [`iris_distance()`].
+///
+/// Bits are set if they are visible in both masks, and different in the two codes. The map has
+/// the same layout as `eye_new`, so it can be used to visualise which parts of the iris drove a
+/// match decision. This is intended for debugging and threshold analysis, so it is not optimised.
+#[must_use = "diff maps do nothing unless you use them"]
+pub fn diff_map<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    eye_store: &IrisCode<STORE_ELEM_LEN>,
+    mask_store: &IrisMask<STORE_ELEM_LEN>,
+) -> IrisCode<STORE_ELEM_LEN> {
+    let best_rotation =
+        iris_distance::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store).best_rotation;
+
+    let eye_store = rotate::<C, STORE_ELEM_LEN>(*eye_store, best_rotation);
+    let mask_store = rotate::<C, STORE_ELEM_LEN>(*mask_store, best_rotation);
+
+    let mut map = (*eye_new ^ eye_store) & *mask_new & mask_store;
+    // Unused bits are never part of the comparison.
+    map[C::DATA_BIT_LEN..].fill(false);
+
+    map
+}
+
+/// Returns true if `eye_new` and `eye_store` match using the runtime configuration `conf`.
+/// See [`is_iris_match()`] for details.
+///
+/// Codes and masks use the same layout as [`IrisConf`] codes, with
+/// [`conf.rows()`](DynIrisConf::rows) bits per column.
+///
+/// # Panics
+///
+/// If any code or mask is shorter than [`conf.data_bit_len()`](DynIrisConf::data_bit_len).
+#[must_use = "matching does nothing unless you check its result"]
+#[allow(clippy::cast_possible_wrap)]
+pub fn is_iris_match_dyn(
+    conf: &DynIrisConf,
+    eye_new: &BitSlice<usize>,
+    mask_new: &BitSlice<usize>,
+    eye_store: &BitSlice<usize>,
+    mask_store: &BitSlice<usize>,
+) -> bool {
+    let bits = dyn_bits(conf, [eye_new, mask_new, eye_store, mask_store]);
+    let threshold = conf.threshold();
+    // Rotation limits are less than the code length, which fits in an isize, so they never wrap.
+    let limit = conf.rotation_limit() as isize;
+
+    (-limit..=limit).any(|rotation| {
+        let (differences, unmasked) =
+            rotated_slice_counts(conf.rows(), conf.columns(), bits, rotation);
+
+        threshold.is_plaintext_match(differences, unmasked)
+    })
+}
+
+/// Returns the smallest fractional Hamming distance between `eye_new` and `eye_store`, using the
+/// runtime configuration `conf`. See [`iris_distance()`] for details.
+///
+/// # Panics
+///
+/// If any code or mask is shorter than [`conf.data_bit_len()`](DynIrisConf::data_bit_len).
+#[must_use = "matching does nothing unless you check its result"]
+#[allow(clippy::cast_possible_wrap)]
+pub fn iris_distance_dyn(
+    conf: &DynIrisConf,
+    eye_new: &BitSlice<usize>,
+    mask_new: &BitSlice<usize>,
+    eye_store: &BitSlice<usize>,
+    mask_store: &BitSlice<usize>,
+) -> IrisDistance {
+    let bits = dyn_bits(conf, [eye_new, mask_new, eye_store, mask_store]);
+
+    // Rotation limits are less than the code length, which fits in an isize, so they never wrap.
+    let limit = conf.rotation_limit() as isize;
+
+    closest_rotation(-limit..=limit, |rotation| {
+        rotated_slice_counts(conf.rows(), conf.columns(), bits, rotation)
+    })
+}
+
+/// Returns `bits` truncated to the length of the codes in `conf`.
+///
+/// # Panics
+///
+/// If any of `bits` is shorter than [`conf.data_bit_len()`](DynIrisConf::data_bit_len).
+fn dyn_bits<'b>(conf: &DynIrisConf, bits: [&'b BitSlice<usize>; 4]) -> [&'b BitSlice<usize>; 4] {
+    bits.map(|bits| {
+        assert!(
+            bits.len() >= conf.data_bit_len(),
+            "codes and masks must contain at least rows * columns bits"
+        );
+        &bits[..conf.data_bit_len()]
+    })
+}
+
+/// Returns the rotation from `rotations` with the smallest fractional distance, using `counts` to
+/// get the number of different and visible bits at each rotation.
+#[allow(clippy::cast_precision_loss)]
+fn closest_rotation(
+    rotations: RangeInclusive<isize>,
+    counts: impl Fn(isize) -> (usize, usize),
+) -> IrisDistance {
+    // (rotation, differences, unmasked)
+    let mut best: Option<(isize, usize, usize)> = None;
+
+    for rotation in rotations {
+        let (differences, unmasked) = counts(rotation);
+
+        // Treat zero visible bits as zero distance, like the threshold check does.
+        let differences = if unmasked == 0 { 0 } else { differences };
+
+        let is_better = match best {
+            None => true,
+            Some((best_rotation, best_differences, best_unmasked)) => {
+                // Compare the fractions exactly: d1 / u1 < d2 / u2
+                let lhs = differences * best_unmasked.max(1);
+                let rhs = best_differences * unmasked.max(1);
+
+                lhs < rhs || (lhs == rhs && rotation.abs() < best_rotation.abs())
+            }
+        };
+
+        if is_better {
+            best = Some((rotation, differences, unmasked));
+        }
+    }
+
+    // There is always at least one rotation.
+    let (best_rotation, differences, visible_bits) = best.unwrap_or((0, 0, 0));
+
+    IrisDistance {
+        min_fraction: if visible_bits == 0 {
+            0.0
+        } else {
+            differences as f64 / visible_bits as f64
+        },
+        best_rotation,
+        visible_bits,
+    }
+}
+
+/// Returns a list of results, which are true if `eye_new` and each code in `gallery` have enough
+/// identical bits to meet the threshold. See [`is_iris_match()`] for details.
+///
+/// With the `parallel` feature, the gallery is matched in parallel using rayon.
+#[must_use = "matching does nothing unless you check its result"]
+pub fn match_many<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    gallery: &[(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>)],
+) -> Vec<bool> {
+    let is_match =
+        |(eye_store, mask_store): &(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>)| {
+            is_iris_match::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store)
+        };
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        crate::parallel::install(|| gallery.par_iter().map(is_match).collect())
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        gallery.iter().map(is_match).collect()
+    }
+}
+
+/// A gallery entry which is close to the query, from [`identify()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Candidate {
+    /// The index of the entry in the gallery.
+    pub index: usize,
+    /// The distance between the query and the entry.
+    pub distance: IrisDistance,
+}
+
+/// Returns the `k` entries in `gallery` that are closest to `eye_new`, sorted from closest to
+/// furthest. Entries with the same distance are sorted by their gallery index.
+///
+/// Distances are calculated using [`iris_distance()`], so they include the best rotation. This is
+/// the reference implementation for 1-to-N identification. If `k` is larger than the gallery,
+/// every entry is returned.
+///
+/// With the `parallel` feature, the distances are calculated in parallel using rayon.
+#[must_use = "identification does nothing unless you check its result"]
+pub fn identify<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    gallery: &[(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>)],
+    k: usize,
+) -> Vec<Candidate> {
+    let candidate = |(index, (eye_store, mask_store)): (
+        usize,
+        &(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>),
+    )| Candidate {
+        index,
+        distance: iris_distance::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store),
+    };
+
+    #[cfg(feature = "parallel")]
+    let mut candidates: Vec<Candidate> = {
+        use rayon::prelude::*;
+
+        crate::parallel::install(|| gallery.par_iter().enumerate().map(candidate).collect())
+    };
+
+    #[cfg(not(feature = "parallel"))]
+    let mut candidates: Vec<Candidate> = gallery.iter().enumerate().map(candidate).collect();
+
+    let closest_first = |a: &Candidate, b: &Candidate| {
+        a.distance
+            .min_fraction
+            .total_cmp(&b.distance.min_fraction)
+            .then(a.index.cmp(&b.index))
+    };
+
+    // Only the closest k candidates need to be sorted.
+    if k < candidates.len() {
+        if k == 0 {
+            return Vec::new();
+        }
+        candidates.select_nth_unstable_by(k - 1, closest_first);
+        candidates.truncate(k);
+    }
+    candidates.sort_unstable_by(closest_first);
+
+    candidates
+}
+
+/// Returns the number of different visible bits, and the number of visible bits, when the stored
+/// code and mask are rotated by `rotation` columns.
+///
+/// Each column is compared with the stored column `rotation` to its left, wrapping around.
+/// This is the same as comparing with `rotate(eye_store, rotation)`.
+fn rotated_counts<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    eye_store: &IrisCode<STORE_ELEM_LEN>,
+    mask_store: &IrisMask<STORE_ELEM_LEN>,
+    rotation: isize,
+) -> (usize, usize) {
+    debug_assert_canonical::<C, STORE_ELEM_LEN>([eye_new, mask_new, eye_store, mask_store]);
+
+    rotated_slice_counts(
+        C::COLUMN_LEN,
+        C::COLUMNS,
+        [eye_new, mask_new, eye_store, mask_store].map(|bits| bits.as_bitslice()),
+        rotation,
+    )
+}
+
+/// Checks that the unused bits at the end of `bits` are clear, in debug builds.
+///
+/// The matchers never read unused bits, but set bits mean the code or mask was built incorrectly.
+fn debug_assert_canonical<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    bits: [&IrisCode<STORE_ELEM_LEN>; 4],
+) {
+    debug_assert!(
+        bits.iter()
+            .all(|bits| !has_trailing_bits::<C, STORE_ELEM_LEN>(bits)),
+        "unused bits in codes and masks must be clear, see mask_trailing_bits()"
+    );
+}
+
+/// Returns the same counts as [`rotated_counts()`], for codes with `column_len` rows and
+/// `columns` columns.
+///
+/// `bits` contains the new code, new mask, stored code, and stored mask, each with at least
+/// `column_len * columns` bits.
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+fn rotated_slice_counts(
+    column_len: usize,
+    columns: usize,
+    [eye_new, mask_new, eye_store, mask_store]: [&BitSlice<usize>; 4],
+    rotation: isize,
+) -> (usize, usize) {
+    const WORD_BITS: usize = u64::BITS as usize;
+
+    let mut differences = 0;
+    let mut unmasked = 0;
+
+    for col_i in 0..columns {
+        // Column indexes are tiny compared to isize, and rem_euclid() is never negative.
+        let store_col_i = (col_i as isize - rotation).rem_euclid(columns as isize) as usize;
+
+        // Compare the column in chunks of up to one word.
+        for row_i in (0..column_len).step_by(WORD_BITS) {
+            let chunk_len = (column_len - row_i).min(WORD_BITS);
+
+            let new_start = index_1d(column_len, row_i, col_i);
+            let store_start = index_1d(column_len, row_i, store_col_i);
+            let new_range = new_start..new_start + chunk_len;
+            let store_range = store_start..store_start + chunk_len;
+
+            let chunk_unmasked = mask_new[new_range.clone()].load_le::<u64>()
+                & mask_store[store_range.clone()].load_le::<u64>();
+            let chunk_differences = masked_differences(
+                eye_new[new_range].load_le::<u64>(),
+                eye_store[store_range].load_le::<u64>(),
+                chunk_unmasked,
+            );
+
+            differences += chunk_differences as usize;
+            unmasked += chunk_unmasked.count_ones() as usize;
+        }
+    }
+
+    (differences, unmasked)
+}
+
+/// Returns the same result as [`is_iris_match()`], by copying and rotating the stored code and
+/// mask for each rotation.
+///
+/// This is slower than [`is_iris_match()`], it is only used to check and benchmark it.
+#[must_use = "matching does nothing unless you check its result"]
+#[allow(clippy::cast_possible_wrap)]
+pub fn is_iris_match_rotated<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    eye_store: &IrisCode<STORE_ELEM_LEN>,
+    mask_store: &IrisMask<STORE_ELEM_LEN>,
+) -> bool {
+    debug_assert_canonical::<C, STORE_ELEM_LEN>([eye_new, mask_new, eye_store, mask_store]);
+
     // Start comparing columns at rotation -IRIS_ROTATION_LIMIT.
-    // TODO:
-    // - Avoid these copies and rotations by comparing bit indexes with an offset and modulus.
-    // - If smaller rotations are more likely to exit early, start with them first.
     let mut eye_store = *eye_store;
     let mut mask_store = *mask_store;
 
@@ -54,44 +697,26 @@ pub fn is_iris_match<C: IrisConf, const STORE_ELEM_LEN: usize>(
     eye_store = rotate::<C, STORE_ELEM_LEN>(eye_store, -(C::ROTATION_LIMIT as isize));
     mask_store = rotate::<C, STORE_ELEM_LEN>(mask_store, -(C::ROTATION_LIMIT as isize));
 
-    for _rotation in 0..C::ROTATION_COMPARISONS {
-        /*dbg!(
-            "rotation: ",
-            -(C::ROTATION_LIMIT as isize) + _rotation as isize
-        );*/
-
-        // TODO:
-        // - Make sure iris codes and masks are the same size.
-        // - Check unused bits are ignored in the tests.
+    let threshold = MatchThreshold::from_conf::<C>();
 
+    for _rotation in 0..C::ROTATION_COMPARISONS {
         // Masking is applied to both iris codes before matching.
-        //
-        // TODO: benchmark these stack allocations:
-        // - on the heap (using BitBox)
-        // - on the heap using scratch memory that is allocated once, then passed to this function
         let unmasked = *mask_new & mask_store;
         let raw_differences = *eye_new ^ eye_store;
         let differences = raw_differences & unmasked;
 
         // A successful match has enough matching unmasked bits to reach the match threshold.
         //
-        // Convert to bit counts.
-        let unmasked = unmasked.count_ones();
-        let differences = differences.count_ones();
-
-        // TODO:
-        // - Make sure the threshold calculation can't overflow.
-        // Currently this is only tested on the data used in debug builds.
+        // Convert to bit counts, ignoring any unused bits.
+        let unmasked = unmasked[..C::DATA_BIT_LEN].count_ones();
+        let differences = differences[..C::DATA_BIT_LEN].count_ones();
 
         // And compare with the threshold.
-        if differences * C::MATCH_DENOMINATOR <= unmasked * C::MATCH_NUMERATOR {
+        if threshold.is_plaintext_match(differences, unmasked) {
             return true;
         }
 
         // Move to the next highest column rotation.
-        // TODO:
-        // - Make this initial rotation part of the stored encoding.
-        // - If smaller rotations are more likely to exit early, start with them first.
         eye_store = rotate::<C, STORE_ELEM_LEN>(eye_store, 1);
         mask_store = rotate::<C, STORE_ELEM_LEN>(mask_store, 1);
     }
@@ -0,0 +1,72 @@
+//! Combining plaintext match results from both eyes.
+//!
+//! The decisions are the same as the encrypted
+//! [`is_match_both_eyes()`](crate::encrypted::EncryptedPolyQuery::is_match_both_eyes), so both
+//! pipelines can be used interchangeably in dual-eye deployments.
+
+use crate::{
+    iris::conf::{FusionPolicy, IrisCode, IrisConf, IrisMask, MatchThreshold},
+    plaintext::{is_iris_match, rotated_counts, rotations},
+};
+
+/// Returns true if the left and right eyes match according to `policy`.
+///
+/// Each eye is a tuple of the new code, new mask, stored code, and stored mask.
+/// See [`is_iris_match()`] for details.
+#[must_use = "matching does nothing unless you check its result"]
+pub fn is_match_both_eyes<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    left: (
+        &IrisCode<STORE_ELEM_LEN>,
+        &IrisMask<STORE_ELEM_LEN>,
+        &IrisCode<STORE_ELEM_LEN>,
+        &IrisMask<STORE_ELEM_LEN>,
+    ),
+    right: (
+        &IrisCode<STORE_ELEM_LEN>,
+        &IrisMask<STORE_ELEM_LEN>,
+        &IrisCode<STORE_ELEM_LEN>,
+        &IrisMask<STORE_ELEM_LEN>,
+    ),
+    policy: FusionPolicy,
+) -> bool {
+    let is_match = |(eye_new, mask_new, eye_store, mask_store)| {
+        is_iris_match::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store)
+    };
+
+    match policy {
+        FusionPolicy::And => is_match(left) && is_match(right),
+        FusionPolicy::Or => is_match(left) || is_match(right),
+        FusionPolicy::MinDistance => {
+            let (left_differences, left_visible) = closest_rotation::<C, STORE_ELEM_LEN>(left);
+            let (right_differences, right_visible) = closest_rotation::<C, STORE_ELEM_LEN>(right);
+
+            MatchThreshold::from_conf::<C>().is_plaintext_match(
+                left_differences + right_differences,
+                left_visible + right_visible,
+            )
+        }
+    }
+}
+
+/// Returns the different and visible bit counts of the rotation with the smallest Hamming
+/// distance.
+///
+/// Rotations without any visible bits are skipped. If no rotations have visible bits, returns
+/// zero counts.
+fn closest_rotation<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    (eye_new, mask_new, eye_store, mask_store): (
+        &IrisCode<STORE_ELEM_LEN>,
+        &IrisMask<STORE_ELEM_LEN>,
+        &IrisCode<STORE_ELEM_LEN>,
+        &IrisMask<STORE_ELEM_LEN>,
+    ),
+) -> (usize, usize) {
+    rotations::<C>(C::ROTATION_LIMIT)
+        .map(|rotation| {
+            rotated_counts::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store, rotation)
+        })
+        .filter(|(_differences, visible)| *visible > 0)
+        // Compare the fractions exactly: d1 / v1 < d2 / v2
+        .min_by(|(d1, v1), (d2, v2)| (d1 * v2).cmp(&(d2 * v1)))
+        .unwrap_or((0, 0))
+}
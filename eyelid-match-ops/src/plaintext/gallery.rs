@@ -0,0 +1,187 @@
+//! Streaming plaintext matching against packed galleries of iris codes and masks.
+//!
+//! A packed gallery starts with the same dimension header as [`iris::io::to_bytes()`], followed
+//! by fixed-length records. Each record is a code then a mask, with their bits packed like
+//! [`iris::io::to_bytes()`], but without a header.
+//!
+//! Large galleries can be memory-mapped, and the mapped bytes passed to [`GalleryView::new()`].
+//! Only the records currently being matched are decoded, so the gallery is never loaded into
+//! memory all at once. This crate forbids unsafe code, so mapping the file is left to the caller,
+//! for example, using the `memmap2` crate.
+//!
+//! [`iris::io::to_bytes()`]: crate::iris::io::to_bytes
+
+use std::{io::Write, marker::PhantomData};
+
+use crate::{
+    iris::{
+        conf::{IrisCode, IrisConf, IrisMask},
+        io::{
+            check_dimensions, pack_bits, packed_len, read_dimensions, unpack_bits,
+            write_dimensions, IoError, HEADER_LEN,
+        },
+    },
+    plaintext::is_iris_match,
+};
+
+/// A borrowed view of a packed gallery, in the `C` configuration.
+#[derive(Debug)]
+pub struct GalleryView<'a, C: IrisConf, const STORE_ELEM_LEN: usize> {
+    /// The packed records, without the header.
+    records: &'a [u8],
+
+    /// The iris configuration of the records.
+    _conf: PhantomData<fn() -> C>,
+}
+
+// Derived impls would require `C: Clone`, but `C` is only a marker type.
+impl<C: IrisConf, const STORE_ELEM_LEN: usize> Clone for GalleryView<'_, C, STORE_ELEM_LEN> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C: IrisConf, const STORE_ELEM_LEN: usize> Copy for GalleryView<'_, C, STORE_ELEM_LEN> {}
+
+impl<'a, C: IrisConf, const STORE_ELEM_LEN: usize> GalleryView<'a, C, STORE_ELEM_LEN> {
+    /// The length of each record: a packed code, then a packed mask.
+    pub const RECORD_LEN: usize = 2 * packed_len::<C>();
+
+    /// Returns a view of the packed gallery in `bytes`.
+    ///
+    /// Returns [`IoError::DimensionMismatch`] if the gallery has different dimensions to `C`,
+    /// or [`IoError::InvalidLength`] if it doesn't contain a whole number of records.
+    /// Records are only checked when they are decoded.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, IoError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(IoError::InvalidLength);
+        }
+        let (header, records) = bytes.split_at(HEADER_LEN);
+        let (rows, columns) = read_dimensions(header)?;
+        check_dimensions::<C>(rows, columns)?;
+
+        if records.len() % Self::RECORD_LEN != 0 {
+            return Err(IoError::InvalidLength);
+        }
+
+        Ok(Self {
+            records,
+            _conf: PhantomData,
+        })
+    }
+
+    /// Returns the number of records in the gallery.
+    pub fn len(&self) -> usize {
+        self.records.len() / Self::RECORD_LEN
+    }
+
+    /// Returns true if the gallery has no records.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the packed bytes of the record at `index`, or `None` if it is out of bounds.
+    pub fn record_bytes(&self, index: usize) -> Option<&'a [u8]> {
+        let start = index.checked_mul(Self::RECORD_LEN)?;
+        self.records
+            .get(start..start.checked_add(Self::RECORD_LEN)?)
+    }
+
+    /// Returns the packed bytes of each record, without decoding them.
+    pub fn records(&self) -> impl ExactSizeIterator<Item = &'a [u8]> {
+        self.records.chunks_exact(Self::RECORD_LEN)
+    }
+
+    /// Decodes the code and mask at `index`, or returns `None` if it is out of bounds.
+    pub fn get(
+        &self,
+        index: usize,
+    ) -> Option<Result<(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>), IoError>> {
+        self.record_bytes(index)
+            .map(decode_record::<C, STORE_ELEM_LEN>)
+    }
+
+    /// Decodes each code and mask in order.
+    pub fn iter(
+        &self,
+    ) -> impl ExactSizeIterator<
+        Item = Result<(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>), IoError>,
+    > + 'a
+    where
+        C: 'a,
+    {
+        self.records().map(decode_record::<C, STORE_ELEM_LEN>)
+    }
+
+    /// Returns a list of results, which are true if `eye_new` and each code in the gallery have
+    /// enough identical bits to meet the threshold. See
+    /// [`match_many()`](crate::plaintext::match_many) for details.
+    ///
+    /// Returns an error if any record can't be decoded.
+    ///
+    /// With the `parallel` feature, the gallery is matched in parallel using rayon.
+    pub fn match_many(
+        &self,
+        eye_new: &IrisCode<STORE_ELEM_LEN>,
+        mask_new: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Result<Vec<bool>, IoError> {
+        let is_match = |record: &[u8]| {
+            let (eye_store, mask_store) = decode_record::<C, STORE_ELEM_LEN>(record)?;
+
+            Ok(is_iris_match::<C, STORE_ELEM_LEN>(
+                eye_new,
+                mask_new,
+                &eye_store,
+                &mask_store,
+            ))
+        };
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+
+            crate::parallel::install(|| {
+                self.records
+                    .par_chunks_exact(Self::RECORD_LEN)
+                    .map(is_match)
+                    .collect()
+            })
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            self.records().map(is_match).collect()
+        }
+    }
+}
+
+/// Writes `gallery` to `writer` as a packed gallery, in the `C` configuration.
+pub fn write_gallery<'g, C: IrisConf, const STORE_ELEM_LEN: usize>(
+    mut writer: impl Write,
+    gallery: impl IntoIterator<Item = &'g (IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>)>,
+) -> std::io::Result<()> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    write_dimensions::<C>(&mut bytes);
+    writer.write_all(&bytes)?;
+
+    for (code, mask) in gallery {
+        bytes.clear();
+        pack_bits::<C, STORE_ELEM_LEN>(code, &mut bytes);
+        pack_bits::<C, STORE_ELEM_LEN>(mask, &mut bytes);
+        writer.write_all(&bytes)?;
+    }
+
+    Ok(())
+}
+
+/// Decodes a single packed record into a code and mask.
+fn decode_record<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    record: &[u8],
+) -> Result<(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>), IoError> {
+    let (code, mask) = record.split_at(packed_len::<C>());
+
+    Ok((
+        unpack_bits::<C, STORE_ELEM_LEN>(code)?,
+        unpack_bits::<C, STORE_ELEM_LEN>(mask)?,
+    ))
+}
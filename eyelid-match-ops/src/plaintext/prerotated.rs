@@ -0,0 +1,122 @@
+//! Stored iris codes with every rotation precomputed at enrollment.
+//!
+//! Pre-rotated codes use [`IrisConf::ROTATION_COMPARISONS`] times as much memory, but matching
+//! compares whole storage words, without any per-match rotation work. This can be chosen for
+//! each gallery: use [`match_many_pre_rotated()`] for hot galleries, and
+//! [`match_many()`](crate::plaintext::match_many) for large galleries.
+
+use std::marker::PhantomData;
+
+use itertools::izip;
+
+use crate::{
+    iris::conf::{IrisCode, IrisConf, IrisMask, MatchThreshold},
+    plaintext::{debug_assert_canonical, rotate, rotations},
+};
+
+/// A stored iris code and mask, with all of their rotations.
+#[derive(Debug)]
+pub struct PreRotatedCode<C: IrisConf, const STORE_ELEM_LEN: usize> {
+    /// The rotated codes and masks, from
+    /// [`-ROTATION_LIMIT..=ROTATION_LIMIT`](IrisConf::ROTATION_LIMIT).
+    rotations: Vec<(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>)>,
+
+    /// The iris configuration of the code.
+    _conf: PhantomData<fn() -> C>,
+}
+
+// Derived impls would require `C: Clone`, but `C` is only a marker type.
+impl<C: IrisConf, const STORE_ELEM_LEN: usize> Clone for PreRotatedCode<C, STORE_ELEM_LEN> {
+    fn clone(&self) -> Self {
+        Self {
+            rotations: self.rotations.clone(),
+            _conf: PhantomData,
+        }
+    }
+}
+
+impl<C: IrisConf, const STORE_ELEM_LEN: usize> PreRotatedCode<C, STORE_ELEM_LEN> {
+    /// Precomputes every rotation of `eye_store` and `mask_store`.
+    pub fn new(
+        eye_store: &IrisCode<STORE_ELEM_LEN>,
+        mask_store: &IrisMask<STORE_ELEM_LEN>,
+    ) -> Self {
+        let rotations = rotations::<C>(C::ROTATION_LIMIT)
+            .map(|rotation| {
+                (
+                    rotate::<C, STORE_ELEM_LEN>(*eye_store, rotation),
+                    rotate::<C, STORE_ELEM_LEN>(*mask_store, rotation),
+                )
+            })
+            .collect();
+
+        Self {
+            rotations,
+            _conf: PhantomData,
+        }
+    }
+
+    /// Returns the stored code and mask, without any rotation.
+    pub fn unrotated(&self) -> &(IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>) {
+        &self.rotations[C::ROTATION_LIMIT]
+    }
+
+    /// Returns true if `eye_new` and the stored code have enough identical bits to meet the
+    /// threshold. Gives the same results as [`is_iris_match()`](crate::plaintext::is_iris_match).
+    #[must_use = "matching does nothing unless you check its result"]
+    pub fn is_match(
+        &self,
+        eye_new: &IrisCode<STORE_ELEM_LEN>,
+        mask_new: &IrisMask<STORE_ELEM_LEN>,
+    ) -> bool {
+        let (eye_store, mask_store) = self.unrotated();
+        debug_assert_canonical::<C, STORE_ELEM_LEN>([eye_new, mask_new, eye_store, mask_store]);
+
+        let threshold = MatchThreshold::from_conf::<C>();
+
+        self.rotations.iter().any(|(eye_store, mask_store)| {
+            let mut differences = 0;
+            let mut unmasked = 0;
+
+            // Unused bits are clear, so whole words can be compared.
+            for (eye_new, mask_new, eye_store, mask_store) in izip!(
+                eye_new.as_raw_slice(),
+                mask_new.as_raw_slice(),
+                eye_store.as_raw_slice(),
+                mask_store.as_raw_slice(),
+            ) {
+                let word_unmasked = mask_new & mask_store;
+
+                differences += ((eye_new ^ eye_store) & word_unmasked).count_ones() as usize;
+                unmasked += word_unmasked.count_ones() as usize;
+            }
+
+            threshold.is_plaintext_match(differences, unmasked)
+        })
+    }
+}
+
+/// Returns a list of results, which are true if `eye_new` and each code in `gallery` have enough
+/// identical bits to meet the threshold. See [`PreRotatedCode::is_match()`] for details.
+///
+/// With the `parallel` feature, the gallery is matched in parallel using rayon.
+#[must_use = "matching does nothing unless you check its result"]
+pub fn match_many_pre_rotated<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eye_new: &IrisCode<STORE_ELEM_LEN>,
+    mask_new: &IrisMask<STORE_ELEM_LEN>,
+    gallery: &[PreRotatedCode<C, STORE_ELEM_LEN>],
+) -> Vec<bool> {
+    let is_match = |code: &PreRotatedCode<C, STORE_ELEM_LEN>| code.is_match(eye_new, mask_new);
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        crate::parallel::install(|| gallery.par_iter().map(is_match).collect())
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        gallery.iter().map(is_match).collect()
+    }
+}
@@ -0,0 +1,107 @@
+//! Salted transforms of plaintext iris codes and masks.
+//!
+//! Codes and masks are transformed before encoding, and the transformed templates are stored
+//! instead of the originals. New codes are transformed with the same key before matching.
+//!
+//! The transform permutes the rows of every column in the same way, then flips the same rows
+//! in every column. Rotations move whole columns, so the transform preserves every rotated
+//! comparison, and match decisions are unchanged. Masks are permuted, but not flipped.
+//!
+//! Salts make the transform repeatable, but they don't make templates unlinkable, and changing
+//! the salt doesn't revoke a leaked template. Each row of a transformed code is a row of the
+//! original code, or its complement. So templates of the same eye with different salts can be
+//! linked by matching their rows, and a leaked template can still be matched after the salt is
+//! changed.
+//!
+//! This is not encryption: the key space is small, and the column structure of the codes is
+//! visible. Use [`encrypted`](crate::encrypted) matching when stored templates must be kept
+//! secret.
+
+use std::marker::PhantomData;
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    iris::conf::{IrisCode, IrisConf, IrisMask},
+    plaintext::{debug_assert_canonical, index_1d},
+};
+
+/// The length of a template protection salt in bytes.
+pub const SALT_LEN: usize = 32;
+
+/// A key for transforming iris codes and masks in the `C` configuration.
+///
+/// The key is derived from a salt, so the same salt always gives the same transform.
+#[derive(Debug)]
+pub struct TemplateKey<C: IrisConf> {
+    /// The original row of each transformed row.
+    row_permutation: Vec<usize>,
+
+    /// True if the code bits in each transformed row are flipped.
+    row_flips: Vec<bool>,
+
+    /// The iris configuration of the key.
+    _conf: PhantomData<fn() -> C>,
+}
+
+// Derived impls would require `C: Clone`, but `C` is only a marker type.
+impl<C: IrisConf> Clone for TemplateKey<C> {
+    fn clone(&self) -> Self {
+        Self {
+            row_permutation: self.row_permutation.clone(),
+            row_flips: self.row_flips.clone(),
+            _conf: PhantomData,
+        }
+    }
+}
+
+impl<C: IrisConf> TemplateKey<C> {
+    /// Derives a template key from `salt`.
+    ///
+    /// The transform only depends on the salt, so it is repeatable across processes and
+    /// platforms.
+    pub fn new(salt: [u8; SALT_LEN]) -> Self {
+        let mut rng = ChaCha20Rng::from_seed(salt);
+
+        let mut row_permutation: Vec<usize> = (0..C::COLUMN_LEN).collect();
+        row_permutation.shuffle(&mut rng);
+
+        let row_flips = (0..C::COLUMN_LEN).map(|_| rng.gen()).collect();
+
+        Self {
+            row_permutation,
+            row_flips,
+            _conf: PhantomData,
+        }
+    }
+
+    /// Returns the transformed `code` and `mask`.
+    ///
+    /// Unused bits stay clear, so transformed templates can be used with any matcher.
+    #[must_use = "transforms do nothing unless you assign them to a variable"]
+    pub fn protect<const STORE_ELEM_LEN: usize>(
+        &self,
+        code: &IrisCode<STORE_ELEM_LEN>,
+        mask: &IrisMask<STORE_ELEM_LEN>,
+    ) -> (IrisCode<STORE_ELEM_LEN>, IrisMask<STORE_ELEM_LEN>) {
+        debug_assert_canonical::<C, STORE_ELEM_LEN>([code, mask, code, mask]);
+
+        let mut protected_code = IrisCode::ZERO;
+        let mut protected_mask = IrisMask::ZERO;
+
+        for col_i in 0..C::COLUMNS {
+            for (row_i, (&from_row_i, &flip)) in
+                self.row_permutation.iter().zip(&self.row_flips).enumerate()
+            {
+                let from_i = index_1d(C::COLUMN_LEN, from_row_i, col_i);
+                let to_i = index_1d(C::COLUMN_LEN, row_i, col_i);
+
+                protected_code.set(to_i, code[from_i] ^ flip);
+                protected_mask.set(to_i, mask[from_i]);
+            }
+        }
+
+        (protected_code, protected_mask)
+    }
+}
@@ -9,7 +9,15 @@ use crate::{
 
 pub mod gen;
 
+#[cfg(test)]
+mod fusion;
+#[cfg(test)]
+mod gallery;
+#[cfg(test)]
+mod template_protection;
+
 pub mod matching;
+pub mod strategy;
 
 /// Assert that iris comparison results are the same regardless of the order of the iris codes.
 pub fn assert_iris_compare<C: IrisConf, const STORE_ELEM_LEN: usize>(
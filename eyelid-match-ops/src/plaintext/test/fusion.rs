@@ -0,0 +1,49 @@
+//! Tests for combining plaintext match results from both eyes.
+
+use crate::iris::conf::{FusionPolicy, IrisConf};
+use crate::plaintext::{
+    is_match_both_eyes,
+    test::gen::{occluded_iris_mask, random_iris_code, similar_iris_code, visible_iris_mask},
+};
+use crate::MiddleBits;
+
+/// Check each fusion policy with one matching eye and one non-matching eye.
+#[test]
+fn test_both_eyes_fusion() {
+    let mask = visible_iris_mask();
+
+    let left_eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let right_eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+
+    // The left eye is identical, and the right eye is a different iris.
+    let identical_left = left_eye;
+    // The left eye is a third different, which doesn't make up for the right eye.
+    let similar_left = similar_iris_code(&left_eye);
+    let different_right = random_iris_code();
+
+    let is_match = |left_code, policy| {
+        is_match_both_eyes::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            (&left_eye, &mask, left_code, &mask),
+            (&right_eye, &mask, &different_right, &mask),
+            policy,
+        )
+    };
+
+    assert!(!is_match(&identical_left, FusionPolicy::And));
+    assert!(is_match(&identical_left, FusionPolicy::Or));
+    assert!(is_match(&identical_left, FusionPolicy::MinDistance));
+    assert!(!is_match(&similar_left, FusionPolicy::MinDistance));
+
+    // An occluded eye doesn't count towards the combined distance.
+    let occluded = occluded_iris_mask();
+    let is_match = |right_code, policy| {
+        is_match_both_eyes::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            (&left_eye, &mask, &similar_left, &mask),
+            (&right_eye, &occluded, right_code, &mask),
+            policy,
+        )
+    };
+
+    assert!(is_match(&different_right, FusionPolicy::And));
+    assert!(is_match(&different_right, FusionPolicy::MinDistance));
+}
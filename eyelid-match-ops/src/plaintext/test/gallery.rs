@@ -0,0 +1,71 @@
+//! Tests for packed galleries.
+
+use crate::{
+    iris::io::{IoError, HEADER_LEN},
+    plaintext::{
+        gallery::{write_gallery, GalleryView},
+        match_many,
+        test::gen::{
+            occluded_iris_mask, random_iris_code, random_iris_mask, similar_iris_code,
+            visible_iris_mask,
+        },
+    },
+    FullBits, IrisConf, MiddleBits,
+};
+
+/// Check that packed galleries round-trip, and match the same way as in-memory galleries.
+#[test]
+fn packed_gallery_matching() {
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    let gallery = [
+        (random_iris_code(), random_iris_mask()),
+        (similar_iris_code(&eye), mask),
+        (eye, occluded_iris_mask()),
+        (random_iris_code(), mask),
+    ];
+
+    let mut bytes = Vec::new();
+    write_gallery::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&mut bytes, &gallery)
+        .expect("writing to a Vec never fails");
+
+    type View<'a> = GalleryView<'a, MiddleBits, { MiddleBits::STORE_ELEM_LEN }>;
+    assert_eq!(bytes.len(), HEADER_LEN + gallery.len() * View::RECORD_LEN);
+
+    let view = View::new(&bytes).expect("gallery is valid");
+    assert_eq!(view.len(), gallery.len());
+    assert_eq!(
+        view.iter().collect::<Result<Vec<_>, _>>(),
+        Ok(gallery.to_vec())
+    );
+    assert_eq!(view.get(1), Some(Ok(gallery[1])));
+    assert_eq!(view.get(gallery.len()), None);
+    // The start of this record fits in a usize, but its end doesn't.
+    assert_eq!(view.get(usize::MAX / View::RECORD_LEN), None);
+
+    assert_eq!(
+        view.match_many(&eye, &mask),
+        Ok(match_many::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            &eye, &mask, &gallery
+        ))
+    );
+
+    // Empty galleries are valid.
+    let view = View::new(&bytes[..HEADER_LEN]).expect("gallery is valid");
+    assert!(view.is_empty());
+    assert_eq!(view.match_many(&eye, &mask), Ok(Vec::new()));
+
+    // Partial records and other configurations are rejected.
+    assert_eq!(
+        View::new(&bytes[..bytes.len() - 1]).map(|view| view.len()),
+        Err(IoError::InvalidLength)
+    );
+    assert_eq!(
+        GalleryView::<FullBits, { FullBits::STORE_ELEM_LEN }>::new(&bytes).map(|view| view.len()),
+        Err(IoError::DimensionMismatch {
+            rows: MiddleBits::COLUMN_LEN,
+            columns: MiddleBits::COLUMNS,
+        })
+    );
+}
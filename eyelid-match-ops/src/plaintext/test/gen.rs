@@ -4,7 +4,7 @@ use rand::Rng;
 
 use crate::{
     iris::conf::{IrisCode, IrisConf, IrisMask},
-    plaintext::rotate,
+    plaintext::{index_1d, rotate},
 };
 
 /// Returns a list of common codes. Random codes are only listed once.
@@ -95,3 +95,126 @@ pub fn visible_iris_mask<const STORE_ELEM_LEN: usize>() -> IrisMask<STORE_ELEM_L
 pub fn occluded_iris_mask<const STORE_ELEM_LEN: usize>() -> IrisMask<STORE_ELEM_LEN> {
     IrisMask::ZERO
 }
+
+/// The smallest side length of a patch of flipped bits in [`correlated_iris_code()`].
+const MIN_PATCH_LEN: usize = 2;
+
+/// The largest side length of a patch of flipped bits in [`correlated_iris_code()`].
+const MAX_PATCH_LEN: usize = 4;
+
+/// Returns an iris code that differs from `base` in exactly `distance` of its bits, rounded to
+/// the nearest bit.
+///
+/// Real iris codes from the same eye differ in small connected patches, not uniformly random
+/// bits. So the bits are flipped in random rectangles of rows and columns, which wrap around
+/// between the first and last columns.
+///
+/// # Panics
+///
+/// If `distance` is not between 0 and 1.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn correlated_iris_code<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    base: &IrisCode<STORE_ELEM_LEN>,
+    distance: f64,
+) -> IrisCode<STORE_ELEM_LEN> {
+    assert!(
+        (0.0..=1.0).contains(&distance),
+        "distance must be between 0 and 1"
+    );
+
+    let mut rng = rand::thread_rng();
+    let target = (distance * C::DATA_BIT_LEN as f64).round() as usize;
+
+    let mut code = *base;
+    let mut flipped: IrisCode<STORE_ELEM_LEN> = IrisCode::ZERO;
+    let mut flip_count = 0;
+
+    while flip_count < target {
+        let rows = rng
+            .gen_range(MIN_PATCH_LEN..=MAX_PATCH_LEN)
+            .min(C::COLUMN_LEN);
+        let columns = rng.gen_range(MIN_PATCH_LEN..=MAX_PATCH_LEN).min(C::COLUMNS);
+        let start_row = rng.gen_range(0..=C::COLUMN_LEN - rows);
+        let start_col = rng.gen_range(0..C::COLUMNS);
+
+        for col_i in (start_col..start_col + columns).map(|col_i| col_i % C::COLUMNS) {
+            for row_i in start_row..start_row + rows {
+                let i = index_1d(C::COLUMN_LEN, row_i, col_i);
+
+                // Only flip each bit once, so the distance is exact.
+                if flip_count < target && !flipped[i] {
+                    flipped.set(i, true);
+                    let bit = code[i];
+                    code.set(i, !bit);
+                    flip_count += 1;
+                }
+            }
+        }
+    }
+
+    code
+}
+
+/// Returns an iris mask with eyelid-shaped occlusions, and a few small random occlusions.
+///
+/// Columns are angles around the iris, starting on the right, and rows are radii, from the pupil
+/// outwards. The upper eyelid covers up to `eyelid_depth` of the outer rows at the top of the
+/// iris, and the lower eyelid covers up to half as much at the bottom. The small occlusions are
+/// similar to eyelashes or reflections.
+///
+/// # Panics
+///
+/// If `eyelid_depth` is not between 0 and 1.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn eyelid_iris_mask<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    eyelid_depth: f64,
+) -> IrisMask<STORE_ELEM_LEN> {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    assert!(
+        (0.0..=1.0).contains(&eyelid_depth),
+        "eyelid depth must be between 0 and 1"
+    );
+
+    let mut rng = rand::thread_rng();
+    let mut mask = IrisMask::ZERO;
+    mask[..C::DATA_BIT_LEN].fill(true);
+
+    // Eyelids aren't perfectly centred.
+    let upper_centre = FRAC_PI_2 + rng.gen_range(-0.2..0.2);
+    let lower_centre = 3.0 * FRAC_PI_2 + rng.gen_range(-0.2..0.2);
+
+    for col_i in 0..C::COLUMNS {
+        let angle = 2.0 * PI * col_i as f64 / C::COLUMNS as f64;
+
+        // The eyelids are deepest at their centres, and curve away to nothing at the sides.
+        let upper = (angle - upper_centre).cos().max(0.0).powi(2) * eyelid_depth;
+        let lower = (angle - lower_centre).cos().max(0.0).powi(2) * eyelid_depth / 2.0;
+        let depth = (upper.max(lower) * C::COLUMN_LEN as f64).round() as usize;
+
+        for row_i in C::COLUMN_LEN - depth.min(C::COLUMN_LEN)..C::COLUMN_LEN {
+            mask.set(index_1d(C::COLUMN_LEN, row_i, col_i), false);
+        }
+    }
+
+    // Add a few small occlusions, like eyelashes or reflections.
+    for _ in 0..rng.gen_range(0..=MAX_PATCH_LEN) {
+        let rows = MIN_PATCH_LEN.min(C::COLUMN_LEN);
+        let col_i = rng.gen_range(0..C::COLUMNS);
+        let start_row = rng.gen_range(0..=C::COLUMN_LEN - rows);
+
+        for row_i in start_row..start_row + rows {
+            mask.set(index_1d(C::COLUMN_LEN, row_i, col_i), false);
+        }
+    }
+
+    mask
+}
@@ -1,7 +1,7 @@
 //! Full match tests for plaintext iris codes and masks.
 
 use crate::{
-    iris::conf::{IrisCode, IrisConf, IrisMask},
+    iris::conf::{mask_trailing_bits, IrisCode, IrisConf, IrisMask},
     plaintext::test::gen::{
         codes, masks, occluded_iris_mask, random_iris_code, rotate_not_too_much, set_iris_code,
         similar_iris_code, unset_iris_code, visible_iris_mask,
@@ -9,7 +9,16 @@ use crate::{
 };
 
 #[cfg(test)]
-use crate::{plaintext::test::assert_iris_compare, MiddleBits, TestBits};
+use crate::{
+    plaintext::{
+        index_1d, is_iris_match,
+        test::{
+            assert_iris_compare,
+            strategy::{iris_mask, rotated_pair},
+        },
+    },
+    MiddleBits, TestBits,
+};
 
 /// Returns a list of mask combinations which are always occluded.
 pub fn occluded<const STORE_ELEM_LEN: usize>(
@@ -83,7 +92,7 @@ pub fn matching<C: IrisConf, const STORE_ELEM_LEN: usize>() -> Vec<(
     ];
 
     // These cases technically match, but only because the numbers of matching and visible
-    // bits are both zero
+    // bits are both zero. `iris_match_with_min_visible()` makes them indeterminate instead.
     for (mask_description, mask_a, mask_b) in occluded().iter() {
         for (eye_a_description, eye_a) in codes().iter() {
             for (eye_b_description, eye_b) in codes().iter() {
@@ -98,7 +107,7 @@ pub fn matching<C: IrisConf, const STORE_ELEM_LEN: usize>() -> Vec<(
         }
     }
 
-    matching
+    canonical::<C, STORE_ELEM_LEN>(matching)
 }
 
 /// Returns a list of test cases which never match.
@@ -153,7 +162,32 @@ pub fn different<C: IrisConf, const STORE_ELEM_LEN: usize>() -> Vec<(
         ));
     }
 
-    res
+    canonical::<C, STORE_ELEM_LEN>(res)
+}
+
+/// Clears the unused bits in each code and mask in `cases`, so they are valid inputs for `C`.
+fn canonical<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    mut cases: Vec<(
+        String,
+        IrisCode<STORE_ELEM_LEN>,
+        IrisMask<STORE_ELEM_LEN>,
+        IrisCode<STORE_ELEM_LEN>,
+        IrisMask<STORE_ELEM_LEN>,
+    )>,
+) -> Vec<(
+    String,
+    IrisCode<STORE_ELEM_LEN>,
+    IrisMask<STORE_ELEM_LEN>,
+    IrisCode<STORE_ELEM_LEN>,
+    IrisMask<STORE_ELEM_LEN>,
+)> {
+    for (_description, eye_a, mask_a, eye_b, mask_b) in cases.iter_mut() {
+        for bits in [eye_a, mask_a, eye_b, mask_b] {
+            mask_trailing_bits::<C, STORE_ELEM_LEN>(bits);
+        }
+    }
+
+    cases
 }
 
 /// Check matching test cases.
@@ -215,3 +249,806 @@ fn different_codes() {
         );
     }
 }
+
+/// Check that matching with column offsets gives the same results as rotating the stored code.
+#[test]
+fn rotation_free_matching() {
+    rotation_free_matching_helper::<TestBits, { TestBits::STORE_ELEM_LEN }>();
+    rotation_free_matching_helper::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>();
+}
+
+/// Compares [`is_iris_match()`] with [`is_iris_match_rotated()`] for every test case, and for
+/// similar codes at every rotation just inside and outside the rotation limit.
+#[cfg(test)]
+#[allow(clippy::cast_possible_wrap)]
+fn rotation_free_matching_helper<C: IrisConf, const STORE_ELEM_LEN: usize>() {
+    use crate::plaintext::{is_iris_match, is_iris_match_rotated, rotate};
+
+    let mut cases = matching::<C, STORE_ELEM_LEN>();
+    cases.extend(different::<C, STORE_ELEM_LEN>());
+
+    let eye = random_iris_code();
+    let similar = similar_iris_code(&eye);
+    let mask = visible_iris_mask();
+    let limit = C::ROTATION_LIMIT as isize;
+
+    for rotation in -limit - 2..=limit + 2 {
+        cases.push((
+            format!("similar, rotated by {rotation}"),
+            eye,
+            mask,
+            rotate::<C, STORE_ELEM_LEN>(similar, rotation),
+            mask,
+        ));
+    }
+
+    for (description, eye_a, mask_a, eye_b, mask_b) in canonical::<C, STORE_ELEM_LEN>(cases).iter()
+    {
+        assert_eq!(
+            is_iris_match::<C, STORE_ELEM_LEN>(eye_a, mask_a, eye_b, mask_b),
+            is_iris_match_rotated::<C, STORE_ELEM_LEN>(eye_a, mask_a, eye_b, mask_b),
+            "{description}",
+        );
+    }
+}
+
+/// Check that matching a gallery gives the same results as matching each code, in order.
+#[test]
+fn match_many_gallery() {
+    use crate::plaintext::{is_iris_match, match_many};
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    let gallery = [
+        (random_iris_code(), mask),
+        (similar_iris_code(&eye), mask),
+        (eye, occluded_iris_mask()),
+        (
+            rotate_not_too_much::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye),
+            mask,
+        ),
+    ];
+
+    let expected = gallery
+        .iter()
+        .map(|(eye_store, mask_store)| {
+            is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                &eye, &mask, eye_store, mask_store,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(expected, vec![false, true, true, true]);
+    assert_eq!(
+        match_many::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &gallery),
+        expected
+    );
+}
+
+/// Check that identification returns the closest gallery entries in order.
+#[test]
+fn identify_top_k() {
+    use crate::plaintext::{identify, iris_distance, rotate};
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    let gallery = [
+        (random_iris_code(), mask),
+        (similar_iris_code(&eye), mask),
+        (random_iris_code(), mask),
+        (
+            rotate::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(eye, 3),
+            mask,
+        ),
+    ];
+
+    let candidates =
+        identify::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &gallery, 2);
+    assert_eq!(
+        candidates.iter().map(|c| c.index).collect::<Vec<_>>(),
+        vec![3, 1]
+    );
+    assert_eq!(candidates[0].distance.min_fraction, 0.0);
+    assert_eq!(candidates[0].distance.best_rotation, -3);
+    assert_eq!(
+        candidates[1].distance,
+        iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            &eye,
+            &mask,
+            &gallery[1].0,
+            &gallery[1].1
+        )
+    );
+
+    // Large k returns the whole gallery, sorted by distance.
+    let candidates =
+        identify::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &gallery, 10);
+    assert_eq!(candidates.len(), gallery.len());
+    assert!(candidates
+        .windows(2)
+        .all(|pair| pair[0].distance.min_fraction <= pair[1].distance.min_fraction));
+
+    assert!(
+        identify::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &gallery, 0).is_empty()
+    );
+    assert!(identify::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &[], 3).is_empty());
+
+    // Partial selection keeps the closest entries, and breaks ties using the gallery index.
+    let ties = [gallery[0], gallery[3], gallery[0], gallery[3], gallery[3]];
+    let candidates = identify::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &ties, 2);
+    assert_eq!(
+        candidates.iter().map(|c| c.index).collect::<Vec<_>>(),
+        vec![1, 3]
+    );
+}
+
+/// Check that pre-rotated codes match the same way as codes that are rotated during matching.
+#[test]
+fn pre_rotated_matching() {
+    use crate::plaintext::{match_many, match_many_pre_rotated, PreRotatedCode};
+
+    let cases = matching::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>()
+        .into_iter()
+        .chain(different::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>());
+
+    for (description, eye_a, mask_a, eye_b, mask_b) in cases {
+        let pre_rotated =
+            PreRotatedCode::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>::new(&eye_b, &mask_b);
+
+        assert_eq!(pre_rotated.unrotated(), &(eye_b, mask_b));
+        assert_eq!(
+            pre_rotated.is_match(&eye_a, &mask_a),
+            is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                &eye_a, &mask_a, &eye_b, &mask_b
+            ),
+            "{description}",
+        );
+    }
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+    let gallery = [
+        (random_iris_code(), mask),
+        (similar_iris_code(&eye), mask),
+        (
+            rotate_not_too_much::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye),
+            mask,
+        ),
+    ];
+    let pre_rotated_gallery = gallery
+        .iter()
+        .map(|(eye_store, mask_store)| PreRotatedCode::new(eye_store, mask_store))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        match_many_pre_rotated::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            &eye,
+            &mask,
+            &pre_rotated_gallery
+        ),
+        match_many::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &gallery),
+    );
+}
+
+/// Check the distance and best rotation of codes, and that it is consistent with matching.
+#[test]
+#[allow(clippy::cast_precision_loss)]
+fn iris_distance_rotation() {
+    use crate::plaintext::{iris_distance, is_iris_match, rotate};
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    let distance =
+        iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &eye, &mask);
+    assert_eq!(distance.min_fraction, 0.0);
+    assert_eq!(distance.best_rotation, 0);
+    assert_eq!(distance.visible_bits, MiddleBits::DATA_BIT_LEN);
+
+    // Rotating the stored code is undone by the opposite rotation.
+    let rotated = rotate::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(eye, 2);
+    let distance =
+        iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &rotated, &mask);
+    assert_eq!(distance.min_fraction, 0.0);
+    assert_eq!(distance.best_rotation, -2);
+
+    // Similar codes have a third of their bits flipped.
+    let similar = similar_iris_code(&eye);
+    let distance =
+        iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &similar, &mask);
+    assert_eq!(distance.best_rotation, 0);
+    assert!(distance.min_fraction > 0.3 && distance.min_fraction < 0.34);
+
+    for (description, eye_a, mask_a, eye_b, mask_b) in
+        matching::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>()
+            .iter()
+            .chain(different::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>().iter())
+    {
+        let distance = iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            eye_a, mask_a, eye_b, mask_b,
+        );
+        let threshold = MiddleBits::MATCH_NUMERATOR as f64 / MiddleBits::MATCH_DENOMINATOR as f64;
+
+        assert_eq!(
+            distance.min_fraction <= threshold,
+            is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                eye_a, mask_a, eye_b, mask_b
+            ),
+            "{description}: {distance:?}",
+        );
+    }
+}
+
+/// Check that smaller rotation limits only match codes rotated within the limit, and the full
+/// limit gives the same results as the default matcher.
+#[test]
+#[allow(clippy::cast_possible_wrap)]
+fn runtime_rotation_limit() {
+    use crate::plaintext::{is_iris_match, is_iris_match_with_rotation_limit, rotate};
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    for rotation in [0, 1, 3] {
+        let rotated = rotate::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(eye, rotation);
+
+        for limit in 0..5 {
+            assert_eq!(
+                is_iris_match_with_rotation_limit::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                    &eye, &mask, &rotated, &mask, limit,
+                ),
+                rotation <= limit as isize,
+                "rotation {rotation}, limit {limit}",
+            );
+        }
+    }
+
+    for (description, eye_a, mask_a, eye_b, mask_b) in
+        matching::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>()
+            .iter()
+            .chain(different::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>().iter())
+    {
+        let expected = is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            eye_a, mask_a, eye_b, mask_b,
+        );
+
+        // Limits above the maximum are the same as the maximum.
+        for limit in [MiddleBits::ROTATION_LIMIT, MiddleBits::ROTATION_LIMIT + 1] {
+            assert_eq!(
+                is_iris_match_with_rotation_limit::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                    eye_a, mask_a, eye_b, mask_b, limit,
+                ),
+                expected,
+                "{description}, limit {limit}",
+            );
+        }
+    }
+}
+
+/// Check the word-based masked Hamming distance against bitvec's bit counts.
+/// Check that evaluating several thresholds at once gives the same results as matching with each
+/// threshold separately.
+#[test]
+#[allow(clippy::cast_possible_wrap)]
+fn multiple_thresholds() {
+    use crate::{
+        iris::conf::MatchThreshold,
+        plaintext::{hamming, is_iris_match_multi, rotate},
+    };
+
+    let thresholds = [
+        MatchThreshold::new(0, 1),
+        MatchThreshold::new(1, 10),
+        Some(MatchThreshold::from_conf::<MiddleBits>()),
+        MatchThreshold::new(1, 2),
+        MatchThreshold::new(1, 1),
+    ]
+    .map(|threshold| threshold.expect("threshold is valid"));
+
+    for (description, eye_a, mask_a, eye_b, mask_b) in
+        matching::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>()
+            .iter()
+            .chain(different::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>().iter())
+    {
+        let results = is_iris_match_multi::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            eye_a,
+            mask_a,
+            eye_b,
+            mask_b,
+            &thresholds,
+        );
+
+        // The configured threshold gives the same result as the standard matcher.
+        assert_eq!(
+            results[2],
+            is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                eye_a, mask_a, eye_b, mask_b
+            ),
+            "{description}",
+        );
+
+        // Each threshold is checked against every rotation.
+        for (threshold, is_match) in thresholds.iter().zip(&results) {
+            let expected = (-(MiddleBits::ROTATION_LIMIT as isize)
+                ..=MiddleBits::ROTATION_LIMIT as isize)
+                .any(|rotation| {
+                    let rotated =
+                        rotate::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(*eye_b, rotation);
+                    let rotated_mask =
+                        rotate::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(*mask_b, rotation);
+                    let (differences, visible) = hamming::<
+                        MiddleBits,
+                        { MiddleBits::STORE_ELEM_LEN },
+                    >(
+                        eye_a, mask_a, &rotated, &rotated_mask
+                    );
+
+                    differences * threshold.denominator() <= visible * threshold.numerator()
+                });
+
+            assert_eq!(*is_match, expected, "{description}, {threshold:?}");
+        }
+
+        // Looser thresholds always match if stricter thresholds do.
+        assert!(results.windows(2).all(|pair| pair[1] || !pair[0]));
+        // Every code matches the loosest threshold.
+        assert!(results[4], "{description}");
+    }
+}
+
+/// Check that difference maps are consistent with the best rotation distance.
+#[test]
+#[allow(clippy::cast_precision_loss)]
+fn difference_map() {
+    use crate::plaintext::{diff_map, iris_distance, rotate, test::gen::random_iris_mask};
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    // Rotated codes have no differences at their best rotation.
+    let rotated = rotate::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(eye, 2);
+    let map = diff_map::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &rotated, &mask);
+    assert_eq!(map.count_ones(), 0);
+
+    // A single flipped bit shows up in the same place in the map.
+    let mut changed = eye;
+    let flipped = index_1d(MiddleBits::COLUMN_LEN, 5, 7);
+    let bit = changed[flipped];
+    changed.set(flipped, !bit);
+    let map = diff_map::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &changed, &mask);
+    assert_eq!(map.iter_ones().collect::<Vec<_>>(), vec![flipped]);
+
+    // Masked bits are never set in the map.
+    let other = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask_a = random_iris_mask::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask_b = random_iris_mask::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let distance =
+        iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask_a, &other, &mask_b);
+    let map =
+        diff_map::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask_a, &other, &mask_b);
+
+    assert_eq!((map & !mask_a).count_ones(), 0);
+    assert_eq!(
+        map.count_ones() as f64 / distance.visible_bits as f64,
+        distance.min_fraction
+    );
+}
+
+/// A configuration with unused bits at the end of its storage.
+#[cfg(test)]
+struct PaddedBits;
+
+#[cfg(test)]
+impl IrisConf for PaddedBits {
+    const COLUMNS: usize = 5;
+    const COLUMN_LEN: usize = 3;
+    const ROTATION_LIMIT: usize = 1;
+}
+
+/// Check that unused bits are cleared, and never change match results.
+#[test]
+fn trailing_bits() {
+    use crate::{
+        iris::conf::has_trailing_bits,
+        plaintext::{is_iris_match_rotated, rotate},
+    };
+
+    let has_trailing = has_trailing_bits::<PaddedBits, { PaddedBits::STORE_ELEM_LEN }>;
+
+    let mut eye = set_iris_code::<{ PaddedBits::STORE_ELEM_LEN }>();
+    let mut mask = visible_iris_mask::<{ PaddedBits::STORE_ELEM_LEN }>();
+    assert!(has_trailing(&mask));
+
+    mask_trailing_bits::<PaddedBits, { PaddedBits::STORE_ELEM_LEN }>(&mut eye);
+    mask_trailing_bits::<PaddedBits, { PaddedBits::STORE_ELEM_LEN }>(&mut mask);
+    assert!(!has_trailing(&mask));
+    assert_eq!(mask.count_ones(), PaddedBits::DATA_BIT_LEN);
+
+    // Every data bit is different, and inverting sets the unused bits.
+    let mut inverted = !eye;
+    assert!(has_trailing(&inverted));
+    mask_trailing_bits::<PaddedBits, { PaddedBits::STORE_ELEM_LEN }>(&mut inverted);
+
+    // Rotation keeps unused bits clear.
+    let rotated = rotate::<PaddedBits, { PaddedBits::STORE_ELEM_LEN }>(inverted, 1);
+    assert!(!has_trailing(&rotated));
+
+    for (eye_store, expected) in [(eye, true), (inverted, false)] {
+        assert_eq!(
+            is_iris_match::<PaddedBits, { PaddedBits::STORE_ELEM_LEN }>(
+                &eye, &mask, &eye_store, &mask
+            ),
+            expected
+        );
+        assert_eq!(
+            is_iris_match_rotated::<PaddedBits, { PaddedBits::STORE_ELEM_LEN }>(
+                &eye, &mask, &eye_store, &mask
+            ),
+            expected
+        );
+    }
+}
+
+/// Check that codes with unused bits set are rejected by the matcher in debug builds.
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "unused bits")]
+fn trailing_bits_rejected() {
+    let eye = IrisCode::<{ PaddedBits::STORE_ELEM_LEN }>::ZERO;
+    let mask = visible_iris_mask::<{ PaddedBits::STORE_ELEM_LEN }>();
+
+    let _ = is_iris_match::<PaddedBits, { PaddedBits::STORE_ELEM_LEN }>(&eye, &mask, &eye, &mask);
+}
+
+/// Check that rotated codes are normalized to the same orientation.
+#[test]
+#[allow(clippy::cast_possible_wrap)]
+fn rotation_normalization() {
+    use crate::plaintext::{is_iris_match_with_rotation_limit, normalize_rotation, rotate};
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+    let (normal_eye, normal_mask, offset) =
+        normalize_rotation::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask);
+
+    assert_eq!(
+        rotate::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(eye, offset),
+        normal_eye
+    );
+    assert_eq!(normal_mask, mask);
+
+    // Normalizing is idempotent.
+    let (_, _, normal_offset) =
+        normalize_rotation::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&normal_eye, &normal_mask);
+    assert_eq!(normal_offset, 0);
+
+    for rotation in [-20, -3, 1, 7, MiddleBits::COLUMNS as isize / 2] {
+        let rotated = rotate::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(eye, rotation);
+        let (rotated_eye, rotated_mask, rotated_offset) =
+            normalize_rotation::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&rotated, &mask);
+
+        assert_eq!(rotated_eye, normal_eye, "rotation {rotation}");
+        assert_eq!(
+            (rotated_offset + rotation).rem_euclid(MiddleBits::COLUMNS as isize),
+            offset,
+            "rotation {rotation}"
+        );
+
+        // Normalized codes match without searching any rotations, even when the original
+        // rotation is outside the rotation limit.
+        assert!(is_iris_match_with_rotation_limit::<
+            MiddleBits,
+            { MiddleBits::STORE_ELEM_LEN },
+        >(
+            &normal_eye, &normal_mask, &rotated_eye, &rotated_mask, 0
+        ));
+    }
+
+    // Codes without an orientation aren't rotated.
+    let occluded = occluded_iris_mask();
+    assert_eq!(
+        normalize_rotation::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &occluded),
+        (eye, occluded, 0)
+    );
+}
+
+/// Check that runtime configurations match the same way as the built-in configurations.
+#[test]
+fn dynamic_configuration() {
+    use bitvec::prelude::*;
+
+    use crate::{
+        iris::conf::{DynIrisConf, MatchThreshold},
+        plaintext::{iris_distance, iris_distance_dyn, is_iris_match_dyn},
+    };
+
+    let conf = DynIrisConf::from_conf::<MiddleBits>();
+    assert_eq!(conf.data_bit_len(), MiddleBits::DATA_BIT_LEN);
+
+    for (description, eye_a, mask_a, eye_b, mask_b) in
+        matching::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>()
+            .iter()
+            .chain(different::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>().iter())
+    {
+        assert_eq!(
+            is_iris_match_dyn(&conf, eye_a, mask_a, eye_b, mask_b),
+            is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                eye_a, mask_a, eye_b, mask_b
+            ),
+            "{description}",
+        );
+        assert_eq!(
+            iris_distance_dyn(&conf, eye_a, mask_a, eye_b, mask_b),
+            iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                eye_a, mask_a, eye_b, mask_b
+            ),
+            "{description}",
+        );
+    }
+
+    // A sensor format without a marker type, with an odd number of rows.
+    let threshold = MatchThreshold::new(1, 4).expect("threshold is valid");
+    let conf = DynIrisConf::new(10, 3, 2, threshold).expect("configuration is valid");
+
+    let mut eye: BitVec<usize> = bitvec![usize, Lsb0; 0; conf.data_bit_len()];
+    let mask = bitvec![usize, Lsb0; 1; conf.data_bit_len()];
+    // Set the first column.
+    eye[..conf.rows()].fill(true);
+
+    // Move the set column 2 columns to the right.
+    let mut rotated = bitvec![usize, Lsb0; 0; conf.data_bit_len()];
+    rotated[2 * conf.rows()..3 * conf.rows()].fill(true);
+
+    let distance = iris_distance_dyn(&conf, &eye, &mask, &rotated, &mask);
+    assert_eq!(distance.min_fraction, 0.0);
+    assert_eq!(distance.best_rotation, -2);
+    assert!(is_iris_match_dyn(&conf, &eye, &mask, &rotated, &mask));
+
+    // Rotating too far gives 2 different columns out of 10, which is within the threshold.
+    let mut rotated = bitvec![usize, Lsb0; 0; conf.data_bit_len()];
+    rotated[3 * conf.rows()..4 * conf.rows()].fill(true);
+    let distance = iris_distance_dyn(&conf, &eye, &mask, &rotated, &mask);
+    assert_eq!(distance.min_fraction, 0.2);
+    assert!(is_iris_match_dyn(&conf, &eye, &mask, &rotated, &mask));
+
+    // But not within a tighter threshold.
+    let threshold = MatchThreshold::new(1, 10).expect("threshold is valid");
+    let conf = DynIrisConf::new(10, 3, 2, threshold).expect("configuration is valid");
+    assert!(!is_iris_match_dyn(&conf, &eye, &mask, &rotated, &mask));
+
+    // Invalid configurations are rejected.
+    assert_eq!(DynIrisConf::new(0, 3, 0, threshold), None);
+    assert_eq!(DynIrisConf::new(10, 0, 0, threshold), None);
+    assert_eq!(DynIrisConf::new(10, 3, 5, threshold), None);
+    assert!(DynIrisConf::new(11, 3, 5, threshold).is_some());
+    assert_eq!(DynIrisConf::new(11, 3, 6, threshold), None);
+
+    // Huge values are rejected, rather than wrapping.
+    assert_eq!(DynIrisConf::new(10, 3, usize::MAX, threshold), None);
+    assert_eq!(
+        DynIrisConf::new(10, 3, 1 << (usize::BITS - 1), threshold),
+        None
+    );
+    assert_eq!(DynIrisConf::new(usize::MAX, 2, 0, threshold), None);
+    assert_eq!(DynIrisConf::new(usize::MAX, 1, 0, threshold), None);
+}
+
+#[test]
+fn hamming_masked_words() {
+    use crate::plaintext::{hamming_masked, test::gen::random_iris_mask};
+
+    let eye_a = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let eye_b = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = random_iris_mask::<{ MiddleBits::STORE_ELEM_LEN }>()
+        & random_iris_mask::<{ MiddleBits::STORE_ELEM_LEN }>();
+
+    let expected = ((eye_a ^ eye_b) & mask).count_ones();
+
+    let words = |code: &IrisCode<{ MiddleBits::STORE_ELEM_LEN }>| {
+        code.as_raw_slice()
+            .iter()
+            .map(|word| *word as u64)
+            .collect::<Vec<_>>()
+    };
+    let (words_a, words_b, mask_words) = (words(&eye_a), words(&eye_b), words(&mask));
+
+    assert_eq!(hamming_masked(&words_a, &words_b, &mask_words), expected);
+
+    // Check lengths that don't fill every lane.
+    for len in 0..words_a.len() {
+        let expected = naive_hamming_masked(&words_a[..len], &words_b[..len], &mask_words[..len]);
+        assert_eq!(
+            hamming_masked(&words_a[..len], &words_b[..len], &mask_words[..len]),
+            expected
+        );
+    }
+}
+
+/// Returns the masked Hamming distance one word at a time.
+#[cfg(test)]
+fn naive_hamming_masked(words_a: &[u64], words_b: &[u64], mask_words: &[u64]) -> usize {
+    itertools::izip!(words_a, words_b, mask_words)
+        .map(|(a, b, mask)| ((a ^ b) & mask).count_ones() as usize)
+        .sum()
+}
+
+/// Check the single alignment Hamming distance against the bit-level definition.
+#[test]
+fn single_alignment_hamming() {
+    use crate::plaintext::{
+        hamming, is_iris_match_with_rotation_limit, test::gen::random_iris_mask,
+    };
+
+    let eye_a = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let eye_b = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask_a = random_iris_mask::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask_b = random_iris_mask::<{ MiddleBits::STORE_ELEM_LEN }>();
+
+    let (differences, visible) =
+        hamming::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye_a, &mask_a, &eye_b, &mask_b);
+
+    let visible_mask = mask_a & mask_b;
+    assert_eq!(
+        visible,
+        visible_mask[..MiddleBits::DATA_BIT_LEN].count_ones()
+    );
+    assert_eq!(
+        differences,
+        ((eye_a ^ eye_b) & visible_mask)[..MiddleBits::DATA_BIT_LEN].count_ones()
+    );
+
+    // The matcher uses the same counts when there is no rotation.
+    assert_eq!(
+        differences * MiddleBits::MATCH_DENOMINATOR <= visible * MiddleBits::MATCH_NUMERATOR,
+        is_iris_match_with_rotation_limit::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            &eye_a, &mask_a, &eye_b, &mask_b, 0,
+        ),
+    );
+
+    let visible_mask = visible_iris_mask::<{ MiddleBits::STORE_ELEM_LEN }>();
+    assert_eq!(
+        hamming::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            &eye_a,
+            &visible_mask,
+            &eye_a,
+            &visible_mask,
+        ),
+        (0, MiddleBits::DATA_BIT_LEN)
+    );
+}
+
+/// Check that codes without enough visible bits are indeterminate, rather than matching.
+#[test]
+fn min_visible_bits() {
+    use crate::iris::conf::MinVisibleBits;
+    use crate::plaintext::{iris_match_with_min_visible, is_iris_match, MatchResult};
+
+    let half = MinVisibleBits::fraction(1, 2).expect("half is a valid fraction");
+    assert_eq!(MinVisibleBits::fraction(1, 0), None);
+    assert_eq!(MinVisibleBits::fraction(3, 2), None);
+    assert_eq!(MinVisibleBits::fraction(usize::MAX, usize::MAX), None);
+
+    // Unchecked fractions can't overflow.
+    let huge = MinVisibleBits::Fraction {
+        numerator: usize::MAX,
+        denominator: 1,
+    };
+    assert_eq!(huge.min_bits::<MiddleBits>(), usize::MAX);
+
+    assert_eq!(half.min_bits::<MiddleBits>(), MiddleBits::DATA_BIT_LEN / 2);
+
+    let expected = [
+        (
+            matching::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(),
+            true,
+        ),
+        (
+            different::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(),
+            false,
+        ),
+    ];
+
+    for (cases, is_match) in expected {
+        for (description, eye_a, mask_a, eye_b, mask_b) in cases.iter() {
+            // The default minimum is the same as the threshold check.
+            let result = iris_match_with_min_visible::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                eye_a,
+                mask_a,
+                eye_b,
+                mask_b,
+                MinVisibleBits::default(),
+            );
+            assert_eq!(
+                result == MatchResult::Match,
+                is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                    eye_a, mask_a, eye_b, mask_b
+                ),
+                "{description}",
+            );
+
+            // Occluded codes don't match, because they have no visible bits.
+            let result = iris_match_with_min_visible::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                eye_a, mask_a, eye_b, mask_b, half,
+            );
+            let expected = if (*mask_a & *mask_b).count_ones() < MiddleBits::DATA_BIT_LEN / 2 {
+                MatchResult::Indeterminate
+            } else if is_match {
+                MatchResult::Match
+            } else {
+                MatchResult::NoMatch
+            };
+            assert_eq!(result, expected, "{description}");
+        }
+    }
+}
+
+/// Check that correlated codes have the target distance, and eyelid masks are realistic.
+#[test]
+fn correlated_codes_and_eyelid_masks() {
+    use crate::plaintext::{
+        iris_distance,
+        test::gen::{correlated_iris_code, eyelid_iris_mask},
+    };
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+
+    for distance in [0.0, 0.1, 0.25, 0.45, 1.0] {
+        let other =
+            correlated_iris_code::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, distance);
+
+        #[allow(clippy::cast_precision_loss)]
+        let actual = (eye ^ other).count_ones() as f64 / MiddleBits::DATA_BIT_LEN as f64;
+        assert!((actual - distance).abs() < 0.001, "{distance}: {actual}");
+    }
+
+    let mask = eyelid_iris_mask::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(0.5);
+    let visible = mask.count_ones();
+    assert!(visible < MiddleBits::DATA_BIT_LEN);
+    assert!(visible > MiddleBits::DATA_BIT_LEN / 2);
+
+    // The outer rows at the top of the iris are covered by the upper eyelid.
+    assert!(
+        !mask[index_1d(
+            MiddleBits::COLUMN_LEN,
+            MiddleBits::COLUMN_LEN - 1,
+            MiddleBits::COLUMNS / 4
+        )]
+    );
+
+    let close = correlated_iris_code::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, 0.2);
+    let far = correlated_iris_code::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, 0.45);
+    let other_mask = eyelid_iris_mask::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(0.5);
+
+    assert!(is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+        &eye,
+        &mask,
+        &close,
+        &other_mask
+    ));
+    let far_distance =
+        iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye, &mask, &far, &other_mask);
+    assert!(far_distance.min_fraction > 0.36, "{far_distance:?}");
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    /// Check that codes rotated within the limit match with visible masks, and that matching
+    /// is symmetric for arbitrary masks.
+    #[test]
+    fn rotated_codes_match(
+        (eye, rotated) in rotated_pair::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(),
+        mask_a in iris_mask::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(0.9),
+        mask_b in iris_mask::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(0.9),
+    ) {
+        let mask = visible_iris_mask();
+        let is_match = is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>;
+
+        proptest::prop_assert!(is_match(&eye, &mask, &rotated, &mask));
+        proptest::prop_assert_eq!(
+            is_match(&eye, &mask_a, &rotated, &mask_b),
+            is_match(&rotated, &mask_b, &eye, &mask_a),
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Proptest strategies for iris codes and masks.
+
+use proptest::{collection::vec, prelude::*};
+
+use crate::{
+    iris::conf::{IrisCode, IrisConf, IrisMask},
+    plaintext::{rotate, rotations},
+};
+
+/// Returns a strategy for iris codes in the `C` configuration, with arbitrary data bits.
+/// Codes shrink towards no bits set.
+pub fn iris_code<C: IrisConf, const STORE_ELEM_LEN: usize>(
+) -> impl Strategy<Value = IrisCode<STORE_ELEM_LEN>> {
+    vec(any::<bool>(), C::DATA_BIT_LEN).prop_map(from_bits)
+}
+
+/// Returns a strategy for iris masks in the `C` configuration, where each data bit is visible
+/// with probability `visible`. Masks shrink towards fully occluded.
+///
+/// # Panics
+///
+/// If `visible` is not between 0 and 1.
+pub fn iris_mask<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    visible: f64,
+) -> impl Strategy<Value = IrisMask<STORE_ELEM_LEN>> {
+    vec(prop::bool::weighted(visible), C::DATA_BIT_LEN).prop_map(from_bits)
+}
+
+/// Returns a strategy for an iris code, and the same code rotated within
+/// [`IrisConf::ROTATION_LIMIT`]. With visible masks, the pair always matches.
+pub fn rotated_pair<C: IrisConf, const STORE_ELEM_LEN: usize>(
+) -> impl Strategy<Value = (IrisCode<STORE_ELEM_LEN>, IrisCode<STORE_ELEM_LEN>)> {
+    (
+        iris_code::<C, STORE_ELEM_LEN>(),
+        rotations::<C>(C::ROTATION_LIMIT),
+    )
+        .prop_map(|(code, rotation)| (code, rotate::<C, STORE_ELEM_LEN>(code, rotation)))
+}
+
+/// Returns a code or mask with the data bits in `bits`, and all trailing bits unset.
+fn from_bits<const STORE_ELEM_LEN: usize>(bits: Vec<bool>) -> IrisCode<STORE_ELEM_LEN> {
+    let mut code = IrisCode::ZERO;
+
+    for (i, bit) in bits.into_iter().enumerate() {
+        code.set(i, bit);
+    }
+
+    code
+}
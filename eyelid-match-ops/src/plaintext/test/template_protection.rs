@@ -0,0 +1,157 @@
+//! Tests for salted plaintext template transforms.
+
+#[cfg(feature = "fhe")]
+use crate::{
+    encoded::{PolyCode, PolyQuery},
+    plaintext::test::gen::rotate_not_too_much,
+};
+use crate::{
+    iris::conf::{IrisCode, IrisConf},
+    plaintext::{
+        index_1d, iris_distance, is_iris_match,
+        template_protection::{TemplateKey, SALT_LEN},
+        test::{
+            gen::{random_iris_code, visible_iris_mask},
+            matching::{different, matching},
+        },
+    },
+    MiddleBits,
+};
+
+/// Check that match decisions and distances are preserved when both templates are transformed
+/// with the same salt.
+#[test]
+fn same_salt_preserves_matches() {
+    let key = TemplateKey::<MiddleBits>::new([7; SALT_LEN]);
+
+    let cases = matching::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>()
+        .into_iter()
+        .chain(different::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>());
+
+    for (description, eye_new, mask_new, eye_store, mask_store) in cases {
+        let (protected_eye_new, protected_mask_new) = key.protect(&eye_new, &mask_new);
+        let (protected_eye_store, protected_mask_store) = key.protect(&eye_store, &mask_store);
+
+        assert_eq!(
+            is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                &protected_eye_new,
+                &protected_mask_new,
+                &protected_eye_store,
+                &protected_mask_store,
+            ),
+            is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                &eye_new,
+                &mask_new,
+                &eye_store,
+                &mask_store,
+            ),
+            "{description}",
+        );
+        assert_eq!(
+            iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                &protected_eye_new,
+                &protected_mask_new,
+                &protected_eye_store,
+                &protected_mask_store,
+            ),
+            iris_distance::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+                &eye_new,
+                &mask_new,
+                &eye_store,
+                &mask_store,
+            ),
+            "{description}",
+        );
+    }
+}
+
+/// Check that transformed templates also match in the encoded pipeline.
+#[cfg(feature = "fhe")]
+#[test]
+fn protected_templates_match_encoded() {
+    let key = TemplateKey::<MiddleBits>::new([7; SALT_LEN]);
+
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+    let rotated = rotate_not_too_much::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&eye);
+
+    let (protected_eye, protected_mask) = key.protect(&eye, &mask);
+    let (protected_rotated, _) = key.protect(&rotated, &mask);
+
+    let query = PolyQuery::<MiddleBits>::from_plaintext(&protected_eye, &protected_mask);
+    let code = PolyCode::<MiddleBits>::from_plaintext(&protected_rotated, &protected_mask);
+    assert!(query.is_match(&code).expect("matching must work"));
+}
+
+/// Returns the rows of `code`, complemented if their first bit is set, in sorted order.
+///
+/// The template transform doesn't change this list, whatever the salt.
+fn rows_up_to_complement(code: &IrisCode<{ MiddleBits::STORE_ELEM_LEN }>) -> Vec<Vec<bool>> {
+    let mut rows: Vec<Vec<bool>> = (0..MiddleBits::COLUMN_LEN)
+        .map(|row_i| {
+            let row: Vec<bool> = (0..MiddleBits::COLUMNS)
+                .map(|col_i| code[index_1d(MiddleBits::COLUMN_LEN, row_i, col_i)])
+                .collect();
+
+            if row[0] {
+                row.iter().map(|bit| !bit).collect()
+            } else {
+                row
+            }
+        })
+        .collect();
+    rows.sort();
+
+    rows
+}
+
+/// Check that the transform is repeatable, and what changing the salt does and doesn't change.
+#[test]
+fn new_salt_is_linkable() {
+    let eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let mask = visible_iris_mask();
+
+    let key = TemplateKey::<MiddleBits>::new([1; SALT_LEN]);
+    let (protected_eye, protected_mask) = key.protect(&eye, &mask);
+
+    assert_ne!(protected_eye, eye);
+    assert_eq!(protected_mask, mask);
+    assert_eq!(
+        TemplateKey::<MiddleBits>::new([1; SALT_LEN]).protect(&eye, &mask),
+        (protected_eye, protected_mask),
+    );
+
+    // A template stored with the old salt doesn't directly match a code transformed with a new
+    // salt.
+    let new_key = TemplateKey::<MiddleBits>::new([2; SALT_LEN]);
+    let (new_eye, new_mask) = new_key.protect(&eye, &mask);
+
+    assert_ne!(new_eye, protected_eye);
+    assert!(
+        !is_iris_match::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(
+            &new_eye,
+            &new_mask,
+            &protected_eye,
+            &protected_mask,
+        )
+    );
+
+    // But the rows are the same up to complement, so templates with different salts can be
+    // linked, and the old template isn't revoked.
+    assert_eq!(
+        rows_up_to_complement(&new_eye),
+        rows_up_to_complement(&protected_eye)
+    );
+    assert_eq!(
+        rows_up_to_complement(&protected_eye),
+        rows_up_to_complement(&eye)
+    );
+
+    // Different eyes have different rows.
+    let other_eye = random_iris_code::<{ MiddleBits::STORE_ELEM_LEN }>();
+    let (other_protected_eye, _) = new_key.protect(&other_eye, &mask);
+    assert_ne!(
+        rows_up_to_complement(&other_protected_eye),
+        rows_up_to_complement(&protected_eye)
+    );
+}
@@ -2,6 +2,7 @@
 //!
 //! Contains interfaces to dependencies that we might want to replace later.
 
+pub mod entropy;
 pub mod hamming;
 pub mod poly;
 pub mod yashe;
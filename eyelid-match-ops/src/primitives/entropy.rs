@@ -0,0 +1,24 @@
+//! Sources of randomness for key generation and encryption.
+//!
+//! By default, randomness comes from [`rand::thread_rng()`], which is seeded and periodically
+//! reseeded by the operating system. Encrypted matchers can be shared between threads, so they
+//! use a [`rand_chacha::ChaCha20Rng`] seeded by the operating system instead.
+//!
+//! Deployments without an operating system RNG, like TEEs or HSM-backed servers, can supply
+//! their own [`EntropySource`] instead. Deterministic test rigs can use a ChaCha20 RNG with a
+//! fixed seed.
+//!
+//! Sources must be cryptographically secure, because they are used to generate private keys and
+//! encryption noise.
+
+use std::fmt::Debug;
+
+use rand::{CryptoRng, RngCore};
+
+/// A cryptographically secure source of randomness.
+///
+/// Implemented for every [`RngCore`] marked with [`CryptoRng`], so existing RNGs can be used
+/// directly. Custom sources only need to implement those traits and [`Debug`].
+pub trait EntropySource: RngCore + CryptoRng + Debug {}
+
+impl<R: RngCore + CryptoRng + Debug + ?Sized> EntropySource for R {}
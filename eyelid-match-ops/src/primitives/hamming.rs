@@ -1,92 +1,196 @@
 //! Implementation of the simple encoding
+//!
+//! Binary vectors are split into blocks of up to [`MAX_POLY_DEGREE`](crate::PolyConf::MAX_POLY_DEGREE) bits, and each
+//! block is encoded in its own polynomial, along with its reverse. The Hamming distance of each
+//! block is at the last coefficient of the block product, so the block products can be added
+//! together to get the distance of the whole vector.
 
 use crate::primitives::poly::Poly;
-use ark_ff::Zero;
-use std::ops::AddAssign;
-
-use rand::rngs::ThreadRng;
+use ark_ff::{One, Zero};
+use bitvec::{slice::BitSlice, vec::BitVec};
+use itertools::Itertools;
+use rand::Rng;
 
+use super::entropy::EntropySource;
 use super::yashe::Yashe;
 use super::yashe::{Ciphertext, Message, PrivateKey, PublicKey, YasheConf};
 
+#[cfg(test)]
+mod test;
+
 /// Contains the message to be encoded such that
 /// the Hamming distance can be computed later.
 pub struct SimpleHammingEncoding<C: YasheConf>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
 {
-    /// The message to be encoded
-    m: Message<C>,
-    /// The reverse of the message to be encoded
-    m_rev: Message<C>,
+    /// The blocks of the message to be encoded
+    m: Vec<Message<C>>,
+    /// The reverse of each block of the message to be encoded
+    m_rev: Vec<Message<C>>,
+    /// The number of bits in each block. The last block is padded with zeroes.
+    block_size: usize,
+    /// The number of bits in the encoded vector
+    len: usize,
 }
 
-/// SimpleHammingEncodingCiphertext is a struct that holds two ciphertexts, c and c_rev,
-/// which are the encodings of the message m and m_rev, respectively. The encoding is
-/// done by reversing the message and encoding it as a regular Yashe Ciphertext.
+/// SimpleHammingEncodingCiphertext is a struct that holds two lists of ciphertexts, c and c_rev,
+/// which are the encodings of each block of the message m and m_rev, respectively. The encoding
+/// is done by reversing each block and encoding it as a regular Yashe Ciphertext.
 pub struct SimpleHammingEncodingCiphertext<C: YasheConf>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
 {
-    /// The ciphertext of the message m
-    c: Ciphertext<C>,
-    /// The ciphertext of the message m_rev
-    c_rev: Ciphertext<C>,
+    /// The ciphertexts of the blocks of the message m
+    c: Vec<Ciphertext<C>>,
+    /// The ciphertexts of the blocks of the message m_rev
+    c_rev: Vec<Ciphertext<C>>,
+    /// The number of bits in each block
+    block_size: usize,
+    /// The number of bits in the encoded vector
+    len: usize,
 }
 
 impl<C: YasheConf> SimpleHammingEncoding<C>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
 {
-    /// Creates a new `SimpleHammingEncoding` with the given message `m` and size `size`.
+    /// Creates a new single block `SimpleHammingEncoding` with the given message `m` and size
+    /// `size`.
+    ///
+    /// `size` can be any value less than or equal to MAX_POLY_DEGREE. This allows vectors to be
+    /// embedded in any polynomial configuration, possibly wasting some coefficients that are not
+    /// used. The encoding depends on this value of size. When the coefficient is reverted, this
+    /// happens inside the sub-vector that has `size` elements, which is different from reverting
+    /// all the coefficients of the polynomial.
     ///
-    /// `size` can be any value less than MAX_POLY_DEGREE. This allows vectors to be embedded
-    /// in any polynomial configuration, possibly wasting some coefficients that are not used.
-    /// The encoding depends on this value of size. When the coefficient is reverted, this happens
-    /// inside the sub-vector that has `size` elements, which is different from reverting all the
-    /// coefficients of the polynomial.
+    /// Use [`SimpleHammingEncoding::from_bits()`] for vectors longer than MAX_POLY_DEGREE.
     pub fn new(m: Message<C>, size: usize) -> Self {
-        // TODO: replace this with coeffs().clone(), Vec resize()/revert(), then truncate_to_canonical_form()
-        let mut m_rev = Message {
-            m: Poly::<C>::zero(),
+        assert!(
+            size <= C::MAX_POLY_DEGREE,
+            "single block vectors must fit in one polynomial"
+        );
+
+        let m_rev = (0..size).map(|i| m.m[size - i - 1]).collect();
+        let m_rev = Message {
+            m: Poly::from_coefficients_vec(m_rev),
         };
-        for i in 0..size {
-            m_rev.m[i] = m.m[size - i - 1];
+
+        Self {
+            m: vec![m],
+            m_rev: vec![m_rev],
+            block_size: size,
+            len: size,
         }
-        Self { m, m_rev }
     }
 
-    /// Sample a random SimpleHammingEncoding, by sampling a random binary Yashe Message, which
-    /// is done by calling function sample_binary_message, and returning a new SimpleHammingEncoding,
-    /// which sets m to the sampled message, and m_rev to the reverse of m.
-    pub fn sample(ctx: Yashe<C>, size: usize, rng: &mut ThreadRng) -> SimpleHammingEncoding<C> {
-        SimpleHammingEncoding::new(ctx.sample_binary_message(rng), size)
+    /// Creates a new `SimpleHammingEncoding` from a binary vector of any length.
+    ///
+    /// The vector is split into blocks of up to MAX_POLY_DEGREE bits, and each block is reversed
+    /// separately. All the blocks have the same size, so the last block is padded with zeroes.
+    pub fn from_bits(bits: &BitSlice) -> Self {
+        let block_size = bits.len().clamp(1, C::MAX_POLY_DEGREE);
+
+        let (m, m_rev) = bits
+            .chunks(block_size)
+            .map(|block| {
+                let mut m = vec![C::Coeff::zero(); block_size];
+                let mut m_rev = m.clone();
+
+                for i in block.iter_ones() {
+                    m[i] = C::Coeff::one();
+                    m_rev[block_size - i - 1] = C::Coeff::one();
+                }
+
+                (
+                    Message {
+                        m: Poly::from_coefficients_vec(m),
+                    },
+                    Message {
+                        m: Poly::from_coefficients_vec(m_rev),
+                    },
+                )
+            })
+            .unzip();
+
+        Self {
+            m,
+            m_rev,
+            block_size,
+            len: bits.len(),
+        }
+    }
+
+    /// Sample a random SimpleHammingEncoding of `size` bits, by sampling a random binary vector,
+    /// and returning a new SimpleHammingEncoding, which sets m to the blocks of the sampled
+    /// vector, and m_rev to the reverse of each block.
+    pub fn sample<E: EntropySource + ?Sized>(size: usize, rng: &mut E) -> SimpleHammingEncoding<C> {
+        let bits: BitVec = (0..size).map(|_| rng.gen::<bool>()).collect();
+
+        SimpleHammingEncoding::from_bits(&bits)
+    }
+
+    /// Returns the number of bits in the encoded vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the encoded vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of blocks, which is the number of polynomials in each component.
+    pub fn num_blocks(&self) -> usize {
+        self.m.len()
     }
 
-    /// Compute the Hamming distance between self and v2. In order to do this,
-    /// we subtract each component of the encoding, namely self.m from v2.m and self.m_rev from v2.m_rev,
-    /// and multiply the obtained Messages, returning a regular Yashe Message as output.
-    pub fn hamming_distance(&self, v2: SimpleHammingEncoding<C>, size: usize) -> C::Coeff {
-        let res: &mut C::Coeff = &mut C::Coeff::zero();
-        for i in 0..size {
-            if self.m.m[i] != v2.m.m[i] {
-                res.add_assign(C::Coeff::from(1u64));
+    /// Compute the Hamming distance between self and v2, by counting the different coefficients
+    /// in each block.
+    ///
+    /// # Panics
+    ///
+    /// If `self` and `v2` have different lengths.
+    pub fn hamming_distance(&self, v2: &SimpleHammingEncoding<C>) -> C::Coeff {
+        assert_eq!(self.len, v2.len, "vectors must have the same length");
+
+        let mut res = C::Coeff::zero();
+        for (block, block2) in self.m.iter().zip_eq(v2.m.iter()) {
+            for i in 0..self.block_size {
+                if block.m[i] != block2.m[i] {
+                    res += C::Coeff::one();
+                }
             }
         }
-        *res
+        res
     }
 
     /// Encrypts the message m encoded as a SimpleHammingEncoding, which is done by encrypting
-    /// each component of the encoding separately, and returning a SimpleHammingEncodingCiphertext.
-    pub fn encrypt_simple_hamming_encoding(
+    /// each block of each component of the encoding separately, and returning a
+    /// SimpleHammingEncodingCiphertext.
+    pub fn encrypt_simple_hamming_encoding<E: EntropySource + ?Sized>(
         &self,
         ctx: Yashe<C>,
         pub_key: &PublicKey<C>,
-        rng: &mut ThreadRng,
+        rng: &mut E,
     ) -> SimpleHammingEncodingCiphertext<C> {
-        let c = ctx.encrypt(self.m.clone(), pub_key, rng);
-        let c_rev = ctx.encrypt(self.m_rev.clone(), pub_key, rng);
-        SimpleHammingEncodingCiphertext { c, c_rev }
+        let c = self
+            .m
+            .iter()
+            .map(|m| ctx.encrypt(m.clone(), pub_key, rng))
+            .collect();
+        let c_rev = self
+            .m_rev
+            .iter()
+            .map(|m_rev| ctx.encrypt(m_rev.clone(), pub_key, rng))
+            .collect();
+
+        SimpleHammingEncodingCiphertext {
+            c,
+            c_rev,
+            block_size: self.block_size,
+            len: self.len,
+        }
     }
 }
 
@@ -94,35 +198,80 @@ impl<C: YasheConf> SimpleHammingEncodingCiphertext<C>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
 {
-    /// Decrypts the SimpleHammingEncodingCiphertext c, by decrypting each component of the encoding
-    /// separately, and returning the result as a SimpleHammingEncoding.
+    /// Decrypts the SimpleHammingEncodingCiphertext c, by decrypting each block of each component
+    /// of the encoding separately, and returning the result as a SimpleHammingEncoding.
     pub fn decrypt_simple_hamming_encoding(
         &self,
         ctx: Yashe<C>,
         priv_key: &PrivateKey<C>,
     ) -> SimpleHammingEncoding<C> {
-        let m = ctx.decrypt(self.c.clone(), priv_key);
-        let m_rev = ctx.decrypt(self.c_rev.clone(), priv_key);
-        SimpleHammingEncoding { m, m_rev }
+        let m = self
+            .c
+            .iter()
+            .map(|c| ctx.decrypt(c.clone(), priv_key))
+            .collect();
+        let m_rev = self
+            .c_rev
+            .iter()
+            .map(|c_rev| ctx.decrypt(c_rev.clone(), priv_key))
+            .collect();
+
+        SimpleHammingEncoding {
+            m,
+            m_rev,
+            block_size: self.block_size,
+            len: self.len,
+        }
     }
 
     /// In order to homomorphically compute the hamming distance between two
     /// SimpleHammingEncodingCiphertexts, we need to subtract each
     /// component respectively. Namely, given c1 and c2, we need to compute
     /// a SimpleHammingEncodingCiphertext c, such that c.c = c1.c - c2.c,
-    /// and c.c_rev = c1.c_rev - c2.c_rev. Then we multiply c.c by c.c_rev
-    /// and return the result as a regular Yashe Ciphertext.
+    /// and c.c_rev = c1.c_rev - c2.c_rev. Then we multiply c.c by c.c_rev for each block,
+    /// and add the block products, returning the result as a regular Yashe Ciphertext.
+    ///
+    /// The result must be decrypted using [`Yashe::decrypt_mul()`], and the distance is at the
+    /// coefficient returned by [`SimpleHammingEncodingCiphertext::distance_index()`].
+    /// Distances are reduced modulo T, so they are only correct if they are less than T.
+    ///
+    /// # Panics
+    ///
+    /// If `self` and `c2` have different lengths.
     pub fn homomorphic_hamming_distance(
         &self,
         ctx: Yashe<C>,
         c2: SimpleHammingEncodingCiphertext<C>,
     ) -> Ciphertext<C> {
-        let c = Ciphertext {
-            c: &self.c.c - &c2.c.c,
-        };
-        let c_rev = Ciphertext {
-            c: &self.c_rev.c - &c2.c_rev.c,
-        };
-        ctx.ciphertext_mul(c, c_rev)
+        assert_eq!(self.len, c2.len, "vectors must have the same length");
+
+        let block_products = self
+            .c
+            .iter()
+            .zip_eq(self.c_rev.iter())
+            .zip_eq(c2.c.iter().zip_eq(c2.c_rev.iter()))
+            .map(|((c1, c1_rev), (c2, c2_rev))| {
+                let c = Ciphertext { c: &c1.c - &c2.c };
+                let c_rev = Ciphertext {
+                    c: &c1_rev.c - &c2_rev.c,
+                };
+                ctx.ciphertext_mul(c, c_rev)
+            });
+
+        // Every block has the same size, so their distances are at the same coefficient.
+        block_products
+            .reduce(|sum, product| ctx.ciphertext_add(sum, product))
+            .unwrap_or_else(|| Ciphertext { c: Poly::zero() })
+    }
+
+    /// Returns the index of the coefficient containing the Hamming distance, after decrypting
+    /// the result of [`SimpleHammingEncodingCiphertext::homomorphic_hamming_distance()`].
+    pub fn distance_index(&self) -> usize {
+        self.block_size - 1
+    }
+
+    /// Returns the number of blocks, which is the number of ciphertexts in each component.
+    pub fn num_blocks(&self) -> usize {
+        self.c.len()
     }
 }
@@ -0,0 +1,112 @@
+//! Tests for the hamming distance calculation.
+
+use std::any::type_name;
+
+use bitvec::prelude::*;
+
+use crate::{
+    encoded::conf::LargeRes,
+    primitives::{
+        hamming::SimpleHammingEncoding,
+        yashe::{Yashe, YasheConf},
+    },
+    FullRes, MiddleRes, PolyConf,
+};
+
+/// Encrypts two random vectors of `size` bits, and checks their homomorphic Hamming distance.
+fn hamming_distance_helper<C: YasheConf>(size: usize)
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<C> = Yashe::new();
+    let (private_key, public_key) = ctx.keygen(&mut rng);
+
+    let v1 = SimpleHammingEncoding::sample(size, &mut rng);
+    let v2 = SimpleHammingEncoding::sample(size, &mut rng);
+    assert_eq!(v1.num_blocks(), size.div_ceil(C::MAX_POLY_DEGREE));
+
+    let c1 = v1.encrypt_simple_hamming_encoding(ctx, &public_key, &mut rng);
+    let c2 = v2.encrypt_simple_hamming_encoding(ctx, &public_key, &mut rng);
+    let index = c1.distance_index();
+    let c = c1.homomorphic_hamming_distance(ctx, c2);
+    let m = ctx.decrypt_mul(c, &private_key);
+
+    let hd = v1.hamming_distance(&v2);
+    assert_eq!(
+        m.m[index],
+        hd,
+        "{size} bit hamming distance failed for {}",
+        type_name::<C>()
+    );
+}
+
+#[test]
+fn test_hamming_distance() {
+    // Must be smaller than or equal to MAX_POLY_DEGREE
+    hamming_distance_helper::<FullRes>(1000);
+    hamming_distance_helper::<LargeRes>(1000);
+}
+
+#[test]
+fn test_multi_block_hamming_distance() {
+    // Random vectors differ in about half their bits, and distances must be less than T.
+    hamming_distance_helper::<FullRes>(FullRes::MAX_POLY_DEGREE + 1);
+    hamming_distance_helper::<FullRes>(3 * FullRes::MAX_POLY_DEGREE + 100);
+    hamming_distance_helper::<LargeRes>(4 * LargeRes::MAX_POLY_DEGREE);
+}
+
+/// Check that single block encodings still work when the message has coefficients after `size`.
+#[test]
+fn test_single_block_encoding() {
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<FullRes> = Yashe::new();
+    let (private_key, public_key) = ctx.keygen(&mut rng);
+    let size = 1000;
+
+    let v1 = SimpleHammingEncoding::new(ctx.sample_binary_message(&mut rng), size);
+    let v2 = SimpleHammingEncoding::new(ctx.sample_binary_message(&mut rng), size);
+    assert_eq!(v1.num_blocks(), 1);
+
+    let c1 = v1.encrypt_simple_hamming_encoding(ctx, &public_key, &mut rng);
+    let c2 = v2.encrypt_simple_hamming_encoding(ctx, &public_key, &mut rng);
+    let c = c1.homomorphic_hamming_distance(ctx, c2);
+    let m = ctx.decrypt_mul(c, &private_key);
+
+    assert_eq!(m.m[size - 1], v1.hamming_distance(&v2));
+}
+
+/// Check that vectors are split into blocks, and the last block is padded.
+#[test]
+fn test_block_split() {
+    let len = 2 * MiddleRes::MAX_POLY_DEGREE + 3;
+    let zeroes = bitvec![0; len];
+    let mut ones = bitvec![1; len];
+
+    let v1 = SimpleHammingEncoding::<MiddleRes>::from_bits(&zeroes);
+    let v2 = SimpleHammingEncoding::<MiddleRes>::from_bits(&ones);
+    assert_eq!(v1.len(), len);
+    assert_eq!(v1.num_blocks(), 3);
+    assert_eq!(
+        v1.hamming_distance(&v2),
+        u64::try_from(len).expect("length fits in u64").into()
+    );
+
+    ones.set(len - 1, false);
+    let v3 = SimpleHammingEncoding::<MiddleRes>::from_bits(&ones);
+    assert_eq!(v2.hamming_distance(&v3), 1_u64.into());
+
+    let empty = SimpleHammingEncoding::<MiddleRes>::from_bits(BitSlice::empty());
+    assert!(empty.is_empty());
+    assert_eq!(empty.num_blocks(), 0);
+    assert_eq!(empty.hamming_distance(&empty), 0_u64.into());
+}
+
+#[test]
+#[should_panic(expected = "vectors must have the same length")]
+fn test_length_mismatch() {
+    let v1 = SimpleHammingEncoding::<MiddleRes>::from_bits(bits![0; 10]);
+    let v2 = SimpleHammingEncoding::<MiddleRes>::from_bits(bits![0; 11]);
+
+    v1.hamming_distance(&v2);
+}
@@ -5,20 +5,26 @@
 
 pub use fq::{Fq66, Fq66bn, Fq79, Fq79bn};
 pub use modular_poly::{
+    backend::{
+        mul_poly, poly_mul_backend, reset_poly_mul_backend, set_poly_mul_backend, CpuBackend,
+        PolyMulBackend,
+    },
     conf::PolyConf,
+    inv::PolyError,
     modulus::{mod_poly, new_unreduced_poly_modulus_slow},
-    mul::mul_poly,
+    mul::ntt_cyclotomic_mul,
+    ntt::NttTables,
     Poly,
 };
 
 // Only for tests.
 
 // Use `mod_poly` outside this module, it is set to the fastest modulus operation.
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub use modular_poly::modulus::{mod_poly_ark_ref_slow, mod_poly_manual_mut};
 
-// Use `mul_poly` outside this module, it is set to the fastest multiplication operation.
-#[cfg(any(test, feature = "benchmark"))]
+// Use `mul_poly` outside this module, it uses the selected multiplication backend.
+#[cfg(any(test, feature = "test-util"))]
 pub use modular_poly::mul::{
     flat_karatsuba_mul, naive_cyclotomic_mul, poly_split, poly_split_half, rec_karatsuba_mul,
 };
@@ -26,7 +32,7 @@ pub use modular_poly::mul::{
 pub mod fq;
 pub mod modular_poly;
 
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub mod test;
 
 // Do not add code here.
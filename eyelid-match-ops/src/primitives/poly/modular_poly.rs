@@ -21,10 +21,15 @@ use ark_poly::polynomial::univariate::{
     DenseOrSparsePolynomial, DensePolynomial, SparsePolynomial,
 };
 use derive_more::{AsRef, Deref, DerefMut, Div, Into, Rem};
+use zeroize::Zeroize;
 
-use crate::primitives::poly::{mod_poly, mul_poly, new_unreduced_poly_modulus_slow, PolyConf};
+use crate::primitives::poly::{
+    mod_poly, mul_poly, new_unreduced_poly_modulus_slow, PolyConf, PolyError,
+};
 
+pub mod backend;
 pub mod conf;
+pub mod ntt;
 
 pub(super) mod inv;
 pub(super) mod modulus;
@@ -32,6 +37,9 @@ pub(super) mod mul;
 
 mod trivial;
 
+#[cfg(feature = "serde")]
+mod serialize;
+
 /// A modular polynomial with coefficients in [`PolyConf::Coeff`], and a generic maximum degree
 /// [`PolyConf::MAX_POLY_DEGREE`]. The polynomial modulus is `X^MAX_POLY_DEGREE + 1`. Polynomials
 /// are always in their canonical, modular reduced form.
@@ -77,6 +85,13 @@ pub struct Poly<C: PolyConf>(
     PhantomData<C>,
 );
 
+impl<C: PolyConf> Zeroize for Poly<C> {
+    /// Overwrites every coefficient with zero, then truncates the polynomial to zero.
+    fn zeroize(&mut self) {
+        self.coeffs.zeroize();
+    }
+}
+
 impl<C: PolyConf> Poly<C> {
     /// The constant maximum degree of this monomorphized polynomial type.
     pub const N: usize = C::MAX_POLY_DEGREE;
@@ -341,7 +356,7 @@ impl<C: PolyConf> Poly<C> {
 
     /// Returns the primitive inverse of this polynomial in the cyclotomic ring, if it exists.
     /// Otherwise, returns an error.
-    pub fn inverse(&self) -> Result<Self, &'static str> {
+    pub fn inverse(&self) -> Result<Self, PolyError> {
         inv::inverse(self)
     }
 
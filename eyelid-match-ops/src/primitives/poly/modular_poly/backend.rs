@@ -0,0 +1,136 @@
+//! Runtime selection of the polynomial multiplication backend.
+//!
+//! [`mul_poly()`](crate::primitives::poly::mul_poly) uses the backend registered for each
+//! [`PolyConf`], falling back to [`CpuBackend`] when no backend is registered, or the registered
+//! backend is unavailable. Accelerator crates implement [`PolyMulBackend`] and register it at
+//! startup, so encoded and encrypted matching use it without any other changes.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, PoisonError, RwLock,
+    },
+};
+
+use lazy_static::lazy_static;
+
+use crate::{
+    metrics::count_poly_mul,
+    primitives::poly::{modular_poly::mul, Poly, PolyConf},
+};
+
+/// The fastest CPU multiplication, which is used when no backend is registered.
+#[cfg(not(feature = "ntt"))]
+use mul::rec_karatsuba_mul as cpu_cyclotomic_mul;
+
+/// The fastest CPU multiplication, which is used when no backend is registered.
+#[cfg(feature = "ntt")]
+use mul::ntt_cyclotomic_mul as cpu_cyclotomic_mul;
+
+/// A cyclotomic polynomial multiplication implementation for polynomials in the `C`
+/// configuration.
+pub trait PolyMulBackend<C: PolyConf>: Send + Sync {
+    /// Returns a short name for the backend, for logs and benchmarks.
+    fn name(&self) -> &'static str;
+
+    /// Returns true if the backend can be used on this machine. For example, if its device is
+    /// present.
+    ///
+    /// Unavailable backends are skipped in favour of [`CpuBackend`].
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Returns `a * b` followed by reduction mod `XˆN + 1`.
+    /// All polynomials have maximum degree [`PolyConf::MAX_POLY_DEGREE`].
+    fn cyclotomic_mul(&self, a: &Poly<C>, b: &Poly<C>) -> Poly<C>;
+}
+
+/// The default backend, which uses the fastest CPU multiplication.
+///
+/// This is [`rec_karatsuba_mul()`](mul::rec_karatsuba_mul) by default, or
+/// [`ntt_cyclotomic_mul()`](mul::ntt_cyclotomic_mul) with the `ntt` feature. Configurations
+/// without [NTT tables](PolyConf::ntt_tables) always use Karatsuba multiplication.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CpuBackend;
+
+impl<C: PolyConf> PolyMulBackend<C> for CpuBackend {
+    fn name(&self) -> &'static str {
+        if cfg!(feature = "ntt") && C::ntt_tables().is_some() {
+            "cpu-ntt"
+        } else {
+            "cpu-karatsuba"
+        }
+    }
+
+    fn cyclotomic_mul(&self, a: &Poly<C>, b: &Poly<C>) -> Poly<C> {
+        cpu_cyclotomic_mul(a, b)
+    }
+}
+
+/// True if a backend has ever been registered. Avoids locking the registry in the default
+/// configuration.
+static ANY_BACKEND_REGISTERED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    /// The registered backends, keyed by the [`TypeId`] of their [`PolyConf`].
+    /// Each value is an `Arc<dyn PolyMulBackend<C>>`.
+    static ref BACKENDS: RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Registers `backend` for multiplying polynomials in the `C` configuration, replacing any
+/// previously registered backend.
+pub fn set_poly_mul_backend<C: PolyConf>(backend: Arc<dyn PolyMulBackend<C>>) {
+    // The registry is never left in an inconsistent state, so poisoning can be ignored.
+    BACKENDS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(TypeId::of::<C>(), Box::new(backend));
+
+    ANY_BACKEND_REGISTERED.store(true, Ordering::Release);
+}
+
+/// Removes the backend registered for the `C` configuration, so it uses [`CpuBackend`].
+pub fn reset_poly_mul_backend<C: PolyConf>() {
+    BACKENDS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&TypeId::of::<C>());
+}
+
+/// Returns the registered backend for the `C` configuration, if there is one and it is
+/// available.
+fn registered_backend<C: PolyConf>() -> Option<Arc<dyn PolyMulBackend<C>>> {
+    if !ANY_BACKEND_REGISTERED.load(Ordering::Acquire) {
+        return None;
+    }
+
+    // Backends are always stored with their configuration's TypeId, so the downcast succeeds.
+    let backend = BACKENDS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&TypeId::of::<C>())?
+        .downcast_ref::<Arc<dyn PolyMulBackend<C>>>()?
+        .clone();
+
+    backend.is_available().then_some(backend)
+}
+
+/// Returns the backend used for multiplying polynomials in the `C` configuration.
+pub fn poly_mul_backend<C: PolyConf>() -> Arc<dyn PolyMulBackend<C>> {
+    registered_backend().unwrap_or_else(|| Arc::new(CpuBackend))
+}
+
+/// Returns `a * b` followed by reduction mod `XˆN + 1`, using the selected backend.
+/// All polynomials have maximum degree [`PolyConf::MAX_POLY_DEGREE`].
+pub fn mul_poly<C: PolyConf>(a: &Poly<C>, b: &Poly<C>) -> Poly<C> {
+    count_poly_mul();
+
+    match registered_backend::<C>() {
+        Some(backend) => backend.cyclotomic_mul(a, b),
+        None => cpu_cyclotomic_mul(a, b),
+    }
+}
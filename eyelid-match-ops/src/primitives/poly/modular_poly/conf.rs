@@ -9,6 +9,7 @@ use crate::{
     encoded::{conf::LargeRes, EncodeConf, FullRes, MiddleRes},
     primitives::poly::{
         fq::{Fq123, Fq123bn},
+        modular_poly::ntt::NttTables,
         Fq66, Fq66bn, Fq79, Fq79bn,
     },
     FullBits, MiddleBits,
@@ -23,7 +24,10 @@ use crate::{
 /// Fixed polynomial parameters.
 ///
 /// Polynomials with different parameters are incompatible.
-pub trait PolyConf: Copy + Clone + Debug + Eq + PartialEq {
+///
+/// Configurations are `'static` marker types, so they can be used to select a
+/// [`PolyMulBackend`](crate::primitives::poly::PolyMulBackend).
+pub trait PolyConf: Copy + Clone + Debug + Eq + PartialEq + Send + Sync + 'static {
     /// The maximum exponent in the polynomial.
     const MAX_POLY_DEGREE: usize;
 
@@ -43,6 +47,14 @@ pub trait PolyConf: Copy + Clone + Debug + Eq + PartialEq {
     ///
     /// Typically, `Coeff::zero()` is more readable and efficient.
     fn coeff_zero() -> &'static Self::Coeff;
+
+    /// The precomputed tables for [`ntt_cyclotomic_mul()`], or `None` if [`PolyConf::Coeff`]
+    /// doesn't have a primitive `2 * MAX_POLY_DEGREE`-th root of unity.
+    ///
+    /// [`ntt_cyclotomic_mul()`]: crate::primitives::poly::ntt_cyclotomic_mul
+    fn ntt_tables() -> Option<&'static NttTables<Self>> {
+        None
+    }
 }
 
 impl PolyConf for LargeRes {
@@ -82,6 +94,10 @@ impl PolyConf for FullRes {
     fn coeff_zero() -> &'static Self::Coeff {
         &FQ79_ZERO
     }
+
+    fn ntt_tables() -> Option<&'static NttTables<Self>> {
+        Some(&FULL_RES_NTT_TABLES)
+    }
 }
 // The polynomial must have enough coefficients to store the underlying iris data.
 const_assert!(FullRes::MAX_POLY_DEGREE >= FullBits::BLOCK_AND_PADS_BIT_LEN);
@@ -213,3 +229,11 @@ lazy_static! {
     /// The zero coefficient as a static constant value.
     static ref FQ66_BN_ZERO: Fq66bn = Fq66bn::zero();
 }
+
+lazy_static! {
+    /// The NTT twiddle factors for full resolution polynomials.
+    ///
+    /// The other configurations use fields without a large enough power of two root of unity.
+    static ref FULL_RES_NTT_TABLES: NttTables<FullRes> =
+        NttTables::new().expect("Fq79 has a primitive 2 * MAX_POLY_DEGREE-th root of unity");
+}
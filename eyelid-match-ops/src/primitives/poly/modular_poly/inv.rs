@@ -4,6 +4,18 @@ use ark_poly::Polynomial;
 
 use crate::primitives::poly::{Poly, PolyConf};
 
+/// Errors that can happen during polynomial operations.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum PolyError {
+    /// The zero polynomial doesn't have an inverse.
+    #[error("can't invert the zero polynomial")]
+    ZeroPolynomial,
+
+    /// The polynomial shares a factor with the polynomial modulus, so it doesn't have an inverse.
+    #[error("non-invertible polynomial")]
+    NonInvertible,
+}
+
 /// Returns the primitive polynomial which is the inverse of `a` in the
 /// cyclotomic ring, if it exists. Otherwise, returns an error.
 ///
@@ -15,17 +27,19 @@ use crate::primitives::poly::{Poly, PolyConf};
 /// When `d` is a constant polynomial and `a` is the polynomial modulus
 /// (which reduces to `0`), we have that `b/cont(d)` is the primitive
 /// multiplicative inverse of `y`.
-pub fn inverse<C: PolyConf>(a: &Poly<C>) -> Result<Poly<C>, &'static str> {
+pub fn inverse<C: PolyConf>(a: &Poly<C>) -> Result<Poly<C>, PolyError> {
+    if a.is_zero() {
+        return Err(PolyError::ZeroPolynomial);
+    }
+
     let unreduced_mod_pol = Poly::new_unreduced_poly_modulus_slow();
 
     let (_x, y, d) = extended_gcd(&unreduced_mod_pol, a);
 
     // If `d` is a non-zero constant, we can compute the inverse of `d`,
     // and calculate the final primitive inverse.
-    if d.is_zero() {
-        Err("Can't invert the zero polynomial")
-    } else if d.degree() > 0 {
-        Err("Non-invertible polynomial")
+    if d.is_zero() || d.degree() > 0 {
+        Err(PolyError::NonInvertible)
     } else {
         // Reduce to a primitive polynomial.
         let mut inv: Poly<C> = y;
@@ -3,7 +3,10 @@
 use ark_ff::{One, Zero};
 use ark_poly::polynomial::Polynomial;
 
-use crate::primitives::poly::{Poly, PolyConf};
+use crate::{
+    metrics::count_poly_reduction,
+    primitives::poly::{Poly, PolyConf},
+};
 
 /// The fastest available modular polynomial operation.
 pub use mod_poly_manual_mut as mod_poly;
@@ -12,6 +15,8 @@ pub use mod_poly_manual_mut as mod_poly;
 ///
 /// This is the most efficient manual implementation.
 pub fn mod_poly_manual_mut<C: PolyConf>(dividend: &mut Poly<C>) {
+    count_poly_reduction();
+
     let mut i = C::MAX_POLY_DEGREE;
     while i < dividend.coeffs.len() {
         let q = i / C::MAX_POLY_DEGREE;
@@ -32,9 +32,6 @@ impl<C: PolyConf> MulAssign<C::Coeff> for &mut Poly<C> {
     }
 }
 
-/// The fastest available cyclotomic polynomial multiplication operation (multiply then reduce).
-pub use rec_karatsuba_mul as mul_poly;
-
 /// Minimum degree for recursive Karatsuba calls.
 // TODO: fine tune this constant
 #[cfg(not(tiny_poly))]
@@ -49,12 +46,12 @@ pub const REC_KARATSUBA_MIN_DEGREE: usize = 2;
 //
 // TODO: fine tune this constant
 #[cfg(not(tiny_poly))]
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub const FLAT_KARATSUBA_INITIAL_LAYER: u32 = 3;
 
 /// Tiny test polynomial initial layer parameter for the flat Karatsuba loop.
 #[cfg(tiny_poly)]
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub const FLAT_KARATSUBA_INITIAL_LAYER: u32 = 2;
 
 /// Returns `a * b` followed by reduction mod `XˆN + 1`.
@@ -192,6 +189,50 @@ fn rec_karatsuba_mul_inner<C: PolyConf>(a: &Poly<C>, b: &Poly<C>, chunk: usize)
     res
 }
 
+/// Returns `a * b` followed by reduction mod `XˆN + 1` using the negacyclic number theoretic
+/// transform. All polynomials have maximum degree [`PolyConf::MAX_POLY_DEGREE`].
+///
+/// Uses the precomputed [`PolyConf::ntt_tables()`]. Configurations without NTT tables fall back
+/// to [`rec_karatsuba_mul()`].
+pub fn ntt_cyclotomic_mul<C: PolyConf>(a: &Poly<C>, b: &Poly<C>) -> Poly<C> {
+    let Some(tables) = C::ntt_tables() else {
+        return rec_karatsuba_mul(a, b);
+    };
+
+    let mut a = ntt_input(a);
+    let mut b = ntt_input(b);
+
+    tables.forward(&mut a);
+    tables.forward(&mut b);
+
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a *= b;
+    }
+
+    tables.inverse(&mut a);
+
+    Poly::from_coefficients_vec(a)
+}
+
+/// Returns the coefficients of `a` as a vector of exactly [`PolyConf::MAX_POLY_DEGREE`]
+/// coefficients, for use as an NTT input.
+///
+/// Polynomials are usually reduced, but any coefficients of `XˆN` or higher are folded back in
+/// using `XˆN = -1`.
+fn ntt_input<C: PolyConf>(a: &Poly<C>) -> Vec<C::Coeff> {
+    let mut res = vec![C::Coeff::zero(); C::MAX_POLY_DEGREE];
+
+    for (i, coeff) in a.coeffs.iter().enumerate() {
+        if (i / C::MAX_POLY_DEGREE) % 2 == 0 {
+            res[i % C::MAX_POLY_DEGREE] += coeff;
+        } else {
+            res[i % C::MAX_POLY_DEGREE] -= coeff;
+        }
+    }
+
+    res
+}
+
 /// Returns `a * b` followed by reduction mod `XˆN + 1` using flat Karatsuba method.
 /// The returned polynomial has a degree less than [`PolyConf::MAX_POLY_DEGREE`].
 ///
@@ -201,7 +242,7 @@ fn rec_karatsuba_mul_inner<C: PolyConf>(a: &Poly<C>, b: &Poly<C>, chunk: usize)
 // TODO:
 // - split the `for` and `while` loops into functions, and benchmark the overall performance.
 // - split large code blocks into smaller functions, and benchmark the overall performance.
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 #[allow(clippy::cognitive_complexity)]
 pub fn flat_karatsuba_mul<C: PolyConf>(a: &Poly<C>, b: &Poly<C>) -> Poly<C> {
     use std::ops::{Add, Sub};
@@ -328,7 +369,7 @@ pub fn flat_karatsuba_mul<C: PolyConf>(a: &Poly<C>, b: &Poly<C>) -> Poly<C> {
 
 /// Split the polynomial into `C::MAX_POLY_DEGREE / k` parts, in order from the constant term to the degree.
 /// Any of the polynomials can be zero.
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub fn poly_split<C: PolyConf>(a: &Poly<C>, k: usize) -> Vec<Poly<C>> {
     // invariant: k must be a power of 2
     debug_assert_eq!(k.count_ones(), 1);
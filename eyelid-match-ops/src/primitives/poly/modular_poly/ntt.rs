@@ -0,0 +1,136 @@
+//! Negacyclic number theoretic transform (NTT) tables and transforms.
+//!
+//! The NTT evaluates a polynomial at the odd powers of a primitive `2N`-th root of unity `psi`,
+//! which are exactly the roots of `X^N + 1`. So cyclotomic multiplication becomes pointwise
+//! multiplication in the NTT domain, without any separate modular reduction.
+//!
+//! The transforms follow Longa and Naehrig, "Speeding up the Number Theoretic Transform for
+//! Faster Ideal Lattice-Based Cryptography", Algorithms 1 and 2:
+//! <https://eprint.iacr.org/2016/504.pdf>
+
+use ark_ff::{FftField, Field, One};
+
+use crate::primitives::poly::PolyConf;
+
+/// Precomputed twiddle factors for the negacyclic NTT of polynomials in the `C` configuration.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NttTables<C: PolyConf> {
+    /// Powers of `psi`, in bit-reversed order.
+    psi_rev: Vec<C::Coeff>,
+
+    /// Powers of `psi^-1`, in bit-reversed order.
+    psi_inv_rev: Vec<C::Coeff>,
+
+    /// The inverse of [`PolyConf::MAX_POLY_DEGREE`], used to scale the inverse transform.
+    n_inv: C::Coeff,
+}
+
+impl<C: PolyConf> NttTables<C> {
+    /// Returns new NTT tables for the `C` configuration.
+    ///
+    /// Returns `None` if [`PolyConf::Coeff`] doesn't have a primitive `2N`-th root of unity,
+    /// where `N` is [`PolyConf::MAX_POLY_DEGREE`].
+    pub fn new() -> Option<Self> {
+        let n = C::MAX_POLY_DEGREE;
+        debug_assert!(n.is_power_of_two());
+
+        let n_u64 = u64::try_from(n).ok()?;
+        let psi = C::Coeff::get_root_of_unity(2 * n_u64)?;
+        let psi_inv = psi.inverse()?;
+        let n_inv = C::Coeff::from(n_u64).inverse()?;
+
+        let log_n = n.trailing_zeros();
+        let powers = |base: C::Coeff| {
+            let mut powers = Vec::with_capacity(n);
+            let mut power = C::Coeff::one();
+            for _ in 0..n {
+                powers.push(power);
+                power *= base;
+            }
+
+            (0..n)
+                .map(|i| powers[bit_reverse(i, log_n)])
+                .collect::<Vec<_>>()
+        };
+
+        Some(Self {
+            psi_rev: powers(psi),
+            psi_inv_rev: powers(psi_inv),
+            n_inv,
+        })
+    }
+
+    /// Transforms `a` into the NTT domain in place, using Cooley-Tukey butterflies.
+    ///
+    /// `a` must have exactly [`PolyConf::MAX_POLY_DEGREE`] coefficients, in normal order.
+    /// The result is in bit-reversed order.
+    pub fn forward(&self, a: &mut [C::Coeff]) {
+        let n = C::MAX_POLY_DEGREE;
+        assert_eq!(
+            a.len(),
+            n,
+            "NTT inputs must have MAX_POLY_DEGREE coefficients"
+        );
+
+        let mut t = n;
+        let mut m = 1;
+        while m < n {
+            t /= 2;
+            for i in 0..m {
+                let s = self.psi_rev[m + i];
+                let (lo, hi) = a[2 * i * t..2 * (i + 1) * t].split_at_mut(t);
+
+                for (u, v) in lo.iter_mut().zip(hi.iter_mut()) {
+                    let sv = *v * s;
+                    *v = *u - sv;
+                    *u += sv;
+                }
+            }
+            m *= 2;
+        }
+    }
+
+    /// Transforms `a` out of the NTT domain in place, using Gentleman-Sande butterflies.
+    ///
+    /// `a` must have exactly [`PolyConf::MAX_POLY_DEGREE`] coefficients, in bit-reversed order.
+    /// The result is in normal order.
+    pub fn inverse(&self, a: &mut [C::Coeff]) {
+        let n = C::MAX_POLY_DEGREE;
+        assert_eq!(
+            a.len(),
+            n,
+            "NTT inputs must have MAX_POLY_DEGREE coefficients"
+        );
+
+        let mut t = 1;
+        let mut m = n;
+        while m > 1 {
+            let h = m / 2;
+            for i in 0..h {
+                let s = self.psi_inv_rev[h + i];
+                let (lo, hi) = a[2 * i * t..2 * (i + 1) * t].split_at_mut(t);
+
+                for (u, v) in lo.iter_mut().zip(hi.iter_mut()) {
+                    let diff = *u - *v;
+                    *u += *v;
+                    *v = diff * s;
+                }
+            }
+            t *= 2;
+            m = h;
+        }
+
+        for coeff in a.iter_mut() {
+            *coeff *= self.n_inv;
+        }
+    }
+}
+
+/// Returns the lowest `bits` bits of `i`, in reverse order.
+fn bit_reverse(i: usize, bits: u32) -> usize {
+    if bits == 0 {
+        return 0;
+    }
+
+    i.reverse_bits() >> (usize::BITS - bits)
+}
@@ -0,0 +1,47 @@
+//! Serde support for [`Poly`].
+//!
+//! Polynomials are serialized as their list of coefficients, from the constant term `X^0`
+//! upwards. Each coefficient is stored as its canonical little-endian bytes.
+
+use ark_ff::{BigInteger, PrimeField};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::primitives::poly::{Poly, PolyConf};
+
+impl<C: PolyConf> Serialize for Poly<C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let coeffs: Vec<Vec<u8>> = self
+            .coeffs
+            .iter()
+            .map(|coeff| coeff.into_bigint().to_bytes_le())
+            .collect();
+
+        coeffs.serialize(serializer)
+    }
+}
+
+impl<'de, C: PolyConf> Deserialize<'de> for Poly<C> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let coeffs = Vec::<Vec<u8>>::deserialize(deserializer)?;
+
+        if coeffs.len() > C::MAX_POLY_DEGREE {
+            return Err(D::Error::custom("polynomial has too many coefficients"));
+        }
+
+        let coeffs = coeffs
+            .iter()
+            .map(|bytes| {
+                let coeff = C::Coeff::from_le_bytes_mod_order(bytes);
+
+                // Reject unreduced coefficients, so every polynomial has a single encoding.
+                if coeff.into_bigint().to_bytes_le() != *bytes {
+                    return Err(D::Error::custom("coefficient is not reduced"));
+                }
+
+                Ok(coeff)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::from_coefficients_vec(coeffs))
+    }
+}
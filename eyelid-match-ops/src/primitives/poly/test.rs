@@ -1,10 +1,16 @@
 //! Tests for basic polynomial operations.
 
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub mod gen;
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod strategy;
+
 #[cfg(test)]
 pub mod mul;
 
 #[cfg(test)]
 pub mod inv;
+
+#[cfg(test)]
+pub mod backend;
@@ -0,0 +1,122 @@
+//! Tests for polynomial multiplication backend selection.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use ark_ff::Zero;
+use lazy_static::lazy_static;
+
+use crate::{
+    primitives::poly::{
+        mul_poly, naive_cyclotomic_mul, poly_mul_backend, reset_poly_mul_backend,
+        set_poly_mul_backend, test::gen::rand_poly, CpuBackend, Fq79, Poly, PolyConf,
+        PolyMulBackend,
+    },
+    FullRes, MiddleRes,
+};
+
+/// Polynomial parameters which are only used by the backend tests, so registering a backend
+/// doesn't affect other tests.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct BackendTest;
+
+impl PolyConf for BackendTest {
+    const MAX_POLY_DEGREE: usize = 16;
+
+    type Coeff = Fq79;
+
+    fn coeff_zero() -> &'static Self::Coeff {
+        &FQ79_ZERO
+    }
+}
+
+lazy_static! {
+    /// The zero coefficient as a static constant value.
+    static ref FQ79_ZERO: Fq79 = Fq79::zero();
+}
+
+/// A backend which counts its multiplications.
+#[derive(Debug, Default)]
+struct CountingBackend {
+    /// The number of multiplications.
+    muls: AtomicUsize,
+
+    /// The value returned by [`PolyMulBackend::is_available()`].
+    available: bool,
+}
+
+impl PolyMulBackend<BackendTest> for CountingBackend {
+    fn name(&self) -> &'static str {
+        "counting"
+    }
+
+    fn is_available(&self) -> bool {
+        self.available
+    }
+
+    fn cyclotomic_mul(&self, a: &Poly<BackendTest>, b: &Poly<BackendTest>) -> Poly<BackendTest> {
+        self.muls.fetch_add(1, Ordering::Relaxed);
+        naive_cyclotomic_mul(a, b)
+    }
+}
+
+/// Check that `mul_poly()` dispatches to the registered backend, and falls back to the CPU when
+/// it is unavailable or reset.
+#[test]
+fn test_backend_selection() {
+    let a: Poly<BackendTest> = rand_poly(BackendTest::MAX_POLY_DEGREE);
+    let b: Poly<BackendTest> = rand_poly(BackendTest::MAX_POLY_DEGREE);
+    let expected = naive_cyclotomic_mul(&a, &b);
+
+    // The CPU backend name depends on the enabled features.
+    let cpu = PolyMulBackend::<BackendTest>::name(&CpuBackend);
+
+    assert_eq!(poly_mul_backend::<BackendTest>().name(), cpu);
+    assert_eq!(mul_poly(&a, &b), expected);
+
+    let unavailable = Arc::new(CountingBackend::default());
+    set_poly_mul_backend::<BackendTest>(unavailable.clone());
+
+    assert_eq!(poly_mul_backend::<BackendTest>().name(), cpu);
+    assert_eq!(&a * &b, expected);
+    assert_eq!(unavailable.muls.load(Ordering::Relaxed), 0);
+
+    let available = Arc::new(CountingBackend {
+        available: true,
+        ..Default::default()
+    });
+    set_poly_mul_backend::<BackendTest>(available.clone());
+
+    assert_eq!(poly_mul_backend::<BackendTest>().name(), "counting");
+    assert_eq!(&a * &b, expected);
+    assert_eq!(available.muls.load(Ordering::Relaxed), 1);
+
+    reset_poly_mul_backend::<BackendTest>();
+
+    assert_eq!(poly_mul_backend::<BackendTest>().name(), cpu);
+    assert_eq!(mul_poly(&a, &b), expected);
+    assert_eq!(available.muls.load(Ordering::Relaxed), 1);
+}
+
+/// Check that the CPU backend name is the multiplication used for each configuration.
+#[test]
+fn test_cpu_backend_name() {
+    // Configurations without NTT tables always use Karatsuba multiplication.
+    assert_eq!(
+        PolyMulBackend::<MiddleRes>::name(&CpuBackend),
+        "cpu-karatsuba"
+    );
+    assert_eq!(
+        PolyMulBackend::<BackendTest>::name(&CpuBackend),
+        "cpu-karatsuba"
+    );
+
+    let full = if cfg!(feature = "ntt") {
+        "cpu-ntt"
+    } else {
+        "cpu-karatsuba"
+    };
+    assert_eq!(PolyMulBackend::<FullRes>::name(&CpuBackend), full);
+}
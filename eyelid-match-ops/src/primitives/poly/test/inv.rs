@@ -10,11 +10,11 @@ use crate::{
         poly::{
             modular_poly::inv::{extended_gcd, inverse},
             test::gen::rand_poly,
-            Poly, PolyConf,
+            Poly, PolyConf, PolyError,
         },
         yashe::Yashe,
     },
-    MiddleRes, TestRes,
+    Error, MiddleRes, TestRes,
 };
 
 fn inverse_test_helper<C: PolyConf>(f: &Poly<C>) {
@@ -103,7 +103,11 @@ fn test_edge_cases() {
 
     // Inverse of zero is error
     out = inverse(&zero_poly);
-    assert!(out.is_err());
+    assert_eq!(out, Err(PolyError::ZeroPolynomial));
+
+    // The error converts into the library-wide error type
+    let err: Error = out.expect_err("just checked").into();
+    assert_eq!(err.to_string(), "can't invert the zero polynomial");
 
     // Inverse of one is one
     let one_poly: Poly<MiddleRes> = Poly::one();
@@ -8,9 +8,11 @@ use ark_poly::Polynomial;
 use crate::{
     primitives::poly::{
         flat_karatsuba_mul, naive_cyclotomic_mul, new_unreduced_poly_modulus_slow,
-        rec_karatsuba_mul, test::gen::rand_poly, Poly, PolyConf,
+        ntt_cyclotomic_mul, rec_karatsuba_mul,
+        test::{gen::rand_poly, strategy::poly},
+        Poly, PolyConf,
     },
-    MiddleRes, TestRes,
+    FullRes, MiddleRes, TestRes,
 };
 
 /// Test cyclotomic multiplication of a random polynomial by `X^{[C::MAX_POLY_DEGREE] - 1}`.
@@ -19,10 +21,12 @@ fn test_cyclotomic_mul_rand_xnm1() {
     check_cyclotomic_mul_rand_xnm1::<TestRes, _>(naive_cyclotomic_mul);
     check_cyclotomic_mul_rand_xnm1::<TestRes, _>(rec_karatsuba_mul);
     check_cyclotomic_mul_rand_xnm1::<TestRes, _>(flat_karatsuba_mul);
+    check_cyclotomic_mul_rand_xnm1::<TestRes, _>(ntt_cyclotomic_mul);
 
     check_cyclotomic_mul_rand_xnm1::<MiddleRes, _>(naive_cyclotomic_mul);
     check_cyclotomic_mul_rand_xnm1::<MiddleRes, _>(rec_karatsuba_mul);
     check_cyclotomic_mul_rand_xnm1::<MiddleRes, _>(flat_karatsuba_mul);
+    check_cyclotomic_mul_rand_xnm1::<MiddleRes, _>(ntt_cyclotomic_mul);
 }
 
 /// Check `mul_fn` correctly implements cyclotomic multiplication of a random polynomial by `X^{[C::MAX_POLY_DEGREE] - 1}`.
@@ -72,10 +76,12 @@ fn test_cyclotomic_mul_max_degree() {
     check_cyclotomic_mul_max_degree::<TestRes, _>(naive_cyclotomic_mul);
     check_cyclotomic_mul_max_degree::<TestRes, _>(rec_karatsuba_mul);
     check_cyclotomic_mul_max_degree::<TestRes, _>(flat_karatsuba_mul);
+    check_cyclotomic_mul_max_degree::<TestRes, _>(ntt_cyclotomic_mul);
 
     check_cyclotomic_mul_max_degree::<MiddleRes, _>(naive_cyclotomic_mul);
     check_cyclotomic_mul_max_degree::<MiddleRes, _>(rec_karatsuba_mul);
     check_cyclotomic_mul_max_degree::<MiddleRes, _>(flat_karatsuba_mul);
+    check_cyclotomic_mul_max_degree::<MiddleRes, _>(ntt_cyclotomic_mul);
 }
 
 /// Check `mul_fn` correctly implements cyclotomic multiplication that results in `X^[C::MAX_POLY_DEGREE]`.
@@ -221,3 +227,69 @@ fn test_karatsuba_mul_rand_consistent() {
     assert_eq!(expected, rec_res);
     assert_eq!(expected, flat_res);
 }
+
+/// Test the NTT tables are only available for fields with large enough roots of unity, and that
+/// the transforms are inverses of each other.
+#[test]
+fn test_ntt_tables() {
+    assert!(MiddleRes::ntt_tables().is_none());
+
+    let tables = FullRes::ntt_tables().expect("Fq79 supports the full resolution NTT");
+
+    let p: Poly<FullRes> = rand_poly(FullRes::MAX_POLY_DEGREE - 1);
+    let mut coeffs = p.coeffs.clone();
+    coeffs.resize(FullRes::MAX_POLY_DEGREE, Zero::zero());
+
+    tables.forward(&mut coeffs);
+    assert_ne!(Poly::from_coefficients_slice(&coeffs), p);
+
+    tables.inverse(&mut coeffs);
+    assert_eq!(Poly::from_coefficients_vec(coeffs), p);
+}
+
+/// Test NTT multiplication of two random full resolution polynomials produces the same result as
+/// naive multiplication.
+#[test]
+fn test_ntt_mul_rand_consistent() {
+    let p1: Poly<FullRes> = rand_poly(FullRes::MAX_POLY_DEGREE - 1);
+    let p2: Poly<FullRes> = rand_poly(FullRes::MAX_POLY_DEGREE - 1);
+
+    let expected = naive_cyclotomic_mul(&p1, &p2);
+    let ntt_res = ntt_cyclotomic_mul(&p1, &p2);
+    assert!(ntt_res.degree() < FullRes::MAX_POLY_DEGREE);
+
+    assert_eq!(expected, ntt_res);
+
+    // Multiplication by X^N = -1, which isn't reduced before multiplying.
+    let mut x_max: Poly<FullRes> = Poly::zero();
+    x_max[FullRes::MAX_POLY_DEGREE] = One::one();
+    assert_eq!(ntt_cyclotomic_mul(&p1, &x_max), -p1);
+}
+
+proptest::proptest! {
+    #![proptest_config(proptest::test_runner::Config::with_cases(32))]
+
+    /// Check that the fast multiplication implementations agree with naive multiplication on
+    /// arbitrary polynomials.
+    #[test]
+    fn karatsuba_matches_naive(
+        a in poly::<MiddleRes>(),
+        b in poly::<MiddleRes>(),
+    ) {
+        let expected = naive_cyclotomic_mul(&a, &b);
+
+        proptest::prop_assert_eq!(rec_karatsuba_mul(&a, &b), expected.clone());
+        proptest::prop_assert_eq!(flat_karatsuba_mul(&a, &b), expected.clone());
+        proptest::prop_assert_eq!(ntt_cyclotomic_mul(&a, &b), expected);
+    }
+
+    /// Check that NTT multiplication agrees with Karatsuba multiplication on arbitrary full
+    /// resolution polynomials.
+    #[test]
+    fn ntt_matches_karatsuba(
+        a in poly::<FullRes>(),
+        b in poly::<FullRes>(),
+    ) {
+        proptest::prop_assert_eq!(ntt_cyclotomic_mul(&a, &b), rec_karatsuba_mul(&a, &b));
+    }
+}
@@ -0,0 +1,13 @@
+//! Proptest strategies for polynomials.
+
+use proptest::{collection::vec, prelude::*};
+
+use crate::primitives::poly::{Poly, PolyConf};
+
+/// Returns a strategy for reduced polynomials in the `C` configuration, with arbitrary degrees
+/// and coefficients. Polynomials shrink towards the zero polynomial.
+pub fn poly<C: PolyConf>() -> impl Strategy<Value = Poly<C>> {
+    vec(any::<u128>(), 0..=C::MAX_POLY_DEGREE).prop_map(|coeffs| {
+        Poly::from_coefficients_vec(coeffs.into_iter().map(C::Coeff::from).collect())
+    })
+}
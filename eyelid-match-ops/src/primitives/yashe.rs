@@ -1,28 +1,37 @@
 //! Implementation of YASHE cryptosystem
 //! `<https://eprint.iacr.org/2013/075.pdf>`
 
-use std::marker::PhantomData;
+use std::{fmt, marker::PhantomData};
 
-use ark_ff::{One, UniformRand};
+use ark_ff::{One, UniformRand, Zero};
+use itertools::Itertools;
 use num_bigint::{BigInt, BigUint, Sign};
 use rand::{
     distributions::uniform::{SampleRange, SampleUniform},
-    rngs::ThreadRng,
     Rng,
 };
 use rand_distr::{Distribution, Normal};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
-use crate::{primitives::poly::Poly, PolyConf};
+use crate::{
+    primitives::{entropy::EntropySource, poly::Poly},
+    PolyConf,
+};
 
 pub use conf::YasheConf;
+pub use shared::SharedContext;
 
 pub mod conf;
+pub mod noise;
+pub mod shared;
 
-#[cfg(any(test, feature = "benchmark"))]
+#[cfg(any(test, feature = "test-util"))]
 pub mod test;
 
 /// Yashe scheme
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct Yashe<C: YasheConf>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
@@ -32,7 +41,11 @@ where
 }
 
 /// Private key struct
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// The key is erased from memory when it is dropped, and its [`Debug`] output is redacted.
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct PrivateKey<C: YasheConf>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
@@ -45,8 +58,42 @@ where
     pub priv_key: Poly<C>,
 }
 
+impl<C: YasheConf> fmt::Debug for PrivateKey<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrivateKey").finish_non_exhaustive()
+    }
+}
+
+impl<C: YasheConf> Zeroize for PrivateKey<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn zeroize(&mut self) {
+        self.f.zeroize();
+        self.priv_key_inv.zeroize();
+        self.priv_key.zeroize();
+    }
+}
+
+impl<C: YasheConf> Drop for PrivateKey<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<C: YasheConf> ZeroizeOnDrop for PrivateKey<C> where C::Coeff: From<u128> + From<u64> + From<i64>
+{}
+
 /// Public key struct
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct PublicKey<C: YasheConf>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
@@ -55,8 +102,24 @@ where
     pub h: Poly<C>,
 }
 
+/// Key switching key struct, which converts the product of ciphertexts encrypted under two
+/// different keys into a ciphertext that can be decrypted by one of those keys.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
+pub struct KeySwitchKey<C: YasheConf>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Encryptions of `wⁱ * priv_key_from`, one for each decomposition digit, where `w` is
+    /// `2^KEY_SWITCH_BASE_BITS`.
+    pub keys: Vec<Poly<C>>,
+}
+
 /// Message struct
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct Message<C: YasheConf>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
@@ -67,6 +130,8 @@ where
 
 /// Ciphertext struct
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct Ciphertext<C: YasheConf>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
@@ -75,6 +140,30 @@ where
     pub c: Poly<C>,
 }
 
+/// Constants used to decrypt every coefficient, converted to big integers once per decryption.
+struct DecryptConstants {
+    /// The plaintext modulus `T`.
+    t: BigUint,
+    /// The ciphertext modulus `Q`.
+    modulus: BigUint,
+    /// `(Q - 1)/2`, used for rounding.
+    modulus_minus_one_div_two: BigUint,
+}
+
+impl DecryptConstants {
+    /// Returns the decryption constants for `C`.
+    fn new<C: YasheConf>() -> Self
+    where
+        C::Coeff: From<u128> + From<u64> + From<i64>,
+    {
+        Self {
+            t: C::t_as_big_uint(),
+            modulus: C::modulus_as_big_uint(),
+            modulus_minus_one_div_two: C::modulus_minus_one_div_two_as_big_uint(),
+        }
+    }
+}
+
 impl<C: YasheConf> Yashe<C>
 where
     C::Coeff: From<u128> + From<u64> + From<i64>,
@@ -85,9 +174,9 @@ where
     }
 
     /// Generate the private key
-    pub fn generate_private_key(&self, rng: &mut ThreadRng) -> PrivateKey<C> {
+    pub fn generate_private_key<E: EntropySource + ?Sized>(&self, rng: &mut E) -> PrivateKey<C> {
         loop {
-            let f = self.sample_key(rng);
+            let mut f = self.sample_key(rng);
 
             // priv_key = f * T + 1
             let mut priv_key = f.clone();
@@ -106,13 +195,17 @@ where
                     priv_key,
                 };
             }
+
+            // Erase rejected keys before sampling a new one.
+            f.zeroize();
+            priv_key.zeroize();
         }
     }
 
     /// Generate the public key
-    pub fn generate_public_key(
+    pub fn generate_public_key<E: EntropySource + ?Sized>(
         &self,
-        rng: &mut ThreadRng,
+        rng: &mut E,
         private_key: &PrivateKey<C>,
     ) -> PublicKey<C> {
         let mut h = self.sample_key(rng);
@@ -125,18 +218,94 @@ where
     }
 
     /// Generate the key pair
-    pub fn keygen(&self, rng: &mut ThreadRng) -> (PrivateKey<C>, PublicKey<C>) {
+    pub fn keygen<E: EntropySource + ?Sized>(&self, rng: &mut E) -> (PrivateKey<C>, PublicKey<C>) {
         let priv_key = self.generate_private_key(rng);
         let pub_key = self.generate_public_key(rng, &priv_key);
         (priv_key, pub_key)
     }
 
+    /// Generate a key switching key, which converts the product of a ciphertext encrypted under
+    /// `from` and a ciphertext encrypted under `to`, into a ciphertext that can be decrypted by
+    /// the private key of `to`.
+    ///
+    /// Generating the key only requires the `from` private key and the `to` public key. But the
+    /// key switching key can be decrypted using the `to` private key, revealing the `from` private
+    /// key. So it should only be sent to the party that owns the `to` private key.
+    pub fn generate_key_switch_key<E: EntropySource + ?Sized>(
+        &self,
+        rng: &mut E,
+        from: &PrivateKey<C>,
+        to: &PublicKey<C>,
+    ) -> KeySwitchKey<C> {
+        // The base of the digit decomposition.
+        let w = C::Coeff::from(1_u128 << C::KEY_SWITCH_BASE_BITS);
+
+        // wⁱ * priv_key_from, starting with i = 0
+        let mut key_power = Zeroizing::new(from.priv_key.clone());
+
+        let keys = (0..C::key_switch_digits())
+            .map(|_| {
+                // Hide each key power using an encryption of zero: s * h + e
+                let s = self.sample_err(rng);
+                let e = self.sample_err(rng);
+                let key = s * &to.h + e + &*key_power;
+
+                *key_power *= w;
+
+                key
+            })
+            .collect();
+
+        KeySwitchKey { keys }
+    }
+
+    /// Convert the product of a ciphertext encrypted under the `from` key and a ciphertext
+    /// encrypted under the `to` key, into a ciphertext that can be decrypted using
+    /// [`Yashe::decrypt()`] and the `to` private key.
+    ///
+    /// The key switching key is generated by [`Yashe::generate_key_switch_key()`].
+    pub fn key_switch(&self, c: Ciphertext<C>, key_switch_key: &KeySwitchKey<C>) -> Ciphertext<C> {
+        let digits = Self::decompose(&c.c);
+        let mut res = Poly::zero();
+
+        // ∑ Dᵢ(c) * keyᵢ
+        for (digit, key) in digits.iter().zip_eq(key_switch_key.keys.iter()) {
+            res += digit * key;
+        }
+
+        Ciphertext { c: res }
+    }
+
+    /// Decompose the coefficients of `poly` into digits of
+    /// [`KEY_SWITCH_BASE_BITS`](YasheConf::KEY_SWITCH_BASE_BITS) bits, from least to most
+    /// significant. Returns one polynomial for each digit.
+    fn decompose(poly: &Poly<C>) -> Vec<Poly<C>> {
+        let digit_mask = (1_u128 << C::KEY_SWITCH_BASE_BITS) - 1;
+        let mut digits =
+            vec![Poly::non_canonical_zeroes(poly.coeffs.len()); C::key_switch_digits()];
+
+        for (i, coeff) in poly.coeffs.iter().enumerate() {
+            let mut coeff = C::coeff_as_u128(*coeff);
+
+            for digit in digits.iter_mut() {
+                digit[i] = C::Coeff::from(coeff & digit_mask);
+                coeff >>= C::KEY_SWITCH_BASE_BITS;
+            }
+        }
+
+        for digit in digits.iter_mut() {
+            digit.truncate_to_canonical_form();
+        }
+
+        digits
+    }
+
     /// Encrypt a message m encoded in the polynomial ring
-    pub fn encrypt(
+    pub fn encrypt<E: EntropySource + ?Sized>(
         &self,
         mut m: Message<C>,
         public_key: &PublicKey<C>,
-        rng: &mut ThreadRng,
+        rng: &mut E,
     ) -> Ciphertext<C> {
         // Create the ciphertext by sampling error polynomials and applying them to the public key.
         let s = self.sample_err(rng);
@@ -164,15 +333,44 @@ where
     /// Decrypt a multiplication
     pub fn decrypt_mul(&self, c: Ciphertext<C>, private_key: &PrivateKey<C>) -> Message<C> {
         // Multiply the ciphertext by the private key polynomial squared.
-        let modified_private_key = &private_key.priv_key * &private_key.priv_key;
+        let modified_private_key = Zeroizing::new(&private_key.priv_key * &private_key.priv_key);
 
         self.decrypt_helper(c, &modified_private_key)
     }
 
+    /// Decrypt a batch of multiplications, which all use the same private key.
+    ///
+    /// This is faster than calling [`Yashe::decrypt_mul()`] for each ciphertext, because the
+    /// private key is only squared once.
+    pub fn decrypt_mul_batch(
+        &self,
+        cs: &[Ciphertext<C>],
+        private_key: &PrivateKey<C>,
+    ) -> Vec<Message<C>> {
+        // Multiply the ciphertext by the private key polynomial squared.
+        let modified_private_key = Zeroizing::new(&private_key.priv_key * &private_key.priv_key);
+        let constants = DecryptConstants::new::<C>();
+
+        cs.iter()
+            .map(|c| self.decrypt_with_constants(c.clone(), &modified_private_key, &constants))
+            .collect()
+    }
+
     /// Decrypt a ciphertext or multiplication, given the `modified_private_key`:
     /// - ciphertexts use the private key itself,
     /// - multiplications use the private key squared.
     fn decrypt_helper(&self, c: Ciphertext<C>, modified_private_key: &Poly<C>) -> Message<C> {
+        self.decrypt_with_constants(c, modified_private_key, &DecryptConstants::new::<C>())
+    }
+
+    /// Decrypt a ciphertext or multiplication, given the `modified_private_key`, and the
+    /// `constants` used for every coefficient.
+    fn decrypt_with_constants(
+        &self,
+        c: Ciphertext<C>,
+        modified_private_key: &Poly<C>,
+        constants: &DecryptConstants,
+    ) -> Message<C> {
         // Multiply the ciphertext by the relevant private key polynomial.
         let mut res = c.c * modified_private_key;
 
@@ -182,13 +380,13 @@ where
             // Convert coefficient to a big integer
             let mut coeff_res: BigUint = (*coeff).into();
             // Multiply by T
-            coeff_res *= C::t_as_big_uint();
+            coeff_res *= &constants.t;
             // Add (Q - 1)/2 to implement rounding rather than truncation
-            coeff_res += C::modulus_minus_one_div_two_as_big_uint();
+            coeff_res += &constants.modulus_minus_one_div_two;
             // Divide by Q
-            coeff_res /= C::modulus_as_big_uint();
+            coeff_res /= &constants.modulus;
             // Modulo T
-            coeff_res %= C::t_as_big_uint();
+            coeff_res %= &constants.t;
             // And update the coefficient
             *coeff = coeff_res.into();
         });
@@ -197,20 +395,20 @@ where
     }
 
     /// Sample a polynomial with small random coefficients using a gaussian distribution.
-    pub fn sample_err(&self, rng: &mut ThreadRng) -> Poly<C> {
+    pub fn sample_err<E: EntropySource + ?Sized>(&self, rng: &mut E) -> Poly<C> {
         self.sample_gaussian(C::ERROR_DELTA, rng)
     }
 
     /// Sample a polynomial with small random coefficients using a gaussian distribution.
     /// TODO: this function seems to be returning too few non-zero elements
-    pub fn sample_key(&self, rng: &mut ThreadRng) -> Poly<C> {
+    pub fn sample_key<E: EntropySource + ?Sized>(&self, rng: &mut E) -> Poly<C> {
         // standard deviation whose output coefficients are -1, 0, 1 with high probability
         self.sample_gaussian(C::KEY_DELTA, rng)
     }
 
     /// Sample a polynomial with small random coefficients using a gaussian distribution.
     #[allow(clippy::cast_possible_truncation)]
-    pub fn sample_gaussian(&self, delta: f64, rng: &mut ThreadRng) -> Poly<C> {
+    pub fn sample_gaussian<E: EntropySource + ?Sized>(&self, delta: f64, rng: &mut E) -> Poly<C> {
         let mut res = Poly::non_canonical_zeroes(C::MAX_POLY_DEGREE);
         Poly::coeffs_modify_include_zero(&mut res, |coeff: &mut <C as PolyConf>::Coeff| {
             // TODO SECURITY: check that the generated integers are secure:
@@ -232,7 +430,7 @@ where
     }
 
     /// Sample a polynomial with unlimited size random coefficients using a uniform distribution.
-    pub fn sample_uniform_coeff(&self, mut rng: &mut ThreadRng) -> Poly<C> {
+    pub fn sample_uniform_coeff<E: EntropySource + ?Sized>(&self, mut rng: &mut E) -> Poly<C> {
         let mut res = Poly::non_canonical_zeroes(C::MAX_POLY_DEGREE);
         Poly::coeffs_modify_include_zero(&mut res, |coeff: &mut <C as PolyConf>::Coeff| {
             let coeff_rand = C::Coeff::rand(&mut rng);
@@ -243,7 +441,11 @@ where
     }
 
     /// Sample a polynomial with random coefficients in `range` using a uniform distribution.
-    pub fn sample_uniform_range<T, R>(&self, range: R, rng: &mut ThreadRng) -> Poly<C>
+    pub fn sample_uniform_range<T, R, E: EntropySource + ?Sized>(
+        &self,
+        range: R,
+        rng: &mut E,
+    ) -> Poly<C>
     where
         T: SampleUniform,
         R: SampleRange<T> + Clone,
@@ -261,13 +463,13 @@ where
     // TODO: move test-only methods to a test module (removing unused production code improves performance)
 
     /// Sample a polynomial with random binnary coefficients, i.e. 0, 1
-    pub fn sample_binary_message(&self, rng: &mut ThreadRng) -> Message<C> {
+    pub fn sample_binary_message<E: EntropySource + ?Sized>(&self, rng: &mut E) -> Message<C> {
         let m = self.sample_uniform_range(0..=1_u64, rng);
         Message { m }
     }
 
     /// Sample a polynomial with random ternary coefficients, i.e. -1, 0, 1, such that -1 is represented as C::T - 1
-    pub fn sample_ternary_message(&self, rng: &mut ThreadRng) -> Message<C> {
+    pub fn sample_ternary_message<E: EntropySource + ?Sized>(&self, rng: &mut E) -> Message<C> {
         let mut m = self.sample_uniform_range(0..=2_u64, rng);
 
         for i in 0..C::MAX_POLY_DEGREE {
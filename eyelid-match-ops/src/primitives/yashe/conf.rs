@@ -50,6 +50,10 @@ where
     /// The default parameters are as recommended in the paper.
     const ERROR_DELTA: f64 = 1.0;
 
+    /// The number of bits in each digit of the key switching decomposition.
+    /// Smaller digits produce less noise, but need more key switching polynomials.
+    const KEY_SWITCH_BASE_BITS: u32 = 8;
+
     /// A convenience method to convert [`T`](Self::T) to the [`Coeff`](PolyConf::Coeff) type.
     fn t_as_coeff() -> Self::Coeff {
         debug_assert!(check_constraints::<Self>());
@@ -86,6 +90,21 @@ where
         BigUint::from(log_max_poly_degree)
     }
 
+    /// The number of digits needed to decompose a [`Coeff`](PolyConf::Coeff) using
+    /// [`KEY_SWITCH_BASE_BITS`](Self::KEY_SWITCH_BASE_BITS) bits per digit.
+    fn key_switch_digits() -> usize {
+        Self::Coeff::MODULUS_BIT_SIZE.div_ceil(Self::KEY_SWITCH_BASE_BITS) as usize
+    }
+
+    /// The number of bits of noise a ciphertext can hold before decryption is incorrect.
+    /// Decryption is correct while the noise is less than `Q / 2T`.
+    fn noise_budget_bits() -> u64 {
+        // floor(log2(Q)) - 1 - log2(T), because T is a power of two.
+        Self::modulus_as_big_uint()
+            .bits()
+            .saturating_sub(2 + u64::from(Self::T.ilog2()))
+    }
+
     /// A convenience method to convert a [`Coeff`](PolyConf::Coeff) to `u128`.
     /// TODO: move this method to a trait implemented on `Coeff` instead.
     /// TODO: take a reference?
@@ -309,7 +328,10 @@ where
         // Check the cast above remains valid.
         D::T >= (1 << f64::MANTISSA_DIGITS) ||
         // The error must be small enough to allow successful message retrieval, with three sigma probability.
-        D::ERROR_DELTA > D::KEY_DELTA / 3.0
+        D::ERROR_DELTA > D::KEY_DELTA / 3.0 ||
+        // Key switching digits must fit in a u128 coefficient conversion.
+        D::KEY_SWITCH_BASE_BITS == 0 ||
+        D::KEY_SWITCH_BASE_BITS >= u128::BITS
     ) {
         panic!("YasheConf parameters are invalid")
     };
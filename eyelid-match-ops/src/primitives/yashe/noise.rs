@@ -0,0 +1,169 @@
+//! Noise tracking for YASHE ciphertexts.
+//!
+//! Each homomorphic operation increases the noise in a ciphertext. Once the noise is larger than
+//! the noise budget, decryption silently produces the wrong message. A [`NoiseTracker`] records
+//! the noise estimated at each stage of the pipeline, so parameter regressions are caught before
+//! they produce wrong results.
+
+use num_bigint::BigUint;
+use zeroize::Zeroizing;
+
+use crate::primitives::{
+    poly::Poly,
+    yashe::{Ciphertext, PrivateKey, Yashe, YasheConf},
+};
+
+/// The stage of the encrypted pipeline where noise was estimated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NoiseStage {
+    /// A freshly encrypted ciphertext.
+    Encrypt,
+    /// The product of two ciphertexts.
+    Multiply,
+    /// The product of two ciphertexts, after key switching.
+    KeySwitch,
+}
+
+/// What a [`NoiseTracker`] does when the remaining noise budget is below its threshold.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum NoiseAction {
+    /// Record a warning, which can be checked using [`NoiseTracker::warnings()`].
+    #[default]
+    Warn,
+    /// Record a warning, and return it as an error.
+    Error,
+}
+
+/// The noise estimated for a single ciphertext.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NoiseRecord {
+    /// The stage where the noise was estimated.
+    pub stage: NoiseStage,
+    /// The number of bits in the largest noise coefficient.
+    pub noise_bits: u64,
+    /// The number of bits of noise budget left after this stage.
+    /// Zero or negative values mean decryption is likely to be incorrect.
+    pub remaining_bits: i64,
+}
+
+/// Records the estimated noise at each stage of the encrypted pipeline, and warns or errors when
+/// the noise budget is nearly exhausted.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NoiseTracker {
+    /// The minimum number of remaining noise budget bits, below which the tracker warns or errors.
+    pub min_remaining_bits: i64,
+    /// What to do when the remaining budget is below `min_remaining_bits`.
+    pub action: NoiseAction,
+    /// All the noise records, in the order they were recorded.
+    records: Vec<NoiseRecord>,
+}
+
+impl NoiseTracker {
+    /// Returns a new tracker, which takes `action` when fewer than `min_remaining_bits` of noise
+    /// budget are left.
+    pub fn new(min_remaining_bits: i64, action: NoiseAction) -> Self {
+        Self {
+            min_remaining_bits,
+            action,
+            records: Vec::new(),
+        }
+    }
+
+    /// Records `noise_bits` of noise at `stage`, out of a total budget of `budget_bits`.
+    ///
+    /// Returns the record as an error if the remaining budget is below the threshold, and the
+    /// tracker's action is [`NoiseAction::Error`].
+    pub fn record(
+        &mut self,
+        stage: NoiseStage,
+        noise_bits: u64,
+        budget_bits: u64,
+    ) -> Result<(), NoiseRecord> {
+        // Noise and budget bits are bounded by the modulus size, so these casts can't wrap.
+        #[allow(clippy::cast_possible_wrap)]
+        let remaining_bits = budget_bits as i64 - noise_bits as i64;
+
+        let record = NoiseRecord {
+            stage,
+            noise_bits,
+            remaining_bits,
+        };
+        self.records.push(record);
+
+        if self.action == NoiseAction::Error && remaining_bits < self.min_remaining_bits {
+            return Err(record);
+        }
+
+        Ok(())
+    }
+
+    /// Returns all the noise records, in the order they were recorded.
+    pub fn records(&self) -> &[NoiseRecord] {
+        &self.records
+    }
+
+    /// Returns the records where the remaining budget was below the threshold.
+    pub fn warnings(&self) -> impl Iterator<Item = &NoiseRecord> {
+        self.records
+            .iter()
+            .filter(|record| record.remaining_bits < self.min_remaining_bits)
+    }
+
+    /// Returns the record with the least remaining budget, if any.
+    pub fn worst(&self) -> Option<&NoiseRecord> {
+        self.records
+            .iter()
+            .min_by_key(|record| record.remaining_bits)
+    }
+}
+
+impl<C: YasheConf> Yashe<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Returns the number of bits of noise in a ciphertext, estimated using the private key.
+    pub fn noise_bits(&self, c: &Ciphertext<C>, private_key: &PrivateKey<C>) -> u64 {
+        self.noise_bits_helper(c, &private_key.priv_key)
+    }
+
+    /// Returns the number of bits of noise in a multiplication, estimated using the private key.
+    pub fn mul_noise_bits(&self, c: &Ciphertext<C>, private_key: &PrivateKey<C>) -> u64 {
+        // Multiplications are decrypted using the private key polynomial squared.
+        let modified_private_key = Zeroizing::new(&private_key.priv_key * &private_key.priv_key);
+
+        self.noise_bits_helper(c, &modified_private_key)
+    }
+
+    /// Returns the number of bits of noise in a ciphertext or multiplication, given the
+    /// `modified_private_key` used to decrypt it.
+    ///
+    /// This is an estimate: once the noise exceeds the budget, it wraps around and decrypts to
+    /// a different message. But wrapped noise is close to uniform, so it is very likely to be
+    /// reported as nearly exhausting the budget.
+    fn noise_bits_helper(&self, c: &Ciphertext<C>, modified_private_key: &Poly<C>) -> u64 {
+        let res = &c.c * modified_private_key;
+
+        let modulus = C::modulus_as_big_uint();
+        let modulus_div_two = C::modulus_minus_one_div_two_as_big_uint();
+
+        // Decryption multiplies by T/Q and rounds, so the noise is the distance between
+        // `coeff * T` and the nearest multiple of Q. This is scaled up by T.
+        let max_noise = res
+            .coeffs
+            .iter()
+            .map(|coeff| {
+                let coeff: BigUint = (*coeff).into();
+                let noise = coeff * C::t_as_big_uint() % &modulus;
+
+                if noise > modulus_div_two {
+                    &modulus - noise
+                } else {
+                    noise
+                }
+            })
+            .max()
+            .unwrap_or_default();
+
+        max_noise.bits().saturating_sub(u64::from(C::T.ilog2()))
+    }
+}
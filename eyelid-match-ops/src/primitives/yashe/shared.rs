@@ -0,0 +1,150 @@
+//! A YASHE context and key pair, which can be shared between threads.
+
+use std::{
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+use zeroize::Zeroizing;
+
+use crate::primitives::{
+    entropy::EntropySource,
+    poly::Poly,
+    yashe::{Ciphertext, DecryptConstants, Message, PrivateKey, PublicKey, Yashe, YasheConf},
+};
+
+/// A YASHE context and key pair, with values derived from the keys cached after their first use.
+///
+/// Clones share the same keys and caches, so one context can be used by every request handler in
+/// a multi-threaded server, without copying the keys. Matchers are built from a shared context
+/// using
+/// [`EncryptedMatcherBuilder::shared_context()`](crate::encrypted::EncryptedMatcherBuilder::shared_context).
+///
+/// The modulus polynomials are already cached for each configuration, so they are shared by
+/// every context.
+#[derive(Clone)]
+pub struct SharedContext<C: YasheConf>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The keys and caches shared by every clone.
+    inner: Arc<SharedInner<C>>,
+}
+
+/// The contents of a [`SharedContext`].
+struct SharedInner<C: YasheConf>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// The encryption context.
+    ctx: Yashe<C>,
+    /// The private key, used to decrypt match results.
+    private_key: PrivateKey<C>,
+    /// The public key, used to encrypt codes and queries.
+    public_key: PublicKey<C>,
+    /// The private key squared, used to decrypt multiplications.
+    /// Erased from memory when the last clone is dropped.
+    private_key_squared: OnceLock<Zeroizing<Poly<C>>>,
+    /// The big integer constants used for decryption.
+    decrypt_constants: OnceLock<DecryptConstants>,
+}
+
+// Request handlers run on different threads.
+assert_impl_all!(SharedContext<crate::FullRes>: Send, Sync);
+
+impl<C: YasheConf> SharedContext<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    /// Returns a shared context using `ctx` and the supplied keys.
+    pub fn new(ctx: Yashe<C>, private_key: PrivateKey<C>, public_key: PublicKey<C>) -> Self {
+        Self {
+            inner: Arc::new(SharedInner {
+                ctx,
+                private_key,
+                public_key,
+                private_key_squared: OnceLock::new(),
+                decrypt_constants: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Returns a shared context using `ctx` and newly generated keys.
+    pub fn generate<E: EntropySource + ?Sized>(ctx: Yashe<C>, rng: &mut E) -> Self {
+        let (private_key, public_key) = ctx.keygen(rng);
+
+        Self::new(ctx, private_key, public_key)
+    }
+
+    /// Returns the encryption context.
+    pub fn ctx(&self) -> Yashe<C> {
+        self.inner.ctx
+    }
+
+    /// Returns the private key.
+    pub fn private_key(&self) -> &PrivateKey<C> {
+        &self.inner.private_key
+    }
+
+    /// Returns the public key.
+    pub fn public_key(&self) -> &PublicKey<C> {
+        &self.inner.public_key
+    }
+
+    /// Decrypts a multiplication, like [`Yashe::decrypt_mul()`], but using the cached private
+    /// key square.
+    pub fn decrypt_mul(&self, c: Ciphertext<C>) -> Message<C> {
+        self.inner.ctx.decrypt_with_constants(
+            c,
+            self.private_key_squared(),
+            self.decrypt_constants(),
+        )
+    }
+
+    /// Decrypts a batch of multiplications, like [`Yashe::decrypt_mul_batch()`], but using the
+    /// cached private key square.
+    pub fn decrypt_mul_batch(&self, cs: &[Ciphertext<C>]) -> Vec<Message<C>> {
+        cs.iter().map(|c| self.decrypt_mul(c.clone())).collect()
+    }
+
+    /// Returns true if `self` and `other` share the same keys and caches.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Returns the private key squared, calculating it if this is the first use.
+    fn private_key_squared(&self) -> &Poly<C> {
+        self.inner.private_key_squared.get_or_init(|| {
+            let private_key = &self.inner.private_key.priv_key;
+
+            Zeroizing::new(private_key * private_key)
+        })
+    }
+
+    /// Returns the decryption constants, calculating them if this is the first use.
+    fn decrypt_constants(&self) -> &DecryptConstants {
+        self.inner
+            .decrypt_constants
+            .get_or_init(DecryptConstants::new::<C>)
+    }
+
+    /// Returns true if the private key square has been calculated.
+    #[cfg(test)]
+    pub(crate) fn is_cached(&self) -> bool {
+        self.inner.private_key_squared.get().is_some()
+    }
+}
+
+impl<C: YasheConf> fmt::Debug for SharedContext<C>
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't reveal the private key square.
+        f.debug_struct("SharedContext")
+            .field("ctx", &self.inner.ctx)
+            .field("private_key", &self.inner.private_key)
+            .field("public_key", &self.inner.public_key)
+            .finish_non_exhaustive()
+    }
+}
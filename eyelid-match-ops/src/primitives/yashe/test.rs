@@ -18,7 +18,13 @@ pub mod hom;
 pub mod keygen;
 
 #[cfg(test)]
-pub mod hamming;
+pub mod keyswitch;
+
+#[cfg(test)]
+pub mod noise;
+
+#[cfg(test)]
+pub mod shared;
 
 // Test-only data generation methods.
 impl<C: YasheConf> Yashe<C>
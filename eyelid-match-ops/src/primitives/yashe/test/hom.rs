@@ -103,6 +103,37 @@ where
     );
 }
 
+// Batch multiplication decryption must give the same results as individual decryptions
+fn homomorphic_multiplication_helper_batch<C: YasheConf>()
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<C> = Yashe::new();
+
+    let (private_key, public_key) = ctx.keygen(&mut rng);
+    let mut messages = Vec::new();
+    let mut ciphertexts = Vec::new();
+
+    for _ in 0..3 {
+        let m1 = ctx.sample_message(&mut rng);
+        let m2 = ctx.sample_message(&mut rng);
+        let c1 = ctx.encrypt(m1.clone(), &public_key, &mut rng);
+        let c2 = ctx.encrypt(m2.clone(), &public_key, &mut rng);
+        messages.push(ctx.plaintext_mul(m1, m2));
+        ciphertexts.push(ctx.ciphertext_mul(c1, c2));
+    }
+
+    let m_dec = ctx.decrypt_mul_batch(&ciphertexts, &private_key);
+
+    assert_eq!(
+        messages,
+        m_dec,
+        "batch multiplication test failed for {}",
+        type_name::<C>()
+    );
+}
+
 // TODO: get these tests working with TestRes
 
 #[test]
@@ -130,3 +161,10 @@ fn homomorphic_positive_multiplication_test() {
     homomorphic_multiplication_helper_positive::<LargeRes>();
     homomorphic_multiplication_helper_positive_ternary::<LargeRes>();
 }
+
+#[test]
+fn homomorphic_batch_multiplication_test() {
+    homomorphic_multiplication_helper_batch::<MiddleRes>();
+    homomorphic_multiplication_helper_batch::<FullRes>();
+    homomorphic_multiplication_helper_batch::<LargeRes>();
+}
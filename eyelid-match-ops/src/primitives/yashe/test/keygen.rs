@@ -2,15 +2,19 @@
 
 use std::any::type_name;
 
-use ark_ff::One;
+use ark_ff::{One, Zero};
 use ark_poly::Polynomial;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use zeroize::Zeroize;
 
 use crate::{
     primitives::{
+        entropy::EntropySource,
         poly::Poly,
         yashe::{Yashe, YasheConf},
     },
-    MiddleRes, TestRes,
+    FullRes, MiddleRes, TestRes,
 };
 
 /// Auxiliary function for testing key generation
@@ -32,7 +36,7 @@ where
     );
 
     assert_eq!(
-        private_key.priv_key * priv_key_inv.expect("Private key must be invertible"),
+        &private_key.priv_key * priv_key_inv.expect("Private key must be invertible"),
         Poly::one(),
         "{}",
         type_name::<C>()
@@ -50,3 +54,36 @@ fn test_keygen() {
     keygen_helper::<TestRes>();
     keygen_helper::<MiddleRes>();
 }
+
+/// Check that private keys are redacted in debug output, and can be erased.
+#[test]
+fn test_private_key_secrecy() {
+    let ctx: Yashe<FullRes> = Yashe::new();
+    let mut private_key = ctx.generate_private_key(&mut rand::thread_rng());
+
+    assert_eq!(format!("{private_key:?}"), "PrivateKey { .. }");
+
+    private_key.zeroize();
+
+    assert!(private_key.f.is_zero());
+    assert!(private_key.priv_key_inv.is_zero());
+    assert!(private_key.priv_key.is_zero());
+}
+
+/// Check that keys are generated using the supplied entropy source.
+#[test]
+fn test_keygen_entropy_source() {
+    let ctx: Yashe<TestRes> = Yashe::new();
+
+    let (private_key, public_key) = ctx.keygen(&mut ChaCha20Rng::seed_from_u64(7));
+    let (same_private_key, same_public_key) = ctx.keygen(&mut ChaCha20Rng::seed_from_u64(7));
+    assert_eq!(private_key, same_private_key);
+    assert_eq!(public_key, same_public_key);
+
+    // Sources can also be used as trait objects.
+    let mut source = ChaCha20Rng::seed_from_u64(8);
+    let source: &mut dyn EntropySource = &mut source;
+    let (other_private_key, other_public_key) = ctx.keygen(source);
+    assert_ne!(private_key, other_private_key);
+    assert_ne!(public_key, other_public_key);
+}
@@ -0,0 +1,46 @@
+//! Unit tests for key switching
+
+use std::any::type_name;
+
+use crate::{
+    encoded::conf::LargeRes,
+    primitives::yashe::{Yashe, YasheConf},
+    FullRes, MiddleRes,
+};
+
+fn key_switch_multiplication_helper<C: YasheConf>()
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<C> = Yashe::new();
+
+    let (private_key_a, public_key_a) = ctx.keygen(&mut rng);
+    let (private_key_b, public_key_b) = ctx.keygen(&mut rng);
+    let key_switch_key = ctx.generate_key_switch_key(&mut rng, &private_key_a, &public_key_b);
+
+    let m1 = ctx.sample_message(&mut rng);
+    let m2 = ctx.sample_message(&mut rng);
+    let c1 = ctx.encrypt(m1.clone(), &public_key_a, &mut rng);
+    let c2 = ctx.encrypt(m2.clone(), &public_key_b, &mut rng);
+
+    // Multiply ciphertexts under different keys, then switch the product to key b.
+    let m = ctx.plaintext_mul(m1, m2);
+    let c = ctx.ciphertext_mul(c1, c2);
+    let c = ctx.key_switch(c, &key_switch_key);
+    let m_dec = ctx.decrypt(c, &private_key_b);
+
+    assert_eq!(
+        m,
+        m_dec,
+        "key switching test failed for {}",
+        type_name::<C>()
+    );
+}
+
+#[test]
+fn key_switch_multiplication_test() {
+    key_switch_multiplication_helper::<MiddleRes>();
+    key_switch_multiplication_helper::<FullRes>();
+    key_switch_multiplication_helper::<LargeRes>();
+}
@@ -0,0 +1,72 @@
+//! Unit tests for noise tracking
+
+use std::any::type_name;
+
+use crate::{
+    encoded::conf::LargeRes,
+    primitives::yashe::{
+        noise::{NoiseAction, NoiseStage, NoiseTracker},
+        Yashe, YasheConf,
+    },
+    FullRes, MiddleRes,
+};
+
+fn noise_helper<C: YasheConf>()
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<C> = Yashe::new();
+    let (private_key, public_key) = ctx.keygen(&mut rng);
+    let budget_bits = C::noise_budget_bits();
+
+    let m1 = ctx.sample_message(&mut rng);
+    let m2 = ctx.sample_message(&mut rng);
+    let c1 = ctx.encrypt(m1, &public_key, &mut rng);
+    let c2 = ctx.encrypt(m2, &public_key, &mut rng);
+
+    let fresh_bits = ctx.noise_bits(&c1, &private_key);
+    let c = ctx.ciphertext_mul(c1, c2);
+    let mul_bits = ctx.mul_noise_bits(&c, &private_key);
+
+    // Both ciphertexts must decrypt correctly, and multiplication adds noise.
+    assert!(
+        fresh_bits < mul_bits,
+        "multiplication must add noise for {}: {fresh_bits} >= {mul_bits}",
+        type_name::<C>()
+    );
+    assert!(
+        mul_bits < budget_bits,
+        "multiplication must be within the noise budget for {}: {mul_bits} >= {budget_bits}",
+        type_name::<C>()
+    );
+
+    // A tracker that warns records everything, and never errors.
+    let mut tracker = NoiseTracker::new(0, NoiseAction::Warn);
+    tracker
+        .record(NoiseStage::Encrypt, fresh_bits, budget_bits)
+        .expect("warning trackers never error");
+    tracker
+        .record(NoiseStage::Multiply, mul_bits, budget_bits)
+        .expect("warning trackers never error");
+    assert_eq!(tracker.records().len(), 2);
+    assert_eq!(tracker.warnings().count(), 0);
+    assert_eq!(
+        tracker.worst().map(|record| record.stage),
+        Some(NoiseStage::Multiply)
+    );
+
+    // A tracker that needs more than the entire budget always errors.
+    #[allow(clippy::cast_possible_wrap)]
+    let mut tracker = NoiseTracker::new(budget_bits as i64 + 1, NoiseAction::Error);
+    let res = tracker.record(NoiseStage::Encrypt, fresh_bits, budget_bits);
+    assert!(res.is_err(), "tracker must error for {}", type_name::<C>());
+    assert_eq!(tracker.warnings().count(), 1);
+}
+
+#[test]
+fn noise_test() {
+    noise_helper::<MiddleRes>();
+    noise_helper::<FullRes>();
+    noise_helper::<LargeRes>();
+}
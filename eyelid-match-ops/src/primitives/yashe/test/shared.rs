@@ -0,0 +1,94 @@
+//! Tests for YASHE contexts shared between threads.
+
+use std::{any::type_name, thread};
+
+use crate::{
+    encoded::conf::LargeRes,
+    primitives::yashe::{SharedContext, Yashe, YasheConf},
+    FullRes, MiddleRes,
+};
+
+/// The number of threads used to decrypt concurrently.
+const THREADS: usize = 4;
+
+// Cached decryptions must give the same results as uncached decryptions, on every thread
+fn shared_decrypt_mul_helper<C: YasheConf>()
+where
+    C::Coeff: From<u128> + From<u64> + From<i64>,
+{
+    let mut rng = rand::thread_rng();
+    let ctx: Yashe<C> = Yashe::new();
+    let shared = SharedContext::generate(ctx, &mut rng);
+    assert!(!shared.is_cached());
+
+    let mut messages = Vec::new();
+    let mut ciphertexts = Vec::new();
+
+    for _ in 0..THREADS {
+        let m1 = ctx.sample_message(&mut rng);
+        let m2 = ctx.sample_message(&mut rng);
+        let c1 = ctx.encrypt(m1.clone(), shared.public_key(), &mut rng);
+        let c2 = ctx.encrypt(m2.clone(), shared.public_key(), &mut rng);
+        messages.push(ctx.plaintext_mul(m1, m2));
+        ciphertexts.push(ctx.ciphertext_mul(c1, c2));
+    }
+
+    let decrypted: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = ciphertexts
+            .iter()
+            .map(|c| {
+                let shared = shared.clone();
+                scope.spawn(move || shared.decrypt_mul(c.clone()))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("decryption must not panic"))
+            .collect()
+    });
+
+    assert_eq!(
+        messages,
+        decrypted,
+        "shared decryption test failed for {}",
+        type_name::<C>()
+    );
+    assert!(shared.is_cached());
+
+    // Batch and uncached decryptions give the same results.
+    assert_eq!(shared.decrypt_mul_batch(&ciphertexts), decrypted);
+    assert_eq!(
+        ctx.decrypt_mul(ciphertexts[0].clone(), shared.private_key()),
+        decrypted[0],
+    );
+}
+
+#[test]
+fn test_shared_decrypt_mul() {
+    shared_decrypt_mul_helper::<FullRes>();
+    shared_decrypt_mul_helper::<MiddleRes>();
+    shared_decrypt_mul_helper::<LargeRes>();
+}
+
+#[test]
+fn test_shared_clones() {
+    let mut rng = rand::thread_rng();
+    let shared = SharedContext::<FullRes>::generate(Yashe::new(), &mut rng);
+    let clone = shared.clone();
+
+    assert!(shared.ptr_eq(&clone));
+    assert_eq!(shared.public_key(), clone.public_key());
+
+    // Contexts with the same keys don't share caches.
+    let copy = SharedContext::new(
+        shared.ctx(),
+        shared.private_key().clone(),
+        shared.public_key().clone(),
+    );
+    assert!(!shared.ptr_eq(&copy));
+
+    // Debug output doesn't include the cached private key square.
+    let debug = format!("{shared:?}");
+    assert!(!debug.contains("private_key_squared"));
+}
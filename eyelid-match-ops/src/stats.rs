@@ -0,0 +1,226 @@
+//! Score distribution statistics, for DET and ROC analysis of matching parameters.
+//!
+//! Genuine pairs are from the same iris, and impostor pairs are from different irises. Their
+//! distances are collected into histograms, which are used to calculate the false match and
+//! false non-match rates at each threshold.
+
+use num_bigint::BigUint;
+
+use crate::{
+    encoded::{MatchError, PolyCode, PolyQuery},
+    iris::conf::{mask_trailing_bits, IrisCode, IrisConf, IrisMask},
+    plaintext::{
+        iris_distance,
+        test::gen::{correlated_iris_code, eyelid_iris_mask, random_iris_code},
+    },
+    EncodeConf, PolyConf,
+};
+
+#[cfg(test)]
+mod test;
+
+/// A pair of iris codes and masks: the new code and mask, then the stored code and mask.
+pub type ScorePair<const STORE_ELEM_LEN: usize> = (
+    IrisCode<STORE_ELEM_LEN>,
+    IrisMask<STORE_ELEM_LEN>,
+    IrisCode<STORE_ELEM_LEN>,
+    IrisMask<STORE_ELEM_LEN>,
+);
+
+/// A histogram of fractional distances between 0 and 1, with equal width bins.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Histogram {
+    /// The number of distances in each bin.
+    bins: Vec<u64>,
+}
+
+impl Histogram {
+    /// Returns an empty histogram with `bins` bins.
+    ///
+    /// # Panics
+    ///
+    /// If `bins` is zero.
+    pub fn new(bins: usize) -> Self {
+        assert!(bins > 0, "histograms must have at least one bin");
+
+        Self {
+            bins: vec![0; bins],
+        }
+    }
+
+    /// Adds `distance` to the histogram. Distances outside `0..=1` are added to the first or
+    /// last bin.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn add(&mut self, distance: f64) {
+        let last = self.bins.len() - 1;
+        // The distance is clamped to the bins, so the cast can't truncate or lose its sign.
+        let bin_i = ((distance * self.bins.len() as f64).floor().max(0.0) as usize).min(last);
+
+        self.bins[bin_i] += 1;
+    }
+
+    /// Returns the number of distances in each bin.
+    pub fn bins(&self) -> &[u64] {
+        &self.bins
+    }
+
+    /// Returns the number of distances in the histogram.
+    pub fn total(&self) -> u64 {
+        self.bins.iter().sum()
+    }
+}
+
+/// The error rates at a single threshold.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DetPoint {
+    /// The distance threshold.
+    pub threshold: f64,
+    /// The fraction of impostor pairs with distances below the threshold.
+    pub false_match_rate: f64,
+    /// The fraction of genuine pairs with distances at or above the threshold.
+    pub false_non_match_rate: f64,
+}
+
+/// The distance distributions of genuine and impostor pairs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScoreStats {
+    /// The distances between codes from the same iris.
+    pub genuine: Histogram,
+    /// The distances between codes from different irises.
+    pub impostor: Histogram,
+}
+
+impl ScoreStats {
+    /// Returns empty statistics with `bins` histogram bins.
+    pub fn new(bins: usize) -> Self {
+        Self {
+            genuine: Histogram::new(bins),
+            impostor: Histogram::new(bins),
+        }
+    }
+
+    /// Returns the error rates at the upper edge of each histogram bin, from the smallest to the
+    /// largest threshold.
+    ///
+    /// Rates are zero if there are no pairs of that kind.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn det_points(&self) -> Vec<DetPoint> {
+        let bins = self.genuine.bins().len();
+        let rate = |count: u64, total: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                count as f64 / total as f64
+            }
+        };
+
+        let (genuine_total, impostor_total) = (self.genuine.total(), self.impostor.total());
+        let (mut genuine_below, mut impostor_below) = (0, 0);
+
+        self.genuine
+            .bins()
+            .iter()
+            .zip(self.impostor.bins())
+            .enumerate()
+            .map(|(bin_i, (genuine, impostor))| {
+                genuine_below += genuine;
+                impostor_below += impostor;
+
+                DetPoint {
+                    threshold: (bin_i + 1) as f64 / bins as f64,
+                    false_match_rate: rate(impostor_below, impostor_total),
+                    false_non_match_rate: rate(genuine_total - genuine_below, genuine_total),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the distance statistics of the plaintext matcher, using
+/// [`iris_distance()`](crate::plaintext::iris_distance).
+pub fn plaintext_stats<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    genuine: &[ScorePair<STORE_ELEM_LEN>],
+    impostor: &[ScorePair<STORE_ELEM_LEN>],
+    bins: usize,
+) -> ScoreStats {
+    let distance = |(eye_new, mask_new, eye_store, mask_store): &ScorePair<STORE_ELEM_LEN>| {
+        iris_distance::<C, STORE_ELEM_LEN>(eye_new, mask_new, eye_store, mask_store).min_fraction
+    };
+
+    let mut stats = ScoreStats::new(bins);
+    genuine
+        .iter()
+        .for_each(|pair| stats.genuine.add(distance(pair)));
+    impostor
+        .iter()
+        .for_each(|pair| stats.impostor.add(distance(pair)));
+
+    stats
+}
+
+/// Returns the distance statistics of the polynomial-encoded matcher, using
+/// [`PolyQuery::min_distance()`].
+pub fn encoded_stats<C: EncodeConf, const STORE_ELEM_LEN: usize>(
+    genuine: &[ScorePair<STORE_ELEM_LEN>],
+    impostor: &[ScorePair<STORE_ELEM_LEN>],
+    bins: usize,
+) -> Result<ScoreStats, MatchError>
+where
+    BigUint: From<<C::PlainConf as PolyConf>::Coeff>,
+{
+    let distance = |(eye_new, mask_new, eye_store, mask_store): &ScorePair<STORE_ELEM_LEN>| {
+        let query = PolyQuery::<C>::from_plaintext(eye_new, mask_new);
+        let code = PolyCode::<C>::from_plaintext(eye_store, mask_store);
+
+        query.min_distance(&code)
+    };
+
+    let mut stats = ScoreStats::new(bins);
+    for pair in genuine {
+        stats.genuine.add(distance(pair)?);
+    }
+    for pair in impostor {
+        stats.impostor.add(distance(pair)?);
+    }
+
+    Ok(stats)
+}
+
+/// Returns `count` generated genuine pairs and `count` generated impostor pairs.
+///
+/// Genuine pairs differ in `genuine_distance` of their bits, in small patches. Every code has
+/// independent eyelid occlusions, covering up to `eyelid_depth` of the outer rows.
+pub fn generated_corpus<C: IrisConf, const STORE_ELEM_LEN: usize>(
+    count: usize,
+    genuine_distance: f64,
+    eyelid_depth: f64,
+) -> (
+    Vec<ScorePair<STORE_ELEM_LEN>>,
+    Vec<ScorePair<STORE_ELEM_LEN>>,
+) {
+    let mask = || eyelid_iris_mask::<C, STORE_ELEM_LEN>(eyelid_depth);
+    let code = || {
+        let mut code = random_iris_code();
+        mask_trailing_bits::<C, STORE_ELEM_LEN>(&mut code);
+        code
+    };
+
+    let genuine = (0..count)
+        .map(|_| {
+            let eye = code();
+            let eye_store = correlated_iris_code::<C, STORE_ELEM_LEN>(&eye, genuine_distance);
+
+            (eye, mask(), eye_store, mask())
+        })
+        .collect();
+
+    let impostor = (0..count)
+        .map(|_| (code(), mask(), code(), mask()))
+        .collect();
+
+    (genuine, impostor)
+}
@@ -0,0 +1,78 @@
+//! Tests for score distribution statistics.
+
+use crate::{
+    iris::conf::IrisConf,
+    stats::{encoded_stats, generated_corpus, plaintext_stats, Histogram, ScoreStats},
+    MiddleBits,
+};
+
+/// The number of histogram bins used in tests.
+const BINS: usize = 100;
+
+/// The bin with an upper edge at the match threshold.
+const THRESHOLD_BIN: usize = BINS * MiddleBits::MATCH_NUMERATOR / MiddleBits::MATCH_DENOMINATOR - 1;
+
+/// Check histogram binning and DET points on known distances.
+#[test]
+fn test_histogram_det_points() {
+    let mut histogram = Histogram::new(4);
+    for distance in [0.0, 0.1, 0.25, 0.7, 1.0, 2.0] {
+        histogram.add(distance);
+    }
+    assert_eq!(histogram.bins(), &[2, 1, 1, 2]);
+    assert_eq!(histogram.total(), 6);
+
+    let mut stats = ScoreStats::new(4);
+    stats.genuine.add(0.1);
+    stats.genuine.add(0.3);
+    stats.impostor.add(0.6);
+    stats.impostor.add(0.9);
+
+    let points = stats.det_points();
+    assert_eq!(
+        points
+            .iter()
+            .map(|point| point.threshold)
+            .collect::<Vec<_>>(),
+        vec![0.25, 0.5, 0.75, 1.0]
+    );
+    assert_eq!(
+        points
+            .iter()
+            .map(|point| (point.false_match_rate, point.false_non_match_rate))
+            .collect::<Vec<_>>(),
+        vec![(0.0, 0.5), (0.0, 0.0), (0.5, 0.0), (1.0, 0.0)]
+    );
+
+    // Empty statistics have zero error rates.
+    assert!(ScoreStats::new(4)
+        .det_points()
+        .iter()
+        .all(|point| point.false_match_rate == 0.0 && point.false_non_match_rate == 0.0));
+}
+
+/// Check that generated genuine and impostor pairs are separated by the match threshold, in
+/// both the plaintext and encoded matchers.
+#[test]
+fn test_generated_corpus_stats() {
+    let (genuine, impostor) =
+        generated_corpus::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(3, 0.1, 0.3);
+    assert_eq!(genuine.len(), 3);
+    assert_eq!(impostor.len(), 3);
+
+    let plaintext =
+        plaintext_stats::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&genuine, &impostor, BINS);
+    let encoded =
+        encoded_stats::<MiddleBits, { MiddleBits::STORE_ELEM_LEN }>(&genuine, &impostor, BINS)
+            .expect("matching must work");
+
+    for stats in [plaintext, encoded] {
+        assert_eq!(stats.genuine.total(), 3);
+        assert_eq!(stats.impostor.total(), 3);
+
+        let point = stats.det_points()[THRESHOLD_BIN];
+        assert_eq!(point.threshold, 0.36);
+        assert_eq!(point.false_match_rate, 0.0, "{stats:?}");
+        assert_eq!(point.false_non_match_rate, 0.0, "{stats:?}");
+    }
+}
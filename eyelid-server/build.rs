@@ -0,0 +1,44 @@
+//! Generates the gRPC service code.
+//!
+//! The messages are written by hand in `src/proto.rs`, so building doesn't need `protoc`.
+
+use tonic_build::manual::{Builder, Method, Service};
+
+fn main() {
+    let method = |name: &str, route_name: &str, input: &str, output: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route_name)
+            .input_type(format!("crate::proto::{input}"))
+            .output_type(format!("crate::proto::{output}"))
+            .codec_path("tonic::codec::ProstCodec")
+            .build()
+    };
+
+    let service = Service::builder()
+        .name("Matcher")
+        .package("eyelid")
+        .method(method(
+            "enroll_encrypted",
+            "EnrollEncrypted",
+            "EnrollEncryptedRequest",
+            "EnrollEncryptedResponse",
+        ))
+        .method(method(
+            "submit_query",
+            "SubmitQuery",
+            "SubmitQueryRequest",
+            "SubmitQueryResponse",
+        ))
+        .method(method(
+            "match_result",
+            "MatchResult",
+            "MatchResultRequest",
+            "MatchResultResponse",
+        ))
+        .build();
+
+    Builder::new().build_client(false).compile(&[service]);
+
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,99 @@
+//! gRPC service for encrypted iris matching.
+//!
+//! See `proto/eyelid.proto` for the service definition.
+//!
+//! The private key passphrase is read from the `EYELID_KEY_PASSPHRASE` environment variable if it
+//! is set. Otherwise, the server prompts for it, and reads it from stdin.
+
+use std::{
+    env,
+    error::Error,
+    io::{self, Write},
+    net::SocketAddr,
+    path::PathBuf,
+    time::Duration,
+};
+
+use clap::Parser;
+use tonic::transport::Server;
+use zeroize::Zeroizing;
+
+use eyelid_match_ops::primitives::yashe::PrivateKey;
+
+use proto::MatcherServer;
+use service::{MatchService, DEFAULT_MAX_IN_FLIGHT_QUERIES, DEFAULT_RESULT_TTL};
+
+mod proto;
+mod service;
+
+/// The environment variable containing the private key passphrase.
+const PASSPHRASE_VAR: &str = "EYELID_KEY_PASSPHRASE";
+
+/// Serves encrypted iris matching over gRPC.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// The encrypted private key file, written by `eyelid keygen`.
+    #[arg(long)]
+    private_key: PathBuf,
+
+    /// The address to listen on.
+    #[arg(long, default_value = "[::1]:50051")]
+    addr: SocketAddr,
+
+    /// The maximum number of queries being matched at the same time.
+    /// Further queries are rejected until a query finishes.
+    #[arg(long, default_value_t = DEFAULT_MAX_IN_FLIGHT_QUERIES)]
+    max_in_flight_queries: usize,
+
+    /// The number of seconds finished results are kept for, if they are not collected.
+    #[arg(long, default_value_t = DEFAULT_RESULT_TTL.as_secs())]
+    result_ttl_secs: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let private_key = PrivateKey::load_encrypted(&args.private_key, read_passphrase()?.as_bytes())?;
+
+    Server::builder()
+        .add_service(MatcherServer::new(
+            MatchService::new(private_key).with_limits(
+                args.max_in_flight_queries,
+                Duration::from_secs(args.result_ttl_secs),
+            ),
+        ))
+        .serve(args.addr)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the private key passphrase from [`PASSPHRASE_VAR`], or prompts for it and reads it
+/// from stdin.
+fn read_passphrase() -> Result<Zeroizing<String>, Box<dyn Error>> {
+    let passphrase = match env::var_os(PASSPHRASE_VAR) {
+        Some(passphrase) => Zeroizing::new(
+            passphrase
+                .into_string()
+                .map_err(|_| format!("{PASSPHRASE_VAR} must be valid Unicode"))?,
+        ),
+        None => {
+            eprint!("Private key passphrase: ");
+            io::stderr().flush()?;
+
+            let mut passphrase = Zeroizing::new(String::new());
+            io::stdin().read_line(&mut passphrase)?;
+
+            let len = passphrase.trim_end_matches(['\r', '\n']).len();
+            passphrase.truncate(len);
+            passphrase
+        }
+    };
+
+    if passphrase.is_empty() {
+        return Err("the private key passphrase must not be empty".into());
+    }
+
+    Ok(passphrase)
+}
@@ -0,0 +1,63 @@
+//! Protobuf messages and generated service code, matching `proto/eyelid.proto`.
+
+#[cfg(test)]
+mod test;
+
+/// A request to store an encrypted iris code.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EnrollEncryptedRequest {
+    /// The id of the code in the store.
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+    /// The encrypted code, encoded by `EncryptedPolyCode::to_bytes()`.
+    #[prost(bytes = "vec", tag = "2")]
+    pub code: Vec<u8>,
+}
+
+/// The response to a successful enrollment.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EnrollEncryptedResponse {}
+
+/// A request to match an encrypted query against every stored code.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubmitQueryRequest {
+    /// The encrypted query, encoded by `EncryptedPolyQuery::to_bytes()`.
+    #[prost(bytes = "vec", tag = "1")]
+    pub query: Vec<u8>,
+}
+
+/// The response to a submitted query.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubmitQueryResponse {
+    /// The id used to fetch the result of the query. Ids are 16 random bytes, so only the client
+    /// which submitted the query can fetch its result.
+    #[prost(bytes = "vec", tag = "1")]
+    pub query_id: Vec<u8>,
+}
+
+/// A request for the result of a submitted query.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MatchResultRequest {
+    /// The id returned by `SubmitQuery`.
+    #[prost(bytes = "vec", tag = "1")]
+    pub query_id: Vec<u8>,
+}
+
+/// The result of a submitted query.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MatchResultResponse {
+    /// True if matching has finished, and `matched_ids` is complete.
+    #[prost(bool, tag = "1")]
+    pub ready: bool,
+    /// The ids of the stored codes that match the query, in id order.
+    #[prost(uint64, repeated, tag = "2")]
+    pub matched_ids: Vec<u64>,
+}
+
+/// The generated server for the `eyelid.Matcher` service.
+#[allow(missing_docs, clippy::missing_docs_in_private_items)]
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/eyelid.Matcher.rs"));
+}
+
+pub use generated::matcher_server::{Matcher, MatcherServer};
@@ -0,0 +1,132 @@
+//! Tests that the hand-written messages match `proto/eyelid.proto`.
+
+use std::collections::{BTreeSet, HashMap};
+
+use prost::Message;
+
+use crate::proto::{
+    EnrollEncryptedRequest, EnrollEncryptedResponse, MatchResultRequest, MatchResultResponse,
+    SubmitQueryRequest, SubmitQueryResponse,
+};
+
+/// The service definition.
+const PROTO: &str = include_str!("../../proto/eyelid.proto");
+
+/// The varint wire type.
+const VARINT: u64 = 0;
+
+/// The length-delimited wire type, which is also used for packed repeated scalars.
+const LEN: u64 = 2;
+
+/// Returns the field numbers and wire types of each message in [`PROTO`].
+fn proto_fields() -> HashMap<&'static str, BTreeSet<(u64, u64)>> {
+    let mut messages = HashMap::new();
+    let mut message = None;
+
+    for line in PROTO.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("message ") {
+            let name = name.trim_end_matches(['{', '}', ' ']);
+            messages.insert(name, BTreeSet::new());
+            message = (!line.ends_with('}')).then_some(name);
+        } else if line == "}" {
+            message = None;
+        } else if let (Some(message), Some((field, number))) = (message, line.split_once(" = ")) {
+            let number = number
+                .trim_end_matches(';')
+                .parse()
+                .expect("field numbers must be integers");
+            let wire_type = match field.split_whitespace().next() {
+                Some("uint64" | "bool") => VARINT,
+                Some("bytes" | "repeated") => LEN,
+                other => panic!("unexpected field type {other:?} in {message}"),
+            };
+
+            messages
+                .get_mut(message)
+                .expect("messages are inserted before their fields")
+                .insert((number, wire_type));
+        }
+    }
+
+    messages
+}
+
+/// Returns the field numbers and wire types in `message`, which must have every field set.
+fn encoded_fields(message: &impl Message) -> BTreeSet<(u64, u64)> {
+    let bytes = message.encode_to_vec();
+    let mut buf = bytes.as_slice();
+    let mut fields = BTreeSet::new();
+
+    while !buf.is_empty() {
+        let key = prost::encoding::decode_varint(&mut buf).expect("keys must be varints");
+        let wire_type = key & 0b111;
+        match wire_type {
+            VARINT => {
+                prost::encoding::decode_varint(&mut buf).expect("values must be varints");
+            }
+            LEN => {
+                let len =
+                    prost::encoding::decode_varint(&mut buf).expect("lengths must be varints");
+                buf = &buf[usize::try_from(len).expect("lengths must fit in usize")..];
+            }
+            _ => panic!("unexpected wire type {wire_type}"),
+        }
+
+        fields.insert((key >> 3, wire_type));
+    }
+
+    fields
+}
+
+/// Check that every message has the same field numbers and wire types as the `.proto` file.
+#[test]
+fn test_field_numbers() {
+    let proto = proto_fields();
+
+    let rust = [
+        (
+            "EnrollEncryptedRequest",
+            encoded_fields(&EnrollEncryptedRequest {
+                id: 1,
+                code: vec![1],
+            }),
+        ),
+        (
+            "EnrollEncryptedResponse",
+            encoded_fields(&EnrollEncryptedResponse {}),
+        ),
+        (
+            "SubmitQueryRequest",
+            encoded_fields(&SubmitQueryRequest { query: vec![1] }),
+        ),
+        (
+            "SubmitQueryResponse",
+            encoded_fields(&SubmitQueryResponse { query_id: vec![1] }),
+        ),
+        (
+            "MatchResultRequest",
+            encoded_fields(&MatchResultRequest { query_id: vec![1] }),
+        ),
+        (
+            "MatchResultResponse",
+            encoded_fields(&MatchResultResponse {
+                ready: true,
+                matched_ids: vec![1],
+            }),
+        ),
+    ];
+
+    assert_eq!(
+        proto.len(),
+        rust.len(),
+        "every message in the .proto file must be tested"
+    );
+
+    for (name, fields) in rust {
+        assert_eq!(
+            proto.get(name),
+            Some(&fields),
+            "{name} must match the .proto file"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! The encrypted matching service.
+//!
+//! The service holds the private key, because matching decrypts the match counts. Clients only
+//! need the public key to encrypt codes and queries.
+//!
+//! Each query searches the whole gallery, so the number of queries being matched at the same
+//! time is limited. Results which aren't collected are discarded after a timeout.
+//!
+//! Query ids are random, so clients can't fetch or discard the results of other clients' queries.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::OsRng, Rng};
+use tonic::{Request, Response, Status};
+
+use eyelid_match_ops::{
+    encrypted::{
+        store::{CodeId, EncryptedCodeStore, MemoryCodeStore},
+        EncryptedPolyCode, EncryptedPolyQuery,
+    },
+    primitives::yashe::{PrivateKey, Yashe},
+    FullBits, FullRes,
+};
+
+use crate::proto::{
+    EnrollEncryptedRequest, EnrollEncryptedResponse, MatchResultRequest, MatchResultResponse,
+    Matcher, SubmitQueryRequest, SubmitQueryResponse,
+};
+
+#[cfg(test)]
+mod test;
+
+/// The default maximum number of queries which are submitted but not finished.
+pub const DEFAULT_MAX_IN_FLIGHT_QUERIES: usize = 16;
+
+/// The default time finished results are kept for, if they are not collected.
+pub const DEFAULT_RESULT_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// The length of a query id in bytes.
+pub const QUERY_ID_LEN: usize = 16;
+
+/// A random id for a submitted query.
+type QueryId = [u8; QUERY_ID_LEN];
+
+/// The result of a submitted query and the time it finished, or `None` if matching hasn't
+/// finished.
+type QueryResult = Option<(Instant, Result<Vec<CodeId>, String>)>;
+
+/// A gRPC service which stores encrypted codes, and matches encrypted queries against them.
+#[derive(Debug)]
+pub struct MatchService {
+    /// The service state, shared with background matching tasks.
+    state: Arc<State>,
+}
+
+/// The state of a [`MatchService`].
+#[derive(Debug)]
+struct State {
+    /// The encryption context.
+    ctx: Yashe<FullRes>,
+
+    /// The private key used to decrypt match results.
+    private_key: PrivateKey<FullRes>,
+
+    /// The enrolled codes.
+    store: RwLock<MemoryCodeStore<FullBits>>,
+
+    /// The results of submitted queries, by query id.
+    results: Mutex<HashMap<QueryId, QueryResult>>,
+
+    /// The number of queries which are submitted but not finished.
+    in_flight_queries: AtomicUsize,
+
+    /// The maximum number of queries which are submitted but not finished.
+    max_in_flight_queries: usize,
+
+    /// The time finished results are kept for, if they are not collected.
+    result_ttl: Duration,
+}
+
+/// Counts a query as in flight, until it is dropped.
+#[derive(Debug)]
+struct InFlightQuery {
+    /// The service state, which counts the in-flight queries.
+    state: Arc<State>,
+}
+
+impl MatchService {
+    /// Returns a new service with an empty store, which decrypts results using `private_key`.
+    pub fn new(private_key: PrivateKey<FullRes>) -> Self {
+        Self {
+            state: Arc::new(State {
+                ctx: Yashe::new(),
+                private_key,
+                store: RwLock::new(MemoryCodeStore::new()),
+                results: Mutex::new(HashMap::new()),
+                in_flight_queries: AtomicUsize::new(0),
+                max_in_flight_queries: DEFAULT_MAX_IN_FLIGHT_QUERIES,
+                result_ttl: DEFAULT_RESULT_TTL,
+            }),
+        }
+    }
+
+    /// Limits the number of queries which are submitted but not finished to
+    /// `max_in_flight_queries`, and discards finished results which haven't been collected
+    /// after `result_ttl`.
+    ///
+    /// # Panics
+    ///
+    /// If the service is already being used.
+    pub fn with_limits(mut self, max_in_flight_queries: usize, result_ttl: Duration) -> Self {
+        let state = Arc::get_mut(&mut self.state).expect("limits must be set before serving");
+        state.max_in_flight_queries = max_in_flight_queries;
+        state.result_ttl = result_ttl;
+
+        self
+    }
+}
+
+impl State {
+    /// Removes finished results which are older than the result TTL.
+    fn expire_results(&self, results: &mut HashMap<QueryId, QueryResult>) {
+        results.retain(|_query_id, result| match result {
+            Some((finished, _matches)) => finished.elapsed() < self.result_ttl,
+            None => true,
+        });
+    }
+}
+
+impl InFlightQuery {
+    /// Counts a new in-flight query, or returns `None` if there are too many in-flight queries.
+    fn start(state: &Arc<State>) -> Option<Self> {
+        state
+            .in_flight_queries
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |queries| {
+                (queries < state.max_in_flight_queries).then_some(queries + 1)
+            })
+            .ok()?;
+
+        Some(Self {
+            state: state.clone(),
+        })
+    }
+}
+
+impl Drop for InFlightQuery {
+    fn drop(&mut self) {
+        self.state.in_flight_queries.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+// The store and results are never left in an inconsistent state, so poisoning can be ignored.
+#[tonic::async_trait]
+impl Matcher for MatchService {
+    async fn enroll_encrypted(
+        &self,
+        request: Request<EnrollEncryptedRequest>,
+    ) -> Result<Response<EnrollEncryptedResponse>, Status> {
+        let request = request.into_inner();
+        let code = EncryptedPolyCode::<FullBits>::from_bytes(&request.code)
+            .map_err(|error| Status::invalid_argument(error.to_string()))?;
+
+        // Searches hold the store lock while they match the whole gallery, so waiting for the
+        // write lock runs outside the async runtime.
+        let state = self.state.clone();
+        tokio::task::spawn_blocking(move || {
+            state
+                .store
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .put(request.id, code)
+        })
+        .await
+        .map_err(|error| Status::internal(error.to_string()))?
+        .map_err(|error| Status::internal(error.to_string()))?;
+
+        Ok(Response::new(EnrollEncryptedResponse {}))
+    }
+
+    async fn submit_query(
+        &self,
+        request: Request<SubmitQueryRequest>,
+    ) -> Result<Response<SubmitQueryResponse>, Status> {
+        let query = EncryptedPolyQuery::<FullBits>::from_bytes(&request.into_inner().query)
+            .map_err(|error| Status::invalid_argument(error.to_string()))?;
+
+        let in_flight = InFlightQuery::start(&self.state).ok_or_else(|| {
+            Status::resource_exhausted("too many queries are being matched, try again later")
+        })?;
+
+        let query_id: QueryId = OsRng.gen();
+        {
+            let mut results = self
+                .state
+                .results
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            self.state.expire_results(&mut results);
+            results.insert(query_id, None);
+        }
+
+        // Matching is CPU-bound, so it runs outside the async runtime.
+        let state = self.state.clone();
+        tokio::task::spawn_blocking(move || {
+            // The query stays in flight until its result is stored.
+            let _in_flight = in_flight;
+
+            let matches = query
+                .search(
+                    state.ctx,
+                    &state.private_key,
+                    &*state.store.read().unwrap_or_else(PoisonError::into_inner),
+                )
+                .map_err(|error| error.to_string());
+
+            state
+                .results
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(query_id, Some((Instant::now(), matches)));
+        });
+
+        Ok(Response::new(SubmitQueryResponse {
+            query_id: query_id.to_vec(),
+        }))
+    }
+
+    async fn match_result(
+        &self,
+        request: Request<MatchResultRequest>,
+    ) -> Result<Response<MatchResultResponse>, Status> {
+        let query_id =
+            QueryId::try_from(request.into_inner().query_id.as_slice()).map_err(|_| {
+                Status::invalid_argument(format!("query ids must be {QUERY_ID_LEN} bytes"))
+            })?;
+        let mut results = self
+            .state
+            .results
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.state.expire_results(&mut results);
+
+        // Finished results are only returned once, and expired results are not found.
+        match results.remove(&query_id) {
+            None => Err(Status::not_found("unknown query id")),
+            Some(None) => {
+                results.insert(query_id, None);
+
+                Ok(Response::new(MatchResultResponse {
+                    ready: false,
+                    matched_ids: Vec::new(),
+                }))
+            }
+            Some(Some((_finished, matches))) => Ok(Response::new(MatchResultResponse {
+                ready: true,
+                matched_ids: matches.map_err(Status::internal)?,
+            })),
+        }
+    }
+}
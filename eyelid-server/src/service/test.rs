@@ -0,0 +1,230 @@
+//! Tests for the encrypted matching service.
+
+use std::{
+    sync::PoisonError,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tonic::{Code, Request};
+
+use eyelid_match_ops::{
+    encoded::{PolyCode, PolyQuery},
+    encrypted::{EncryptedPolyCode, EncryptedPolyQuery},
+    iris::conf::{IrisCode, IrisMask},
+    primitives::yashe::{PublicKey, Yashe},
+    FullBits, FullRes, IrisConf,
+};
+
+use crate::{
+    proto::{
+        EnrollEncryptedRequest, MatchResultRequest, MatchResultResponse, Matcher,
+        SubmitQueryRequest,
+    },
+    service::{InFlightQuery, MatchService, QueryId, DEFAULT_RESULT_TTL, QUERY_ID_LEN},
+};
+
+/// Returns a new service with an empty store, and the public key for its private key.
+fn service() -> (MatchService, PublicKey<FullRes>) {
+    let (private_key, public_key) = Yashe::<FullRes>::new().keygen(&mut rand::thread_rng());
+
+    (MatchService::new(private_key), public_key)
+}
+
+/// Returns an encrypted query for an all-zero code, encoded for `SubmitQuery`.
+fn query_bytes(public_key: &PublicKey<FullRes>) -> Vec<u8> {
+    let code = IrisCode::<{ FullBits::STORE_ELEM_LEN }>::ZERO;
+    let mask = IrisMask::<{ FullBits::STORE_ELEM_LEN }>::ZERO;
+
+    EncryptedPolyQuery::<FullBits>::convert_and_encrypt_query(
+        Yashe::new(),
+        PolyQuery::from_plaintext(&code, &mask),
+        public_key,
+        &mut rand::thread_rng(),
+    )
+    .to_bytes()
+}
+
+/// Submits `query` to `service`, and returns its query id.
+async fn submit(service: &MatchService, query: &[u8]) -> Vec<u8> {
+    service
+        .submit_query(Request::new(SubmitQueryRequest {
+            query: query.to_vec(),
+        }))
+        .await
+        .expect("valid queries must be accepted")
+        .into_inner()
+        .query_id
+}
+
+/// Returns the result of the query with `query_id`.
+async fn poll(service: &MatchService, query_id: &[u8]) -> Result<MatchResultResponse, Code> {
+    service
+        .match_result(Request::new(MatchResultRequest {
+            query_id: query_id.to_vec(),
+        }))
+        .await
+        .map(tonic::Response::into_inner)
+        .map_err(|status| status.code())
+}
+
+/// Waits until the query with `query_id` has finished, and returns its result.
+async fn wait_for_result(service: &MatchService, query_id: &[u8]) -> MatchResultResponse {
+    loop {
+        let result = poll(service, query_id)
+            .await
+            .expect("submitted queries must have results");
+        if result.ready {
+            return result;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Check that encrypted codes and queries with invalid encodings are rejected.
+#[tokio::test]
+async fn test_invalid_bytes() {
+    let (service, _public_key) = service();
+
+    let res = service
+        .enroll_encrypted(Request::new(EnrollEncryptedRequest {
+            id: 0,
+            code: vec![1, 2, 3],
+        }))
+        .await;
+    assert_eq!(
+        res.expect_err("invalid codes must be rejected").code(),
+        Code::InvalidArgument
+    );
+
+    let res = service
+        .submit_query(Request::new(SubmitQueryRequest {
+            query: vec![1, 2, 3],
+        }))
+        .await;
+    assert_eq!(
+        res.expect_err("invalid queries must be rejected").code(),
+        Code::InvalidArgument
+    );
+
+    assert_eq!(
+        poll(&service, &[0; QUERY_ID_LEN - 1]).await,
+        Err(Code::InvalidArgument),
+        "query ids with the wrong length must be rejected"
+    );
+}
+
+/// Check that valid encrypted codes can be enrolled.
+#[tokio::test]
+async fn test_enroll() {
+    let (service, public_key) = service();
+
+    let code = IrisCode::<{ FullBits::STORE_ELEM_LEN }>::ZERO;
+    let mask = IrisMask::<{ FullBits::STORE_ELEM_LEN }>::ZERO;
+    let encrypted = EncryptedPolyCode::<FullBits>::convert_and_encrypt_code(
+        Yashe::new(),
+        PolyCode::from_plaintext(&code, &mask),
+        &public_key,
+        &mut rand::thread_rng(),
+    );
+
+    service
+        .enroll_encrypted(Request::new(EnrollEncryptedRequest {
+            id: 7,
+            code: encrypted.to_bytes(),
+        }))
+        .await
+        .expect("valid codes must be enrolled");
+}
+
+/// Check that results are only returned once, and only using the random query id.
+#[tokio::test]
+async fn test_result_returned_once() {
+    let (service, public_key) = service();
+    let query = query_bytes(&public_key);
+
+    let query_id = submit(&service, &query).await;
+    let other_query_id = submit(&service, &query).await;
+    assert_eq!(query_id.len(), QUERY_ID_LEN);
+    assert_ne!(query_id, other_query_id, "query ids must be unique");
+
+    assert_eq!(
+        poll(&service, &[0; QUERY_ID_LEN]).await,
+        Err(Code::NotFound),
+        "unknown query ids must not be found"
+    );
+
+    let result = wait_for_result(&service, &query_id).await;
+    assert!(result.matched_ids.is_empty(), "the store is empty");
+
+    assert_eq!(
+        poll(&service, &query_id).await,
+        Err(Code::NotFound),
+        "results must only be returned once"
+    );
+
+    // Collecting one result doesn't affect other queries.
+    wait_for_result(&service, &other_query_id).await;
+}
+
+/// Check that queries are rejected when too many queries are in flight.
+#[tokio::test]
+async fn test_in_flight_limit() {
+    let (service, public_key) = service();
+    let service = service.with_limits(1, DEFAULT_RESULT_TTL);
+    let query = query_bytes(&public_key);
+
+    let in_flight = InFlightQuery::start(&service.state).expect("the limit is one query");
+    let res = service
+        .submit_query(Request::new(SubmitQueryRequest {
+            query: query.clone(),
+        }))
+        .await;
+    assert_eq!(
+        res.expect_err("queries over the limit must be rejected")
+            .code(),
+        Code::ResourceExhausted
+    );
+
+    drop(in_flight);
+    let query_id = submit(&service, &query).await;
+    wait_for_result(&service, &query_id).await;
+}
+
+/// Check that finished results are discarded after the result TTL.
+#[tokio::test]
+async fn test_result_ttl() {
+    let ttl = Duration::from_secs(60);
+    let (service, _public_key) = service();
+    let service = service.with_limits(1, ttl);
+
+    let expired: QueryId = [1; QUERY_ID_LEN];
+    let fresh: QueryId = [2; QUERY_ID_LEN];
+    {
+        let mut results = service
+            .state
+            .results
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let expired_time = Instant::now()
+            .checked_sub(2 * ttl)
+            .expect("the system has been up longer than the TTL");
+        results.insert(expired, Some((expired_time, Ok(vec![1]))));
+        results.insert(fresh, Some((Instant::now(), Ok(vec![2]))));
+    }
+
+    assert_eq!(
+        poll(&service, &expired).await,
+        Err(Code::NotFound),
+        "expired results must be discarded"
+    );
+    assert_eq!(
+        poll(&service, &fresh).await,
+        Ok(MatchResultResponse {
+            ready: true,
+            matched_ids: vec![2],
+        }),
+        "results within the TTL must be returned"
+    );
+}
@@ -0,0 +1,218 @@
+//! Structured JSON export of benchmark results.
+//!
+//! Criterion saves the results of each benchmark in its output directory. After the benchmarks
+//! have run, [`export_from_env()`] collects the results from the current run, and writes them to
+//! the file in [`BENCH_JSON_VAR`] as a JSON array of [`BenchResult`]s. Downstream tooling can
+//! compare these files across branches, without parsing Criterion's console output.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod test;
+
+/// The environment variable containing the path of the JSON results file.
+/// If it isn't set, results are not exported.
+pub const BENCH_JSON_VAR: &str = "EYELID_BENCH_JSON";
+
+/// The result of a single benchmark.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// The benchmarked operation, which is the full Criterion benchmark id.
+    pub operation: String,
+    /// The parameter configuration used by the operation.
+    pub config: String,
+    /// The backend used by the operation.
+    pub backend: String,
+    /// The typical time taken by each operation, in nanoseconds.
+    pub ns_per_op: f64,
+    /// The number of elements or bytes processed each second, if the benchmark sets a throughput.
+    pub throughput: Option<f64>,
+    /// The unit of [`BenchResult::throughput`]: `elements/s` or `bytes/s`.
+    pub throughput_unit: Option<String>,
+}
+
+/// The id of a Criterion benchmark, used to describe its configuration and backend.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+pub struct BenchId {
+    /// The benchmark group, or the function name for benchmarks outside a group.
+    pub group_id: String,
+    /// The function name for benchmarks in a group.
+    pub function_id: Option<String>,
+    /// The benchmark's input parameter.
+    pub value_str: Option<String>,
+    /// The full benchmark id, including the group, function, and parameter.
+    pub full_id: String,
+}
+
+/// The benchmark metadata saved by Criterion in `new/benchmark.json`.
+#[derive(Debug, Deserialize)]
+struct SavedBenchmark {
+    /// The benchmark id.
+    #[serde(flatten)]
+    id: BenchId,
+    /// The amount of work done by each iteration.
+    throughput: Option<SavedThroughput>,
+}
+
+/// A Criterion throughput, saved as a single-entry map.
+#[derive(Debug, Deserialize)]
+enum SavedThroughput {
+    /// Binary bytes processed by each iteration.
+    Bytes(u64),
+    /// Decimal bytes processed by each iteration.
+    BytesDecimal(u64),
+    /// Elements processed by each iteration.
+    Elements(u64),
+}
+
+/// The estimates saved by Criterion in `new/estimates.json`.
+#[derive(Debug, Deserialize)]
+struct SavedEstimates {
+    /// The mean time per iteration.
+    mean: SavedEstimate,
+    /// The time per iteration from a linear regression, if Criterion used linear sampling.
+    slope: Option<SavedEstimate>,
+}
+
+/// A single saved Criterion estimate.
+#[derive(Debug, Deserialize)]
+struct SavedEstimate {
+    /// The estimated time, in nanoseconds.
+    point_estimate: f64,
+}
+
+/// Writes the results of the benchmarks that finished after `started` to the file in
+/// [`BENCH_JSON_VAR`]. Does nothing if that variable isn't set.
+///
+/// `describe` returns the configuration and backend of each benchmark.
+pub fn export_from_env(
+    started: SystemTime,
+    describe: impl Fn(&BenchId) -> (String, String),
+) -> io::Result<()> {
+    let Some(output) = env::var_os(BENCH_JSON_VAR) else {
+        return Ok(());
+    };
+
+    let results = read_criterion_results(&criterion_dir()?, started, describe)?;
+    write_json(Path::new(&output), &results)
+}
+
+/// Returns Criterion's output directory, using the same environment variables as Criterion.
+///
+/// If they aren't set, assumes the benchmark is running from `target/<profile>/deps`.
+pub fn criterion_dir() -> io::Result<PathBuf> {
+    if let Some(dir) = env::var_os("CRITERION_HOME") {
+        return Ok(PathBuf::from(dir));
+    }
+
+    if let Some(dir) = env::var_os("CARGO_TARGET_DIR") {
+        return Ok(PathBuf::from(dir).join("criterion"));
+    }
+
+    let exe = env::current_exe()?;
+    let target = exe
+        .ancestors()
+        .nth(3)
+        .ok_or_else(|| io::Error::other("benchmark is not in a cargo target directory"))?;
+
+    Ok(target.join("criterion"))
+}
+
+/// Returns the results saved in the Criterion directory `dir` by benchmarks that finished after
+/// `started`, sorted by operation.
+///
+/// `describe` returns the configuration and backend of each benchmark.
+pub fn read_criterion_results(
+    dir: &Path,
+    started: SystemTime,
+    describe: impl Fn(&BenchId) -> (String, String),
+) -> io::Result<Vec<BenchResult>> {
+    let mut results = Vec::new();
+
+    for new_dir in find_new_dirs(dir)? {
+        let benchmark_path = new_dir.join("benchmark.json");
+        let estimates_path = new_dir.join("estimates.json");
+
+        // Skip results from previous runs.
+        if fs::metadata(&estimates_path)?.modified()? < started {
+            continue;
+        }
+
+        let benchmark: SavedBenchmark = read_json(&benchmark_path)?;
+        let estimates: SavedEstimates = read_json(&estimates_path)?;
+
+        let ns_per_op = estimates.slope.unwrap_or(estimates.mean).point_estimate;
+        let (throughput, throughput_unit) = match benchmark.throughput {
+            Some(SavedThroughput::Bytes(bytes) | SavedThroughput::BytesDecimal(bytes)) => {
+                (Some(per_second(bytes, ns_per_op)), Some("bytes/s"))
+            }
+            Some(SavedThroughput::Elements(elements)) => {
+                (Some(per_second(elements, ns_per_op)), Some("elements/s"))
+            }
+            None => (None, None),
+        };
+
+        let (config, backend) = describe(&benchmark.id);
+
+        results.push(BenchResult {
+            operation: benchmark.id.full_id,
+            config,
+            backend,
+            ns_per_op,
+            throughput,
+            throughput_unit: throughput_unit.map(str::to_string),
+        });
+    }
+
+    results.sort_by(|a, b| a.operation.cmp(&b.operation));
+
+    Ok(results)
+}
+
+/// Writes `results` to `path` as a JSON array.
+pub fn write_json(path: &Path, results: &[BenchResult]) -> io::Result<()> {
+    let json = serde_json::to_vec_pretty(results).map_err(io::Error::other)?;
+
+    fs::write(path, json)
+}
+
+/// Returns every directory named `new` containing Criterion results, under `dir`.
+fn find_new_dirs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut new_dirs = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        if path.file_name().is_some_and(|name| name == "new")
+            && path.join("benchmark.json").is_file()
+            && path.join("estimates.json").is_file()
+        {
+            new_dirs.push(path);
+        } else {
+            new_dirs.extend(find_new_dirs(&path)?);
+        }
+    }
+
+    Ok(new_dirs)
+}
+
+/// Reads a JSON file saved by Criterion.
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<T> {
+    serde_json::from_slice(&fs::read(path)?)
+        .map_err(|error| io::Error::other(format!("{}: {error}", path.display())))
+}
+
+/// Returns the number of `amount`s processed per second, if each one takes `ns_per_op`.
+#[allow(clippy::cast_precision_loss)]
+fn per_second(amount: u64, ns_per_op: f64) -> f64 {
+    amount as f64 * 1e9 / ns_per_op
+}
@@ -0,0 +1,76 @@
+//! Tests for benchmark result export.
+
+use std::{env, fs, path::Path, process, time::SystemTime};
+
+use super::{read_criterion_results, write_json, BenchId, BenchResult};
+
+/// Saves a Criterion result in `dir`, in the same layout as Criterion.
+fn save_result(dir: &Path, benchmark: &str, estimates: &str) {
+    let new_dir = dir.join("new");
+    fs::create_dir_all(&new_dir).expect("temporary directory must be writable");
+
+    fs::write(new_dir.join("benchmark.json"), benchmark).expect("temporary file must be writable");
+    fs::write(new_dir.join("estimates.json"), estimates).expect("temporary file must be writable");
+}
+
+/// Check that saved Criterion results are converted into JSON results.
+#[test]
+fn test_read_criterion_results() {
+    let dir = env::temp_dir().join(format!("eyelid-bench-test-{}", process::id()));
+    let started = SystemTime::UNIX_EPOCH;
+
+    save_result(
+        &dir.join("Plaintext identification/random/1000"),
+        r#"{"group_id":"Plaintext identification","function_id":"random","value_str":"1000",
+            "throughput":{"Elements":1000},"full_id":"Plaintext identification/random/1000",
+            "directory_name":"Plaintext identification/random/1000","title":"unused"}"#,
+        r#"{"mean":{"point_estimate":2000000.0},"slope":{"point_estimate":1000000.0}}"#,
+    );
+    save_result(
+        &dir.join("YASHE enc/small rand"),
+        r#"{"group_id":"YASHE enc","function_id":null,"value_str":"small rand",
+            "throughput":null,"full_id":"YASHE enc/small rand",
+            "directory_name":"YASHE enc/small rand","title":"unused"}"#,
+        r#"{"mean":{"point_estimate":500.0},"slope":null}"#,
+    );
+
+    let describe = |id: &BenchId| (id.group_id.clone(), "cpu".to_string());
+    let results = read_criterion_results(&dir, started, describe).expect("results must be read");
+
+    assert_eq!(
+        results,
+        vec![
+            BenchResult {
+                operation: "Plaintext identification/random/1000".to_string(),
+                config: "Plaintext identification".to_string(),
+                backend: "cpu".to_string(),
+                ns_per_op: 1_000_000.0,
+                throughput: Some(1_000_000.0),
+                throughput_unit: Some("elements/s".to_string()),
+            },
+            BenchResult {
+                operation: "YASHE enc/small rand".to_string(),
+                config: "YASHE enc".to_string(),
+                backend: "cpu".to_string(),
+                ns_per_op: 500.0,
+                throughput: None,
+                throughput_unit: None,
+            },
+        ]
+    );
+
+    let output = dir.join("results.json");
+    write_json(&output, &results).expect("results must be written");
+    let written: Vec<BenchResult> =
+        serde_json::from_slice(&fs::read(&output).expect("results file must exist"))
+            .expect("results file must be valid JSON");
+    assert_eq!(written, results);
+
+    // Results from earlier runs are skipped.
+    let later = SystemTime::now() + std::time::Duration::from_secs(60);
+    assert!(read_criterion_results(&dir, later, describe)
+        .expect("results must be read")
+        .is_empty());
+
+    fs::remove_dir_all(&dir).expect("temporary directory must be removable");
+}
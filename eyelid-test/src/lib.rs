@@ -1,4 +1,8 @@
 //! Iris match generic testing functionality.
+//!
+//! [`bench`] exports benchmark results as JSON, for tracking performance across branches.
 
 //#[macro_use]
 //extern crate static_assertions;
+
+pub mod bench;